@@ -0,0 +1,247 @@
+use std::fs;
+use std::path::PathBuf;
+use chrono::{DateTime, TimeDelta, Utc};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::dangerous::insecure_decode;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use serde::Deserialize;
+
+// https://www.rfc-editor.org/rfc/rfc7523#section-2.2
+pub const CLIENT_ASSERTION_TYPE: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+// Asymmetric for private_key_jwt, HS256 for client_secret_jwt.
+pub const ASSERTION_SIGNING_ALGORITHMS: &[Algorithm] = &[Algorithm::RS256, Algorithm::ES256, Algorithm::EdDSA, Algorithm::HS256];
+
+// TODO - Extract into configuration
+// How far into the future an assertion may expire, which bounds how long its jti must be remembered.
+pub const MAXIMUM_LIFETIME: TimeDelta = TimeDelta::minutes(10);
+
+// Where to find the keys an assertion is signed with, see https://www.rfc-editor.org/rfc/rfc7591#section-2
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub enum JwksSource {
+    Inline(JwkSet),
+    // Read on every use, so the keys can be rotated without a restart.
+    File(PathBuf),
+    // The client's own secrets as HMAC keys, for client_secret_jwt, managed and rotated like any other client secret.
+    ClientSecret,
+}
+
+impl JwksSource {
+    // Client secrets are only held by the client secret repository, so can't be loaded from here.
+    pub fn load(&self) -> Option<JwkSet> {
+        match self {
+            JwksSource::Inline(jwks) => Some(jwks.clone()),
+            JwksSource::File(path) => fs::read(path).ok().and_then(|json| serde_json::from_slice(&json).ok()),
+            JwksSource::ClientSecret => None,
+        }
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc7523#section-3
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Debug))]
+pub struct AssertionClaims {
+    pub iss: String,
+    pub sub: String,
+    pub jti: String,
+    pub exp: i64,
+    // Not defined by RFC 7523, but how an issuer says what the assertion is good for.
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+impl AssertionClaims {
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.exp, 0)
+    }
+}
+
+// Who an assertion claims to be from and about, which can only be trusted once it's been verified with the issuer's keys.
+pub fn unverified_claims(assertion: &str) -> Option<AssertionClaims> {
+    insecure_decode::<AssertionClaims>(assertion).ok().map(|data| data.claims)
+}
+
+// The assertion must be from the issuer, about the subject if one is expected, and intended for one of the audiences.
+// https://www.rfc-editor.org/rfc/rfc7523#section-3
+pub fn verify_assertion(jwks: &JwkSet, assertion: &str, issuer: &str, subject: Option<&str>, audiences: &[String]) -> Option<AssertionClaims> {
+
+    let header = decode_header(assertion).ok()?;
+
+    if !ASSERTION_SIGNING_ALGORITHMS.contains(&header.alg) {
+        return None
+    }
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_required_spec_claims(&["exp", "iss", "sub", "aud"]);
+    validation.set_issuer(&[issuer]);
+    validation.sub = subject.map(String::from);
+    validation.set_audience(audiences);
+
+    let claims = jwks.keys.iter()
+        .filter(|jwk| header.kid.is_none() || jwk.common.key_id == header.kid)
+        .filter(|jwk| can_verify(header.alg, jwk))
+        .filter_map(|jwk| DecodingKey::from_jwk(jwk).ok())
+        .find_map(|key| decode::<AssertionClaims>(assertion, &key, &validation).ok())?
+        .claims;
+
+    match claims.expires_at() {
+        Some(expires_at) if expires_at <= Utc::now() + MAXIMUM_LIFETIME => Some(claims),
+        _ => None,
+    }
+}
+
+// Only try keys meant for the algorithm, so a public key can never be mistaken for an HMAC secret.
+fn can_verify(algorithm: Algorithm, jwk: &Jwk) -> bool {
+
+    let key_algorithm_matches = jwk.common.key_algorithm
+        .is_none_or(|key_algorithm| key_algorithm.to_string() == format!("{algorithm:?}"));
+
+    key_algorithm_matches && match (algorithm, &jwk.algorithm) {
+        (Algorithm::RS256, AlgorithmParameters::RSA(_)) => true,
+        (Algorithm::ES256, AlgorithmParameters::EllipticCurve(ec)) => ec.curve == EllipticCurve::P256,
+        (Algorithm::EdDSA, AlgorithmParameters::OctetKeyPair(okp)) => okp.curve == EllipticCurve::Ed25519,
+        (Algorithm::HS256, AlgorithmParameters::OctetKey(_)) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use assertables::*;
+    use chrono::{TimeDelta, Utc};
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    pub const TEST_FERRET_KEY: &[u8] = include_bytes!("../../tests/fixtures/clients/ferret.pem");
+    pub const TEST_GECKO_SECRET: &[u8] = b"hedgehog-hedgehog-hedgehog-hedgehog";
+
+    pub fn new_claims(client_id: &str, audience: &str) -> Value {
+        json!({
+            "iss": client_id,
+            "sub": client_id,
+            "aud": audience,
+            "jti": Uuid::new_v4().to_string(),
+            "exp": (Utc::now() + TimeDelta::minutes(1)).timestamp(),
+        })
+    }
+
+    // As ferret would, with its private key.
+    pub fn sign_private_key_jwt(claims: &Value) -> String {
+        let header = Header { kid: Some("ferret-2026-01".into()), ..Header::new(Algorithm::ES256) };
+        assert_ok!(encode(&header, claims, &assert_ok!(EncodingKey::from_ec_pem(TEST_FERRET_KEY))))
+    }
+
+    // As gecko would, with its shared secret.
+    pub fn sign_client_secret_jwt(claims: &Value) -> String {
+        assert_ok!(encode(&Header::new(Algorithm::HS256), claims, &EncodingKey::from_secret(TEST_GECKO_SECRET)))
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use crate::assertion::test_support::*;
+    use crate::client::configuration::{ClientConfigurationRepository, InMemoryClientConfigurationRepository};
+    use crate::client::secret::{shared_secret_jwks, ClientSecretRepository, InMemoryClientSecretRepository};
+
+    const TEST_AUDIENCE: &str = "http://127.0.0.1:8080/token";
+
+    fn jwks(client_id: &str) -> JwkSet {
+        let configuration = assert_some!(InMemoryClientConfigurationRepository::new().find_by_client_id(client_id));
+        match assert_some!(configuration.jwks) {
+            JwksSource::ClientSecret => shared_secret_jwks(&InMemoryClientSecretRepository::new().find_all_by_client(&configuration.client_id)),
+            jwks => assert_some!(jwks.load()),
+        }
+    }
+
+    fn verify(client_id: &str, assertion: &str) -> Option<AssertionClaims> {
+        verify_assertion(&jwks(client_id), assertion, client_id, Some(client_id), &[TEST_AUDIENCE.into()])
+    }
+
+    #[test]
+    fn should_verify_a_private_key_jwt() {
+        let claims = new_claims("ferret", TEST_AUDIENCE);
+
+        let verified = assert_some!(verify("ferret", &sign_private_key_jwt(&claims)));
+        assert_eq!(verified.jti, claims["jti"]);
+    }
+
+    #[test]
+    fn should_verify_a_client_secret_jwt() {
+        assert_some!(verify("gecko", &sign_client_secret_jwt(&new_claims("gecko", TEST_AUDIENCE))));
+    }
+
+    #[test]
+    fn should_return_the_unverified_claims() {
+        let claims = assert_some!(unverified_claims(&sign_private_key_jwt(&new_claims("ferret", TEST_AUDIENCE))));
+        assert_eq!(claims.iss, "ferret");
+        assert_eq!(claims.sub, "ferret");
+        assert_none!(unverified_claims("aardvark"));
+    }
+
+    #[test]
+    fn should_reject_another_clients_assertion() {
+        assert_none!(verify("ferret", &sign_private_key_jwt(&new_claims("cicada", TEST_AUDIENCE))));
+    }
+
+    #[test]
+    fn should_reject_another_audience() {
+        assert_none!(verify("ferret", &sign_private_key_jwt(&new_claims("ferret", "https://cicada.example.com"))));
+    }
+
+    #[test]
+    fn should_reject_an_expired_assertion() {
+        let mut claims = new_claims("ferret", TEST_AUDIENCE);
+        claims["exp"] = (Utc::now() - TimeDelta::minutes(5)).timestamp().into();
+
+        assert_none!(verify("ferret", &sign_private_key_jwt(&claims)));
+    }
+
+    #[test]
+    fn should_reject_an_assertion_that_lives_too_long() {
+        let mut claims = new_claims("ferret", TEST_AUDIENCE);
+        claims["exp"] = (Utc::now() + TimeDelta::hours(1)).timestamp().into();
+
+        assert_none!(verify("ferret", &sign_private_key_jwt(&claims)));
+    }
+
+    #[test]
+    fn should_reject_an_assertion_without_a_jti() {
+        let mut claims = new_claims("ferret", TEST_AUDIENCE);
+        claims["jti"] = serde_json::Value::Null;
+
+        assert_none!(verify("ferret", &sign_private_key_jwt(&claims)));
+    }
+
+    #[test]
+    fn should_reject_an_assertion_signed_by_another_key() {
+        let claims = new_claims("ferret", TEST_AUDIENCE);
+        let assertion = assert_ok!(encode(&Header::new(Algorithm::ES256), &claims, &assert_ok!(EncodingKey::from_ec_pem(crate::key::test_support::TEST_EC_KEY))));
+
+        assert_none!(verify("ferret", &assertion));
+    }
+
+    #[test]
+    fn should_never_use_a_public_key_as_an_hmac_secret() {
+        let jwks = jwks("ferret");
+        let public_key = assert_ok!(serde_json::to_vec(&jwks.keys[0]));
+
+        // Signed with what an attacker knows, the public key, pretending to be HS256.
+        let assertion = assert_ok!(encode(&Header::new(Algorithm::HS256), &new_claims("ferret", TEST_AUDIENCE), &EncodingKey::from_secret(&public_key)));
+
+        assert_none!(verify("ferret", &assertion));
+        assert!(!can_verify(Algorithm::HS256, &jwks.keys[0]));
+    }
+
+    #[test]
+    fn should_reject_an_unsupported_algorithm() {
+        let assertion = assert_ok!(encode(&Header::new(Algorithm::HS512), &new_claims("gecko", TEST_AUDIENCE), &EncodingKey::from_secret(TEST_GECKO_SECRET)));
+
+        assert_none!(verify("gecko", &assertion));
+    }
+}
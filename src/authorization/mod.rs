@@ -0,0 +1,7 @@
+mod route;
+mod request;
+mod response;
+mod page;
+
+pub use route::*;
+pub use request::{AuthorizationRequest, ResponseType};
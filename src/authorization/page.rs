@@ -0,0 +1,91 @@
+use crate::authorization::request::AuthorizationRequest;
+use crate::util::html::escape;
+use crate::util::value_struct::ValueStruct;
+
+// TODO - Replace with a templating engine once the pages grow beyond a simple form
+pub fn sign_in(request: &AuthorizationRequest, maybe_error: Option<&str>) -> String {
+
+    let hidden_inputs = hidden_inputs(request.parameters());
+
+    let error = maybe_error
+        .map(|error| format!(r#"<p role="alert">{}</p>"#, escape(error)))
+        .unwrap_or_default();
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>Sign in</title>
+  </head>
+  <body>
+    <h1>Sign in to continue to {client_id}</h1>
+    {error}
+    <form method="post" action="/authorize">
+      {hidden_inputs}
+      <label>Username <input type="text" name="username" autocomplete="username" required></label>
+      <label>Password <input type="password" name="password" autocomplete="current-password" required></label>
+      <button type="submit">Sign in</button>
+    </form>
+  </body>
+</html>
+"#, client_id = escape(request.client_id.value()))
+}
+
+// Only the scopes the resource owner hasn't agreed to before are listed, any they untick are left out of what's issued.
+pub fn consent(request: &AuthorizationRequest, consent_id: &str, scopes: &[(String, String)]) -> String {
+
+    // Everything else about the request was kept when the resource owner signed in.
+    let hidden_inputs = hidden_inputs(vec![("consent_id", consent_id.to_string())]);
+
+    let checkboxes = scopes.iter()
+        .map(|(scope, description)| format!(
+            r#"<label><input type="checkbox" name="{CONSENT_SCOPE_PREFIX}{scope}" checked> {description} ({scope})</label>"#,
+            scope = escape(scope),
+            description = escape(description),
+        ))
+        .collect::<Vec<String>>()
+        .join("\n      ");
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>Allow access</title>
+  </head>
+  <body>
+    <h1>Allow {client_id} to access your account?</h1>
+    <form method="post" action="/authorize">
+      {hidden_inputs}
+      {checkboxes}
+      <button type="submit" name="decision" value="approve">Allow</button>
+      <button type="submit" name="decision" value="deny">Deny</button>
+    </form>
+  </body>
+</html>
+"#, client_id = escape(request.client_id.value()))
+}
+
+// Each scope on the consent form is its own checkbox, only sent back when ticked.
+pub const CONSENT_SCOPE_PREFIX: &str = "consent:";
+
+fn hidden_inputs(parameters: Vec<(&'static str, String)>) -> String {
+    parameters.into_iter()
+        .map(|(name, value)| format!(r#"<input type="hidden" name="{name}" value="{}">"#, escape(&value)))
+        .collect::<Vec<String>>()
+        .join("\n      ")
+}
+
+pub fn error(error_description: &str) -> String {
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>Invalid request</title>
+  </head>
+  <body>
+    <h1>Invalid request</h1>
+    <p>{}</p>
+  </body>
+</html>
+"#, escape(error_description))
+}
@@ -0,0 +1,681 @@
+use std::collections::HashMap;
+use crate::authorization::response::{AuthorizationFailure, ErrorType, PushedAuthorizationResponse};
+use crate::client::{ClientAction, ClientId, ClientPrincipal, GrantType};
+use crate::client::configuration::ClientConfigurationRepository;
+use crate::enum_with_from_str;
+use crate::resource::ProtectedResourceRepository;
+use crate::pkce::{is_valid_code_verifier, CodeChallenge, CodeChallengeMethod};
+use crate::scope::Scopes;
+use crate::scope::parser::parse_scopes;
+use crate::scope::repository::ScopeRepository;
+use crate::token::PushedAuthorizationRequest;
+use crate::token::repository::TokenRepository;
+use crate::util::value_struct::ValueStruct;
+
+enum_with_from_str! {
+    #[derive(Eq, PartialEq, Clone)]
+    #[cfg_attr(test, derive(Debug))]
+    pub enum ResponseType {
+        Code: "code",
+    }
+}
+
+#[derive(Eq, PartialEq, Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct AuthorizationRequest {
+    pub client_id: ClientId,
+    pub response_type: ResponseType,
+    pub redirect_uri: String,
+    pub scopes: Option<Scopes>,
+    pub state: Option<String>,
+    pub code_challenge: Option<CodeChallenge>,
+    // https://openid.net/specs/openid-connect-core-1_0.html#AuthRequest
+    pub nonce: Option<String>,
+    // When the parameters were pushed beforehand, see https://www.rfc-editor.org/rfc/rfc9126#section-4
+    pub request_uri: Option<String>,
+    // https://www.rfc-editor.org/rfc/rfc8707#section-2.1
+    pub resource: Option<String>,
+}
+
+impl AuthorizationRequest {
+
+    // The parameters required to replay this request, such as from a hidden form.
+    pub fn parameters(&self) -> Vec<(&'static str, String)> {
+        // Pushed parameters are only ever referred to, so they can't be tampered with along the way.
+        if let Some(request_uri) = &self.request_uri {
+            return vec![
+                ("client_id", self.client_id.value().clone()),
+                ("request_uri", request_uri.clone()),
+            ];
+        }
+        let mut parameters = vec![
+            ("response_type", self.response_type.to_string()),
+            ("client_id", self.client_id.value().clone()),
+            ("redirect_uri", self.redirect_uri.clone()),
+        ];
+        if let Some(scopes) = &self.scopes {
+            parameters.push(("scope", scopes.to_string()));
+        }
+        if let Some(state) = &self.state {
+            parameters.push(("state", state.clone()));
+        }
+        if let Some(code_challenge) = &self.code_challenge {
+            parameters.push(("code_challenge", code_challenge.challenge.clone()));
+            parameters.push(("code_challenge_method", code_challenge.method.to_string()));
+        }
+        if let Some(nonce) = &self.nonce {
+            parameters.push(("nonce", nonce.clone()));
+        }
+        if let Some(resource) = &self.resource {
+            parameters.push(("resource", resource.clone()));
+        }
+        parameters
+    }
+}
+
+// Either every parameter is in the request, or they were pushed beforehand and the request_uri refers to them.
+// https://www.rfc-editor.org/rfc/rfc9126#section-4
+pub fn resolve_authorization_request<C, P>(
+    client_configuration_repository: &C,
+    pushed_authorization_request_repository: &P,
+    request: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, AuthorizationFailure>
+where
+    C: ClientConfigurationRepository,
+    P: TokenRepository<PushedAuthorizationRequest>,
+{
+    let client = match request.get("client_id") {
+        None => Err(AuthorizationFailure::missing_parameter("client_id"))?,
+        Some(client_id) => match client_configuration_repository.find_by_client_id(client_id) {
+            None => Err(AuthorizationFailure::invalid_parameter("client_id"))?,
+            Some(client) => client,
+        },
+    };
+
+    let request_uri = match request.get("request_uri") {
+        None if client.allowed_actions.contains(&ClientAction::PushedAuthorizationRequest) => {
+            Err(AuthorizationFailure::missing_parameter("request_uri"))?
+        },
+        None => return Ok(request.clone()),
+        Some(request_uri) => request_uri,
+    };
+
+    // Only the client that pushed the request can refer to it.
+    let pushed_authorization_request = PushedAuthorizationRequest::parse_request_uri(request_uri)
+        .and_then(|id| pushed_authorization_request_repository.get_token(id))
+        .filter(|pushed_authorization_request| pushed_authorization_request.client_id == client.client_id)
+        .ok_or_else(|| AuthorizationFailure::invalid_parameter("request_uri"))?;
+
+    let mut parameters = pushed_authorization_request.parameters;
+    parameters.insert("request_uri".into(), request_uri.clone());
+    Ok(parameters)
+}
+
+// Validated just as they would be at the authorization endpoint, so the client finds out about any problems straight away.
+// https://www.rfc-editor.org/rfc/rfc9126#section-2.1
+pub fn validate_pushed_authorization_request<C: ClientConfigurationRepository, R: ProtectedResourceRepository, Q: ScopeRepository>(
+    client_configuration_repository: &C,
+    protected_resource_repository: &R,
+    scope_repository: &Q,
+    principal: &ClientPrincipal,
+    mut request: HashMap<String, String>,
+) -> Result<HashMap<String, String>, PushedAuthorizationResponse> {
+
+    if request.contains_key("request_uri") {
+        Err(PushedAuthorizationResponse::invalid_parameter("request_uri"))?
+    }
+
+    if request.get("client_id").is_some_and(|client_id| client_id != principal.id().value()) {
+        Err(PushedAuthorizationResponse::invalid_parameter("client_id"))?
+    }
+
+    // How the client authenticated isn't part of the authorization request.
+    request.remove("client_assertion_type");
+    request.remove("client_assertion");
+    request.insert("client_id".into(), principal.id().value().clone());
+
+    validate_authorization_request(client_configuration_repository, protected_resource_repository, scope_repository, &request)?;
+
+    Ok(request)
+}
+
+pub fn validate_authorization_request<C: ClientConfigurationRepository, R: ProtectedResourceRepository, Q: ScopeRepository>(
+    client_configuration_repository: &C,
+    protected_resource_repository: &R,
+    scope_repository: &Q,
+    request: &HashMap<String, String>,
+) -> Result<AuthorizationRequest, AuthorizationFailure> {
+
+    // Until the client and redirect_uri have been verified, we cannot redirect back with any errors.
+    let client = match request.get("client_id") {
+        None => Err(AuthorizationFailure::missing_parameter("client_id"))?,
+        Some(client_id) => match client_configuration_repository.find_by_client_id(client_id) {
+            None => Err(AuthorizationFailure::invalid_parameter("client_id"))?,
+            Some(client) => client,
+        },
+    };
+
+    let redirect_uri = match request.get("redirect_uri") {
+        None => Err(AuthorizationFailure::missing_parameter("redirect_uri"))?,
+        Some(redirect_uri) if !client.redirect_uris.contains(redirect_uri) => Err(AuthorizationFailure::invalid_parameter("redirect_uri"))?,
+        Some(redirect_uri) => redirect_uri.clone(),
+    };
+
+    let state = request.get("state").cloned();
+
+    let redirect_failure = |error: ErrorType, error_description: String| AuthorizationFailure::Redirect {
+        redirect_uri: redirect_uri.clone(),
+        error,
+        error_description: Some(error_description),
+        state: state.clone(),
+    };
+
+    let response_type = match request.get("response_type").map(|s| s.parse::<ResponseType>()) {
+        None => Err(redirect_failure(ErrorType::InvalidRequest, "missing parameter: response_type".into()))?,
+        Some(Err(error_message)) => Err(redirect_failure(ErrorType::UnsupportedResponseType, error_message))?,
+        Some(Ok(response_type)) => response_type,
+    };
+
+    if !client.allowed_grant_types.contains(&GrantType::AuthorizationCode) {
+        Err(redirect_failure(ErrorType::UnauthorizedClient, format!("not authorized to: {:?}", GrantType::AuthorizationCode)))?
+    }
+
+    let scopes = match parse_scopes(scope_repository, Some(&client.client_id), request.get("scope")) {
+        Err(_) => Err(redirect_failure(ErrorType::InvalidScope, "invalid parameter: scope".into()))?,
+        Ok(Some(Scopes(scopes))) if !scopes.iter().all(|scope| client.allowed_scopes.iter().any(|allowed| scope.is_granted_by(allowed))) => {
+            Err(redirect_failure(ErrorType::InvalidScope, "invalid parameter: scope".into()))?
+        },
+        Ok(None) => Some(Scopes(client.default_scopes.clone())),
+        Ok(scopes) => scopes,
+    };
+
+    // Only the resources the client is allowed to target, which the resource owner is then authorizing access to.
+    let resource = match request.get("resource").map(|resource| protected_resource_repository.find_by_uri(resource)) {
+        None => None,
+        Some(Some(protected_resource)) if protected_resource.can_be_targeted_by(&client.client_id) => Some(protected_resource.uri),
+        Some(_) => Err(redirect_failure(ErrorType::InvalidTarget, "invalid parameter: resource".into()))?,
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc7636#section-4.4.1
+    let code_challenge = match (request.get("code_challenge"), request.get("code_challenge_method")) {
+        (None, Some(_)) => Err(redirect_failure(ErrorType::InvalidRequest, "missing parameter: code_challenge".into()))?,
+        (None, None) if client.allowed_actions.contains(&ClientAction::ProofKeyForCodeExchange) => {
+            Err(redirect_failure(ErrorType::InvalidRequest, "missing parameter: code_challenge".into()))?
+        },
+        (None, None) => None,
+        (Some(challenge), _) if !is_valid_code_verifier(challenge) => {
+            Err(redirect_failure(ErrorType::InvalidRequest, "invalid parameter: code_challenge".into()))?
+        },
+        (Some(challenge), maybe_method) => match maybe_method.map(|s| s.parse::<CodeChallengeMethod>()) {
+            Some(Err(_)) => Err(redirect_failure(ErrorType::InvalidRequest, "invalid parameter: code_challenge_method".into()))?,
+            Some(Ok(method)) => Some(CodeChallenge { challenge: challenge.clone(), method }),
+            // Defaults to plain when not present in the request.
+            None => Some(CodeChallenge { challenge: challenge.clone(), method: CodeChallengeMethod::Plain }),
+        },
+    };
+
+    let nonce = request.get("nonce").cloned();
+
+    let request_uri = request.get("request_uri").cloned();
+
+    Ok(AuthorizationRequest {
+        client_id: client.client_id,
+        response_type,
+        redirect_uri,
+        scopes,
+        state,
+        code_challenge,
+        nonce,
+        request_uri,
+        resource,
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use std::collections::HashSet;
+    use crate::client::configuration::InMemoryClientConfigurationRepository;
+    use crate::map_of;
+    use crate::resource::InMemoryProtectedResourceRepository;
+    use crate::scope::repository::InMemoryScopeRepository;
+    use crate::scope::Scope;
+
+    const REDIRECT_URI: &str = "https://redirect.baconi.co.uk";
+
+    fn validate(request: HashMap<String, String>) -> Result<AuthorizationRequest, AuthorizationFailure> {
+        validate_authorization_request(&InMemoryClientConfigurationRepository::new(), &InMemoryProtectedResourceRepository::new(), &InMemoryScopeRepository::new(), &request)
+    }
+
+    fn redirect_failure(error: ErrorType, error_description: &str) -> AuthorizationFailure {
+        AuthorizationFailure::Redirect {
+            redirect_uri: REDIRECT_URI.into(),
+            error,
+            error_description: Some(error_description.into()),
+            state: Some("aardvark".into()),
+        }
+    }
+
+    mod client {
+        use super::*;
+
+        #[test]
+        fn should_not_redirect_on_missing_client_id() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "redirect_uri" => REDIRECT_URI,
+            });
+
+            assert_eq!(assert_err!(result), AuthorizationFailure::missing_parameter("client_id"));
+        }
+
+        #[test]
+        fn should_not_redirect_on_unknown_client_id() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "cicada",
+                "redirect_uri" => REDIRECT_URI,
+            });
+
+            assert_eq!(assert_err!(result), AuthorizationFailure::invalid_parameter("client_id"));
+        }
+    }
+
+    mod redirect_uri {
+        use super::*;
+
+        #[test]
+        fn should_not_redirect_on_missing_redirect_uri() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), AuthorizationFailure::missing_parameter("redirect_uri"));
+        }
+
+        #[test]
+        fn should_not_redirect_on_unregistered_redirect_uri() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => "https://evil.example.com",
+            });
+
+            assert_eq!(assert_err!(result), AuthorizationFailure::invalid_parameter("redirect_uri"));
+        }
+    }
+
+    mod response_type {
+        use super::*;
+
+        #[test]
+        fn should_redirect_on_missing_response_type() {
+            let result = validate(map_of! {
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), redirect_failure(ErrorType::InvalidRequest, "missing parameter: response_type"));
+        }
+
+        #[test]
+        fn should_redirect_on_unsupported_response_type() {
+            let result = validate(map_of! {
+                "response_type" => "token",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), redirect_failure(ErrorType::UnsupportedResponseType, "unsupported: token"));
+        }
+    }
+
+    mod scope {
+        use super::*;
+
+        #[test]
+        fn should_redirect_on_invalid_scope() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "scope" => "basic cicada",
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), redirect_failure(ErrorType::InvalidScope, "invalid parameter: scope"));
+        }
+
+        #[test]
+        fn should_redirect_on_unauthorised_scope() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "scope" => "write",
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), redirect_failure(ErrorType::InvalidScope, "invalid parameter: scope"));
+        }
+
+        #[test]
+        fn should_accept_a_parameterised_scope_allowed_by_a_wildcard() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "scope" => "accounts:read:1234",
+            });
+
+            assert_eq!(assert_ok!(result).scopes, Some(Scopes(HashSet::from([Scope::from("accounts:read:1234")]))));
+        }
+
+        #[test]
+        fn should_redirect_on_an_unauthorised_parameterised_scope() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "scope" => "accounts:write:1234",
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), redirect_failure(ErrorType::InvalidScope, "invalid parameter: scope"));
+        }
+    }
+
+    mod resource {
+        use super::*;
+
+        #[test]
+        fn should_accept_a_resource_the_client_may_target() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "resource" => "https://api.baconi.co.uk",
+            });
+
+            assert_eq!(assert_ok!(result).resource.as_deref(), Some("https://api.baconi.co.uk"));
+        }
+
+        #[test]
+        fn should_redirect_on_a_resource_the_client_may_not_target() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "resource" => "https://example.com",
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), redirect_failure(ErrorType::InvalidTarget, "invalid parameter: resource"));
+        }
+    }
+
+    mod code_challenge {
+        use super::*;
+
+        const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+        fn invalid_request(error_description: &str) -> AuthorizationFailure {
+            redirect_failure(ErrorType::InvalidRequest, error_description)
+        }
+
+        #[test]
+        fn should_redirect_on_missing_code_challenge_when_required_by_the_client() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "badger",
+                "redirect_uri" => REDIRECT_URI,
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), invalid_request("missing parameter: code_challenge"));
+        }
+
+        #[test]
+        fn should_redirect_on_code_challenge_method_without_code_challenge() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "code_challenge_method" => "S256",
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), invalid_request("missing parameter: code_challenge"));
+        }
+
+        #[test]
+        fn should_redirect_on_malformed_code_challenge() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "badger",
+                "redirect_uri" => REDIRECT_URI,
+                "code_challenge" => "too-short",
+                "code_challenge_method" => "S256",
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), invalid_request("invalid parameter: code_challenge"));
+        }
+
+        #[test]
+        fn should_redirect_on_unsupported_code_challenge_method() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "badger",
+                "redirect_uri" => REDIRECT_URI,
+                "code_challenge" => CODE_CHALLENGE,
+                "code_challenge_method" => "S512",
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_err!(result), invalid_request("invalid parameter: code_challenge_method"));
+        }
+
+        #[test]
+        fn should_default_to_the_plain_code_challenge_method() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "badger",
+                "redirect_uri" => REDIRECT_URI,
+                "code_challenge" => CODE_CHALLENGE,
+            });
+
+            assert_eq!(assert_ok!(result).code_challenge, Some(CodeChallenge {
+                challenge: CODE_CHALLENGE.into(),
+                method: CodeChallengeMethod::Plain,
+            }));
+        }
+
+        #[test]
+        fn should_return_valid_request_with_s256_code_challenge() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "badger",
+                "redirect_uri" => REDIRECT_URI,
+                "code_challenge" => CODE_CHALLENGE,
+                "code_challenge_method" => "S256",
+            });
+
+            assert_eq!(assert_ok!(result).code_challenge, Some(CodeChallenge {
+                challenge: CODE_CHALLENGE.into(),
+                method: CodeChallengeMethod::S256,
+            }));
+        }
+    }
+
+    mod pushed_authorization_request {
+        use super::*;
+        use crate::token::repository::InMemoryTokenRepository;
+
+        fn resolve(pushed_authorization_request_repository: &InMemoryTokenRepository<PushedAuthorizationRequest>, request: HashMap<String, String>) -> Result<HashMap<String, String>, AuthorizationFailure> {
+            resolve_authorization_request(&InMemoryClientConfigurationRepository::new(), pushed_authorization_request_repository, &request)
+        }
+
+        fn validate_pushed(request: HashMap<String, String>) -> Result<HashMap<String, String>, PushedAuthorizationResponse> {
+            validate_pushed_authorization_request(&InMemoryClientConfigurationRepository::new(), &InMemoryProtectedResourceRepository::new(), &InMemoryScopeRepository::new(), &ClientPrincipal::new_confidential_principal("aardvark"), request)
+        }
+
+        #[test]
+        fn should_pass_through_a_request_without_a_request_uri() {
+            let request: HashMap<String, String> = map_of! { "client_id" => "aardvark", "state" => "badger" };
+
+            assert_eq!(assert_ok!(resolve(&InMemoryTokenRepository::new(), request.clone())), request);
+        }
+
+        #[test]
+        fn should_resolve_the_pushed_parameters() {
+            let repository = InMemoryTokenRepository::new();
+            let pushed_authorization_request = PushedAuthorizationRequest::new(String::from("aardvark").into(), map_of! { "client_id" => "aardvark", "state" => "badger" });
+            repository.save_token(&pushed_authorization_request);
+
+            let result = resolve(&repository, map_of! { "client_id" => "aardvark", "request_uri" => pushed_authorization_request.request_uri(), "state" => "cicada" });
+
+            assert_eq!(assert_ok!(result), map_of! {
+                "client_id" => "aardvark",
+                "state" => "badger",
+                "request_uri" => pushed_authorization_request.request_uri(),
+            });
+        }
+
+        #[test]
+        fn should_not_redirect_on_an_unknown_request_uri() {
+            let result = resolve(&InMemoryTokenRepository::new(), map_of! { "client_id" => "aardvark", "request_uri" => "urn:ietf:params:oauth:request_uri:cicada" });
+
+            assert_eq!(assert_err!(result), AuthorizationFailure::invalid_parameter("request_uri"));
+        }
+
+        #[test]
+        fn should_not_redirect_on_a_missing_request_uri_when_required_by_the_client() {
+            let result = resolve(&InMemoryTokenRepository::new(), map_of! { "client_id" => "jackal", "response_type" => "code", "redirect_uri" => REDIRECT_URI });
+
+            assert_eq!(assert_err!(result), AuthorizationFailure::missing_parameter("request_uri"));
+        }
+
+        #[test]
+        fn should_replay_only_the_request_uri_in_the_sign_in_form() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "state" => "aardvark",
+                "request_uri" => "urn:ietf:params:oauth:request_uri:badger",
+            });
+
+            assert_eq!(assert_ok!(result).parameters(), vec![
+                ("client_id", String::from("aardvark")),
+                ("request_uri", String::from("urn:ietf:params:oauth:request_uri:badger")),
+            ]);
+        }
+
+        #[test]
+        fn should_reject_a_request_uri_being_pushed() {
+            let result = validate_pushed(map_of! {
+                "response_type" => "code",
+                "redirect_uri" => REDIRECT_URI,
+                "request_uri" => "urn:ietf:params:oauth:request_uri:badger",
+            });
+
+            assert_eq!(assert_err!(result), PushedAuthorizationResponse::invalid_parameter("request_uri"));
+        }
+
+        #[test]
+        fn should_reject_a_client_id_for_another_client() {
+            let result = validate_pushed(map_of! {
+                "response_type" => "code",
+                "client_id" => "badger",
+                "redirect_uri" => REDIRECT_URI,
+            });
+
+            assert_eq!(assert_err!(result), PushedAuthorizationResponse::invalid_parameter("client_id"));
+        }
+
+        #[test]
+        fn should_return_the_parameters_for_the_authenticated_client() {
+            let result = validate_pushed(map_of! {
+                "response_type" => "code",
+                "redirect_uri" => REDIRECT_URI,
+                "client_assertion_type" => "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+                "client_assertion" => "aardvark",
+            });
+
+            assert_eq!(assert_ok!(result), map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+            });
+        }
+    }
+
+    mod valid {
+        use super::*;
+
+        #[test]
+        fn should_return_valid_request() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "scope" => "basic",
+                "state" => "aardvark",
+            });
+
+            assert_eq!(assert_ok!(result), AuthorizationRequest {
+                client_id: String::from("aardvark").into(),
+                response_type: ResponseType::Code,
+                redirect_uri: REDIRECT_URI.into(),
+                scopes: Some(Scopes(HashSet::from([Scope::from("basic")]))),
+                state: Some("aardvark".into()),
+                code_challenge: None,
+                nonce: None,
+                request_uri: None,
+                resource: None,
+            });
+        }
+
+        #[test]
+        fn should_return_valid_request_with_the_default_scopes_without_scope_or_state() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+            });
+
+            assert_eq!(assert_ok!(result), AuthorizationRequest {
+                client_id: String::from("aardvark").into(),
+                response_type: ResponseType::Code,
+                redirect_uri: REDIRECT_URI.into(),
+                scopes: Some(Scopes(HashSet::from([Scope::from("basic")]))),
+                state: None,
+                code_challenge: None,
+                nonce: None,
+                request_uri: None,
+                resource: None,
+            });
+        }
+
+        #[test]
+        fn should_return_valid_request_with_a_nonce() {
+            let result = validate(map_of! {
+                "response_type" => "code",
+                "client_id" => "aardvark",
+                "redirect_uri" => REDIRECT_URI,
+                "scope" => "openid",
+                "nonce" => "badger",
+            });
+
+            let request = assert_ok!(result);
+            assert_some_eq_x!(&request.nonce, &String::from("badger"));
+            assert_contains!(request.parameters(), &("nonce", String::from("badger")));
+        }
+    }
+}
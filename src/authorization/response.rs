@@ -0,0 +1,184 @@
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Json, Redirect, Response};
+use serde::{Serialize, Serializer};
+use crate::authorization::page;
+use crate::enum_with_from_str;
+use crate::token::PushedAuthorizationRequest;
+
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub enum AuthorizationFailure {
+
+    // If the request fails due to a missing, invalid, or mismatching redirection URI, or if the
+    // client identifier is missing or invalid, the authorization server SHOULD inform the resource
+    // owner of the error and MUST NOT automatically redirect the user-agent to the invalid
+    // redirection URI.
+    NotRedirectable {
+        error_description: String,
+    },
+
+    // Otherwise the authorization server informs the client by adding the error parameters to the
+    // query component of the redirection URI.
+    Redirect {
+        redirect_uri: String,
+        error: ErrorType,
+        error_description: Option<String>,
+        state: Option<String>,
+    },
+}
+
+impl AuthorizationFailure {
+
+    pub fn missing_parameter(parameter: &str) -> Self {
+        AuthorizationFailure::NotRedirectable {
+            error_description: format!("missing parameter: {parameter}"),
+        }
+    }
+
+    pub fn invalid_parameter(parameter: &str) -> Self {
+        AuthorizationFailure::NotRedirectable {
+            error_description: format!("invalid parameter: {parameter}"),
+        }
+    }
+}
+
+impl IntoResponse for AuthorizationFailure {
+    fn into_response(self) -> Response {
+        match self {
+            AuthorizationFailure::NotRedirectable { error_description } => {
+                (StatusCode::BAD_REQUEST, Html(page::error(&error_description))).into_response()
+            },
+            AuthorizationFailure::Redirect { redirect_uri, error, error_description, state } => {
+                let mut parameters = vec![("error", error.to_string())];
+                if let Some(error_description) = error_description {
+                    parameters.push(("error_description", error_description));
+                }
+                if let Some(state) = state {
+                    parameters.push(("state", state));
+                }
+                redirect_with_parameters(&redirect_uri, parameters).into_response()
+            },
+        }
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc9126#section-2.2
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum PushedAuthorizationResponse {
+    Success {
+
+        // Refers to the pushed parameters when the user is sent to the authorization endpoint.
+        request_uri: String,
+
+        // The lifetime in seconds of the "request_uri".
+        expires_in: i64,
+    },
+    Failure {
+
+        // The same error codes as the authorization endpoint, see https://www.rfc-editor.org/rfc/rfc9126#section-2.3
+        error: ErrorType,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_description: Option<String>,
+    },
+}
+
+impl PushedAuthorizationResponse {
+
+    pub fn success(pushed_authorization_request: &PushedAuthorizationRequest) -> Self {
+        PushedAuthorizationResponse::Success {
+            request_uri: pushed_authorization_request.request_uri(),
+            expires_in: pushed_authorization_request.expires_in(),
+        }
+    }
+
+    pub fn invalid_parameter(parameter: &str) -> Self {
+        PushedAuthorizationResponse::Failure {
+            error: ErrorType::InvalidRequest,
+            error_description: Some(format!("invalid parameter: {parameter}")),
+        }
+    }
+}
+
+// There's no one to redirect back to, the client is told about every failure directly.
+impl From<AuthorizationFailure> for PushedAuthorizationResponse {
+    fn from(failure: AuthorizationFailure) -> Self {
+        match failure {
+            AuthorizationFailure::NotRedirectable { error_description } => PushedAuthorizationResponse::Failure {
+                error: ErrorType::InvalidRequest,
+                error_description: Some(error_description),
+            },
+            AuthorizationFailure::Redirect { error, error_description, .. } => PushedAuthorizationResponse::Failure {
+                error,
+                error_description,
+            },
+        }
+    }
+}
+
+impl IntoResponse for PushedAuthorizationResponse {
+    fn into_response(self) -> Response {
+        match self {
+            PushedAuthorizationResponse::Success { .. } => (StatusCode::CREATED, Json(self)).into_response(),
+            PushedAuthorizationResponse::Failure { .. } => (StatusCode::BAD_REQUEST, Json(self)).into_response(),
+        }
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc6749#section-4.1.2
+pub fn redirect_with_code(redirect_uri: &str, code: &str, state: Option<String>) -> Redirect {
+    let mut parameters = vec![("code", code.to_string())];
+    if let Some(state) = state {
+        parameters.push(("state", state));
+    }
+    redirect_with_parameters(redirect_uri, parameters)
+}
+
+fn redirect_with_parameters(redirect_uri: &str, parameters: Vec<(&str, String)>) -> Redirect {
+
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(parameters)
+        .finish();
+
+    // The redirection endpoint URI MAY include a query component, which MUST be retained.
+    let separator = if redirect_uri.contains('?') { '&' } else { '?' };
+
+    Redirect::to(&format!("{redirect_uri}{separator}{query}"))
+}
+
+// https://www.rfc-editor.org/rfc/rfc6749#section-4.1.2.1
+enum_with_from_str! {
+    #[derive(Eq, PartialEq, Clone)]
+    #[cfg_attr(test, derive(Debug))]
+    pub enum ErrorType {
+
+        // The request is missing a required parameter, includes an invalid parameter value,
+        // includes a parameter more than once, or is otherwise malformed.
+        InvalidRequest: "invalid_request",
+
+        // The client is not authorized to request an authorization code using this method.
+        UnauthorizedClient: "unauthorized_client",
+
+        // The authorization server does not support obtaining an authorization code using this method.
+        UnsupportedResponseType: "unsupported_response_type",
+
+        // The resource owner or authorization server denied the request.
+        AccessDenied: "access_denied",
+
+        // The requested scope is invalid, unknown, or malformed.
+        InvalidScope: "invalid_scope",
+
+        // The requested resource is invalid, missing, unknown, or malformed.
+        // https://www.rfc-editor.org/rfc/rfc8707#section-2
+        InvalidTarget: "invalid_target",
+    }
+}
+
+impl Serialize for ErrorType {
+    // Serialize as the error code, just as it appears in the redirect.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_string().serialize(serializer)
+    }
+}
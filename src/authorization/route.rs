@@ -0,0 +1,895 @@
+use std::collections::HashMap;
+use axum::extract::{Query, State};
+use chrono::Utc;
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{middleware, Extension, Form, Router};
+use middleware::from_fn_with_state;
+use crate::authorization::page;
+use crate::authorization::request::{resolve_authorization_request, AuthorizationRequest, validate_authorization_request, validate_pushed_authorization_request};
+use crate::authorization::response::{redirect_with_code, AuthorizationFailure, ErrorType, PushedAuthorizationResponse};
+use crate::client::authentication::ClientAuthenticator;
+use crate::client::configuration::ClientConfigurationRepository;
+use crate::client::middleware::require_client_authentication;
+use crate::client::{ClientAction, ClientPrincipal};
+use crate::consent::{Consent, ConsentRepository};
+use crate::resource::ProtectedResourceRepository;
+use crate::scope::Scopes;
+use crate::scope::repository::ScopeRepository;
+use crate::token::{AuthorizationCode, PendingConsent, PushedAuthorizationRequest};
+use crate::token::repository::TokenRepository;
+use crate::user::authentication::UserAuthenticator;
+use crate::util::value_struct::ValueStruct;
+use uuid::Uuid;
+
+pub const AUTHORIZATION_ENDPOINT: &str = "/authorize";
+pub const PUSHED_AUTHORIZATION_REQUEST_ENDPOINT: &str = "/par";
+
+// https://www.rfc-editor.org/rfc/rfc6749#section-3.1
+pub fn route<Z, P, C, A, U, R, Q, N, W>(state: AuthorizationState<Z, P, C, A, U, R, Q, N, W>) -> Router<()>
+where
+    Z: TokenRepository<AuthorizationCode> + 'static,
+    P: TokenRepository<PushedAuthorizationRequest> + 'static,
+    C: ClientConfigurationRepository + 'static,
+    A: ClientAuthenticator + 'static,
+    U: UserAuthenticator + 'static,
+    R: ProtectedResourceRepository + 'static,
+    Q: ScopeRepository + 'static,
+    N: ConsentRepository + 'static,
+    W: TokenRepository<PendingConsent> + 'static,
+{
+    Router::new()
+        .route(PUSHED_AUTHORIZATION_REQUEST_ENDPOINT, post(pushed_authorization_request_handler))
+        // Only pushing an authorization request requires client authentication, not the resource owner's sign in.
+        .route_layer(from_fn_with_state(state.client_authenticator.clone(), require_client_authentication::<A>))
+        .route(AUTHORIZATION_ENDPOINT, get(authorization_page_handler).post(authorization_handler))
+        .with_state(state)
+}
+
+#[derive(Clone)]
+pub struct AuthorizationState<Z: TokenRepository<AuthorizationCode>, P: TokenRepository<PushedAuthorizationRequest>, C: ClientConfigurationRepository, A: ClientAuthenticator, U: UserAuthenticator, R: ProtectedResourceRepository, Q: ScopeRepository, N: ConsentRepository, W: TokenRepository<PendingConsent>> {
+    pub authorization_code_repository: Z,
+    pub pushed_authorization_request_repository: P,
+    pub client_configuration_repository: C,
+    pub client_authenticator: A,
+    pub user_authenticator: U,
+    pub protected_resource_repository: R,
+    pub scope_repository: Q,
+    pub consent_repository: N,
+    pub pending_consent_repository: W,
+}
+
+// https://www.rfc-editor.org/rfc/rfc9126#section-2
+// Allowed because the state is generic over every repository and service the authorization endpoints need
+#[allow(clippy::type_complexity)]
+async fn pushed_authorization_request_handler<Z, P, C, A, U, R, Q, N, W>(
+    State(state): State<AuthorizationState<Z, P, C, A, U, R, Q, N, W>>,
+    Extension(principal): Extension<ClientPrincipal>,
+    Form(parameters): Form<HashMap<String, String>>,
+) -> PushedAuthorizationResponse
+where
+    Z: TokenRepository<AuthorizationCode>,
+    P: TokenRepository<PushedAuthorizationRequest>,
+    C: ClientConfigurationRepository,
+    A: ClientAuthenticator,
+    U: UserAuthenticator,
+    R: ProtectedResourceRepository,
+    Q: ScopeRepository,
+    N: ConsentRepository,
+    W: TokenRepository<PendingConsent>,
+{
+    let parameters = match validate_pushed_authorization_request(&state.client_configuration_repository, &state.protected_resource_repository, &state.scope_repository, &principal, parameters) {
+        Err(failure) => return failure,
+        Ok(parameters) => parameters,
+    };
+
+    let pushed_authorization_request = PushedAuthorizationRequest::new(principal.id().clone(), parameters);
+
+    state.pushed_authorization_request_repository.save_token(&pushed_authorization_request);
+
+    PushedAuthorizationResponse::success(&pushed_authorization_request)
+}
+
+// TODO - Replace the sign in form with a session once we have one [authenticate/authenticated]
+#[allow(clippy::type_complexity)]
+async fn authorization_page_handler<Z, P, C, A, U, R, Q, N, W>(
+    State(state): State<AuthorizationState<Z, P, C, A, U, R, Q, N, W>>,
+    Query(parameters): Query<HashMap<String, String>>,
+) -> Response
+where
+    Z: TokenRepository<AuthorizationCode>,
+    P: TokenRepository<PushedAuthorizationRequest>,
+    C: ClientConfigurationRepository,
+    A: ClientAuthenticator,
+    U: UserAuthenticator,
+    R: ProtectedResourceRepository,
+    Q: ScopeRepository,
+    N: ConsentRepository,
+    W: TokenRepository<PendingConsent>,
+{
+    let request = resolve_authorization_request(&state.client_configuration_repository, &state.pushed_authorization_request_repository, &parameters)
+        .and_then(|request| validate_authorization_request(&state.client_configuration_repository, &state.protected_resource_repository, &state.scope_repository, &request));
+
+    match request {
+        Err(failure) => failure.into_response(),
+        Ok(request) => Html(page::sign_in(&request, None)).into_response(),
+    }
+}
+
+#[allow(clippy::type_complexity)]
+async fn authorization_handler<Z, P, C, A, U, R, Q, N, W>(
+    State(state): State<AuthorizationState<Z, P, C, A, U, R, Q, N, W>>,
+    Form(parameters): Form<HashMap<String, String>>,
+) -> Response
+where
+    Z: TokenRepository<AuthorizationCode>,
+    P: TokenRepository<PushedAuthorizationRequest>,
+    C: ClientConfigurationRepository,
+    A: ClientAuthenticator,
+    U: UserAuthenticator,
+    R: ProtectedResourceRepository,
+    Q: ScopeRepository,
+    N: ConsentRepository,
+    W: TokenRepository<PendingConsent>,
+{
+    // Having signed in, the resource owner comes back from the consent screen with what they agreed to.
+    let pending_consent = match parameters.get("consent_id") {
+        None => None,
+        Some(consent_id) => {
+
+            // Only the client the resource owner signed in to can be consented to.
+            let pending_consent = Uuid::parse_str(consent_id).ok()
+                .and_then(|id| state.pending_consent_repository.remove_token(id))
+                .filter(|pending_consent| parameters.get("client_id").is_none_or(|client_id| client_id == pending_consent.request.client_id.value()));
+
+            match pending_consent {
+                None => return AuthorizationFailure::invalid_parameter("consent_id").into_response(),
+                Some(pending_consent) => Some(pending_consent),
+            }
+        },
+    };
+
+    // Only the decision and scopes are taken from the consent form, the rest of the request is as it was first validated.
+    let request = match &pending_consent {
+        Some(pending_consent) => Ok(pending_consent.request.clone()),
+        None => resolve_authorization_request(&state.client_configuration_repository, &state.pushed_authorization_request_repository, &parameters)
+            .and_then(|request| validate_authorization_request(&state.client_configuration_repository, &state.protected_resource_repository, &state.scope_repository, &request)),
+    };
+
+    let request = match request {
+        Err(failure) => return failure.into_response(),
+        Ok(request) => request,
+    };
+
+    let requested = request.scopes.clone().unwrap_or_default();
+
+    let (username, auth_time, scopes) = match pending_consent {
+        Some(pending_consent) => {
+
+            if parameters.get("decision").is_none_or(|decision| decision != "approve") {
+                return access_denied(request).into_response();
+            }
+
+            // Any scopes left unticked are neither remembered nor issued.
+            let Scopes(approved) = pending_consent.scopes;
+            let approved = approved.into_iter()
+                .filter(|scope| parameters.contains_key(&format!("{}{scope}", page::CONSENT_SCOPE_PREFIX)));
+
+            let mut consent = state.consent_repository.find_consent(&pending_consent.username, &request.client_id)
+                .unwrap_or_else(|| Consent::new(pending_consent.username.clone(), request.client_id.clone(), Scopes::default()));
+            consent.scopes.0.extend(approved);
+            consent.granted_at = Utc::now();
+            state.consent_repository.save_consent(&consent);
+
+            let granted = Scopes(requested.0.intersection(&consent.scopes.0).cloned().collect());
+            if granted.0.is_empty() && !requested.0.is_empty() {
+                return access_denied(request).into_response();
+            }
+
+            (pending_consent.username, pending_consent.auth_time, granted)
+        },
+        None => {
+
+            let maybe_user = match (parameters.get("username"), parameters.get("password")) {
+                (Some(username), Some(password)) => state.user_authenticator.authenticate(username, password.as_bytes()),
+                _ => None,
+            };
+
+            let user = match maybe_user {
+                None => return (StatusCode::UNAUTHORIZED, Html(page::sign_in(&request, Some("invalid username or password")))).into_response(),
+                Some(user) => user,
+            };
+
+            let first_party = state.client_configuration_repository.find_by_id(&request.client_id)
+                .is_some_and(|client| client.allowed_actions.contains(&ClientAction::SkipConsent));
+
+            // The resource owner is only asked about the scopes they've not agreed to before.
+            let not_granted = match state.consent_repository.find_consent(&user.username, &request.client_id) {
+                _ if first_party => Scopes::default(),
+                None => requested.clone(),
+                Some(consent) => consent.not_granted(&requested),
+            };
+
+            if !not_granted.0.is_empty() {
+                let mut descriptions = not_granted.0.iter()
+                    .map(|scope| {
+                        let description = state.scope_repository.find_by_name(&scope.to_string())
+                            .map(|definition| definition.description)
+                            .unwrap_or_default();
+                        (scope.to_string(), description)
+                    })
+                    .collect::<Vec<_>>();
+                descriptions.sort();
+
+                let pending_consent = PendingConsent::new(request.clone(), user.username, not_granted);
+                state.pending_consent_repository.save_token(&pending_consent);
+
+                return Html(page::consent(&request, &pending_consent.id.to_string(), &descriptions)).into_response();
+            }
+
+            (user.username, Utc::now(), requested)
+        },
+    };
+
+    // A pushed authorization request can only be used the once, https://www.rfc-editor.org/rfc/rfc9126#section-4
+    let pushed_authorization_request_id = request.request_uri.as_deref().and_then(PushedAuthorizationRequest::parse_request_uri);
+    if let Some(id) = pushed_authorization_request_id && state.pushed_authorization_request_repository.remove_token(id).is_none() {
+        return AuthorizationFailure::invalid_parameter("request_uri").into_response();
+    }
+
+    let authorization_code = AuthorizationCode {
+        resource: request.resource,
+        auth_time,
+        ..AuthorizationCode::new(
+            request.client_id,
+            username,
+            request.redirect_uri,
+            scopes,
+            request.state,
+            request.code_challenge,
+            request.nonce,
+        )
+    };
+
+    state.authorization_code_repository.save_token(&authorization_code);
+
+    redirect_with_code(
+        &authorization_code.redirect_uri,
+        &authorization_code.id.to_string(),
+        authorization_code.state.clone(),
+    ).into_response()
+}
+
+// https://www.rfc-editor.org/rfc/rfc6749#section-4.1.2.1
+fn access_denied(request: AuthorizationRequest) -> AuthorizationFailure {
+    AuthorizationFailure::Redirect {
+        redirect_uri: request.redirect_uri,
+        error: ErrorType::AccessDenied,
+        error_description: None,
+        state: request.state,
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+
+    use super::*;
+
+    use assertables::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, LOCATION};
+    use base64::prelude::*;
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+    use crate::token::repository::InMemoryTokenRepository;
+
+    const APPLICATION_WWW_FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
+    const REDIRECT_URI: &str = "https://redirect.baconi.co.uk";
+    const ENCODED_REDIRECT_URI: &str = "https%3A%2F%2Fredirect.baconi.co.uk";
+
+    macro_rules! under_test {
+        () => {
+            under_test!(InMemoryTokenRepository::new())
+        };
+        ($authorization_code_repository:expr) => {
+            under_test!($authorization_code_repository, InMemoryTokenRepository::new())
+        };
+        ($authorization_code_repository:expr, $pushed_authorization_request_repository:expr) => {
+            under_test!($authorization_code_repository, $pushed_authorization_request_repository, crate::consent::InMemoryConsentRepository::new())
+        };
+        ($authorization_code_repository:expr, $pushed_authorization_request_repository:expr, $consent_repository:expr) => {
+            route(AuthorizationState {
+                authorization_code_repository: $authorization_code_repository,
+                pushed_authorization_request_repository: $pushed_authorization_request_repository,
+                client_configuration_repository: crate::client::configuration::InMemoryClientConfigurationRepository::new(),
+                client_authenticator: crate::client::authentication::ClientAuthenticationService::new(
+                    "http://127.0.0.1:8080".into(),
+                    crate::client::secret::InMemoryClientSecretRepository::new(),
+                    crate::client::configuration::InMemoryClientConfigurationRepository::new(),
+                    crate::replay::InMemoryReplayCache::new(),
+                ),
+                user_authenticator: crate::user::authentication::UserAuthenticationService::new(
+                    crate::user::repository::InMemoryUserRepository::new(),
+                ),
+                protected_resource_repository: crate::resource::InMemoryProtectedResourceRepository::new(),
+                scope_repository: crate::scope::repository::InMemoryScopeRepository::new(),
+                consent_repository: $consent_repository,
+                pending_consent_repository: InMemoryTokenRepository::new(),
+            })
+        };
+    }
+
+    async fn extract_text_body(response: Response) -> String {
+        let body_bytes = assert_ok!(response.into_body().collect().await);
+        assert_ok!(String::from_utf8(body_bytes.to_bytes().to_vec()))
+    }
+
+    fn extract_location(response: &Response) -> String {
+        let location = assert_some!(response.headers().get(LOCATION));
+        assert_ok!(location.to_str()).to_string()
+    }
+
+    fn extract_query(location: &str) -> HashMap<String, String> {
+        let (_, query) = assert_some!(location.split_once('?'));
+        form_urlencoded::parse(query.as_bytes()).into_owned().collect()
+    }
+
+    async fn get_authorize(router: Router, query: &str) -> Response {
+        let request = assert_ok!(Request::builder()
+            .method(Method::GET)
+            .uri(format!("{AUTHORIZATION_ENDPOINT}?{query}"))
+            .body(Body::empty())
+        );
+        assert_ok!(router.oneshot(request).await)
+    }
+
+    async fn post_authorize(router: Router, body: &str) -> Response {
+        let request = assert_ok!(Request::builder()
+            .method(Method::POST)
+            .uri(AUTHORIZATION_ENDPOINT)
+            .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+            .body(Body::from(body.to_string()))
+        );
+        assert_ok!(router.oneshot(request).await)
+    }
+
+    async fn post_par(router: Router, maybe_authorization: Option<String>, body: &str) -> Response {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(PUSHED_AUTHORIZATION_REQUEST_ENDPOINT)
+            .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED);
+        if let Some(authorization) = maybe_authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        let request = assert_ok!(request.body(Body::from(body.to_string())));
+        assert_ok!(router.oneshot(request).await)
+    }
+
+    async fn extract_json_body(response: Response) -> HashMap<String, Value> {
+        let body_bytes = assert_ok!(response.into_body().collect().await);
+        assert_ok!(serde_json::from_slice(body_bytes.to_bytes().as_ref()))
+    }
+
+    fn basic_authorization() -> Option<String> {
+        Some(format!("Basic {}", BASE64_STANDARD.encode("aardvark:badger")))
+    }
+
+    // As if the test user had already agreed to the client having the scopes.
+    fn consented_to(client_id: &str, scopes: &[&'static str]) -> crate::consent::InMemoryConsentRepository {
+        let consent_repository = crate::consent::InMemoryConsentRepository::new();
+        consent_repository.save_consent(&Consent::new(
+            crate::user::Username::from(String::from("aardvark")),
+            crate::client::ClientId::from(String::from(client_id)),
+            Scopes(scopes.iter().map(|scope| crate::scope::Scope::from(*scope)).collect()),
+        ));
+        consent_repository
+    }
+
+    mod pushed_authorization_request {
+        use super::*;
+        use crate::client::ClientId;
+
+        fn new_pushed_authorization_request(client_id: &str) -> PushedAuthorizationRequest {
+            PushedAuthorizationRequest::new(ClientId::from(String::from(client_id)), HashMap::from([
+                (String::from("response_type"), String::from("code")),
+                (String::from("client_id"), String::from(client_id)),
+                (String::from("redirect_uri"), String::from(REDIRECT_URI)),
+                (String::from("scope"), String::from("basic")),
+                (String::from("state"), String::from("badger")),
+            ]))
+        }
+
+        fn encode(value: &str) -> String {
+            form_urlencoded::byte_serialize(value.as_bytes()).collect()
+        }
+
+        #[tokio::test]
+        async fn should_store_the_parameters_and_return_a_request_uri() {
+            let pushed_authorization_request_repository = InMemoryTokenRepository::new();
+            let router = under_test!(InMemoryTokenRepository::new(), pushed_authorization_request_repository.clone());
+
+            let response = post_par(router, basic_authorization(), &format!("response_type=code&redirect_uri={ENCODED_REDIRECT_URI}&scope=basic&state=badger")).await;
+            assert_eq!(response.status(), StatusCode::CREATED);
+
+            let body = extract_json_body(response).await;
+            let request_uri = assert_some!(body.get("request_uri").and_then(Value::as_str));
+            assert_starts_with!(request_uri, PushedAuthorizationRequest::REQUEST_URI_PREFIX);
+            assert_some_eq_x!(body.get("expires_in"), &Value::from(PushedAuthorizationRequest::TIME_TO_LIVE.num_seconds()));
+
+            let id = assert_some!(PushedAuthorizationRequest::parse_request_uri(request_uri));
+            let saved: PushedAuthorizationRequest = assert_some!(pushed_authorization_request_repository.get_token(id));
+            assert_eq!(saved.client_id, String::from("aardvark").into());
+            assert_some_eq_x!(saved.parameters.get("client_id"), "aardvark");
+            assert_some_eq_x!(saved.parameters.get("state"), "badger");
+        }
+
+        #[tokio::test]
+        async fn should_accept_a_public_client() {
+            let response = post_par(under_test!(), None, &format!("response_type=code&client_id=jackal&redirect_uri={ENCODED_REDIRECT_URI}")).await;
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unauthenticated_client() {
+            let response = post_par(under_test!(), None, &format!("response_type=code&redirect_uri={ENCODED_REDIRECT_URI}")).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_invalid_authorization_request() {
+            let response = post_par(under_test!(), basic_authorization(), &format!("response_type=code&redirect_uri={ENCODED_REDIRECT_URI}&scope=cicada")).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("error"), &Value::from("invalid_scope"));
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unregistered_redirect_uri() {
+            let response = post_par(under_test!(), basic_authorization(), "response_type=code&redirect_uri=https%3A%2F%2Fevil.example.com").await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("error"), &Value::from("invalid_request"));
+            assert_some_eq_x!(body.get("error_description"), &Value::from("invalid parameter: redirect_uri"));
+        }
+
+        #[tokio::test]
+        async fn should_render_the_sign_in_form_for_a_pushed_request() {
+            let pushed_authorization_request_repository = InMemoryTokenRepository::new();
+            let pushed_authorization_request = new_pushed_authorization_request("aardvark");
+            pushed_authorization_request_repository.save_token(&pushed_authorization_request);
+            let router = under_test!(InMemoryTokenRepository::new(), pushed_authorization_request_repository);
+
+            let request_uri = pushed_authorization_request.request_uri();
+            let response = get_authorize(router, &format!("client_id=aardvark&request_uri={}", encode(&request_uri))).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_text_body(response).await;
+            assert_contains!(body, &format!(r#"<input type="hidden" name="request_uri" value="{request_uri}">"#));
+            assert_not_contains!(body, r#"name="state""#);
+        }
+
+        #[tokio::test]
+        async fn should_redirect_with_a_code_for_the_pushed_parameters_only_the_once() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let pushed_authorization_request_repository = InMemoryTokenRepository::new();
+            let pushed_authorization_request = new_pushed_authorization_request("aardvark");
+            pushed_authorization_request_repository.save_token(&pushed_authorization_request);
+            let router = under_test!(authorization_code_repository.clone(), pushed_authorization_request_repository);
+
+            // Anything else in the request is ignored in favour of what was pushed.
+            let body = format!("client_id=aardvark&request_uri={}&state=cicada&username=aardvark&password=P%4055w0rd", encode(&pushed_authorization_request.request_uri()));
+
+            let response = post_authorize(router.clone(), &body).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let query = extract_query(&extract_location(&response));
+            assert_some_eq_x!(query.get("state"), "badger");
+
+            let code = assert_ok!(Uuid::parse_str(assert_some!(query.get("code"))));
+            let authorization_code: AuthorizationCode = assert_some!(authorization_code_repository.get_token(code));
+            assert_eq!(authorization_code.scopes, crate::scope::Scopes(std::collections::HashSet::from([crate::scope::Scope::from("basic")])));
+
+            let response = post_authorize(router, &body).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_contains!(extract_text_body(response).await, "invalid parameter: request_uri");
+        }
+
+        #[tokio::test]
+        async fn should_not_redirect_for_a_request_uri_pushed_by_another_client() {
+            let pushed_authorization_request_repository = InMemoryTokenRepository::new();
+            let pushed_authorization_request = new_pushed_authorization_request("aardvark");
+            pushed_authorization_request_repository.save_token(&pushed_authorization_request);
+            let router = under_test!(InMemoryTokenRepository::new(), pushed_authorization_request_repository);
+
+            let response = get_authorize(router, &format!("client_id=badger&request_uri={}", encode(&pushed_authorization_request.request_uri()))).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_none!(response.headers().get(LOCATION));
+        }
+
+        #[tokio::test]
+        async fn should_require_a_pushed_request_when_configured_for_the_client() {
+            let response = get_authorize(under_test!(), &format!("response_type=code&client_id=jackal&redirect_uri={ENCODED_REDIRECT_URI}")).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_none!(response.headers().get(LOCATION));
+
+            let body = extract_text_body(response).await;
+            assert_contains!(body, "missing parameter: request_uri");
+        }
+    }
+
+    mod sign_in_page {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_render_the_sign_in_form_for_a_valid_request() {
+            let response = get_authorize(under_test!(), &format!("response_type=code&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&scope=basic&state=%3Cbadger%3E")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_text_body(response).await;
+            assert_contains!(body, r#"<form method="post" action="/authorize">"#);
+            assert_contains!(body, r#"<input type="hidden" name="client_id" value="aardvark">"#);
+            assert_contains!(body, r#"<input type="hidden" name="state" value="&lt;badger&gt;">"#);
+        }
+
+        #[tokio::test]
+        async fn should_not_redirect_for_an_unknown_client() {
+            let response = get_authorize(under_test!(), &format!("response_type=code&client_id=cicada&redirect_uri={ENCODED_REDIRECT_URI}")).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_none!(response.headers().get(LOCATION));
+
+            let body = extract_text_body(response).await;
+            assert_contains!(body, "invalid parameter: client_id");
+        }
+
+        #[tokio::test]
+        async fn should_not_redirect_to_an_unregistered_redirect_uri() {
+            let response = get_authorize(under_test!(), "response_type=code&client_id=aardvark&redirect_uri=https%3A%2F%2Fevil.example.com").await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_none!(response.headers().get(LOCATION));
+
+            let body = extract_text_body(response).await;
+            assert_contains!(body, "invalid parameter: redirect_uri");
+        }
+
+        #[tokio::test]
+        async fn should_redirect_with_an_error_for_an_unsupported_response_type() {
+            let response = get_authorize(under_test!(), &format!("response_type=token&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&state=badger")).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let location = extract_location(&response);
+            assert_starts_with!(location, REDIRECT_URI);
+
+            let query = extract_query(&location);
+            assert_some_eq_x!(query.get("error"), "unsupported_response_type");
+            assert_some_eq_x!(query.get("state"), "badger");
+        }
+
+        #[tokio::test]
+        async fn should_redirect_with_an_error_for_an_invalid_scope() {
+            let response = get_authorize(under_test!(), &format!("response_type=code&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&scope=cicada")).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let query = extract_query(&extract_location(&response));
+            assert_some_eq_x!(query.get("error"), "invalid_scope");
+            assert_none!(query.get("state"));
+        }
+
+        #[tokio::test]
+        async fn should_redirect_with_an_error_for_an_unregistered_resource() {
+            let response = get_authorize(under_test!(), &format!("response_type=code&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&resource=https%3A%2F%2Fexample.com&state=badger")).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let query = extract_query(&extract_location(&response));
+            assert_some_eq_x!(query.get("error"), "invalid_target");
+            assert_some_eq_x!(query.get("state"), "badger");
+        }
+    }
+
+    mod sign_in {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_redirect_with_a_code_on_valid_credentials() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let router = under_test!(authorization_code_repository.clone());
+
+            let response = post_authorize(router, &format!("response_type=code&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&scope=basic&state=badger&username=aardvark&password=P%4055w0rd")).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let location = extract_location(&response);
+            assert_starts_with!(location, REDIRECT_URI);
+
+            let query = extract_query(&location);
+            assert_some_eq_x!(query.get("state"), "badger");
+
+            let code = assert_ok!(Uuid::parse_str(assert_some!(query.get("code"))));
+            let authorization_code: AuthorizationCode = assert_some!(authorization_code_repository.get_token(code));
+            assert_eq!(authorization_code.client_id, String::from("aardvark").into());
+            assert_eq!(authorization_code.username, String::from("aardvark").into());
+            assert_eq!(authorization_code.redirect_uri, REDIRECT_URI);
+            assert_eq!(authorization_code.state, Some("badger".into()));
+        }
+
+        #[tokio::test]
+        async fn should_record_the_code_challenge_on_the_issued_code() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let router = under_test!(authorization_code_repository.clone(), InMemoryTokenRepository::new(), consented_to("badger", &["basic"]));
+
+            let response = post_authorize(router, &format!("response_type=code&client_id=badger&redirect_uri={ENCODED_REDIRECT_URI}&code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM&code_challenge_method=S256&username=aardvark&password=P%4055w0rd")).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let query = extract_query(&extract_location(&response));
+            let code = assert_ok!(Uuid::parse_str(assert_some!(query.get("code"))));
+            let authorization_code: AuthorizationCode = assert_some!(authorization_code_repository.get_token(code));
+            assert_eq!(authorization_code.code_challenge, Some(crate::pkce::CodeChallenge {
+                challenge: "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".into(),
+                method: crate::pkce::CodeChallengeMethod::S256,
+            }));
+        }
+
+        #[tokio::test]
+        async fn should_record_the_nonce_on_the_issued_code() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let router = under_test!(authorization_code_repository.clone());
+
+            let response = post_authorize(router, &format!("response_type=code&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&scope=openid&nonce=cicada&username=aardvark&password=P%4055w0rd")).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let query = extract_query(&extract_location(&response));
+            let code = assert_ok!(Uuid::parse_str(assert_some!(query.get("code"))));
+            let authorization_code: AuthorizationCode = assert_some!(authorization_code_repository.get_token(code));
+            assert_eq!(authorization_code.nonce.as_deref(), Some("cicada"));
+        }
+
+        #[tokio::test]
+        async fn should_record_the_resource_on_the_issued_code() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let router = under_test!(authorization_code_repository.clone());
+
+            let response = post_authorize(router, &format!("response_type=code&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&scope=basic&resource=https%3A%2F%2Fapi.baconi.co.uk&username=aardvark&password=P%4055w0rd")).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let query = extract_query(&extract_location(&response));
+            let code = assert_ok!(Uuid::parse_str(assert_some!(query.get("code"))));
+            let authorization_code: AuthorizationCode = assert_some!(authorization_code_repository.get_token(code));
+            assert_eq!(authorization_code.resource.as_deref(), Some("https://api.baconi.co.uk"));
+        }
+
+        #[tokio::test]
+        async fn should_show_the_sign_in_form_again_on_invalid_credentials() {
+            let authorization_code_repository = InMemoryTokenRepository::<AuthorizationCode>::new();
+            let router = under_test!(authorization_code_repository.clone());
+
+            let response = post_authorize(router, &format!("response_type=code&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&username=aardvark&password=badger")).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_none!(response.headers().get(LOCATION));
+
+            let body = extract_text_body(response).await;
+            assert_contains!(body, "invalid username or password");
+        }
+
+        #[tokio::test]
+        async fn should_not_redirect_on_an_invalid_request() {
+            let response = post_authorize(under_test!(), "response_type=code&client_id=cicada&username=aardvark&password=P%4055w0rd").await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_none!(response.headers().get(LOCATION));
+        }
+    }
+
+    mod consent {
+        use super::*;
+        use crate::consent::InMemoryConsentRepository;
+        use crate::pkce::{CodeChallenge, CodeChallengeMethod};
+        use crate::scope::Scope;
+        use crate::user::Username;
+        use std::collections::HashSet;
+
+        // Badger is a third-party public client, so must use PKCE and is asked for consent.
+        fn badger_request(scope: &str) -> String {
+            format!("response_type=code&client_id=badger&redirect_uri={ENCODED_REDIRECT_URI}&scope={scope}&state=badger&code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM&code_challenge_method=S256")
+        }
+
+        fn extract_consent_id(body: &str) -> String {
+            let (_, rest) = assert_some!(body.split_once(r#"name="consent_id" value=""#));
+            let (consent_id, _) = assert_some!(rest.split_once('"'));
+            consent_id.to_string()
+        }
+
+        fn find_consent(consent_repository: &InMemoryConsentRepository, client_id: &str) -> Option<Consent> {
+            consent_repository.find_consent(&Username::from(String::from("aardvark")), &crate::client::ClientId::from(String::from(client_id)))
+        }
+
+        async fn sign_in(router: Router, request: &str) -> String {
+            let response = post_authorize(router, &format!("{request}&username=aardvark&password=P%4055w0rd")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            extract_consent_id(&extract_text_body(response).await)
+        }
+
+        #[tokio::test]
+        async fn should_ask_for_consent_to_the_requested_scopes_after_signing_in() {
+            let authorization_code_repository = InMemoryTokenRepository::<AuthorizationCode>::new();
+            let router = under_test!(authorization_code_repository.clone());
+
+            let response = post_authorize(router, &format!("{}&username=aardvark&password=P%4055w0rd", badger_request("basic%20openid"))).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_none!(response.headers().get(LOCATION));
+
+            let body = extract_text_body(response).await;
+            assert_contains!(body, r#"<input type="checkbox" name="consent:basic" checked> Basic access to your account (basic)</label>"#);
+            assert_contains!(body, r#"<input type="checkbox" name="consent:openid" checked> Sign you in (openid)</label>"#);
+            assert_not_contains!(body, r#"name="state""#);
+            assert_not_contains!(body, r#"name="redirect_uri""#);
+            assert_not_contains!(body, "P@55w0rd");
+        }
+
+        #[tokio::test]
+        async fn should_issue_and_remember_only_the_scopes_left_ticked() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let consent_repository = InMemoryConsentRepository::new();
+            let router = under_test!(authorization_code_repository.clone(), InMemoryTokenRepository::new(), consent_repository.clone());
+
+            let consent_id = sign_in(router.clone(), &badger_request("basic%20openid")).await;
+
+            let response = post_authorize(router, &format!("{}&consent_id={consent_id}&consent%3Abasic=on&decision=approve", badger_request("basic%20openid"))).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let query = extract_query(&extract_location(&response));
+            assert_some_eq_x!(query.get("state"), "badger");
+
+            let code = assert_ok!(Uuid::parse_str(assert_some!(query.get("code"))));
+            let authorization_code: AuthorizationCode = assert_some!(authorization_code_repository.get_token(code));
+            assert_eq!(authorization_code.scopes, Scopes(HashSet::from([Scope::from("basic")])));
+            assert_eq!(authorization_code.username, Username::from(String::from("aardvark")));
+
+            let consent = assert_some!(find_consent(&consent_repository, "badger"));
+            assert_eq!(consent.scopes, Scopes(HashSet::from([Scope::from("basic")])));
+            assert_le!(consent.granted_at, chrono::Utc::now());
+        }
+
+        #[tokio::test]
+        async fn should_not_ask_again_for_scopes_already_consented_to() {
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), consented_to("badger", &["basic", "openid"]));
+
+            let response = post_authorize(router, &format!("{}&username=aardvark&password=P%4055w0rd", badger_request("basic"))).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+            assert_ok!(Uuid::parse_str(assert_some!(extract_query(&extract_location(&response)).get("code"))));
+        }
+
+        #[tokio::test]
+        async fn should_only_ask_about_the_scopes_not_consented_to_before() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let consent_repository = consented_to("badger", &["basic"]);
+            let router = under_test!(authorization_code_repository.clone(), InMemoryTokenRepository::new(), consent_repository.clone());
+
+            let response = post_authorize(router.clone(), &format!("{}&username=aardvark&password=P%4055w0rd", badger_request("basic%20openid"))).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_text_body(response).await;
+            assert_contains!(body, r#"name="consent:openid""#);
+            assert_not_contains!(body, r#"name="consent:basic""#);
+
+            let response = post_authorize(router, &format!("{}&consent_id={}&consent%3Aopenid=on&decision=approve", badger_request("basic%20openid"), extract_consent_id(&body))).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let code = assert_ok!(Uuid::parse_str(assert_some!(extract_query(&extract_location(&response)).get("code"))));
+            let authorization_code: AuthorizationCode = assert_some!(authorization_code_repository.get_token(code));
+            assert_eq!(authorization_code.scopes, Scopes(HashSet::from([Scope::from("basic"), Scope::OPENID])));
+
+            let consent = assert_some!(find_consent(&consent_repository, "badger"));
+            assert_eq!(consent.scopes, Scopes(HashSet::from([Scope::from("basic"), Scope::OPENID])));
+        }
+
+        #[tokio::test]
+        async fn should_redirect_with_access_denied_when_the_user_denies() {
+            let consent_repository = InMemoryConsentRepository::new();
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), consent_repository.clone());
+
+            let consent_id = sign_in(router.clone(), &badger_request("basic")).await;
+
+            let response = post_authorize(router, &format!("{}&consent_id={consent_id}&consent%3Abasic=on&decision=deny", badger_request("basic"))).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let query = extract_query(&extract_location(&response));
+            assert_some_eq_x!(query.get("error"), "access_denied");
+            assert_some_eq_x!(query.get("state"), "badger");
+            assert_none!(query.get("code"));
+            assert_none!(find_consent(&consent_repository, "badger"));
+        }
+
+        #[tokio::test]
+        async fn should_redirect_with_access_denied_when_every_scope_is_unticked() {
+            let router = under_test!();
+
+            let consent_id = sign_in(router.clone(), &badger_request("basic")).await;
+
+            let response = post_authorize(router, &format!("{}&consent_id={consent_id}&decision=approve", badger_request("basic"))).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let query = extract_query(&extract_location(&response));
+            assert_some_eq_x!(query.get("error"), "access_denied");
+        }
+
+        #[tokio::test]
+        async fn should_not_issue_scopes_added_to_the_request_after_signing_in() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let router = under_test!(authorization_code_repository.clone());
+
+            let consent_id = sign_in(router.clone(), &badger_request("basic")).await;
+
+            let response = post_authorize(router, &format!("{}&consent_id={consent_id}&consent%3Abasic=on&consent%3Aopenid=on&decision=approve", badger_request("basic%20openid"))).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let code = assert_ok!(Uuid::parse_str(assert_some!(extract_query(&extract_location(&response)).get("code"))));
+            let authorization_code: AuthorizationCode = assert_some!(authorization_code_repository.get_token(code));
+            assert_eq!(authorization_code.scopes, Scopes(HashSet::from([Scope::from("basic")])));
+        }
+
+        #[tokio::test]
+        async fn should_ignore_the_request_sent_back_with_the_consent_form() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let router = under_test!(authorization_code_repository.clone());
+
+            let consent_id = sign_in(router.clone(), &badger_request("basic")).await;
+
+            let tampered = "response_type=code&client_id=badger&redirect_uri=https%3A%2F%2Fattacker.example&scope=basic&state=cicada&nonce=cicada&code_challenge=cicada-cicada-cicada-cicada-cicada-cicada-cicad&code_challenge_method=plain";
+            let response = post_authorize(router, &format!("{tampered}&consent_id={consent_id}&consent%3Abasic=on&decision=approve")).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let location = extract_location(&response);
+            assert_starts_with!(location, REDIRECT_URI);
+            let query = extract_query(&location);
+            assert_some_eq_x!(query.get("state"), "badger");
+
+            let code = assert_ok!(Uuid::parse_str(assert_some!(query.get("code"))));
+            let authorization_code: AuthorizationCode = assert_some!(authorization_code_repository.get_token(code));
+            assert_eq!(authorization_code.redirect_uri, REDIRECT_URI);
+            assert_none!(authorization_code.nonce);
+            assert_eq!(authorization_code.code_challenge, Some(CodeChallenge {
+                challenge: String::from("E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"),
+                method: CodeChallengeMethod::S256,
+            }));
+        }
+
+        #[tokio::test]
+        async fn should_only_accept_a_consent_id_the_once() {
+            let router = under_test!();
+
+            let consent_id = sign_in(router.clone(), &badger_request("basic")).await;
+            let body = format!("{}&consent_id={consent_id}&consent%3Abasic=on&decision=approve", badger_request("basic"));
+
+            let response = post_authorize(router.clone(), &body).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+            let response = post_authorize(router, &body).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_contains!(extract_text_body(response).await, "invalid parameter: consent_id");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_consent_id_for_another_client() {
+            let router = under_test!();
+
+            let consent_id = sign_in(router.clone(), &badger_request("basic")).await;
+
+            let response = post_authorize(router, &format!("response_type=code&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&consent_id={consent_id}&consent%3Abasic=on&decision=approve")).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_contains!(extract_text_body(response).await, "invalid parameter: consent_id");
+        }
+
+        #[tokio::test]
+        async fn should_skip_consent_for_a_first_party_client() {
+            let consent_repository = InMemoryConsentRepository::new();
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), consent_repository.clone());
+
+            let response = post_authorize(router, &format!("response_type=code&client_id=aardvark&redirect_uri={ENCODED_REDIRECT_URI}&scope=openid&username=aardvark&password=P%4055w0rd")).await;
+            assert_eq!(response.status(), StatusCode::SEE_OTHER);
+            assert_none!(find_consent(&consent_repository, "aardvark"));
+        }
+    }
+}
@@ -1,32 +1,41 @@
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use crate::assertion::{unverified_claims, verify_assertion, JwksSource};
 use crate::client::{ClientType, ConfidentialClient, PublicClient};
 use crate::client::configuration::ClientConfigurationRepository;
-use crate::client::secret::ClientSecretRepository;
+use crate::client::secret::{shared_secret_jwks, ClientSecretRepository};
+use crate::replay::ReplayCache;
 
 pub trait ClientAuthenticator: Send + Sync + Clone {
     fn authenticate_as_public_client(&self, client_id: &str) -> Option<PublicClient>;
     fn authenticate_as_confidential_client(&self, client_id: &str, client_secret: &[u8]) -> Option<ConfidentialClient>;
+    // The endpoint is the path the assertion was presented to, which along with the issuer is an acceptable audience.
+    fn authenticate_with_assertion(&self, client_id: Option<&str>, assertion: &str, endpoint: &str) -> Option<ConfidentialClient>;
 }
 
 #[derive(Clone)]
-pub struct ClientAuthenticationService<S: ClientSecretRepository, C: ClientConfigurationRepository> {
+pub struct ClientAuthenticationService<S: ClientSecretRepository, C: ClientConfigurationRepository, J: ReplayCache> {
+    issuer: String,
     secret_repository: S,
     client_configuration_repository: C,
+    replay_cache: J,
 }
 
-impl<S: ClientSecretRepository, C: ClientConfigurationRepository> ClientAuthenticationService<S, C> {
-    pub fn new(secret_repository: S, client_configuration_repository: C) -> Self {
+impl<S: ClientSecretRepository, C: ClientConfigurationRepository, J: ReplayCache> ClientAuthenticationService<S, C, J> {
+    pub fn new(issuer: String, secret_repository: S, client_configuration_repository: C, replay_cache: J) -> Self {
         Self {
+            issuer,
             secret_repository,
             client_configuration_repository,
+            replay_cache,
         }
     }
 }
 
-impl<S, C> ClientAuthenticator for ClientAuthenticationService<S, C>
+impl<S, C, J> ClientAuthenticator for ClientAuthenticationService<S, C, J>
 where
     S: ClientSecretRepository,
     C: ClientConfigurationRepository,
+    J: ReplayCache,
 {
     fn authenticate_as_public_client(&self, client_id: &str) -> Option<PublicClient> {
 
@@ -43,7 +52,9 @@ where
 
         let secrets = self.secret_repository.find_all_by_client_id(client_id);
 
+        // Expired and revoked secrets are skipped without being checked, a retired secret is as good as a wrong one.
         let maybe_secret = secrets.iter()
+            .filter(|secret| secret.is_active())
             .find(|secret| {
                 let hash = match PasswordHash::new(&secret.hashed_secret) {
                     Err(_) => return false,
@@ -64,4 +75,34 @@ where
             _ => None
         }
     }
+
+    // https://www.rfc-editor.org/rfc/rfc7523#section-2.2
+    fn authenticate_with_assertion(&self, client_id: Option<&str>, assertion: &str, endpoint: &str) -> Option<ConfidentialClient> {
+
+        let subject = unverified_claims(assertion)?.sub;
+
+        if client_id.is_some_and(|client_id| client_id != subject) {
+            return None
+        }
+
+        let configuration = match self.client_configuration_repository.find_by_client_id(&subject) {
+            Some(configuration) if configuration.client_type == ClientType::Confidential => configuration,
+            _ => return None,
+        };
+
+        let jwks = match configuration.jwks.as_ref()? {
+            JwksSource::ClientSecret => shared_secret_jwks(&self.secret_repository.find_all_by_client(&configuration.client_id)),
+            jwks => jwks.load()?,
+        };
+        let audiences = [self.issuer.clone(), format!("{}{endpoint}", self.issuer)];
+        // For client authentication both the issuer and subject must be the client.
+        let claims = verify_assertion(&jwks, assertion, &subject, Some(&subject), &audiences)?;
+
+        // An assertion can only ever be used once, it's remembered until it would have expired anyway.
+        if !self.replay_cache.first_use(&format!("{subject}:{}", claims.jti), claims.expires_at()?) {
+            return None
+        }
+
+        Some(ConfidentialClient { configuration })
+    }
 }
@@ -8,7 +8,8 @@ macro_rules! principal {
         }
     ) => {
         $(#[$m])*
-        #[derive(Clone)]
+        #[derive(Clone, Eq, PartialEq)]
+        #[cfg_attr(test, derive(Debug))]
         pub enum $name {
             $(
                 $variant($variant_client)
@@ -16,13 +17,51 @@ macro_rules! principal {
         }
 
         impl $name {
+            pub fn id(&self) -> &crate::client::ClientId {
+                match self {
+                    $($name::$variant(client) => client.id(),)+
+                }
+            }
+
             pub fn can_perform_grant_type(&self, grant_type: &crate::client::GrantType) -> bool {
                 match self {
                     $($name::$variant(client) => client.can_perform_grant_type(grant_type),)+
                 }
             }
+
+            pub fn can_perform_action(&self, action: &crate::client::ClientAction) -> bool {
+                match self {
+                    $($name::$variant(client) => client.can_perform_action(action),)+
+                }
+            }
+
+            pub fn can_be_issued(&self, scope: &crate::scope::Scope) -> bool {
+                match self {
+                    $($name::$variant(client) => client.can_be_issued(scope),)+
+                }
+            }
+
+            pub fn default_scopes(&self) -> crate::scope::Scopes {
+                match self {
+                    $($name::$variant(client) => client.default_scopes(),)+
+                }
+            }
+
+            pub fn can_exchange_for(&self, audience: &str) -> bool {
+                match self {
+                    $($name::$variant(client) => client.can_exchange_for(audience),)+
+                }
+            }
+
+            pub fn access_token_format(&self) -> &crate::client::AccessTokenFormat {
+                match self {
+                    $($name::$variant(client) => client.access_token_format(),)+
+                }
+            }
         }
 
+        disable_deserialization!($name);
+
         $(define_principal! {
             pub struct $variant_client;
         })+
@@ -42,11 +81,12 @@ macro_rules! define_principal {
             configuration: crate::client::configuration::ClientConfiguration,
         }
 
+        #[allow(dead_code)] // Not every principal makes use of every check
         impl $struct_name {
 
-            // pub fn id(&self) -> &crate::client::ClientId {
-            //     &self.configuration.client_id
-            // }
+            pub fn id(&self) -> &crate::client::ClientId {
+                &self.configuration.client_id
+            }
 
             pub fn can_perform_action(&self, action: &crate::client::ClientAction) -> bool {
                 self.configuration.allowed_actions.contains(action)
@@ -57,7 +97,19 @@ macro_rules! define_principal {
             }
 
             pub fn can_be_issued(&self, scope: &crate::scope::Scope) -> bool {
-                self.configuration.allowed_scopes.contains(scope)
+                self.configuration.allowed_scopes.iter().any(|allowed| scope.is_granted_by(allowed))
+            }
+
+            pub fn default_scopes(&self) -> crate::scope::Scopes {
+                crate::scope::Scopes(self.configuration.default_scopes.clone())
+            }
+
+            pub fn can_exchange_for(&self, audience: &str) -> bool {
+                self.configuration.allowed_audiences.contains(audience)
+            }
+
+            pub fn access_token_format(&self) -> &crate::client::AccessTokenFormat {
+                &self.configuration.access_token_format
             }
 
             // pub fn has_redirect_uri(&self, redirect_uri: &str) -> bool {
@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex, MutexGuard};
-use crate::client::{ClientAction, ClientId, ClientType, GrantType};
+use crate::assertion::JwksSource;
+use crate::client::{AccessTokenFormat, ClientAction, ClientId, ClientType, GrantType};
 use crate::scope::Scope;
 
 #[derive(Clone, Eq, PartialEq)]
@@ -10,13 +11,23 @@ pub struct ClientConfiguration {
     pub client_type: ClientType,
     pub redirect_uris: HashSet<String>,
     pub allowed_scopes: HashSet<Scope>,
+    // Issued when the client doesn't ask for any scope, see https://www.rfc-editor.org/rfc/rfc6749#section-3.3
+    pub default_scopes: HashSet<Scope>,
     pub allowed_actions: HashSet<ClientAction>,
     pub allowed_grant_types: HashSet<GrantType>,
+    // Who this client may exchange tokens for, see https://www.rfc-editor.org/rfc/rfc8693#section-2.1
+    pub allowed_audiences: HashSet<String>,
+    // The keys this client signs its assertions with, see https://www.rfc-editor.org/rfc/rfc7523#section-2.2
+    pub jwks: Option<JwksSource>,
+    pub access_token_format: AccessTokenFormat,
 }
 
 pub trait ClientConfigurationRepository: Send + Sync + Clone {
     fn find_by_id(&self, client_id: &ClientId) -> Option<ClientConfiguration>;
     fn find_by_client_id(&self, client_id: &str) -> Option<ClientConfiguration>;
+    // Creates the client, or replaces its configuration if it already exists.
+    fn save(&self, configuration: &ClientConfiguration);
+    fn remove_by_id(&self, client_id: &ClientId) -> Option<ClientConfiguration>;
 }
 
 #[derive(Clone, Default)]
@@ -26,27 +37,77 @@ pub struct InMemoryClientConfigurationRepository {
 
 impl InMemoryClientConfigurationRepository {
     pub fn new() -> Self {
-        Self {
+        let repository = Self {
             store: Arc::new(Mutex::new(HashMap::from([
                 // TODO - Remove once we've got a means of creating new clients
                 Self::create_entry(ClientConfiguration {
                     client_id: ClientId(String::from("aardvark")),
                     client_type: ClientType::Confidential,
+                    redirect_uris: HashSet::from([String::from("https://redirect.baconi.co.uk")]),
+                    allowed_scopes: HashSet::from([Scope::from("basic"), Scope::OPENID, Scope::PROFILE, Scope::EMAIL, Scope::from("accounts:read:*")]),
+                    default_scopes: HashSet::from([Scope::from("basic")]),
+                    allowed_actions: HashSet::from([ClientAction::Introspect, ClientAction::SkipConsent]),
+                    allowed_grant_types: HashSet::from([GrantType::AuthorizationCode, GrantType::ClientCredentials, GrantType::DeviceCode, GrantType::JwtBearer, GrantType::Password, GrantType::RefreshToken, GrantType::TokenExchange]),
+                    allowed_audiences: HashSet::from([String::from("https://api.baconi.co.uk")]),
+                    jwks: None,
+                    access_token_format: AccessTokenFormat::Opaque,
+                }),
+                Self::create_entry(ClientConfiguration {
+                    client_id: ClientId(String::from("badger")),
+                    client_type: ClientType::Public,
+                    redirect_uris: HashSet::from([String::from("https://redirect.baconi.co.uk")]),
+                    allowed_scopes: HashSet::from([Scope::from("basic"), Scope::OPENID, Scope::PROFILE, Scope::EMAIL]),
+                    default_scopes: HashSet::from([Scope::from("basic")]),
+                    allowed_actions: HashSet::from([ClientAction::ProofKeyForCodeExchange]),
+                    allowed_grant_types: HashSet::from([GrantType::AuthorizationCode, GrantType::DeviceCode]),
+                    allowed_audiences: HashSet::from([]),
+                    jwks: None,
+                    access_token_format: AccessTokenFormat::Opaque,
+                }),
+                Self::create_entry(ClientConfiguration {
+                    client_id: ClientId(String::from("dingo")),
+                    client_type: ClientType::Confidential,
                     redirect_uris: HashSet::from([]),
-                    allowed_scopes: HashSet::from([Scope::Basic]),
+                    allowed_scopes: HashSet::from([Scope::from("basic")]),
+                    default_scopes: HashSet::from([Scope::from("basic")]),
                     allowed_actions: HashSet::from([ClientAction::Introspect]),
-                    allowed_grant_types: HashSet::from([GrantType::Password]),
+                    allowed_grant_types: HashSet::from([GrantType::ClientCredentials, GrantType::Password, GrantType::RefreshToken]),
+                    allowed_audiences: HashSet::from([]),
+                    jwks: None,
+                    access_token_format: AccessTokenFormat::Jwt,
                 }),
+                // Can only start an authorization request by pushing it first, see https://www.rfc-editor.org/rfc/rfc9126#section-5
                 Self::create_entry(ClientConfiguration {
-                    client_id: ClientId(String::from("badger")),
+                    client_id: ClientId(String::from("jackal")),
                     client_type: ClientType::Public,
+                    redirect_uris: HashSet::from([String::from("https://redirect.baconi.co.uk")]),
+                    allowed_scopes: HashSet::from([Scope::from("basic")]),
+                    default_scopes: HashSet::from([Scope::from("basic")]),
+                    allowed_actions: HashSet::from([ClientAction::PushedAuthorizationRequest]),
+                    allowed_grant_types: HashSet::from([GrantType::AuthorizationCode]),
+                    allowed_audiences: HashSet::from([]),
+                    jwks: None,
+                    access_token_format: AccessTokenFormat::Opaque,
+                }),
+                // Only manages other clients' secrets, it can't be granted tokens of its own.
+                Self::create_entry(ClientConfiguration {
+                    client_id: ClientId(String::from("koala")),
+                    client_type: ClientType::Confidential,
                     redirect_uris: HashSet::from([]),
-                    allowed_scopes: HashSet::from([Scope::Basic]),
-                    allowed_actions: HashSet::from([]),
+                    allowed_scopes: HashSet::from([]),
+                    default_scopes: HashSet::from([]),
+                    allowed_actions: HashSet::from([ClientAction::ManageClientSecrets]),
                     allowed_grant_types: HashSet::from([]),
+                    allowed_audiences: HashSet::from([]),
+                    jwks: None,
+                    access_token_format: AccessTokenFormat::Opaque,
                 })
             ])))
-        }
+        };
+        // Their keys are committed as test fixtures, so these clients must never exist outside of the tests.
+        #[cfg(test)]
+        test_support::test_clients().iter().for_each(|configuration| repository.save(configuration));
+        repository
     }
     fn create_entry(configuration: ClientConfiguration) -> (ClientId, ClientConfiguration) {
         (configuration.client_id.clone(), configuration)
@@ -63,4 +124,51 @@ impl ClientConfigurationRepository for InMemoryClientConfigurationRepository {
     fn find_by_client_id(&self, client_id: &str) -> Option<ClientConfiguration> {
         self.find_by_id(&ClientId(String::from(client_id)))
     }
+    fn save(&self, configuration: &ClientConfiguration) {
+        self.lock_store().insert(configuration.client_id.clone(), configuration.clone());
+    }
+    fn remove_by_id(&self, client_id: &ClientId) -> Option<ClientConfiguration> {
+        self.lock_store().remove(client_id)
+    }
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+    use crate::assertion::JwksSource;
+    use crate::client::{AccessTokenFormat, ClientAction, ClientId, ClientType, GrantType};
+    use crate::client::configuration::ClientConfiguration;
+    use crate::scope::Scope;
+
+    pub fn test_clients() -> [ClientConfiguration; 2] {
+        [
+            // Authenticates with private_key_jwt, see https://www.rfc-editor.org/rfc/rfc7523#section-2.2
+            ClientConfiguration {
+                client_id: ClientId(String::from("ferret")),
+                client_type: ClientType::Confidential,
+                redirect_uris: HashSet::from([]),
+                allowed_scopes: HashSet::from([Scope::from("basic")]),
+                default_scopes: HashSet::from([Scope::from("basic")]),
+                allowed_actions: HashSet::from([ClientAction::Introspect]),
+                allowed_grant_types: HashSet::from([GrantType::ClientCredentials]),
+                allowed_audiences: HashSet::from([]),
+                jwks: Some(JwksSource::File(PathBuf::from("tests/fixtures/clients/ferret.jwks.json"))),
+                access_token_format: AccessTokenFormat::Opaque,
+            },
+            // Authenticates with client_secret_jwt, signed with one of its client secrets.
+            ClientConfiguration {
+                client_id: ClientId(String::from("gecko")),
+                client_type: ClientType::Confidential,
+                redirect_uris: HashSet::from([]),
+                allowed_scopes: HashSet::from([Scope::from("basic")]),
+                default_scopes: HashSet::from([Scope::from("basic")]),
+                allowed_actions: HashSet::from([]),
+                allowed_grant_types: HashSet::from([GrantType::ClientCredentials]),
+                allowed_audiences: HashSet::from([]),
+                jwks: Some(JwksSource::ClientSecret),
+                access_token_format: AccessTokenFormat::Opaque,
+            },
+        ]
+    }
 }
@@ -1,33 +1,68 @@
-use axum::body::Body;
+use axum::Extension;
+use axum::body::{Body, Bytes};
 use axum::extract::{Request, State};
 use axum::http::StatusCode;
+use axum::http::request::Parts;
 use axum::middleware::Next;
 use axum::response::Response;
 use axum_extra::headers::Authorization;
 use axum_extra::headers::authorization::Basic;
 use axum_extra::TypedHeader;
+use crate::assertion::CLIENT_ASSERTION_TYPE;
 use crate::client::authentication::ClientAuthenticator;
-use crate::client::ClientPrincipal;
+use crate::client::{ClientAction, ClientAuthenticationMethod, ClientPrincipal, ConfidentialClient};
+
+// What require_confidential_client_authentication accepts.
+pub const CONFIDENTIAL_CLIENT_AUTHENTICATION_METHODS: &[ClientAuthenticationMethod] = &[
+    ClientAuthenticationMethod::ClientSecretBasic,
+    ClientAuthenticationMethod::ClientSecretJwt,
+    ClientAuthenticationMethod::PrivateKeyJwt,
+];
+
+// What require_client_authentication accepts.
+pub const CLIENT_AUTHENTICATION_METHODS: &[ClientAuthenticationMethod] = &[
+    ClientAuthenticationMethod::ClientSecretBasic,
+    ClientAuthenticationMethod::ClientSecretJwt,
+    ClientAuthenticationMethod::PrivateKeyJwt,
+    ClientAuthenticationMethod::None,
+];
 
 pub async fn require_confidential_client_authentication<C: ClientAuthenticator>(
     State(authenticator): State<C>,
     maybe_basic_auth: Option<TypedHeader<Authorization<Basic>>>,
-    mut request: Request,
+    request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
 
-    let client = match maybe_basic_auth {
-        None => return Err(StatusCode::UNAUTHORIZED),
-        Some(TypedHeader(Authorization(basic))) => {
+    let (parts, body_bytes) = buffer_body(request).await?;
+    let maybe_client_id = find_parameter(&body_bytes, "client_id");
+    let maybe_client_assertion = find_client_assertion(&body_bytes)?;
+
+    let client = match (maybe_basic_auth, maybe_client_id, maybe_client_assertion) {
+
+        // Confidential client via a client assertion, with an optional client_id that must match it.
+        (None, maybe_client_id, Some(client_assertion)) => {
+            authenticator.authenticate_with_assertion(maybe_client_id.as_deref(), &client_assertion, parts.uri.path())
+        },
+
+        // Confidential client via Basic auth
+        (Some(TypedHeader(Authorization(basic))), None, None) => {
             authenticator.authenticate_as_confidential_client(basic.username(), basic.password().as_bytes())
                 //.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-                .ok_or(StatusCode::UNAUTHORIZED)?
         },
-    };
 
-    request.extensions_mut().insert(client);
+        // Neither, or more than one → reject per RFC 6749 §2.3
+        _ => return Err(StatusCode::UNAUTHORIZED),
+    };
 
-    Ok(next.run(request).await)
+    match client {
+        None => Err(StatusCode::UNAUTHORIZED),
+        Some(client) => {
+            let mut new_request = Request::from_parts(parts, Body::from(body_bytes));
+            new_request.extensions_mut().insert(client);
+            Ok(next.run(new_request).await)
+        }
+    }
 }
 
 pub async fn require_client_authentication<C: ClientAuthenticator>(
@@ -37,35 +72,33 @@ pub async fn require_client_authentication<C: ClientAuthenticator>(
     next: Next,
 ) -> Result<Response, StatusCode> {
 
-    // Split the request into parts so we can rebuild it later.
-    let (parts, body) = request.into_parts();
+    let (parts, body_bytes) = buffer_body(request).await?;
+    let maybe_client_id = find_parameter(&body_bytes, "client_id");
+    let maybe_client_assertion = find_client_assertion(&body_bytes)?;
 
-    // Buffer the body to peek at client_id
-    let body_bytes = axum::body::to_bytes(body, usize::MAX)
-        .await
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let principal = match (maybe_basic_auth, maybe_client_id, maybe_client_assertion) {
 
-    // Look for client_id in the body.
-    let maybe_client_id = form_urlencoded::parse(&body_bytes)
-        .find(|(k, _)| k == "client_id")
-        .map(|(_, v)| v.into_owned());
+        // Confidential client via a client assertion, with an optional client_id that must match it.
+        (None, maybe_client_id, Some(client_assertion)) => {
+            authenticator.authenticate_with_assertion(maybe_client_id.as_deref(), &client_assertion, parts.uri.path())
+                .map(ClientPrincipal::Confidential)
+        },
 
-    let principal = match (maybe_basic_auth, maybe_client_id) {
-        // Both are present → reject per RFC 6749 §2.3
-        (Some(_), Some(_)) => return Err(StatusCode::UNAUTHORIZED),
+        // Basic auth with anything else → reject per RFC 6749 §2.3
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) => return Err(StatusCode::UNAUTHORIZED),
 
         // Neither is present → reject
-        (None, None) => return Err(StatusCode::UNAUTHORIZED),
+        (None, None, None) => return Err(StatusCode::UNAUTHORIZED),
 
         // Confidential client via Basic auth
-        (Some(TypedHeader(Authorization(basic))), None) => {
+        (Some(TypedHeader(Authorization(basic))), None, None) => {
             authenticator.authenticate_as_confidential_client(basic.username(), basic.password().as_bytes())
                 //.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
                 .map(ClientPrincipal::Confidential)
         },
 
         // Public client via body client_id
-        (None, Some(client_id)) => {
+        (None, Some(client_id), None) => {
             authenticator.authenticate_as_public_client(&client_id)
                 //.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
                 .map(ClientPrincipal::Public)
@@ -81,3 +114,43 @@ pub async fn require_client_authentication<C: ClientAuthenticator>(
         }
     }
 }
+
+pub async fn require_confidential_client_action(
+    Extension(client): Extension<ConfidentialClient>,
+    State(action): State<ClientAction>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if client.can_perform_action(&action) {
+        Ok(next.run(request).await)
+    } else {
+        Err(StatusCode::FORBIDDEN) // TODO - Return { error: "unauthorized_client", error_description: "Client is not authorized to perform this action" }
+    }
+}
+
+// Split the request into parts and buffer the body, so we can peek at it and rebuild the request later.
+async fn buffer_body(request: Request) -> Result<(Parts, Bytes), StatusCode> {
+    let (parts, body) = request.into_parts();
+
+    let body_bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    Ok((parts, body_bytes))
+}
+
+fn find_parameter(body_bytes: &[u8], name: &str) -> Option<String> {
+    form_urlencoded::parse(body_bytes)
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+// Both parameters are required together, and we only understand JWT assertions.
+// https://www.rfc-editor.org/rfc/rfc7521#section-4.2
+fn find_client_assertion(body_bytes: &[u8]) -> Result<Option<String>, StatusCode> {
+    match (find_parameter(body_bytes, "client_assertion_type"), find_parameter(body_bytes, "client_assertion")) {
+        (None, None) => Ok(None),
+        (Some(client_assertion_type), Some(client_assertion)) if client_assertion_type == CLIENT_ASSERTION_TYPE => Ok(Some(client_assertion)),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
@@ -1,5 +1,6 @@
 #[macro_use]
 mod client_principal;
+pub mod registration;
 pub mod secret;
 pub mod authentication;
 pub mod configuration;
@@ -27,15 +28,47 @@ pub enum ClientType {
 pub enum ClientAction {
     // Authorize,
     Introspect,
-    // ProofKeyForCodeExchange,
+    // Operators, who may manage the secrets of any client, including those configured rather than registered.
+    ManageClientSecrets,
+    // Require a code_challenge on every authorization request, see RFC 7636.
+    ProofKeyForCodeExchange,
+    // Require every authorization request to be pushed before the user is sent to us, see RFC 9126.
+    PushedAuthorizationRequest,
+    // Trusted first-party clients, the resource owner isn't asked to consent to what they request.
+    SkipConsent,
+}
+
+// https://www.rfc-editor.org/rfc/rfc9068
+#[derive(Default, Eq, PartialEq, Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub enum AccessTokenFormat {
+    #[default]
+    Opaque,
+    Jwt,
+}
+
+// https://www.rfc-editor.org/rfc/rfc7591#section-2
+enum_with_from_str! {
+    #[derive(Eq, PartialEq, Clone)]
+    #[cfg_attr(test, derive(Debug))]
+    pub enum ClientAuthenticationMethod {
+        ClientSecretBasic: "client_secret_basic",
+        ClientSecretJwt: "client_secret_jwt",
+        PrivateKeyJwt: "private_key_jwt",
+        None: "none",
+    }
 }
 
 enum_with_from_str! {
     #[derive(Debug, Hash, Eq, PartialEq, Clone)]
     pub enum GrantType {
-        // AuthorizationCode: "authorization_code",
+        AuthorizationCode: "authorization_code",
+        ClientCredentials: "client_credentials",
+        DeviceCode: "urn:ietf:params:oauth:grant-type:device_code",
+        JwtBearer: "urn:ietf:params:oauth:grant-type:jwt-bearer",
         Password: "password",
-        // RefreshToken: "refresh_token",
+        RefreshToken: "refresh_token",
+        TokenExchange: "urn:ietf:params:oauth:grant-type:token-exchange",
     }
 }
 
@@ -85,9 +118,13 @@ pub mod test_support {
                 client_id: ClientId(client_id.into()),
                 client_type,
                 redirect_uris: Default::default(),
-                allowed_scopes: HashSet::from([Scope::Basic, Scope::Read, Scope::Write]),
+                allowed_scopes: HashSet::from([Scope::from("basic"), Scope::from("read"), Scope::from("write"), Scope::from("accounts:read:*")]),
+                default_scopes: HashSet::from([Scope::from("basic")]),
                 allowed_actions: Default::default(),
-                allowed_grant_types: HashSet::from([GrantType::Password]),
+                allowed_grant_types: HashSet::from([GrantType::AuthorizationCode, GrantType::ClientCredentials, GrantType::DeviceCode, GrantType::JwtBearer, GrantType::Password, GrantType::RefreshToken, GrantType::TokenExchange]),
+                allowed_audiences: HashSet::from([String::from("https://api.baconi.co.uk")]),
+                jwks: Default::default(),
+                access_token_format: Default::default(),
             }
         }
     }
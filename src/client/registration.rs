@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use sha2::{Digest, Sha256};
+use crate::client::ClientId;
+
+// Lets whoever registered a client read, update and delete it, see https://www.rfc-editor.org/rfc/rfc7592#section-1.2
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct RegistrationAccessToken {
+    pub client_id: ClientId,
+    pub hashed_token: String,
+}
+
+impl RegistrationAccessToken {
+
+    // Generated by us with plenty of entropy, so a fast hash is enough to keep it from being read back.
+    pub fn new(client_id: ClientId, token: &str) -> Self {
+        Self {
+            client_id,
+            hashed_token: hash_token(token),
+        }
+    }
+
+    pub fn matches(&self, token: &str) -> bool {
+        self.hashed_token == hash_token(token)
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+pub trait RegistrationAccessTokenRepository: Send + Sync + Clone {
+    fn find_by_client(&self, client_id: &ClientId) -> Option<RegistrationAccessToken>;
+    // Each client only ever has the one, saving another replaces it.
+    fn save(&self, registration_access_token: &RegistrationAccessToken);
+    fn remove_by_client(&self, client_id: &ClientId);
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryRegistrationAccessTokenRepository {
+    store: Arc<Mutex<HashMap<ClientId, RegistrationAccessToken>>>,
+}
+
+impl InMemoryRegistrationAccessTokenRepository {
+    pub fn new() -> Self {
+        Self { store: Arc::new(Mutex::new(HashMap::new())) }
+    }
+    fn lock_store(&self) -> MutexGuard<'_, HashMap<ClientId, RegistrationAccessToken>> {
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl RegistrationAccessTokenRepository for InMemoryRegistrationAccessTokenRepository {
+    fn find_by_client(&self, client_id: &ClientId) -> Option<RegistrationAccessToken> {
+        self.lock_store().get(client_id).cloned()
+    }
+    fn save(&self, registration_access_token: &RegistrationAccessToken) {
+        self.lock_store().insert(registration_access_token.client_id.clone(), registration_access_token.clone());
+    }
+    fn remove_by_client(&self, client_id: &ClientId) {
+        self.lock_store().remove(client_id);
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+
+    #[test]
+    fn should_only_match_the_token_it_was_created_with() {
+        let registration_access_token = RegistrationAccessToken::new(ClientId(String::from("aardvark")), "badger");
+
+        assert_not_contains!(registration_access_token.hashed_token, "badger");
+        assert!(registration_access_token.matches("badger"));
+        assert!(!registration_access_token.matches("cicada"));
+    }
+}
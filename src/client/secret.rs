@@ -1,20 +1,98 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, MutexGuard};
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHasher, SaltString};
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::jwk::{AlgorithmParameters, CommonParameters, Jwk, JwkSet, KeyAlgorithm, OctetKeyParameters, OctetKeyType};
 use uuid::Uuid;
 use crate::client::ClientId;
 use crate::util::value_struct::ValueStruct;
 
 #[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
 pub struct ClientSecret {
     pub id: Uuid,
     pub client_id: ClientId,
     pub hashed_secret: String,
+    pub created_at: DateTime<Utc>,
+    // A client can hold several secrets at once, so a new one can be rolled out before the old one is retired.
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    // Kept in the clear only for client_secret_jwt, as the client signs its assertions with the secret itself.
+    // https://www.rfc-editor.org/rfc/rfc7523#section-2.2
+    pub shared_key: Option<Vec<u8>>,
+}
+
+impl ClientSecret {
+    // Only the hash is kept, so the plain secret can only ever be shown the once it's generated.
+    pub fn new(client_id: ClientId, client_secret: &[u8], expires_at: Option<DateTime<Utc>>) -> Result<Self, argon2::password_hash::Error> {
+        let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())?;
+        let hashed_secret = Argon2::default().hash_password(client_secret, &salt)?.to_string();
+        Ok(Self {
+            id: Uuid::new_v4(),
+            client_id,
+            hashed_secret,
+            created_at: Utc::now(),
+            expires_at,
+            revoked_at: None,
+            shared_key: None,
+        })
+    }
+
+    // For a client authenticating with client_secret_jwt, which can't verify an assertion against a hash.
+    pub fn new_shared(client_id: ClientId, client_secret: &[u8], expires_at: Option<DateTime<Utc>>) -> Result<Self, argon2::password_hash::Error> {
+        Ok(Self {
+            shared_key: Some(client_secret.to_vec()),
+            ..Self::new(client_id, client_secret, expires_at)?
+        })
+    }
+
+    // Only active secrets can be used to authenticate.
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > Utc::now())
+    }
+
+    pub fn revoke(&mut self) {
+        self.revoked_at.get_or_insert_with(Utc::now);
+    }
+}
+
+// 256 bits straight from the operating system's CSPRNG.
+pub fn generate_secret() -> Result<String, getrandom::Error> {
+    let mut random = [0u8; 32];
+    getrandom::fill(&mut random)?;
+    Ok(BASE64_URL_SAFE_NO_PAD.encode(random))
+}
+
+// The keys a client_secret_jwt may be signed with, one for each of the client's active shared secrets.
+pub fn shared_secret_jwks(secrets: &[ClientSecret]) -> JwkSet {
+    JwkSet {
+        keys: secrets.iter()
+            .filter(|secret| secret.is_active())
+            .filter_map(|secret| secret.shared_key.as_ref().map(|shared_key| Jwk {
+                common: CommonParameters {
+                    key_id: Some(secret.id.to_string()),
+                    key_algorithm: Some(KeyAlgorithm::HS256),
+                    ..Default::default()
+                },
+                algorithm: AlgorithmParameters::OctetKey(OctetKeyParameters {
+                    key_type: OctetKeyType::Octet,
+                    value: BASE64_URL_SAFE_NO_PAD.encode(shared_key),
+                }),
+            }))
+            .collect(),
+    }
 }
 
 pub trait ClientSecretRepository: Send + Sync + Clone {
     fn find_by_id(&self, id: &Uuid) -> Option<ClientSecret>;
     fn find_all_by_client(&self, client_id: &ClientId) -> Vec<ClientSecret>;
     fn find_all_by_client_id(&self, client_id: &str) -> Vec<ClientSecret>;
+    // Creates the secret, or replaces it if it already exists.
+    fn save(&self, secret: &ClientSecret);
+    fn remove_all_by_client(&self, client_id: &ClientId);
 }
 
 #[derive(Clone, Default)]
@@ -24,11 +102,19 @@ pub struct InMemoryClientSecretRepository {
 
 impl InMemoryClientSecretRepository {
     pub fn new() -> Self {
-        Self {
+        let repository = Self {
             store: Arc::new(Mutex::new(HashMap::from([
                 Self::create_hashed_entry("aardvark", b"badger"),
+                Self::create_hashed_entry("dingo", b"echidna"),
             ])))
-        }
+        };
+        // The secret is committed as a test fixture, so must never be usable outside of the tests.
+        #[cfg(test)]
+        repository.save(&test_support::new_gecko_secret());
+        // Whoever holds the operator's secret can impersonate any client, so it's only ever provisioned where it's deployed.
+        #[cfg(test)]
+        repository.save(&test_support::new_koala_secret());
+        repository
     }
 
     // TODO - Remove once we've got a means of creating new clients
@@ -52,7 +138,11 @@ impl InMemoryClientSecretRepository {
         (client_secret_id, ClientSecret {
             id: client_secret_id,
             client_id: ClientId(String::from(client_id)),
-            hashed_secret: hashed
+            hashed_secret: hashed,
+            created_at: Utc::now(),
+            expires_at: None,
+            revoked_at: None,
+            shared_key: None,
         })
     }
 
@@ -71,4 +161,116 @@ impl ClientSecretRepository for InMemoryClientSecretRepository {
     fn find_all_by_client_id(&self, client_id: &str) -> Vec<ClientSecret> {
         self.lock_store().values().filter(|secret| secret.client_id.value() == client_id).cloned().collect()
     }
+    fn save(&self, secret: &ClientSecret) {
+        self.lock_store().insert(secret.id, secret.clone());
+    }
+    fn remove_all_by_client(&self, client_id: &ClientId) {
+        self.lock_store().retain(|_, secret| &secret.client_id != client_id)
+    }
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use assertables::*;
+    use crate::assertion::test_support::TEST_GECKO_SECRET;
+    use crate::client::ClientId;
+    use crate::client::secret::ClientSecret;
+
+    pub const TEST_KOALA_SECRET: &str = "wombat";
+
+    pub fn new_gecko_secret() -> ClientSecret {
+        assert_ok!(ClientSecret::new_shared(ClientId(String::from("gecko")), TEST_GECKO_SECRET, None))
+    }
+
+    pub fn new_koala_secret() -> ClientSecret {
+        assert_ok!(ClientSecret::new(ClientId(String::from("koala")), TEST_KOALA_SECRET.as_bytes(), None))
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use argon2::{PasswordHash, PasswordVerifier};
+    use chrono::TimeDelta;
+
+    #[test]
+    fn should_generate_a_different_secret_every_time() {
+        let secret = assert_ok!(generate_secret());
+
+        assert_eq!(secret.len(), 43);
+        assert_ne!(secret, assert_ok!(generate_secret()));
+    }
+
+    #[test]
+    fn should_only_keep_a_salted_hash_of_the_secret() {
+        let first = assert_ok!(ClientSecret::new(ClientId(String::from("aardvark")), b"badger", None));
+        let second = assert_ok!(ClientSecret::new(ClientId(String::from("aardvark")), b"badger", None));
+
+        assert_not_contains!(first.hashed_secret, "badger");
+        assert_ne!(first.hashed_secret, second.hashed_secret);
+        assert_ok!(Argon2::default().verify_password(b"badger", &assert_ok!(PasswordHash::new(&first.hashed_secret))));
+    }
+
+    #[test]
+    fn should_only_be_active_until_it_expires_or_is_revoked() {
+        let mut secret = assert_ok!(ClientSecret::new(ClientId(String::from("aardvark")), b"badger", None));
+        assert!(secret.is_active());
+
+        secret.expires_at = Some(Utc::now() + TimeDelta::minutes(1));
+        assert!(secret.is_active());
+
+        secret.expires_at = Some(Utc::now() - TimeDelta::minutes(1));
+        assert!(!secret.is_active());
+
+        secret.expires_at = None;
+        secret.revoke();
+        assert!(!secret.is_active());
+    }
+
+    #[test]
+    fn should_keep_when_it_was_first_revoked() {
+        let mut secret = assert_ok!(ClientSecret::new(ClientId(String::from("aardvark")), b"badger", None));
+        secret.revoke();
+        let revoked_at = secret.revoked_at;
+
+        secret.revoke();
+
+        assert_some!(revoked_at);
+        assert_eq!(secret.revoked_at, revoked_at);
+    }
+
+    #[test]
+    fn should_remove_every_secret_for_the_client() {
+        let repository = InMemoryClientSecretRepository::new();
+        repository.save(&assert_ok!(ClientSecret::new(ClientId(String::from("aardvark")), b"cicada", None)));
+
+        repository.remove_all_by_client(&ClientId(String::from("aardvark")));
+
+        assert_is_empty!(repository.find_all_by_client_id("aardvark"));
+        assert_not_empty!(repository.find_all_by_client_id("dingo"));
+    }
+
+    #[test]
+    fn should_only_keep_the_secret_in_the_clear_for_client_secret_jwt() {
+        let hashed = assert_ok!(ClientSecret::new(ClientId(String::from("gecko")), b"hedgehog", None));
+        let shared = assert_ok!(ClientSecret::new_shared(ClientId(String::from("gecko")), b"hedgehog", None));
+
+        assert_none!(hashed.shared_key);
+        assert_some_eq_x!(&shared.shared_key, &b"hedgehog".to_vec());
+    }
+
+    #[test]
+    fn should_only_publish_the_active_shared_secrets_as_keys() {
+        let hashed = assert_ok!(ClientSecret::new(ClientId(String::from("gecko")), b"hedgehog", None));
+        let shared = assert_ok!(ClientSecret::new_shared(ClientId(String::from("gecko")), b"hedgehog", None));
+        let mut revoked = assert_ok!(ClientSecret::new_shared(ClientId(String::from("gecko")), b"iguana", None));
+        revoked.revoke();
+
+        let jwks = shared_secret_jwks(&[hashed, shared.clone(), revoked]);
+
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(jwks.keys[0].common.key_id, Some(shared.id.to_string()));
+    }
 }
@@ -0,0 +1,7 @@
+mod route;
+mod operator;
+mod request;
+mod response;
+
+pub use route::*;
+pub use operator::*;
@@ -0,0 +1,213 @@
+use axum::extract::{Path, State};
+use axum::extract::rejection::JsonRejection;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get};
+use axum::{middleware, Json, Router};
+use middleware::from_fn_with_state;
+use tower::ServiceBuilder;
+use crate::client::{ClientAction, ClientId};
+use crate::client::authentication::ClientAuthenticator;
+use crate::client::configuration::{ClientConfiguration, ClientConfigurationRepository};
+use crate::client::middleware::{require_confidential_client_action, require_confidential_client_authentication};
+use crate::client::secret::ClientSecretRepository;
+use crate::client_registration::request::ClientSecretRequest;
+use crate::client_registration::route::{issue_secret, list_secrets, retire_secret};
+
+pub const OPERATOR_CLIENT_SECRETS_ENDPOINT: &str = "/clients/{client_id}/secrets";
+pub const OPERATOR_CLIENT_SECRET_ENDPOINT: &str = "/clients/{client_id}/secrets/{secret_id}";
+
+// The same secret management as a registered client has, but for an operator authenticating as a client allowed to
+// manage the secrets of others, so that the clients we configure ourselves can have their secrets rotated too.
+pub fn operator_route<C, S, A>(state: ClientOperatorState<C, S, A>) -> Router<()>
+where
+    C: ClientConfigurationRepository + 'static,
+    S: ClientSecretRepository + 'static,
+    A: ClientAuthenticator + 'static,
+{
+    Router::new()
+        .route(OPERATOR_CLIENT_SECRETS_ENDPOINT, get(list_secrets_handler).post(issue_secret_handler))
+        .route(OPERATOR_CLIENT_SECRET_ENDPOINT, delete(retire_secret_handler))
+        .route_layer(
+            ServiceBuilder::new()
+                .layer(from_fn_with_state(state.client_authenticator.clone(), require_confidential_client_authentication::<A>))
+                .layer(from_fn_with_state(ClientAction::ManageClientSecrets, require_confidential_client_action))
+        )
+        .with_state(state)
+}
+
+#[derive(Clone)]
+pub struct ClientOperatorState<C: ClientConfigurationRepository, S: ClientSecretRepository, A: ClientAuthenticator> {
+    pub client_configuration_repository: C,
+    pub client_secret_repository: S,
+    pub client_authenticator: A,
+}
+
+impl<C: ClientConfigurationRepository, S: ClientSecretRepository, A: ClientAuthenticator> ClientOperatorState<C, S, A> {
+    fn find_client(&self, client_id: String) -> Option<ClientConfiguration> {
+        self.client_configuration_repository.find_by_id(&ClientId::from(client_id))
+    }
+}
+
+async fn list_secrets_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, A: ClientAuthenticator>(
+    State(state): State<ClientOperatorState<C, S, A>>,
+    Path(client_id): Path<String>,
+) -> Response {
+
+    match state.find_client(client_id) {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(configuration) => list_secrets(&state.client_secret_repository, &configuration),
+    }
+}
+
+async fn issue_secret_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, A: ClientAuthenticator>(
+    State(state): State<ClientOperatorState<C, S, A>>,
+    Path(client_id): Path<String>,
+    maybe_request: Result<Option<Json<ClientSecretRequest>>, JsonRejection>,
+) -> Response {
+
+    match state.find_client(client_id) {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(configuration) => issue_secret(&state.client_secret_repository, configuration, maybe_request),
+    }
+}
+
+async fn retire_secret_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, A: ClientAuthenticator>(
+    State(state): State<ClientOperatorState<C, S, A>>,
+    Path((client_id, secret_id)): Path<(String, String)>,
+) -> Response {
+
+    match state.find_client(client_id) {
+        None => StatusCode::NOT_FOUND.into_response(),
+        Some(configuration) => retire_secret(&state.client_secret_repository, &configuration, &secret_id),
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+
+    use super::*;
+
+    use assertables::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use axum::http::header::AUTHORIZATION;
+    use base64::prelude::*;
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use crate::client::authentication::ClientAuthenticationService;
+    use crate::client::configuration::InMemoryClientConfigurationRepository;
+    use crate::client::secret::InMemoryClientSecretRepository;
+    use crate::client::secret::test_support::TEST_KOALA_SECRET;
+    use crate::replay::InMemoryReplayCache;
+
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
+
+    #[derive(Clone)]
+    struct Repositories {
+        client_configuration_repository: InMemoryClientConfigurationRepository,
+        client_secret_repository: InMemoryClientSecretRepository,
+    }
+
+    impl Repositories {
+        fn new() -> Self {
+            Self {
+                client_configuration_repository: InMemoryClientConfigurationRepository::new(),
+                client_secret_repository: InMemoryClientSecretRepository::new(),
+            }
+        }
+
+        fn client_authenticator(&self) -> ClientAuthenticationService<InMemoryClientSecretRepository, InMemoryClientConfigurationRepository, InMemoryReplayCache> {
+            ClientAuthenticationService::new(
+                TEST_ISSUER.into(),
+                self.client_secret_repository.clone(),
+                self.client_configuration_repository.clone(),
+                InMemoryReplayCache::new(),
+            )
+        }
+
+        fn router(&self) -> Router {
+            operator_route(ClientOperatorState {
+                client_configuration_repository: self.client_configuration_repository.clone(),
+                client_secret_repository: self.client_secret_repository.clone(),
+                client_authenticator: self.client_authenticator(),
+            })
+        }
+    }
+
+    fn basic_auth(username: &str, password: &str) -> String {
+        format!("Basic {}", BASE64_STANDARD.encode(format!("{username}:{password}")))
+    }
+
+    async fn send(router: Router, method: Method, uri: &str, operator: (&str, &str)) -> Response {
+        let request = assert_ok!(Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(AUTHORIZATION, basic_auth(operator.0, operator.1))
+            .body(Body::empty()));
+        assert_ok!(router.oneshot(request).await)
+    }
+
+    async fn extract_json_body(response: Response) -> Value {
+        let body_bytes = assert_ok!(response.into_body().collect().await);
+        assert_ok!(serde_json::from_slice(body_bytes.to_bytes().as_ref()))
+    }
+
+    #[tokio::test]
+    async fn should_rotate_the_secret_of_a_configured_client() {
+        let repositories = Repositories::new();
+        let authenticator = repositories.client_authenticator();
+
+        let response = send(repositories.router(), Method::POST, "/clients/dingo/secrets", ("koala", TEST_KOALA_SECRET)).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let issued = extract_json_body(response).await;
+        let new_secret = assert_some!(issued["client_secret"].as_str());
+        assert_some!(authenticator.authenticate_as_confidential_client("dingo", b"echidna"));
+        assert_some!(authenticator.authenticate_as_confidential_client("dingo", new_secret.as_bytes()));
+
+        let response = send(repositories.router(), Method::GET, "/clients/dingo/secrets", ("koala", TEST_KOALA_SECRET)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let listed = extract_json_body(response).await;
+        let secrets = assert_some!(listed["client_secrets"].as_array());
+        assert_eq!(secrets.len(), 2);
+
+        let old_secret_id = assert_some!(assert_some!(secrets.iter().find(|secret| secret["id"] != issued["id"]))["id"].as_str());
+
+        let response = send(repositories.router(), Method::DELETE, &format!("/clients/dingo/secrets/{old_secret_id}"), ("koala", TEST_KOALA_SECRET)).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        assert_none!(authenticator.authenticate_as_confidential_client("dingo", b"echidna"));
+        assert_some!(authenticator.authenticate_as_confidential_client("dingo", new_secret.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn should_forbid_clients_that_are_not_operators() {
+        let response = send(Repositories::new().router(), Method::POST, "/clients/aardvark/secrets", ("dingo", "echidna")).await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn should_forbid_the_demo_client_from_minting_another_clients_secret() {
+        let response = send(Repositories::new().router(), Method::POST, "/clients/dingo/secrets", ("aardvark", "badger")).await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn should_reject_an_unauthenticated_operator() {
+        let response = send(Repositories::new().router(), Method::GET, "/clients/dingo/secrets", ("koala", "cicada")).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn should_return_not_found_for_an_unknown_client() {
+        let response = send(Repositories::new().router(), Method::GET, "/clients/cicada/secrets", ("koala", TEST_KOALA_SECRET)).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
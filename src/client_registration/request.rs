@@ -0,0 +1,398 @@
+use std::collections::HashSet;
+use axum::extract::{FromRequest, Request};
+use axum::extract::rejection::JsonRejection;
+use axum::Json;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+use serde::Deserialize;
+use serde_json::Value;
+use crate::assertion::JwksSource;
+use crate::authorization::ResponseType;
+use crate::client::{AccessTokenFormat, ClientAction, ClientAuthenticationMethod, ClientId, ClientType, GrantType};
+use crate::client::configuration::ClientConfiguration;
+use crate::client_registration::response::{ClientRegistrationResponse, ErrorType};
+use crate::scope::Scopes;
+use crate::scope::parser::parse_scopes;
+use crate::scope::repository::{ScopeRepository, ScopeRepositoryState};
+
+// How a registered client may authenticate.
+pub const REGISTRABLE_AUTHENTICATION_METHODS: &[ClientAuthenticationMethod] = &[
+    ClientAuthenticationMethod::ClientSecretBasic,
+    ClientAuthenticationMethod::ClientSecretJwt,
+    ClientAuthenticationMethod::PrivateKeyJwt,
+    ClientAuthenticationMethod::None,
+];
+
+// The private halves of RSA, EC and OKP keys, and the secret of a symmetric key.
+// https://www.rfc-editor.org/rfc/rfc7518#section-6
+const PRIVATE_KEY_PARAMETERS: &[&str] = &["d", "p", "q", "dp", "dq", "qi", "oth", "k"];
+
+// What a registered client may ask for, the others act on behalf of users without asking them so must be configured by us.
+pub const REGISTRABLE_GRANT_TYPES: &[GrantType] = &[
+    GrantType::AuthorizationCode,
+    GrantType::ClientCredentials,
+    GrantType::DeviceCode,
+    GrantType::RefreshToken,
+];
+
+// https://www.rfc-editor.org/rfc/rfc7591#section-2
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Debug))]
+pub struct ClientMetadata {
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    pub token_endpoint_auth_method: Option<String>,
+    pub grant_types: Option<Vec<String>>,
+    pub response_types: Option<Vec<String>>,
+    pub scope: Option<String>,
+    // Kept as sent until validated, parsing it as a JwkSet would silently drop any private key parameters.
+    pub jwks: Option<Value>,
+    // Only sent when updating a client, see https://www.rfc-editor.org/rfc/rfc7592#section-2.2
+    pub client_id: Option<String>,
+}
+
+// Asks for another secret, which only expires if asked to.
+#[derive(Deserialize, Default)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(deny_unknown_fields)]
+pub struct ClientSecretRequest {
+    // Seconds until the secret expires.
+    pub expires_in: Option<u32>,
+}
+
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct ClientRegistrationRequest {
+    pub client_id: Option<String>,
+    pub token_endpoint_auth_method: ClientAuthenticationMethod,
+    pub redirect_uris: HashSet<String>,
+    pub grant_types: HashSet<GrantType>,
+    pub scopes: Scopes,
+    pub default_scopes: Scopes,
+    pub jwks: Option<JwkSet>,
+}
+
+impl ClientRegistrationRequest {
+
+    pub fn into_configuration(self, client_id: ClientId) -> ClientConfiguration {
+
+        let client_type = match self.token_endpoint_auth_method {
+            ClientAuthenticationMethod::None => ClientType::Public,
+            _ => ClientType::Confidential,
+        };
+
+        // Public clients can't keep a secret, so can only protect their authorization codes with PKCE.
+        let allowed_actions = match client_type {
+            ClientType::Public => HashSet::from([ClientAction::ProofKeyForCodeExchange]),
+            ClientType::Confidential => HashSet::new(),
+        };
+
+        // A client_secret_jwt is signed with one of the client's secrets, rather than keys of its own.
+        let jwks = match self.token_endpoint_auth_method {
+            ClientAuthenticationMethod::ClientSecretJwt => Some(JwksSource::ClientSecret),
+            _ => self.jwks.map(JwksSource::Inline),
+        };
+
+        ClientConfiguration {
+            client_id,
+            client_type,
+            redirect_uris: self.redirect_uris,
+            allowed_scopes: self.scopes.0,
+            default_scopes: self.default_scopes.0,
+            allowed_actions,
+            allowed_grant_types: self.grant_types,
+            allowed_audiences: HashSet::new(),
+            jwks,
+            access_token_format: AccessTokenFormat::default(),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct ClientRegistrationJson(pub ClientRegistrationRequest);
+
+// Both the request and the responses are JSON.
+impl<S> FromRequest<S> for ClientRegistrationJson
+where
+    S: ScopeRepositoryState + Send + Sync,
+    Json<ClientMetadata>: FromRequest<S, Rejection = JsonRejection>,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<ClientMetadata>::from_request(req, state).await {
+            Err(rejection) => Err(handle_json_rejection(rejection)),
+            Ok(Json(metadata)) => match validate_client_metadata(state.scope_repository(), metadata) {
+                Err(failure) => Err(handle_validation_failure(failure)),
+                Ok(valid) => Ok(ClientRegistrationJson(valid)),
+            }
+        }
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc7591#section-2
+pub fn validate_client_metadata<Q: ScopeRepository>(scope_repository: &Q, metadata: ClientMetadata) -> Result<ClientRegistrationRequest, ClientRegistrationResponse> {
+
+    let token_endpoint_auth_method = match metadata.token_endpoint_auth_method.map(|method| method.parse::<ClientAuthenticationMethod>()) {
+        None => ClientAuthenticationMethod::ClientSecretBasic,
+        Some(Ok(method)) if REGISTRABLE_AUTHENTICATION_METHODS.contains(&method) => method,
+        Some(_) => Err(ClientRegistrationResponse::invalid_client_metadata("token_endpoint_auth_method"))?,
+    };
+
+    // The keys are only needed to verify a private_key_jwt, and it can't be verified without them.
+    let jwks = match (&token_endpoint_auth_method, metadata.jwks.map(parse_public_jwks)) {
+        (ClientAuthenticationMethod::PrivateKeyJwt, Some(Some(jwks))) if !jwks.keys.is_empty() => Some(jwks),
+        (_, None) if token_endpoint_auth_method != ClientAuthenticationMethod::PrivateKeyJwt => None,
+        _ => Err(ClientRegistrationResponse::invalid_client_metadata("jwks"))?,
+    };
+
+    let grant_types = match metadata.grant_types {
+        None => HashSet::from([GrantType::AuthorizationCode]),
+        Some(grant_types) => grant_types.iter()
+            .map(|grant_type| grant_type.parse::<GrantType>().ok().filter(|grant_type| REGISTRABLE_GRANT_TYPES.contains(grant_type)))
+            .collect::<Option<HashSet<GrantType>>>()
+            .ok_or_else(|| ClientRegistrationResponse::invalid_client_metadata("grant_types"))?,
+    };
+
+    // Without a secret or keys there is no client to speak of, only a user.
+    if token_endpoint_auth_method == ClientAuthenticationMethod::None && grant_types.contains(&GrantType::ClientCredentials) {
+        Err(ClientRegistrationResponse::invalid_client_metadata("grant_types"))?
+    }
+
+    // The code response type and authorization_code grant type only make sense together.
+    // https://www.rfc-editor.org/rfc/rfc7591#section-2.1
+    let authorization_code = grant_types.contains(&GrantType::AuthorizationCode);
+    let response_types_match = match metadata.response_types {
+        None => true,
+        Some(response_types) => {
+            let response_types = response_types.iter().map(|response_type| response_type.parse::<ResponseType>()).collect::<Result<Vec<_>, _>>();
+            matches!(response_types, Ok(response_types) if response_types.contains(&ResponseType::Code) == authorization_code)
+        },
+    };
+
+    if !response_types_match {
+        Err(ClientRegistrationResponse::invalid_client_metadata("response_types"))?
+    }
+
+    let redirect_uris = match metadata.redirect_uris {
+        redirect_uris if authorization_code && redirect_uris.is_empty() => Err(ClientRegistrationResponse::invalid_redirect_uri("missing parameter: redirect_uris"))?,
+        redirect_uris if !redirect_uris.iter().all(|redirect_uri| is_valid_redirect_uri(redirect_uri)) => {
+            Err(ClientRegistrationResponse::invalid_redirect_uri("invalid parameter: redirect_uris"))?
+        },
+        redirect_uris => redirect_uris.into_iter().collect(),
+    };
+
+    let registry_defaults = scope_repository.find_all().into_iter()
+        .filter(|definition| definition.default && definition.can_be_requested_by(None))
+        .map(|definition| definition.scope)
+        .collect::<HashSet<_>>();
+
+    // Until registered there's no client to grant restricted scopes to, so only unrestricted ones can be asked for.
+    let scopes = match parse_scopes(scope_repository, None, metadata.scope.as_ref()) {
+        Err(_) => Err(ClientRegistrationResponse::invalid_client_metadata("scope"))?,
+        Ok(None) => Scopes(registry_defaults.clone()),
+        Ok(Some(scopes)) => scopes,
+    };
+
+    // What the client gets when it doesn't ask for a scope, the registry's defaults it registered for.
+    let default_scopes = Scopes(scopes.0.intersection(&registry_defaults).cloned().collect());
+
+    Ok(ClientRegistrationRequest {
+        client_id: metadata.client_id,
+        token_endpoint_auth_method,
+        redirect_uris,
+        grant_types,
+        scopes,
+        default_scopes,
+        jwks,
+    })
+}
+
+// Only public asymmetric keys, a client sharing its private key or a secret with us has given away its credentials.
+fn parse_public_jwks(jwks: Value) -> Option<JwkSet> {
+
+    let has_private_parameters = jwks.get("keys").and_then(Value::as_array).is_some_and(|keys| {
+        keys.iter().any(|key| PRIVATE_KEY_PARAMETERS.iter().any(|parameter| key.get(parameter).is_some()))
+    });
+
+    if has_private_parameters {
+        return None
+    }
+
+    let jwks: JwkSet = serde_json::from_value(jwks).ok()?;
+    jwks.keys.iter()
+        .all(|key| !matches!(key.algorithm, AlgorithmParameters::OctetKey(_)))
+        .then_some(jwks)
+}
+
+// Absolute and without a fragment, plain http is only allowed for native apps listening on the loopback interface.
+// https://www.rfc-editor.org/rfc/rfc8252#section-7.3
+fn is_valid_redirect_uri(redirect_uri: &str) -> bool {
+
+    let authority = |rest: &str| rest.split(['/', '?']).next().unwrap_or_default().to_string();
+
+    let acceptable = match (redirect_uri.strip_prefix("https://"), redirect_uri.strip_prefix("http://")) {
+        (Some(rest), _) => !authority(rest).is_empty(),
+        (_, Some(rest)) => {
+            let authority = authority(rest);
+            ["127.0.0.1", "[::1]"].iter().any(|loopback| authority == *loopback || authority.starts_with(&format!("{loopback}:")))
+        },
+        _ => false,
+    };
+
+    acceptable && !redirect_uri.contains('#')
+}
+
+fn handle_validation_failure(failure: ClientRegistrationResponse) -> Response {
+    (StatusCode::BAD_REQUEST, Json(failure)).into_response()
+}
+
+fn handle_json_rejection(rejection: JsonRejection) -> Response {
+    (rejection.status(), Json(ClientRegistrationResponse::Failure {
+        error: ErrorType::InvalidClientMetadata,
+        error_description: Some(rejection.body_text()),
+    })).into_response()
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use crate::scope::repository::InMemoryScopeRepository;
+    use crate::scope::Scope;
+    use assertables::*;
+    use serde_json::{json, Value};
+
+    fn validate(metadata: Value) -> Result<ClientRegistrationRequest, ClientRegistrationResponse> {
+        validate_client_metadata(&InMemoryScopeRepository::new(), assert_ok!(serde_json::from_value(metadata)))
+    }
+
+    #[test]
+    fn should_default_to_a_confidential_authorization_code_client() {
+        let result = validate(json!({ "redirect_uris": ["https://redirect.baconi.co.uk"] }));
+
+        assert_eq!(assert_ok!(result), ClientRegistrationRequest {
+            client_id: None,
+            token_endpoint_auth_method: ClientAuthenticationMethod::ClientSecretBasic,
+            redirect_uris: HashSet::from([String::from("https://redirect.baconi.co.uk")]),
+            grant_types: HashSet::from([GrantType::AuthorizationCode]),
+            scopes: Scopes(HashSet::from([Scope::from("basic")])),
+            default_scopes: Scopes(HashSet::from([Scope::from("basic")])),
+            jwks: None,
+        });
+    }
+
+    #[test]
+    fn should_require_a_redirect_uri_for_the_authorization_code_grant() {
+        let result = validate(json!({}));
+
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_redirect_uri("missing parameter: redirect_uris"));
+    }
+
+    #[test]
+    fn should_reject_redirect_uris_that_are_not_https_or_loopback() {
+        for redirect_uri in ["http://redirect.baconi.co.uk", "http://127.0.0.1.example.com", "https://redirect.baconi.co.uk#fragment", "https://", "redirect"] {
+            let result = validate(json!({ "redirect_uris": [redirect_uri] }));
+
+            assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_redirect_uri("invalid parameter: redirect_uris"));
+        }
+    }
+
+    #[test]
+    fn should_accept_loopback_redirect_uris_for_native_apps() {
+        let result = validate(json!({ "redirect_uris": ["http://127.0.0.1:8123/callback", "http://[::1]/callback"], "token_endpoint_auth_method": "none" }));
+
+        assert_eq!(assert_ok!(result).redirect_uris.len(), 2);
+    }
+
+    #[test]
+    fn should_reject_grant_types_that_cannot_be_registered() {
+        let result = validate(json!({ "grant_types": ["password"] }));
+
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("grant_types"));
+    }
+
+    #[test]
+    fn should_reject_client_credentials_for_a_public_client() {
+        let result = validate(json!({ "grant_types": ["client_credentials"], "token_endpoint_auth_method": "none" }));
+
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("grant_types"));
+    }
+
+    #[test]
+    fn should_reject_a_code_response_type_without_the_authorization_code_grant() {
+        let result = validate(json!({ "grant_types": ["client_credentials"], "response_types": ["code"] }));
+
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("response_types"));
+    }
+
+    #[test]
+    fn should_reject_an_unsupported_authentication_method() {
+        let result = validate(json!({ "grant_types": ["client_credentials"], "token_endpoint_auth_method": "tls_client_auth" }));
+
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("token_endpoint_auth_method"));
+    }
+
+    #[test]
+    fn should_require_keys_for_private_key_jwt_and_only_for_it() {
+        let result = validate(json!({ "grant_types": ["client_credentials"], "token_endpoint_auth_method": "private_key_jwt" }));
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("jwks"));
+
+        let result = validate(json!({ "grant_types": ["client_credentials"], "jwks": { "keys": [] } }));
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("jwks"));
+    }
+
+    #[test]
+    fn should_only_accept_public_asymmetric_keys() {
+        let mut jwks: Value = assert_ok!(serde_json::from_slice(include_bytes!("../../tests/fixtures/clients/ferret.jwks.json")));
+        let result = validate(json!({ "grant_types": ["client_credentials"], "token_endpoint_auth_method": "private_key_jwt", "jwks": jwks }));
+        assert!(assert_ok!(result).jwks.is_some());
+
+        jwks["keys"][0]["d"] = json!("jpsQnnGQmL-YBIffH1136cspYG6-0iY7X1fCE9-E9LI");
+        let result = validate(json!({ "grant_types": ["client_credentials"], "token_endpoint_auth_method": "private_key_jwt", "jwks": jwks }));
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("jwks"));
+
+        let jwks = json!({ "keys": [{ "kty": "oct", "kid": "cicada", "k": "c2hhcmVkLXNlY3JldA" }] });
+        let result = validate(json!({ "grant_types": ["client_credentials"], "token_endpoint_auth_method": "private_key_jwt", "jwks": jwks }));
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("jwks"));
+    }
+
+    #[test]
+    fn should_reject_an_unknown_scope() {
+        let result = validate(json!({ "grant_types": ["client_credentials"], "scope": "basic cicada" }));
+
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("scope"));
+    }
+
+    #[test]
+    fn should_reject_a_scope_restricted_to_other_clients() {
+        let scope_repository = InMemoryScopeRepository::with_restricted([("cicada", &["aardvark"][..])]);
+        let metadata = assert_ok!(serde_json::from_value(json!({ "grant_types": ["client_credentials"], "scope": "basic cicada" })));
+
+        let result = validate_client_metadata(&scope_repository, metadata);
+
+        assert_eq!(assert_err!(result), ClientRegistrationResponse::invalid_client_metadata("scope"));
+    }
+
+    #[test]
+    fn should_configure_a_public_client_to_require_pkce() {
+        let request = assert_ok!(validate(json!({ "redirect_uris": ["https://redirect.baconi.co.uk"], "token_endpoint_auth_method": "none", "scope": "openid profile" })));
+
+        let configuration = request.into_configuration(ClientId::from(String::from("aardvark")));
+
+        assert_eq!(configuration.client_type, ClientType::Public);
+        assert_eq!(configuration.allowed_actions, HashSet::from([ClientAction::ProofKeyForCodeExchange]));
+        assert_eq!(configuration.allowed_scopes, HashSet::from([Scope::OPENID, Scope::PROFILE]));
+    }
+
+    #[test]
+    fn should_default_to_the_registered_scopes_that_are_registry_defaults() {
+        let request = assert_ok!(validate(json!({ "grant_types": ["client_credentials"], "scope": "basic read" })));
+        assert_eq!(request.default_scopes, Scopes(HashSet::from([Scope::from("basic")])));
+
+        let request = assert_ok!(validate(json!({ "grant_types": ["client_credentials"], "scope": "read" })));
+        assert_eq!(request.default_scopes, Scopes::default());
+    }
+}
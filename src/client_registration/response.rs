@@ -0,0 +1,188 @@
+use axum::http::StatusCode;
+use axum::http::header::WWW_AUTHENTICATE;
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::jwk::JwkSet;
+use serde::Serialize;
+use crate::assertion::JwksSource;
+use crate::authorization::ResponseType;
+use crate::client::{ClientAuthenticationMethod, ClientType, GrantType};
+use crate::client::configuration::ClientConfiguration;
+use crate::client::secret::ClientSecret;
+use crate::util::value_struct::ValueStruct;
+
+// https://www.rfc-editor.org/rfc/rfc7591#section-3.2
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum ClientRegistrationResponse {
+    Success(Box<RegisteredClient>),
+    Failure {
+
+        // A single ASCII error code from the defined list.
+        error: ErrorType,
+
+        // Description Human-readable ASCII text providing additional information, used
+        // to assist the client developer in understanding the error that occurred.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_description: Option<String>,
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc7591#section-3.2.1
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+pub struct RegisteredClient {
+
+    // The unique identifier we issued the client.
+    client_id: String,
+
+    // Only ever returned the once, when it's generated, as we only keep its hash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+
+    // Required alongside a client_secret, zero as our secrets don't expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret_expires_at: Option<i64>,
+
+    // Used to read, update and delete the client, and like the secret only returned when it's generated.
+    // https://www.rfc-editor.org/rfc/rfc7592#section-3
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registration_access_token: Option<String>,
+
+    // Where the client can be read, updated and deleted.
+    registration_client_uri: String,
+
+    token_endpoint_auth_method: String,
+    grant_types: Vec<String>,
+    response_types: Vec<String>,
+    redirect_uris: Vec<String>,
+    scope: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jwks: Option<JwkSet>,
+}
+
+impl ClientRegistrationResponse {
+
+    // Describes the client as it's configured, sorted so the response is stable.
+    pub fn success(registration_client_uri: String, configuration: &ClientConfiguration, client_secret: Option<String>, registration_access_token: Option<String>) -> Self {
+
+        let response_types = match configuration.allowed_grant_types.contains(&GrantType::AuthorizationCode) {
+            true => vec![ResponseType::Code.to_string()],
+            false => vec![],
+        };
+
+        let jwks = match &configuration.jwks {
+            Some(JwksSource::Inline(jwks)) => Some(jwks.clone()),
+            _ => None,
+        };
+
+        ClientRegistrationResponse::Success(Box::new(RegisteredClient {
+            client_id: configuration.client_id.value().clone(),
+            client_secret_expires_at: client_secret.as_ref().map(|_| 0),
+            client_secret,
+            registration_access_token,
+            registration_client_uri,
+            token_endpoint_auth_method: token_endpoint_auth_method(configuration).to_string(),
+            grant_types: sorted(configuration.allowed_grant_types.iter().map(GrantType::to_string)),
+            response_types,
+            redirect_uris: sorted(configuration.redirect_uris.iter().cloned()),
+            scope: sorted(configuration.allowed_scopes.iter().map(ToString::to_string)).join(" "),
+            jwks,
+        }))
+    }
+
+    pub fn invalid_client_metadata(parameter: &str) -> Self {
+        ClientRegistrationResponse::Failure {
+            error: ErrorType::InvalidClientMetadata,
+            error_description: Some(format!("invalid parameter: {parameter}")),
+        }
+    }
+
+    pub fn invalid_redirect_uri(error_description: &str) -> Self {
+        ClientRegistrationResponse::Failure {
+            error: ErrorType::InvalidRedirectUri,
+            error_description: Some(error_description.into()),
+        }
+    }
+}
+
+// Everything about a secret except the secret itself, times are in seconds since the epoch.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+pub struct ClientSecretMetadata {
+    id: String,
+    created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revoked_at: Option<i64>,
+    active: bool,
+}
+
+impl From<&ClientSecret> for ClientSecretMetadata {
+    fn from(secret: &ClientSecret) -> Self {
+        Self {
+            id: secret.id.to_string(),
+            created_at: secret.created_at.timestamp(),
+            expires_at: secret.expires_at.map(|expires_at| expires_at.timestamp()),
+            revoked_at: secret.revoked_at.map(|revoked_at| revoked_at.timestamp()),
+            active: secret.is_active(),
+        }
+    }
+}
+
+// The only time the secret is ever shown.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+pub struct IssuedClientSecret {
+    pub client_secret: String,
+    #[serde(flatten)]
+    pub metadata: ClientSecretMetadata,
+}
+
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+pub struct ClientSecrets {
+    pub client_secrets: Vec<ClientSecretMetadata>,
+}
+
+// Registered clients either hold keys or secrets, unless they're public.
+pub fn token_endpoint_auth_method(configuration: &ClientConfiguration) -> ClientAuthenticationMethod {
+    match (&configuration.client_type, &configuration.jwks) {
+        (ClientType::Public, _) => ClientAuthenticationMethod::None,
+        (ClientType::Confidential, Some(JwksSource::ClientSecret)) => ClientAuthenticationMethod::ClientSecretJwt,
+        (ClientType::Confidential, Some(_)) => ClientAuthenticationMethod::PrivateKeyJwt,
+        (ClientType::Confidential, None) => ClientAuthenticationMethod::ClientSecretBasic,
+    }
+}
+
+fn sorted(values: impl Iterator<Item = String>) -> Vec<String> {
+    let mut values: Vec<String> = values.collect();
+    values.sort();
+    values
+}
+
+// https://www.rfc-editor.org/rfc/rfc7591#section-3.2.2
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorType {
+
+    // The value of one or more redirection URIs is invalid.
+    InvalidRedirectUri,
+
+    // The value of one of the client metadata fields is invalid and the server has rejected this request.
+    InvalidClientMetadata,
+}
+
+// Whether the client doesn't exist or the token is wrong, the response is the same so clients can't be probed for.
+// https://www.rfc-editor.org/rfc/rfc7592#section-2.1
+#[cfg_attr(test, derive(Debug))]
+pub struct InvalidRegistrationAccessToken;
+
+impl IntoResponse for InvalidRegistrationAccessToken {
+    fn into_response(self) -> Response {
+        (StatusCode::UNAUTHORIZED, [(WWW_AUTHENTICATE, r#"Bearer error="invalid_token""#)]).into_response()
+    }
+}
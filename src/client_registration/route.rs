@@ -0,0 +1,779 @@
+use axum::extract::{Path, State};
+use axum::extract::rejection::JsonRejection;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use axum_extra::headers::Authorization;
+use axum_extra::headers::authorization::Bearer;
+use axum_extra::TypedHeader;
+use chrono::{DateTime, TimeDelta, Utc};
+use uuid::Uuid;
+use crate::client::{ClientAuthenticationMethod, ClientId};
+use crate::client::configuration::{ClientConfiguration, ClientConfigurationRepository};
+use crate::client::registration::{RegistrationAccessToken, RegistrationAccessTokenRepository};
+use crate::client::secret::{generate_secret, ClientSecret, ClientSecretRepository};
+use crate::client_registration::request::{ClientRegistrationJson, ClientSecretRequest};
+use crate::client_registration::response::{token_endpoint_auth_method, ClientRegistrationResponse, ClientSecretMetadata, ClientSecrets, InvalidRegistrationAccessToken, IssuedClientSecret};
+use crate::scope::repository::{ScopeRepository, ScopeRepositoryState};
+use crate::util::value_struct::ValueStruct;
+
+pub const REGISTRATION_ENDPOINT: &str = "/register";
+pub const CLIENT_CONFIGURATION_ENDPOINT: &str = "/register/{client_id}";
+pub const CLIENT_SECRETS_ENDPOINT: &str = "/register/{client_id}/secrets";
+pub const CLIENT_SECRET_ENDPOINT: &str = "/register/{client_id}/secrets/{secret_id}";
+
+// https://www.rfc-editor.org/rfc/rfc7591#section-3
+// https://www.rfc-editor.org/rfc/rfc7592#section-2
+pub fn route<C, S, R, Q>(state: ClientRegistrationState<C, S, R, Q>) -> Router<()>
+where
+    C: ClientConfigurationRepository + 'static,
+    S: ClientSecretRepository + 'static,
+    R: RegistrationAccessTokenRepository + 'static,
+    Q: ScopeRepository + 'static,
+{
+    Router::new()
+        .route(REGISTRATION_ENDPOINT, post(registration_handler))
+        .route(CLIENT_CONFIGURATION_ENDPOINT, get(read_handler).put(update_handler).delete(delete_handler))
+        .route(CLIENT_SECRETS_ENDPOINT, get(list_secrets_handler).post(issue_secret_handler))
+        .route(CLIENT_SECRET_ENDPOINT, delete(retire_secret_handler))
+        .with_state(state)
+}
+
+#[derive(Clone)]
+pub struct ClientRegistrationState<C: ClientConfigurationRepository, S: ClientSecretRepository, R: RegistrationAccessTokenRepository, Q: ScopeRepository> {
+    pub issuer: String,
+    pub client_configuration_repository: C,
+    pub client_secret_repository: S,
+    pub registration_access_token_repository: R,
+    pub scope_repository: Q,
+}
+
+impl<C, S, R, Q> ScopeRepositoryState for ClientRegistrationState<C, S, R, Q>
+where
+    C: ClientConfigurationRepository,
+    S: ClientSecretRepository,
+    R: RegistrationAccessTokenRepository,
+    Q: ScopeRepository,
+{
+    type ScopeRepository = Q;
+    fn scope_repository(&self) -> &Q {
+        &self.scope_repository
+    }
+}
+
+impl<C, S, R, Q> ClientRegistrationState<C, S, R, Q>
+where
+    C: ClientConfigurationRepository,
+    S: ClientSecretRepository,
+    R: RegistrationAccessTokenRepository,
+    Q: ScopeRepository,
+{
+    fn registration_client_uri(&self, client_id: &ClientId) -> String {
+        format!("{}{REGISTRATION_ENDPOINT}/{}", self.issuer, client_id.value())
+    }
+
+    // Only the registration access token issued with the client can manage it.
+    fn authorize(&self, client_id: String, maybe_bearer: Option<TypedHeader<Authorization<Bearer>>>) -> Option<ClientConfiguration> {
+        let TypedHeader(Authorization(bearer)) = maybe_bearer?;
+        let client_id = ClientId::from(client_id);
+        self.registration_access_token_repository.find_by_client(&client_id)
+            .filter(|registration_access_token| registration_access_token.matches(bearer.token()))?;
+        self.client_configuration_repository.find_by_id(&client_id)
+    }
+
+    // Generates a secret when the client needs one but doesn't have one, and removes them when it no longer needs one.
+    fn provision_client_secret(&self, configuration: &ClientConfiguration) -> Result<Option<String>, StatusCode> {
+
+        let needs_secret = uses_client_secrets(configuration);
+        let has_secret = self.client_secret_repository.find_all_by_client(&configuration.client_id).iter().any(ClientSecret::is_active);
+
+        match (needs_secret, has_secret) {
+            (true, false) => {
+                let client_secret = generate_secret().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                let hashed = new_client_secret(configuration, &client_secret, None)
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                self.client_secret_repository.save(&hashed);
+                Ok(Some(client_secret))
+            },
+            (false, true) => {
+                self.client_secret_repository.remove_all_by_client(&configuration.client_id);
+                Ok(None)
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+// TODO - Require an initial access token once we've got a means of issuing them, see https://www.rfc-editor.org/rfc/rfc7591#section-3
+async fn registration_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, R: RegistrationAccessTokenRepository, Q: ScopeRepository>(
+    State(state): State<ClientRegistrationState<C, S, R, Q>>,
+    ClientRegistrationJson(request): ClientRegistrationJson,
+) -> Response {
+
+    // We always choose the identifier, anything the client asked for is ignored.
+    let configuration = request.into_configuration(ClientId::from(Uuid::new_v4().to_string()));
+
+    let registration_access_token = match generate_secret() {
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        Ok(registration_access_token) => registration_access_token,
+    };
+
+    let client_secret = match state.provision_client_secret(&configuration) {
+        Err(status) => return status.into_response(),
+        Ok(client_secret) => client_secret,
+    };
+
+    state.registration_access_token_repository.save(&RegistrationAccessToken::new(configuration.client_id.clone(), &registration_access_token));
+
+    state.client_configuration_repository.save(&configuration);
+
+    let registration_client_uri = state.registration_client_uri(&configuration.client_id);
+
+    (StatusCode::CREATED, Json(ClientRegistrationResponse::success(
+        registration_client_uri,
+        &configuration,
+        client_secret,
+        Some(registration_access_token),
+    ))).into_response()
+}
+
+// https://www.rfc-editor.org/rfc/rfc7592#section-2.1
+async fn read_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, R: RegistrationAccessTokenRepository, Q: ScopeRepository>(
+    State(state): State<ClientRegistrationState<C, S, R, Q>>,
+    Path(client_id): Path<String>,
+    maybe_bearer: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Response {
+
+    let configuration = match state.authorize(client_id, maybe_bearer) {
+        None => return InvalidRegistrationAccessToken.into_response(),
+        Some(configuration) => configuration,
+    };
+
+    let registration_client_uri = state.registration_client_uri(&configuration.client_id);
+
+    Json(ClientRegistrationResponse::success(registration_client_uri, &configuration, None, None)).into_response()
+}
+
+// Replaces every field of the client's metadata, anything left out is reset to its default.
+// https://www.rfc-editor.org/rfc/rfc7592#section-2.2
+async fn update_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, R: RegistrationAccessTokenRepository, Q: ScopeRepository>(
+    State(state): State<ClientRegistrationState<C, S, R, Q>>,
+    Path(client_id): Path<String>,
+    maybe_bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    ClientRegistrationJson(request): ClientRegistrationJson,
+) -> Response {
+
+    let existing = match state.authorize(client_id, maybe_bearer) {
+        None => return InvalidRegistrationAccessToken.into_response(),
+        Some(configuration) => configuration,
+    };
+
+    if request.client_id.as_deref() != Some(existing.client_id.value()) {
+        return (StatusCode::BAD_REQUEST, Json(ClientRegistrationResponse::invalid_client_metadata("client_id"))).into_response();
+    }
+
+    let configuration = request.into_configuration(existing.client_id);
+
+    let client_secret = match state.provision_client_secret(&configuration) {
+        Err(status) => return status.into_response(),
+        Ok(client_secret) => client_secret,
+    };
+
+    state.client_configuration_repository.save(&configuration);
+
+    let registration_client_uri = state.registration_client_uri(&configuration.client_id);
+
+    Json(ClientRegistrationResponse::success(registration_client_uri, &configuration, client_secret, None)).into_response()
+}
+
+// https://www.rfc-editor.org/rfc/rfc7592#section-2.3
+async fn delete_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, R: RegistrationAccessTokenRepository, Q: ScopeRepository>(
+    State(state): State<ClientRegistrationState<C, S, R, Q>>,
+    Path(client_id): Path<String>,
+    maybe_bearer: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Response {
+
+    let configuration = match state.authorize(client_id, maybe_bearer) {
+        None => return InvalidRegistrationAccessToken.into_response(),
+        Some(configuration) => configuration,
+    };
+
+    state.client_configuration_repository.remove_by_id(&configuration.client_id);
+    state.client_secret_repository.remove_all_by_client(&configuration.client_id);
+    state.registration_access_token_repository.remove_by_client(&configuration.client_id);
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+// Secrets are rotated without downtime by issuing a new one, rolling it out, then retiring the old one.
+// Operators can do the same for any client, including those configured rather than registered, see operator_route.
+async fn list_secrets_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, R: RegistrationAccessTokenRepository, Q: ScopeRepository>(
+    State(state): State<ClientRegistrationState<C, S, R, Q>>,
+    Path(client_id): Path<String>,
+    maybe_bearer: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Response {
+
+    match state.authorize(client_id, maybe_bearer) {
+        None => InvalidRegistrationAccessToken.into_response(),
+        Some(configuration) => list_secrets(&state.client_secret_repository, &configuration),
+    }
+}
+
+async fn issue_secret_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, R: RegistrationAccessTokenRepository, Q: ScopeRepository>(
+    State(state): State<ClientRegistrationState<C, S, R, Q>>,
+    Path(client_id): Path<String>,
+    maybe_bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    maybe_request: Result<Option<Json<ClientSecretRequest>>, JsonRejection>,
+) -> Response {
+
+    match state.authorize(client_id, maybe_bearer) {
+        None => InvalidRegistrationAccessToken.into_response(),
+        Some(configuration) => issue_secret(&state.client_secret_repository, configuration, maybe_request),
+    }
+}
+
+async fn retire_secret_handler<C: ClientConfigurationRepository, S: ClientSecretRepository, R: RegistrationAccessTokenRepository, Q: ScopeRepository>(
+    State(state): State<ClientRegistrationState<C, S, R, Q>>,
+    Path((client_id, secret_id)): Path<(String, String)>,
+    maybe_bearer: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Response {
+
+    match state.authorize(client_id, maybe_bearer) {
+        None => InvalidRegistrationAccessToken.into_response(),
+        Some(configuration) => retire_secret(&state.client_secret_repository, &configuration, &secret_id),
+    }
+}
+
+// Oldest first, without the secrets themselves as only their hashes are kept.
+pub fn list_secrets<S: ClientSecretRepository>(client_secret_repository: &S, configuration: &ClientConfiguration) -> Response {
+
+    let mut secrets = client_secret_repository.find_all_by_client(&configuration.client_id);
+    secrets.sort_by_key(|secret| secret.created_at);
+
+    Json(ClientSecrets {
+        client_secrets: secrets.iter().map(ClientSecretMetadata::from).collect(),
+    }).into_response()
+}
+
+// The secret is only ever returned the once, here.
+pub fn issue_secret<S: ClientSecretRepository>(client_secret_repository: &S, configuration: ClientConfiguration, maybe_request: Result<Option<Json<ClientSecretRequest>>, JsonRejection>) -> Response {
+
+    let request = match maybe_request {
+        Err(_) | Ok(Some(Json(ClientSecretRequest { expires_in: Some(0) }))) => {
+            return (StatusCode::BAD_REQUEST, Json(ClientRegistrationResponse::invalid_client_metadata("expires_in"))).into_response()
+        },
+        Ok(maybe_request) => maybe_request.map(|Json(request)| request).unwrap_or_default(),
+    };
+
+    // Clients holding keys or no credentials at all have no use for a secret.
+    if !uses_client_secrets(&configuration) {
+        return (StatusCode::BAD_REQUEST, Json(ClientRegistrationResponse::invalid_client_metadata("token_endpoint_auth_method"))).into_response()
+    }
+
+    let expires_at = request.expires_in.map(|expires_in| Utc::now() + TimeDelta::seconds(expires_in.into()));
+
+    let client_secret = match generate_secret() {
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        Ok(client_secret) => client_secret,
+    };
+    let hashed = match new_client_secret(&configuration, &client_secret, expires_at) {
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        Ok(hashed) => hashed,
+    };
+    client_secret_repository.save(&hashed);
+
+    (StatusCode::CREATED, Json(IssuedClientSecret {
+        client_secret,
+        metadata: ClientSecretMetadata::from(&hashed),
+    })).into_response()
+}
+
+fn uses_client_secrets(configuration: &ClientConfiguration) -> bool {
+    matches!(token_endpoint_auth_method(configuration), ClientAuthenticationMethod::ClientSecretBasic | ClientAuthenticationMethod::ClientSecretJwt)
+}
+
+// Only a hash is kept, unless the client signs its assertions with the secret itself.
+fn new_client_secret(configuration: &ClientConfiguration, client_secret: &str, expires_at: Option<DateTime<Utc>>) -> Result<ClientSecret, argon2::password_hash::Error> {
+    let client_id = configuration.client_id.clone();
+    match token_endpoint_auth_method(configuration) {
+        ClientAuthenticationMethod::ClientSecretJwt => ClientSecret::new_shared(client_id, client_secret.as_bytes(), expires_at),
+        _ => ClientSecret::new(client_id, client_secret.as_bytes(), expires_at),
+    }
+}
+
+// Retired secrets are kept so they can still be listed, but can no longer be used.
+pub fn retire_secret<S: ClientSecretRepository>(client_secret_repository: &S, configuration: &ClientConfiguration, secret_id: &str) -> Response {
+
+    let maybe_secret = Uuid::parse_str(secret_id).ok()
+        .and_then(|secret_id| client_secret_repository.find_by_id(&secret_id))
+        .filter(|secret| secret.client_id == configuration.client_id);
+
+    let mut secret = match maybe_secret {
+        None => return StatusCode::NOT_FOUND.into_response(),
+        Some(secret) => secret,
+    };
+
+    secret.revoke();
+    client_secret_repository.save(&secret);
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+#[cfg(test)]
+mod integration_tests {
+
+    use super::*;
+
+    use assertables::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use crate::assertion::test_support::new_claims;
+    use crate::client::authentication::{ClientAuthenticationService, ClientAuthenticator};
+    use crate::client::configuration::InMemoryClientConfigurationRepository;
+    use crate::client::registration::InMemoryRegistrationAccessTokenRepository;
+    use crate::client::secret::InMemoryClientSecretRepository;
+    use crate::replay::InMemoryReplayCache;
+
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
+    const APPLICATION_JSON: &str = "application/json";
+
+    #[derive(Clone)]
+    struct Repositories {
+        client_configuration_repository: InMemoryClientConfigurationRepository,
+        client_secret_repository: InMemoryClientSecretRepository,
+        registration_access_token_repository: InMemoryRegistrationAccessTokenRepository,
+    }
+
+    impl Repositories {
+        fn new() -> Self {
+            Self {
+                client_configuration_repository: InMemoryClientConfigurationRepository::new(),
+                client_secret_repository: InMemoryClientSecretRepository::new(),
+                registration_access_token_repository: InMemoryRegistrationAccessTokenRepository::new(),
+            }
+        }
+
+        fn router(&self) -> Router {
+            route(ClientRegistrationState {
+                issuer: TEST_ISSUER.into(),
+                client_configuration_repository: self.client_configuration_repository.clone(),
+                client_secret_repository: self.client_secret_repository.clone(),
+                registration_access_token_repository: self.registration_access_token_repository.clone(),
+                scope_repository: crate::scope::repository::InMemoryScopeRepository::new(),
+            })
+        }
+
+        fn client_authenticator(&self) -> impl ClientAuthenticator {
+            ClientAuthenticationService::new(
+                TEST_ISSUER.into(),
+                self.client_secret_repository.clone(),
+                self.client_configuration_repository.clone(),
+                InMemoryReplayCache::new(),
+            )
+        }
+    }
+
+    async fn send(router: Router, method: Method, uri: &str, maybe_token: Option<&str>, maybe_body: Option<Value>) -> Response {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = maybe_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let body = match maybe_body {
+            None => Body::empty(),
+            Some(body) => {
+                request = request.header(CONTENT_TYPE, APPLICATION_JSON);
+                Body::from(body.to_string())
+            },
+        };
+        assert_ok!(router.oneshot(assert_ok!(request.body(body))).await)
+    }
+
+    async fn extract_json_body(response: Response) -> Value {
+        let body_bytes = assert_ok!(response.into_body().collect().await);
+        assert_ok!(serde_json::from_slice(body_bytes.to_bytes().as_ref()))
+    }
+
+    fn path(body: &Value) -> String {
+        let registration_client_uri = assert_some!(body["registration_client_uri"].as_str());
+        assert_some!(registration_client_uri.strip_prefix(TEST_ISSUER)).to_string()
+    }
+
+    async fn register(router: Router, metadata: Value) -> Value {
+        let response = send(router, Method::POST, REGISTRATION_ENDPOINT, None, Some(metadata)).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        extract_json_body(response).await
+    }
+
+    fn confidential_client() -> Value {
+        json!({
+            "redirect_uris": ["https://redirect.baconi.co.uk"],
+            "grant_types": ["authorization_code", "client_credentials", "refresh_token"],
+            "scope": "basic openid",
+        })
+    }
+
+    mod registration {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_register_a_confidential_client_that_can_authenticate_with_its_secret() {
+            let repositories = Repositories::new();
+
+            let body = register(repositories.router(), confidential_client()).await;
+            let client_id = assert_some!(body["client_id"].as_str());
+            let client_secret = assert_some!(body["client_secret"].as_str());
+            assert_some!(body["registration_access_token"].as_str());
+            assert_eq!(body["registration_client_uri"], format!("{TEST_ISSUER}/register/{client_id}"));
+            assert_eq!(body["client_secret_expires_at"], 0);
+            assert_eq!(body["token_endpoint_auth_method"], "client_secret_basic");
+            assert_eq!(body["grant_types"], json!(["authorization_code", "client_credentials", "refresh_token"]));
+            assert_eq!(body["response_types"], json!(["code"]));
+            assert_eq!(body["redirect_uris"], json!(["https://redirect.baconi.co.uk"]));
+            assert_eq!(body["scope"], "basic openid");
+
+            assert_some!(repositories.client_authenticator().authenticate_as_confidential_client(client_id, client_secret.as_bytes()));
+
+            let secrets = repositories.client_secret_repository.find_all_by_client_id(client_id);
+            assert_eq!(secrets.len(), 1);
+            assert_not_contains!(secrets[0].hashed_secret, client_secret);
+        }
+
+        #[tokio::test]
+        async fn should_register_a_public_client_without_a_secret() {
+            let repositories = Repositories::new();
+
+            let body = register(repositories.router(), json!({
+                "redirect_uris": ["http://127.0.0.1:8123/callback"],
+                "token_endpoint_auth_method": "none",
+            })).await;
+            assert_none!(body.get("client_secret"));
+            assert_eq!(body["token_endpoint_auth_method"], "none");
+
+            let client_id = assert_some!(body["client_id"].as_str());
+            assert_some!(repositories.client_authenticator().authenticate_as_public_client(client_id));
+            assert_is_empty!(repositories.client_secret_repository.find_all_by_client_id(client_id));
+        }
+
+        #[tokio::test]
+        async fn should_register_a_private_key_jwt_client_with_its_keys() {
+            let jwks: Value = assert_ok!(serde_json::from_slice(include_bytes!("../../tests/fixtures/clients/ferret.jwks.json")));
+
+            let body = register(Repositories::new().router(), json!({
+                "grant_types": ["client_credentials"],
+                "token_endpoint_auth_method": "private_key_jwt",
+                "jwks": jwks,
+            })).await;
+            assert_none!(body.get("client_secret"));
+            assert_eq!(body["token_endpoint_auth_method"], "private_key_jwt");
+            assert_eq!(body["jwks"], jwks);
+            assert_eq!(body["response_types"], json!([]));
+        }
+
+        #[tokio::test]
+        async fn should_reject_invalid_client_metadata() {
+            let response = send(Repositories::new().router(), Method::POST, REGISTRATION_ENDPOINT, None, Some(json!({ "grant_types": ["password"] }))).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_client_metadata");
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_invalid_redirect_uri() {
+            let response = send(Repositories::new().router(), Method::POST, REGISTRATION_ENDPOINT, None, Some(json!({ "redirect_uris": ["http://redirect.baconi.co.uk"] }))).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_redirect_uri");
+        }
+
+        #[tokio::test]
+        async fn should_reject_malformed_json() {
+            let response = send(Repositories::new().router(), Method::POST, REGISTRATION_ENDPOINT, None, Some(json!({ "redirect_uris": "https://redirect.baconi.co.uk" }))).await;
+            assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_client_metadata");
+        }
+    }
+
+    mod management {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_read_the_client_without_its_secret() {
+            let router = Repositories::new().router();
+            let registered = register(router.clone(), confidential_client()).await;
+            let token = assert_some!(registered["registration_access_token"].as_str());
+
+            let response = send(router, Method::GET, &path(&registered), Some(token), None).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["client_id"], registered["client_id"]);
+            assert_eq!(body["scope"], "basic openid");
+            assert_none!(body.get("client_secret"));
+            assert_none!(body.get("registration_access_token"));
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_missing_or_another_clients_registration_access_token() {
+            let router = Repositories::new().router();
+            let aardvark = register(router.clone(), confidential_client()).await;
+            let badger = register(router.clone(), confidential_client()).await;
+
+            let response = send(router.clone(), Method::GET, &path(&aardvark), None, None).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+            let response = send(router, Method::GET, &path(&aardvark), badger["registration_access_token"].as_str(), None).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_some_eq_x!(response.headers().get(WWW_AUTHENTICATE), r#"Bearer error="invalid_token""#);
+        }
+
+        #[tokio::test]
+        async fn should_not_manage_clients_that_were_not_registered() {
+            let response = send(Repositories::new().router(), Method::GET, "/register/aardvark", Some("badger"), None).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        #[tokio::test]
+        async fn should_replace_the_clients_metadata() {
+            let repositories = Repositories::new();
+            let registered = register(repositories.router(), confidential_client()).await;
+            let client_id = assert_some!(registered["client_id"].as_str());
+            let token = assert_some!(registered["registration_access_token"].as_str());
+
+            let response = send(repositories.router(), Method::PUT, &path(&registered), Some(token), Some(json!({
+                "client_id": client_id,
+                "grant_types": ["client_credentials"],
+                "scope": "read",
+            }))).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["grant_types"], json!(["client_credentials"]));
+            assert_eq!(body["redirect_uris"], json!([]));
+            assert_eq!(body["scope"], "read");
+            // It already has a secret, which is kept.
+            assert_none!(body.get("client_secret"));
+
+            let configuration = assert_some!(repositories.client_configuration_repository.find_by_client_id(client_id));
+            assert_eq!(configuration.allowed_scopes, std::collections::HashSet::from([crate::scope::Scope::from("read")]));
+        }
+
+        #[tokio::test]
+        async fn should_issue_a_secret_when_a_public_client_becomes_confidential() {
+            let repositories = Repositories::new();
+            let registered = register(repositories.router(), json!({ "redirect_uris": ["https://redirect.baconi.co.uk"], "token_endpoint_auth_method": "none" })).await;
+            let client_id = assert_some!(registered["client_id"].as_str());
+            let token = assert_some!(registered["registration_access_token"].as_str());
+
+            let response = send(repositories.router(), Method::PUT, &path(&registered), Some(token), Some(json!({
+                "client_id": client_id,
+                "redirect_uris": ["https://redirect.baconi.co.uk"],
+            }))).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            let client_secret = assert_some!(body["client_secret"].as_str());
+            assert_some!(repositories.client_authenticator().authenticate_as_confidential_client(client_id, client_secret.as_bytes()));
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_update_for_another_client_id() {
+            let router = Repositories::new().router();
+            let registered = register(router.clone(), confidential_client()).await;
+            let token = assert_some!(registered["registration_access_token"].as_str());
+
+            let response = send(router, Method::PUT, &path(&registered), Some(token), Some(json!({
+                "client_id": "aardvark",
+                "grant_types": ["client_credentials"],
+            }))).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_client_metadata");
+        }
+
+        #[tokio::test]
+        async fn should_delete_the_client_and_its_credentials() {
+            let repositories = Repositories::new();
+            let registered = register(repositories.router(), confidential_client()).await;
+            let client_id = assert_some!(registered["client_id"].as_str());
+            let token = assert_some!(registered["registration_access_token"].as_str());
+
+            let response = send(repositories.router(), Method::DELETE, &path(&registered), Some(token), None).await;
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+            assert_none!(repositories.client_configuration_repository.find_by_client_id(client_id));
+            assert_is_empty!(repositories.client_secret_repository.find_all_by_client_id(client_id));
+
+            let response = send(repositories.router(), Method::GET, &path(&registered), Some(token), None).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    mod secret_rotation {
+        use super::*;
+        use chrono::{TimeDelta, Utc};
+
+        fn secrets_path(registered: &Value) -> String {
+            format!("{}/secrets", path(registered))
+        }
+
+        #[tokio::test]
+        async fn should_rotate_secrets_without_downtime() {
+            let repositories = Repositories::new();
+            let registered = register(repositories.router(), confidential_client()).await;
+            let client_id = assert_some!(registered["client_id"].as_str());
+            let token = assert_some!(registered["registration_access_token"].as_str());
+            let old_secret = assert_some!(registered["client_secret"].as_str());
+
+            let response = send(repositories.router(), Method::POST, &secrets_path(&registered), Some(token), None).await;
+            assert_eq!(response.status(), StatusCode::CREATED);
+
+            let issued = extract_json_body(response).await;
+            let new_secret = assert_some!(issued["client_secret"].as_str());
+            assert_none!(issued.get("expires_at"));
+            assert_eq!(issued["active"], true);
+
+            // Both work while the new secret is rolled out.
+            let authenticator = repositories.client_authenticator();
+            assert_some!(authenticator.authenticate_as_confidential_client(client_id, old_secret.as_bytes()));
+            assert_some!(authenticator.authenticate_as_confidential_client(client_id, new_secret.as_bytes()));
+
+            let response = send(repositories.router(), Method::GET, &secrets_path(&registered), Some(token), None).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let listed = extract_json_body(response).await;
+            let secrets = assert_some!(listed["client_secrets"].as_array());
+            assert_eq!(secrets.len(), 2);
+            assert_not_contains!(listed.to_string(), old_secret);
+            assert_not_contains!(listed.to_string(), new_secret);
+
+            let old_secret_id = assert_some!(secrets.iter().find(|secret| secret["id"] != issued["id"]))["id"].clone();
+
+            let response = send(repositories.router(), Method::DELETE, &format!("{}/{}", secrets_path(&registered), assert_some!(old_secret_id.as_str())), Some(token), None).await;
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+            assert_none!(authenticator.authenticate_as_confidential_client(client_id, old_secret.as_bytes()));
+            assert_some!(authenticator.authenticate_as_confidential_client(client_id, new_secret.as_bytes()));
+
+            let response = send(repositories.router(), Method::GET, &secrets_path(&registered), Some(token), None).await;
+            let listed = extract_json_body(response).await;
+            let retired = assert_some!(assert_some!(listed["client_secrets"].as_array()).iter().find(|secret| secret["id"] == old_secret_id));
+            assert_eq!(retired["active"], false);
+            assert_some!(retired["revoked_at"].as_i64());
+        }
+
+        #[tokio::test]
+        async fn should_verify_client_secret_jwt_with_the_clients_active_secrets() {
+            let repositories = Repositories::new();
+            let registered = register(repositories.router(), json!({
+                "grant_types": ["client_credentials"],
+                "token_endpoint_auth_method": "client_secret_jwt",
+            })).await;
+            assert_eq!(registered["token_endpoint_auth_method"], "client_secret_jwt");
+            let client_id = assert_some!(registered["client_id"].as_str());
+            let token = assert_some!(registered["registration_access_token"].as_str());
+            let secret = assert_some!(registered["client_secret"].as_str());
+
+            let sign = |claims: &Value| assert_ok!(encode(&Header::new(Algorithm::HS256), claims, &EncodingKey::from_secret(secret.as_bytes())));
+            let authenticator = repositories.client_authenticator();
+            assert_some!(authenticator.authenticate_with_assertion(Some(client_id), &sign(&new_claims(client_id, TEST_ISSUER)), "/token"));
+
+            let response = send(repositories.router(), Method::GET, &secrets_path(&registered), Some(token), None).await;
+            let listed = extract_json_body(response).await;
+            let secret_id = assert_some!(listed["client_secrets"][0]["id"].as_str());
+
+            let response = send(repositories.router(), Method::DELETE, &format!("{}/{secret_id}", secrets_path(&registered)), Some(token), None).await;
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+            assert_none!(authenticator.authenticate_with_assertion(Some(client_id), &sign(&new_claims(client_id, TEST_ISSUER)), "/token"));
+        }
+
+        #[tokio::test]
+        async fn should_issue_a_secret_that_expires() {
+            let router = Repositories::new().router();
+            let registered = register(router.clone(), confidential_client()).await;
+            let token = assert_some!(registered["registration_access_token"].as_str());
+
+            let response = send(router, Method::POST, &secrets_path(&registered), Some(token), Some(json!({ "expires_in": 3600 }))).await;
+            assert_eq!(response.status(), StatusCode::CREATED);
+
+            let issued = extract_json_body(response).await;
+            let created_at = assert_some!(issued["created_at"].as_i64());
+            let expires_at = assert_some!(issued["expires_at"].as_i64());
+            assert_in_delta!(expires_at, created_at + 3600, 1);
+        }
+
+        #[tokio::test]
+        async fn should_not_authenticate_with_an_expired_secret() {
+            let repositories = Repositories::new();
+            let expired = assert_ok!(ClientSecret::new(ClientId::from(String::from("aardvark")), b"cicada", Some(Utc::now() - TimeDelta::seconds(1))));
+            repositories.client_secret_repository.save(&expired);
+
+            assert_none!(repositories.client_authenticator().authenticate_as_confidential_client("aardvark", b"cicada"));
+            assert_some!(repositories.client_authenticator().authenticate_as_confidential_client("aardvark", b"badger"));
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_invalid_expiry() {
+            let router = Repositories::new().router();
+            let registered = register(router.clone(), confidential_client()).await;
+            let token = assert_some!(registered["registration_access_token"].as_str());
+
+            for expires_in in [json!(0), json!(-1), json!("soon")] {
+                let response = send(router.clone(), Method::POST, &secrets_path(&registered), Some(token), Some(json!({ "expires_in": expires_in }))).await;
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+                let body = extract_json_body(response).await;
+                assert_eq!(body["error"], "invalid_client_metadata");
+            }
+        }
+
+        #[tokio::test]
+        async fn should_not_issue_secrets_to_clients_that_do_not_use_them() {
+            let router = Repositories::new().router();
+            let registered = register(router.clone(), json!({ "redirect_uris": ["https://redirect.baconi.co.uk"], "token_endpoint_auth_method": "none" })).await;
+            let token = assert_some!(registered["registration_access_token"].as_str());
+
+            let response = send(router, Method::POST, &secrets_path(&registered), Some(token), None).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        #[tokio::test]
+        async fn should_not_retire_another_clients_secret() {
+            let repositories = Repositories::new();
+            let registered = register(repositories.router(), confidential_client()).await;
+            let token = assert_some!(registered["registration_access_token"].as_str());
+
+            let aardvark = repositories.client_secret_repository.find_all_by_client_id("aardvark");
+            let aardvark_secret_id = assert_some!(aardvark.first()).id;
+
+            for secret_id in [aardvark_secret_id.to_string(), String::from("badger")] {
+                let response = send(repositories.router(), Method::DELETE, &format!("{}/{secret_id}", secrets_path(&registered)), Some(token), None).await;
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            }
+
+            assert_some!(repositories.client_authenticator().authenticate_as_confidential_client("aardvark", b"badger"));
+        }
+
+        #[tokio::test]
+        async fn should_require_the_registration_access_token() {
+            let router = Repositories::new().router();
+            let registered = register(router.clone(), confidential_client()).await;
+
+            for method in [Method::GET, Method::POST] {
+                let response = send(router.clone(), method, &secrets_path(&registered), None, None).await;
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use chrono::{DateTime, Utc};
+use crate::client::ClientId;
+use crate::scope::Scopes;
+use crate::user::Username;
+
+// What a resource owner has agreed a client may do on their behalf, so they're only asked again about new scopes.
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Consent {
+    pub username: Username,
+    pub client_id: ClientId,
+    pub scopes: Scopes,
+    pub granted_at: DateTime<Utc>,
+}
+
+impl Consent {
+    pub fn new(username: Username, client_id: ClientId, scopes: Scopes) -> Self {
+        Self {
+            username,
+            client_id,
+            scopes,
+            granted_at: Utc::now(),
+        }
+    }
+
+    // Those of the requested scopes the resource owner has yet to agree to.
+    pub fn not_granted(&self, Scopes(requested): &Scopes) -> Scopes {
+        Scopes(requested.difference(&self.scopes.0).cloned().collect())
+    }
+}
+
+pub trait ConsentRepository: Send + Sync + Clone {
+    fn find_consent(&self, username: &Username, client_id: &ClientId) -> Option<Consent>;
+    // Replaces any consent previously given by the resource owner to the client.
+    fn save_consent(&self, consent: &Consent);
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryConsentRepository {
+    store: Arc<Mutex<HashMap<(Username, ClientId), Consent>>>,
+}
+
+impl InMemoryConsentRepository {
+    pub fn new() -> Self {
+        Self { store: Arc::new(Mutex::new(HashMap::new())) }
+    }
+    fn lock_store(&self) -> MutexGuard<'_, HashMap<(Username, ClientId), Consent>> {
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ConsentRepository for InMemoryConsentRepository {
+    fn find_consent(&self, username: &Username, client_id: &ClientId) -> Option<Consent> {
+        self.lock_store().get(&(username.clone(), client_id.clone())).cloned()
+    }
+    fn save_consent(&self, consent: &Consent) {
+        self.lock_store().insert((consent.username.clone(), consent.client_id.clone()), consent.clone());
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use std::collections::HashSet;
+    use crate::scope::Scope;
+
+    fn new_consent(client_id: &str, scopes: &[&'static str]) -> Consent {
+        Consent::new(
+            Username::from(String::from("aardvark")),
+            ClientId::from(String::from(client_id)),
+            Scopes(scopes.iter().map(|scope| Scope::from(*scope)).collect()),
+        )
+    }
+
+    #[test]
+    fn should_find_the_consent_given_to_the_client() {
+        let repository = InMemoryConsentRepository::new();
+        let consent = new_consent("badger", &["basic"]);
+        repository.save_consent(&consent);
+
+        assert_some_eq_x!(repository.find_consent(&Username::from(String::from("aardvark")), &ClientId::from(String::from("badger"))), consent);
+        assert_none!(repository.find_consent(&Username::from(String::from("aardvark")), &ClientId::from(String::from("dingo"))));
+        assert_none!(repository.find_consent(&Username::from(String::from("badger")), &ClientId::from(String::from("badger"))));
+    }
+
+    #[test]
+    fn should_replace_the_consent_previously_given_to_the_client() {
+        let repository = InMemoryConsentRepository::new();
+        repository.save_consent(&new_consent("badger", &["basic"]));
+        let consent = new_consent("badger", &["basic", "read"]);
+        repository.save_consent(&consent);
+
+        assert_some_eq_x!(repository.find_consent(&consent.username, &consent.client_id), consent);
+    }
+
+    #[test]
+    fn should_only_return_the_scopes_not_yet_granted() {
+        let consent = new_consent("badger", &["basic", "read"]);
+
+        assert_eq!(consent.not_granted(&Scopes(HashSet::from([Scope::from("basic"), Scope::from("write")]))), Scopes(HashSet::from([Scope::from("write")])));
+        assert_eq!(consent.not_granted(&Scopes(HashSet::from([Scope::from("read")]))), Scopes::default());
+    }
+}
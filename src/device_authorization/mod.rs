@@ -0,0 +1,6 @@
+mod route;
+mod request;
+mod response;
+mod page;
+
+pub use route::*;
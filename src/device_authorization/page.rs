@@ -0,0 +1,87 @@
+use crate::token::DeviceCode;
+use crate::util::html::escape;
+use crate::util::value_struct::ValueStruct;
+
+// TODO - Replace with a templating engine once the pages grow beyond a simple form
+pub fn enter_code(maybe_user_code: Option<&str>, maybe_error: Option<&str>) -> String {
+
+    let user_code = escape(maybe_user_code.unwrap_or_default());
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>Connect a device</title>
+  </head>
+  <body>
+    <h1>Enter the code shown on your device</h1>
+    {error}
+    <form method="get" action="/device">
+      <label>Code <input type="text" name="user_code" value="{user_code}" autocomplete="off" required></label>
+      <button type="submit">Continue</button>
+    </form>
+  </body>
+</html>
+"#, error = alert(maybe_error))
+}
+
+// The client and what it's asking for are shown before anything is approved, so a code passed on by someone else
+// can be recognised for what it is.
+// https://www.rfc-editor.org/rfc/rfc8628#section-5.4
+pub fn verification(device_code: &DeviceCode, scopes: &[(String, String)], maybe_error: Option<&str>) -> String {
+
+    let scope_items = scopes.iter()
+        .map(|(scope, description)| format!("<li>{} ({})</li>", escape(description), escape(scope)))
+        .collect::<Vec<String>>()
+        .join("\n      ");
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>Connect a device</title>
+  </head>
+  <body>
+    <h1>Allow {client_id} to access your account?</h1>
+    <p>Only continue if you started this on your own device, and it shows the code {user_code}.</p>
+    <ul>
+      {scope_items}
+    </ul>
+    {error}
+    <form method="post" action="/device">
+      <input type="hidden" name="user_code" value="{user_code}">
+      <label>Username <input type="text" name="username" autocomplete="username" required></label>
+      <label>Password <input type="password" name="password" autocomplete="current-password" required></label>
+      <button type="submit" name="decision" value="approve">Approve</button>
+      <button type="submit" name="decision" value="deny">Deny</button>
+    </form>
+  </body>
+</html>
+"#,
+        client_id = escape(device_code.client_id.value()),
+        user_code = escape(&device_code.user_code),
+        error = alert(maybe_error),
+    )
+}
+
+fn alert(maybe_error: Option<&str>) -> String {
+    maybe_error
+        .map(|error| format!(r#"<p role="alert">{}</p>"#, escape(error)))
+        .unwrap_or_default()
+}
+
+pub fn complete(approved: bool) -> String {
+    let outcome = if approved { "approved" } else { "denied" };
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <title>Device {outcome}</title>
+  </head>
+  <body>
+    <h1>Device {outcome}</h1>
+    <p>You may now return to your device.</p>
+  </body>
+</html>
+"#)
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use axum::extract::{FromRequest, Request};
+use axum::extract::rejection::FormRejection;
+use axum::{Form, Json};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use crate::client::{ClientPrincipal, GrantType};
+use crate::device_authorization::response::{DeviceAuthorizationResponse, ErrorType};
+use crate::scope::Scopes;
+use crate::scope::parser::parse_scopes;
+use crate::scope::repository::{ScopeRepository, ScopeRepositoryState};
+
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct DeviceAuthorizationRequest {
+    pub principal: ClientPrincipal,
+    pub scopes: Scopes,
+}
+
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct DeviceAuthorizationForm(pub DeviceAuthorizationRequest);
+
+// The request is a URL encoded form, but the responses are JSON.
+impl<S> FromRequest<S> for DeviceAuthorizationForm
+where
+    S: ScopeRepositoryState + Send + Sync,
+    Form<HashMap<String, String>>: FromRequest<S, Rejection = FormRejection>,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+
+        let principal = req.extensions()
+            .get::<ClientPrincipal>()
+            .cloned()
+            .ok_or_else(|| handle_validation_failure(DeviceAuthorizationResponse::Failure {
+                error: ErrorType::InvalidRequest,
+                error_description: Some("missing client authentication".into()),
+            }))?;
+
+        match Form::<HashMap<String, String>>::from_request(req, state).await {
+            Err(rejection) => Err(handle_form_rejection(rejection)),
+            Ok(Form(request)) => match validate_device_authorization_request(principal, state.scope_repository(), request) {
+                Err(failure) => Err(handle_validation_failure(failure)),
+                Ok(valid) => Ok(DeviceAuthorizationForm(valid)),
+            }
+        }
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc8628#section-3.1
+pub fn validate_device_authorization_request<Q: ScopeRepository>(principal: ClientPrincipal, scope_repository: &Q, request: HashMap<String, String>) -> Result<DeviceAuthorizationRequest, DeviceAuthorizationResponse> {
+
+    if !principal.can_perform_grant_type(&GrantType::DeviceCode) {
+        Err(DeviceAuthorizationResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some(format!("not authorized to: {:?}", GrantType::DeviceCode)),
+        })?
+    }
+
+    let scopes = match parse_scopes(scope_repository, Some(principal.id()), request.get("scope")) {
+        Err(_) => Err(DeviceAuthorizationResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        })?,
+        Ok(Some(Scopes(scopes))) if !scopes.iter().all(|scope| principal.can_be_issued(scope)) => {
+            Err(DeviceAuthorizationResponse::Failure {
+                error: ErrorType::InvalidScope,
+                error_description: Some("invalid parameter: scope".into()),
+            })?
+        },
+        Ok(maybe_scopes) => maybe_scopes.unwrap_or_else(|| principal.default_scopes()),
+    };
+
+    Ok(DeviceAuthorizationRequest {
+        principal,
+        scopes,
+    })
+}
+
+fn handle_validation_failure(failure: DeviceAuthorizationResponse) -> Response {
+    (StatusCode::BAD_REQUEST, Json(failure)).into_response()
+}
+
+fn handle_form_rejection(rejection: FormRejection) -> Response {
+    (rejection.status(), Json(DeviceAuthorizationResponse::Failure {
+        error: ErrorType::InvalidRequest,
+        error_description: Some(rejection.body_text()),
+    })).into_response()
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use std::collections::HashSet;
+    use crate::client::ClientType;
+    use crate::client::configuration::ClientConfiguration;
+    use crate::scope::Scope;
+    use crate::scope::repository::InMemoryScopeRepository;
+    use crate::map_of;
+
+    #[test]
+    fn should_return_unauthorized_client_for_an_unauthorised_client() {
+        let result = validate_device_authorization_request(
+            ClientPrincipal::new_principal(ClientConfiguration {
+                client_id: String::from("unauthorised").into(),
+                client_type: ClientType::Confidential,
+                redirect_uris: Default::default(),
+                allowed_scopes: Default::default(),
+                default_scopes: Default::default(),
+                allowed_actions: Default::default(),
+                allowed_grant_types: Default::default(),
+                allowed_audiences: Default::default(),
+                jwks: Default::default(),
+                access_token_format: Default::default(),
+            }),
+            &InMemoryScopeRepository::new(),
+            map_of! {},
+        );
+
+        assert_eq!(assert_err!(result), DeviceAuthorizationResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some("not authorized to: DeviceCode".into()),
+        });
+    }
+
+    #[test]
+    fn should_return_invalid_scope_on_a_scope_the_client_is_not_allowed() {
+        let result = validate_device_authorization_request(
+            ClientPrincipal::new_public_principal("badger"),
+            &InMemoryScopeRepository::new(),
+            map_of! { "scope" => "basic openid" },
+        );
+
+        assert_eq!(assert_err!(result), DeviceAuthorizationResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        });
+    }
+
+    #[test]
+    fn should_return_valid_request_for_a_public_client() {
+        let result = validate_device_authorization_request(
+            ClientPrincipal::new_public_principal("badger"),
+            &InMemoryScopeRepository::new(),
+            map_of! { "scope" => "basic" },
+        );
+
+        assert_eq!(assert_ok!(result), DeviceAuthorizationRequest {
+            principal: ClientPrincipal::new_public_principal("badger"),
+            scopes: Scopes(HashSet::from([Scope::from("basic")])),
+        });
+    }
+
+    #[test]
+    fn should_return_valid_request_with_the_default_scopes_without_any_scope() {
+        let result = validate_device_authorization_request(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            &InMemoryScopeRepository::new(),
+            map_of! {},
+        );
+
+        assert_eq!(assert_ok!(result), DeviceAuthorizationRequest {
+            principal: ClientPrincipal::new_confidential_principal("aardvark"),
+            scopes: Scopes(HashSet::from([Scope::from("basic")])),
+        });
+    }
+}
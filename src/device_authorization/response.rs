@@ -0,0 +1,75 @@
+use serde::Serialize;
+use uuid::Uuid;
+use crate::token::DeviceCode;
+
+// https://www.rfc-editor.org/rfc/rfc8628#section-3.2
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum DeviceAuthorizationResponse {
+    Success {
+
+        // The device verification code.
+        device_code: Uuid,
+
+        // The end-user verification code.
+        user_code: String,
+
+        // The end-user verification URI on the authorization server, short and easy
+        // to remember as end users will be asked to manually type it into their user agent.
+        verification_uri: String,
+
+        // A verification URI that includes the "user_code", designed for non-textual
+        // transmission such as a QR code.
+        verification_uri_complete: String,
+
+        // The lifetime in seconds of the "device_code" and "user_code".
+        expires_in: i64,
+
+        // The minimum amount of time in seconds that the client SHOULD wait between polling requests.
+        interval: i64,
+    },
+    Failure {
+
+        // A single ASCII error code from the defined list.
+        error: ErrorType,
+
+        // Description Human-readable ASCII text providing additional information, used
+        // to assist the client developer in understanding the error that occurred.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_description: Option<String>,
+    }
+}
+
+impl DeviceAuthorizationResponse {
+
+    pub fn success(verification_uri: String, device_code: &DeviceCode) -> Self {
+        let encoded_user_code: String = form_urlencoded::byte_serialize(device_code.user_code.as_bytes()).collect();
+        DeviceAuthorizationResponse::Success {
+            device_code: device_code.id,
+            user_code: device_code.user_code.clone(),
+            verification_uri_complete: format!("{verification_uri}?user_code={encoded_user_code}"),
+            verification_uri,
+            expires_in: device_code.expires_in(),
+            interval: device_code.interval,
+        }
+    }
+}
+
+// The same error responses as the token endpoint, see https://www.rfc-editor.org/rfc/rfc8628#section-3.2
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorType {
+
+    // The request is missing a required parameter, includes an
+    // unsupported parameter value, repeats a parameter, or is otherwise malformed.
+    InvalidRequest,
+
+    // The requested scope is invalid, unknown, malformed, or exceeds
+    // the scope the client is allowed.
+    InvalidScope,
+
+    // The authenticated client is not authorized to use the device authorization grant.
+    UnauthorizedClient,
+}
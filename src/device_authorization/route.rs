@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Json, Response};
+use axum::routing::{get, post};
+use axum::{middleware, Form, Router};
+use chrono::Utc;
+use middleware::from_fn_with_state;
+use crate::client::authentication::ClientAuthenticator;
+use crate::client::middleware::require_client_authentication;
+use crate::device_authorization::page;
+use crate::device_authorization::request::DeviceAuthorizationForm;
+use crate::device_authorization::response::DeviceAuthorizationResponse;
+use crate::scope::repository::{ScopeRepository, ScopeRepositoryState};
+use crate::token::{user_code, DeviceCode, DeviceCodeStatus};
+use crate::token::repository::TokenRepository;
+use crate::user::authentication::UserAuthenticator;
+
+pub const DEVICE_AUTHORIZATION_ENDPOINT: &str = "/device_authorization";
+pub const DEVICE_VERIFICATION_ENDPOINT: &str = "/device";
+
+// https://www.rfc-editor.org/rfc/rfc8628#section-3.1
+pub fn route<D, C, U, Q>(state: DeviceAuthorizationState<D, C, U, Q>) -> Router<()>
+where
+    D: TokenRepository<DeviceCode> + 'static,
+    C: ClientAuthenticator + 'static,
+    U: UserAuthenticator + 'static,
+    Q: ScopeRepository + 'static,
+{
+    Router::new()
+        .route(DEVICE_AUTHORIZATION_ENDPOINT, post(device_authorization_handler))
+        // Only the device authorization endpoint requires client authentication, not the resource owner's verification page.
+        .route_layer(from_fn_with_state(state.client_authenticator.clone(), require_client_authentication::<C>))
+        .route(DEVICE_VERIFICATION_ENDPOINT, get(verification_page_handler).post(verification_handler))
+        .with_state(state)
+}
+
+#[derive(Clone)]
+pub struct DeviceAuthorizationState<D: TokenRepository<DeviceCode>, C: ClientAuthenticator, U: UserAuthenticator, Q: ScopeRepository> {
+    pub issuer: String,
+    pub device_code_repository: D,
+    pub client_authenticator: C,
+    pub user_authenticator: U,
+    pub scope_repository: Q,
+}
+
+impl<D: TokenRepository<DeviceCode>, C: ClientAuthenticator, U: UserAuthenticator, Q: ScopeRepository> ScopeRepositoryState for DeviceAuthorizationState<D, C, U, Q> {
+    type ScopeRepository = Q;
+    fn scope_repository(&self) -> &Q {
+        &self.scope_repository
+    }
+}
+
+async fn device_authorization_handler<D: TokenRepository<DeviceCode>, C: ClientAuthenticator, U: UserAuthenticator, Q: ScopeRepository>(
+    State(state): State<DeviceAuthorizationState<D, C, U, Q>>,
+    DeviceAuthorizationForm(request): DeviceAuthorizationForm,
+) -> Response {
+
+    // A user code must only ever lead to the one pending device code, so draw again on the rare collision.
+    let device_code = loop {
+        let device_code = match DeviceCode::new(request.principal.id().clone(), request.scopes.clone()) {
+            Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            Ok(device_code) => device_code,
+        };
+        if find_pending_device_code(&state.device_code_repository, &device_code.user_code).is_none() {
+            break device_code
+        }
+    };
+
+    state.device_code_repository.save_token(&device_code);
+
+    let verification_uri = format!("{}{DEVICE_VERIFICATION_ENDPOINT}", state.issuer);
+
+    (StatusCode::OK, Json(DeviceAuthorizationResponse::success(verification_uri, &device_code))).into_response()
+}
+
+// https://www.rfc-editor.org/rfc/rfc8628#section-3.3
+// TODO - Replace the sign in form with a session once we have one [authenticate/authenticated]
+async fn verification_page_handler<D: TokenRepository<DeviceCode>, C: ClientAuthenticator, U: UserAuthenticator, Q: ScopeRepository>(
+    State(state): State<DeviceAuthorizationState<D, C, U, Q>>,
+    Query(parameters): Query<HashMap<String, String>>,
+) -> Html<String> {
+
+    let maybe_user_code = parameters.get("user_code").map(String::as_str);
+
+    let Some(user_code) = maybe_user_code else {
+        return Html(page::enter_code(None, None))
+    };
+
+    match find_pending_device_code(&state.device_code_repository, &user_code::normalise(user_code)).filter(DeviceCode::is_usable) {
+        None => Html(page::enter_code(maybe_user_code, Some("invalid or expired code"))),
+        Some(device_code) => Html(page::verification(&device_code, &describe_scopes(&state, &device_code), None)),
+    }
+}
+
+async fn verification_handler<D: TokenRepository<DeviceCode>, C: ClientAuthenticator, U: UserAuthenticator, Q: ScopeRepository>(
+    State(state): State<DeviceAuthorizationState<D, C, U, Q>>,
+    Form(parameters): Form<HashMap<String, String>>,
+) -> Response {
+
+    let maybe_user_code = parameters.get("user_code").map(String::as_str);
+
+    // Only a pending code can be decided upon, and only the once.
+    let maybe_device_code = maybe_user_code
+        .map(user_code::normalise)
+        .and_then(|user_code| find_pending_device_code(&state.device_code_repository, &user_code))
+        .filter(DeviceCode::is_usable);
+
+    let device_code = match maybe_device_code {
+        None => return (StatusCode::BAD_REQUEST, Html(page::enter_code(maybe_user_code, Some("invalid or expired code")))).into_response(),
+        Some(device_code) => device_code,
+    };
+
+    let maybe_user = match (parameters.get("username"), parameters.get("password")) {
+        (Some(username), Some(password)) => state.user_authenticator.authenticate(username, password.as_bytes()),
+        _ => None,
+    };
+
+    let user = match maybe_user {
+        None => return (StatusCode::UNAUTHORIZED, Html(page::verification(&device_code, &describe_scopes(&state, &device_code), Some("invalid username or password")))).into_response(),
+        Some(user) => user,
+    };
+
+    let approved = parameters.get("decision").is_some_and(|decision| decision == "approve");
+
+    let status = if approved {
+        DeviceCodeStatus::Approved { username: user.username, auth_time: Utc::now() }
+    } else {
+        DeviceCodeStatus::Denied
+    };
+
+    // Only decided if still pending, and without disturbing any polling going on at the same time.
+    let decided = state.device_code_repository.update_token(device_code.id, |device_code| {
+        let pending = matches!(device_code.status, DeviceCodeStatus::Pending);
+        if pending {
+            device_code.status = status;
+        }
+        pending
+    });
+
+    if decided != Some(true) {
+        return (StatusCode::BAD_REQUEST, Html(page::enter_code(maybe_user_code, Some("invalid or expired code")))).into_response()
+    }
+
+    Html(page::complete(approved)).into_response()
+}
+
+fn find_pending_device_code<D: TokenRepository<DeviceCode>>(device_code_repository: &D, user_code: &str) -> Option<DeviceCode> {
+    device_code_repository.find_token_where(|device_code| {
+        device_code.user_code == user_code && matches!(device_code.status, DeviceCodeStatus::Pending)
+    })
+}
+
+fn describe_scopes<D: TokenRepository<DeviceCode>, C: ClientAuthenticator, U: UserAuthenticator, Q: ScopeRepository>(
+    state: &DeviceAuthorizationState<D, C, U, Q>,
+    device_code: &DeviceCode,
+) -> Vec<(String, String)> {
+    let mut descriptions = device_code.scopes.0.iter()
+        .map(|scope| {
+            let description = state.scope_repository.find_by_name(&scope.to_string())
+                .map(|definition| definition.description)
+                .unwrap_or_default();
+            (scope.to_string(), description)
+        })
+        .collect::<Vec<_>>();
+    descriptions.sort();
+    descriptions
+}
+
+#[cfg(test)]
+mod integration_tests {
+
+    use super::*;
+
+    use assertables::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+    use http_body_util::BodyExt;
+    use base64::prelude::*;
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+    use crate::client::ClientId;
+    use crate::scope::Scopes;
+    use crate::token::repository::InMemoryTokenRepository;
+    use crate::user::Username;
+
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
+    const APPLICATION_WWW_FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
+    const TEST_CLIENT_USERNAME: &str = "aardvark";
+    const TEST_CLIENT_PASSWORD: &str = "badger";
+
+    macro_rules! under_test {
+        () => {
+            under_test!(InMemoryTokenRepository::new())
+        };
+        ($device_code_repository:expr) => {
+            route(DeviceAuthorizationState {
+                issuer: TEST_ISSUER.into(),
+                device_code_repository: $device_code_repository,
+                client_authenticator: crate::client::authentication::ClientAuthenticationService::new(
+                    TEST_ISSUER.into(),
+                    crate::client::secret::InMemoryClientSecretRepository::new(),
+                    crate::client::configuration::InMemoryClientConfigurationRepository::new(),
+                    crate::replay::InMemoryReplayCache::new(),
+                ),
+                user_authenticator: crate::user::authentication::UserAuthenticationService::new(
+                    crate::user::repository::InMemoryUserRepository::new(),
+                ),
+                scope_repository: crate::scope::repository::InMemoryScopeRepository::new(),
+            })
+        };
+    }
+
+    fn new_device_code() -> DeviceCode {
+        assert_ok!(DeviceCode::new(ClientId::from(String::from("aardvark")), Scopes::default()))
+    }
+
+    async fn extract_json_body(response: Response) -> HashMap<String, Value> {
+        let body_bytes = assert_ok!(response.into_body().collect().await);
+        assert_ok!(serde_json::from_slice(body_bytes.to_bytes().as_ref()))
+    }
+
+    async fn extract_text_body(response: Response) -> String {
+        let body_bytes = assert_ok!(response.into_body().collect().await);
+        assert_ok!(String::from_utf8(body_bytes.to_bytes().to_vec()))
+    }
+
+    async fn post_device_authorization(router: Router, maybe_authorization: Option<String>, body: &str) -> Response {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(DEVICE_AUTHORIZATION_ENDPOINT)
+            .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED);
+        if let Some(authorization) = maybe_authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        let request = assert_ok!(request.body(Body::from(body.to_string())));
+        assert_ok!(router.oneshot(request).await)
+    }
+
+    async fn post_verification(router: Router, body: &str) -> Response {
+        let request = assert_ok!(Request::builder()
+            .method(Method::POST)
+            .uri(DEVICE_VERIFICATION_ENDPOINT)
+            .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+            .body(Body::from(body.to_string()))
+        );
+        assert_ok!(router.oneshot(request).await)
+    }
+
+    fn basic_authorization() -> Option<String> {
+        Some(format!("Basic {}", BASE64_STANDARD.encode(format!("{TEST_CLIENT_USERNAME}:{TEST_CLIENT_PASSWORD}"))))
+    }
+
+    mod device_authorization {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_issue_a_device_code_to_a_confidential_client() {
+            let device_code_repository = InMemoryTokenRepository::new();
+
+            let response = post_device_authorization(under_test!(device_code_repository.clone()), basic_authorization(), "scope=basic").await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            let device_code = assert_some!(body.get("device_code").and_then(Value::as_str));
+            let user_code = assert_some!(body.get("user_code").and_then(Value::as_str));
+            assert_some_eq_x!(body.get("verification_uri"), &Value::from("http://127.0.0.1:8080/device"));
+            assert_some_eq_x!(body.get("verification_uri_complete"), &Value::from(format!("http://127.0.0.1:8080/device?user_code={user_code}")));
+            assert_some_eq_x!(body.get("expires_in"), &Value::from(DeviceCode::TIME_TO_LIVE.num_seconds()));
+            assert_some_eq_x!(body.get("interval"), &Value::from(DeviceCode::INTERVAL));
+
+            let saved = assert_some!(device_code_repository.get_token(assert_ok!(Uuid::parse_str(device_code))));
+            assert_eq!(saved.user_code, user_code);
+            assert_eq!(saved.status, DeviceCodeStatus::Pending);
+        }
+
+        #[tokio::test]
+        async fn should_issue_a_device_code_to_a_public_client() {
+            let response = post_device_authorization(under_test!(), None, "client_id=badger").await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some!(body.get("device_code"));
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unauthenticated_client() {
+            let response = post_device_authorization(under_test!(), None, "scope=basic").await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_client_not_allowed_the_device_code_grant() {
+            let authorization = format!("Basic {}", BASE64_STANDARD.encode("dingo:echidna"));
+
+            let response = post_device_authorization(under_test!(), Some(authorization), "").await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("error"), &Value::from("unauthorized_client"));
+        }
+    }
+
+    mod verification {
+        use super::*;
+
+        async fn get_verification_page(router: Router, query: &str) -> String {
+            let request = assert_ok!(Request::builder()
+                .method(Method::GET)
+                .uri(format!("{DEVICE_VERIFICATION_ENDPOINT}{query}"))
+                .body(Body::empty())
+            );
+            let response = assert_ok!(router.oneshot(request).await);
+            assert_eq!(response.status(), StatusCode::OK);
+            extract_text_body(response).await
+        }
+
+        #[tokio::test]
+        async fn should_ask_for_the_user_code() {
+            let body = get_verification_page(under_test!(), "").await;
+
+            assert_contains!(body, r#"<form method="get" action="/device">"#);
+            assert_not_contains!(body, "Approve");
+        }
+
+        #[tokio::test]
+        async fn should_ask_again_for_an_unknown_user_code() {
+            let body = get_verification_page(under_test!(), "?user_code=%3Cbadger%3E").await;
+
+            assert_contains!(body, r#"<form method="get" action="/device">"#);
+            assert_contains!(body, r#"name="user_code" value="&lt;badger&gt;""#);
+            assert_contains!(body, "invalid or expired code");
+            assert_not_contains!(body, "Approve");
+        }
+
+        #[tokio::test]
+        async fn should_show_the_client_and_scopes_before_asking_for_approval() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = DeviceCode {
+                scopes: Scopes(std::collections::HashSet::from([crate::scope::Scope::from("basic")])),
+                ..new_device_code()
+            };
+            device_code_repository.save_token(&device_code);
+
+            let typed = device_code.user_code.to_lowercase();
+            let body = get_verification_page(under_test!(device_code_repository), &format!("?user_code={typed}")).await;
+
+            assert_contains!(body, "Allow aardvark to access your account?");
+            assert_contains!(body, "(basic)</li>");
+            assert_contains!(body, &format!(r#"<input type="hidden" name="user_code" value="{}">"#, device_code.user_code));
+            assert_contains!(body, r#"<form method="post" action="/device">"#);
+        }
+
+        #[tokio::test]
+        async fn should_not_show_a_user_code_that_has_already_been_decided() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = DeviceCode { status: DeviceCodeStatus::Denied, ..new_device_code() };
+            device_code_repository.save_token(&device_code);
+
+            let body = get_verification_page(under_test!(device_code_repository), &format!("?user_code={}", device_code.user_code)).await;
+
+            assert_contains!(body, "invalid or expired code");
+            assert_not_contains!(body, "aardvark");
+        }
+
+        #[tokio::test]
+        async fn should_approve_the_device_code_however_the_user_code_was_typed() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = new_device_code();
+            device_code_repository.save_token(&device_code);
+
+            let typed = device_code.user_code.to_lowercase().replace('-', "");
+            let response = post_verification(under_test!(device_code_repository.clone()), &format!("user_code={typed}&username=aardvark&password=P%4055w0rd&decision=approve")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_contains!(extract_text_body(response).await, "Device approved");
+
+            let saved = assert_some!(device_code_repository.get_token(device_code.id));
+            assert_matches!(saved.status, DeviceCodeStatus::Approved { username, .. } if username == Username::from(String::from("aardvark")));
+        }
+
+        #[tokio::test]
+        async fn should_deny_the_device_code() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = new_device_code();
+            device_code_repository.save_token(&device_code);
+
+            let response = post_verification(under_test!(device_code_repository.clone()), &format!("user_code={}&username=aardvark&password=P%4055w0rd&decision=deny", device_code.user_code)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_contains!(extract_text_body(response).await, "Device denied");
+
+            let saved = assert_some!(device_code_repository.get_token(device_code.id));
+            assert_eq!(saved.status, DeviceCodeStatus::Denied);
+        }
+
+        #[tokio::test]
+        async fn should_reject_invalid_user_credentials() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = new_device_code();
+            device_code_repository.save_token(&device_code);
+
+            let response = post_verification(under_test!(device_code_repository.clone()), &format!("user_code={}&username=aardvark&password=cicada&decision=approve", device_code.user_code)).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_contains!(extract_text_body(response).await, "invalid username or password");
+
+            let saved = assert_some!(device_code_repository.get_token(device_code.id));
+            assert_eq!(saved.status, DeviceCodeStatus::Pending);
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unknown_user_code() {
+            let response = post_verification(under_test!(), "user_code=BCDF-GHJK&username=aardvark&password=P%4055w0rd&decision=approve").await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_contains!(extract_text_body(response).await, "invalid or expired code");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_user_code_that_has_already_been_decided() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = DeviceCode { status: DeviceCodeStatus::Denied, ..new_device_code() };
+            device_code_repository.save_token(&device_code);
+
+            let response = post_verification(under_test!(device_code_repository.clone()), &format!("user_code={}&username=aardvark&password=P%4055w0rd&decision=approve", device_code.user_code)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let saved = assert_some!(device_code_repository.get_token(device_code.id));
+            assert_eq!(saved.status, DeviceCodeStatus::Denied);
+        }
+    }
+}
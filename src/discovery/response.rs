@@ -0,0 +1,123 @@
+use std::fmt::Display;
+use jsonwebtoken::Algorithm;
+use serde::Serialize;
+use crate::authorization::{ResponseType, AUTHORIZATION_ENDPOINT, PUSHED_AUTHORIZATION_REQUEST_ENDPOINT};
+use crate::client::GrantType;
+use crate::assertion::ASSERTION_SIGNING_ALGORITHMS;
+use crate::client_registration::REGISTRATION_ENDPOINT;
+use crate::client::middleware::{CLIENT_AUTHENTICATION_METHODS, CONFIDENTIAL_CLIENT_AUTHENTICATION_METHODS};
+use crate::device_authorization::DEVICE_AUTHORIZATION_ENDPOINT;
+use crate::key::{JWKS_ENDPOINT, SUPPORTED_ALGORITHMS};
+use crate::openid::PASSWORD_ACR;
+use crate::pkce::CodeChallengeMethod;
+use crate::scope::repository::ScopeRepository;
+use crate::token_exchange::TOKEN_ENDPOINT;
+use crate::token_introspection::INTROSPECTION_ENDPOINT;
+use crate::token_revocation::REVOCATION_ENDPOINT;
+use crate::userinfo::USERINFO_ENDPOINT;
+
+// https://www.rfc-editor.org/rfc/rfc8414#section-2
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug))]
+pub struct AuthorizationServerMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+    pub scopes_supported: Vec<String>,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub token_endpoint_auth_signing_alg_values_supported: Vec<Algorithm>,
+    pub revocation_endpoint: String,
+    pub revocation_endpoint_auth_methods_supported: Vec<String>,
+    pub revocation_endpoint_auth_signing_alg_values_supported: Vec<Algorithm>,
+    pub introspection_endpoint: String,
+    pub introspection_endpoint_auth_methods_supported: Vec<String>,
+    pub introspection_endpoint_auth_signing_alg_values_supported: Vec<Algorithm>,
+    pub code_challenge_methods_supported: Vec<String>,
+    // https://www.rfc-editor.org/rfc/rfc8628#section-4
+    pub device_authorization_endpoint: String,
+    // https://www.rfc-editor.org/rfc/rfc9449#section-5.1
+    pub dpop_signing_alg_values_supported: Vec<Algorithm>,
+    // https://www.rfc-editor.org/rfc/rfc9126#section-5
+    pub pushed_authorization_request_endpoint: String,
+    // Only required of the clients configured to, not globally.
+    pub require_pushed_authorization_requests: bool,
+    // https://www.rfc-editor.org/rfc/rfc7591#section-3
+    pub registration_endpoint: String,
+}
+
+impl AuthorizationServerMetadata {
+    // Built from the same constants and enums the routes use, so it describes what is actually mounted.
+    pub fn new<Q: ScopeRepository>(issuer: &str, scope_repository: &Q) -> Self {
+        Self {
+            issuer: issuer.into(),
+            authorization_endpoint: format!("{issuer}{AUTHORIZATION_ENDPOINT}"),
+            token_endpoint: format!("{issuer}{TOKEN_ENDPOINT}"),
+            jwks_uri: format!("{issuer}{JWKS_ENDPOINT}"),
+            scopes_supported: scopes_supported(scope_repository),
+            response_types_supported: to_strings(ResponseType::VALUES),
+            grant_types_supported: to_strings(GrantType::VALUES),
+            token_endpoint_auth_methods_supported: to_strings(CLIENT_AUTHENTICATION_METHODS),
+            token_endpoint_auth_signing_alg_values_supported: ASSERTION_SIGNING_ALGORITHMS.to_vec(),
+            revocation_endpoint: format!("{issuer}{REVOCATION_ENDPOINT}"),
+            revocation_endpoint_auth_methods_supported: to_strings(CLIENT_AUTHENTICATION_METHODS),
+            revocation_endpoint_auth_signing_alg_values_supported: ASSERTION_SIGNING_ALGORITHMS.to_vec(),
+            introspection_endpoint: format!("{issuer}{INTROSPECTION_ENDPOINT}"),
+            introspection_endpoint_auth_methods_supported: to_strings(CONFIDENTIAL_CLIENT_AUTHENTICATION_METHODS),
+            introspection_endpoint_auth_signing_alg_values_supported: ASSERTION_SIGNING_ALGORITHMS.to_vec(),
+            code_challenge_methods_supported: to_strings(CodeChallengeMethod::VALUES),
+            device_authorization_endpoint: format!("{issuer}{DEVICE_AUTHORIZATION_ENDPOINT}"),
+            dpop_signing_alg_values_supported: SUPPORTED_ALGORITHMS.to_vec(),
+            pushed_authorization_request_endpoint: format!("{issuer}{PUSHED_AUTHORIZATION_REQUEST_ENDPOINT}"),
+            require_pushed_authorization_requests: false,
+            registration_endpoint: format!("{issuer}{REGISTRATION_ENDPOINT}"),
+        }
+    }
+}
+
+fn to_strings<T: Display>(values: &[T]) -> Vec<String> {
+    values.iter().map(T::to_string).collect()
+}
+
+// Sorted, as the registry has no order of its own, so the metadata is stable.
+fn scopes_supported<Q: ScopeRepository>(scope_repository: &Q) -> Vec<String> {
+    // Templates aren't scopes that can be requested as they are, they need their parameters filling in.
+    let mut scopes: Vec<String> = scope_repository.find_all().iter()
+        .filter(|definition| !definition.is_template())
+        .map(|definition| definition.scope.to_string())
+        .collect();
+    scopes.sort();
+    scopes
+}
+
+// https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug))]
+pub struct OpenIdProviderMetadata {
+    #[serde(flatten)]
+    pub authorization_server: AuthorizationServerMetadata,
+    pub userinfo_endpoint: String,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+    pub acr_values_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
+
+impl OpenIdProviderMetadata {
+    pub fn new<Q: ScopeRepository>(issuer: &str, scope_repository: &Q) -> Self {
+        Self {
+            authorization_server: AuthorizationServerMetadata::new(issuer, scope_repository),
+            userinfo_endpoint: format!("{issuer}{USERINFO_ENDPOINT}"),
+            // Every client sees the same subject, the username.
+            subject_types_supported: vec!["public".into()],
+            id_token_signing_alg_values_supported: SUPPORTED_ALGORITHMS.to_vec(),
+            acr_values_supported: vec![PASSWORD_ACR.into()],
+            claims_supported: to_strings(&[
+                "iss", "sub", "aud", "exp", "iat", "auth_time", "nonce", "at_hash", "acr",
+                "name", "preferred_username", "email", "email_verified",
+            ]),
+        }
+    }
+}
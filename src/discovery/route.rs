@@ -0,0 +1,134 @@
+use axum::extract::State;
+use axum::{Json, Router};
+use axum::routing::get;
+use crate::discovery::response::{AuthorizationServerMetadata, OpenIdProviderMetadata};
+use crate::scope::repository::ScopeRepository;
+
+pub const METADATA_ENDPOINT: &str = "/.well-known/oauth-authorization-server";
+pub const OPENID_CONFIGURATION_ENDPOINT: &str = "/.well-known/openid-configuration";
+
+// https://www.rfc-editor.org/rfc/rfc8414#section-3
+// https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderConfig
+pub fn route<S, Q: ScopeRepository + 'static>(state: DiscoveryState<Q>) -> Router<S> {
+    Router::new()
+        .route(METADATA_ENDPOINT, get(metadata_handler))
+        .route(OPENID_CONFIGURATION_ENDPOINT, get(openid_configuration_handler))
+        .with_state(state)
+}
+
+#[derive(Clone)]
+pub struct DiscoveryState<Q: ScopeRepository> {
+    pub issuer: String,
+    pub scope_repository: Q,
+}
+
+async fn metadata_handler<Q: ScopeRepository>(State(state): State<DiscoveryState<Q>>) -> Json<AuthorizationServerMetadata> {
+    Json(AuthorizationServerMetadata::new(&state.issuer, &state.scope_repository))
+}
+
+async fn openid_configuration_handler<Q: ScopeRepository>(State(state): State<DiscoveryState<Q>>) -> Json<OpenIdProviderMetadata> {
+    Json(OpenIdProviderMetadata::new(&state.issuer, &state.scope_repository))
+}
+
+#[cfg(test)]
+mod integration_tests {
+
+    use super::*;
+    use assertables::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request, StatusCode};
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+    use crate::client::GrantType;
+    use crate::scope::repository::InMemoryScopeRepository;
+
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
+
+    async fn get_metadata() -> Value {
+        get(METADATA_ENDPOINT).await
+    }
+
+    async fn get(uri: &str) -> Value {
+        let request = assert_ok!(Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .body(Body::empty())
+        );
+
+        let router = route::<(), _>(DiscoveryState { issuer: TEST_ISSUER.into(), scope_repository: InMemoryScopeRepository::new() });
+
+        let response = assert_ok!(router.oneshot(request).await);
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = assert_ok!(response.into_body().collect().await).to_bytes();
+        assert_ok!(serde_json::from_slice(&body))
+    }
+
+    #[tokio::test]
+    async fn should_describe_the_issuer_and_its_endpoints() {
+        let metadata = get_metadata().await;
+
+        assert_eq!(metadata["issuer"], TEST_ISSUER);
+        assert_eq!(metadata["authorization_endpoint"], "http://127.0.0.1:8080/authorize");
+        assert_eq!(metadata["token_endpoint"], "http://127.0.0.1:8080/token");
+        assert_eq!(metadata["jwks_uri"], "http://127.0.0.1:8080/jwks.json");
+        assert_eq!(metadata["revocation_endpoint"], "http://127.0.0.1:8080/revoke");
+        assert_eq!(metadata["introspection_endpoint"], "http://127.0.0.1:8080/introspect");
+        assert_eq!(metadata["device_authorization_endpoint"], "http://127.0.0.1:8080/device_authorization");
+        assert_eq!(metadata["pushed_authorization_request_endpoint"], "http://127.0.0.1:8080/par");
+        assert_eq!(metadata["registration_endpoint"], "http://127.0.0.1:8080/register");
+    }
+
+    #[tokio::test]
+    async fn should_describe_what_is_supported() {
+        let metadata = get_metadata().await;
+
+        assert_eq!(metadata["response_types_supported"], json!(["code"]));
+        assert_eq!(metadata["token_endpoint_auth_methods_supported"], json!(["client_secret_basic", "client_secret_jwt", "private_key_jwt", "none"]));
+        assert_eq!(metadata["token_endpoint_auth_signing_alg_values_supported"], json!(["RS256", "ES256", "EdDSA", "HS256"]));
+        assert_eq!(metadata["revocation_endpoint_auth_methods_supported"], json!(["client_secret_basic", "client_secret_jwt", "private_key_jwt", "none"]));
+        assert_eq!(metadata["introspection_endpoint_auth_methods_supported"], json!(["client_secret_basic", "client_secret_jwt", "private_key_jwt"]));
+        assert_eq!(metadata["code_challenge_methods_supported"], json!(["plain", "S256"]));
+        assert_eq!(metadata["dpop_signing_alg_values_supported"], json!(["RS256", "ES256", "EdDSA"]));
+        assert_eq!(metadata["require_pushed_authorization_requests"], json!(false));
+    }
+
+    #[tokio::test]
+    async fn should_list_every_grant_type_and_scope() {
+        let metadata = get_metadata().await;
+
+        let grant_types = assert_some!(metadata["grant_types_supported"].as_array());
+        assert_eq!(grant_types.len(), GrantType::VALUES.len());
+        for grant_type in GrantType::VALUES {
+            assert_contains!(grant_types, &Value::String(grant_type.to_string()));
+        }
+
+        assert_eq!(metadata["scopes_supported"], json!(["basic", "email", "openid", "profile", "read", "write"]));
+    }
+
+    mod openid_configuration {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_include_the_authorization_server_metadata() {
+            let configuration = get(OPENID_CONFIGURATION_ENDPOINT).await;
+            let metadata = get_metadata().await;
+
+            for (name, value) in assert_some!(metadata.as_object()) {
+                assert_eq!(&configuration[name], value);
+            }
+        }
+
+        #[tokio::test]
+        async fn should_describe_the_openid_provider() {
+            let configuration = get(OPENID_CONFIGURATION_ENDPOINT).await;
+
+            assert_eq!(configuration["userinfo_endpoint"], "http://127.0.0.1:8080/userinfo");
+            assert_eq!(configuration["subject_types_supported"], json!(["public"]));
+            assert_eq!(configuration["id_token_signing_alg_values_supported"], json!(["RS256", "ES256", "EdDSA"]));
+            assert_contains!(assert_some!(configuration["scopes_supported"].as_array()), &json!("openid"));
+            assert_contains!(assert_some!(configuration["claims_supported"].as_array()), &json!("at_hash"));
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use chrono::{DateTime, TimeDelta, Utc};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use crate::key::SUPPORTED_ALGORITHMS;
+use crate::replay::ReplayCache;
+use crate::token::Confirmation;
+
+// https://www.rfc-editor.org/rfc/rfc9449#section-4.1
+pub const DPOP_HEADER: &str = "DPoP";
+
+// https://www.rfc-editor.org/rfc/rfc9449#section-4.2
+pub const DPOP_PROOF_TYPE: &str = "dpop+jwt";
+
+// TODO - Extract into configuration
+// How far either side of now a proof may have been issued, to allow for clock skew between client and server.
+pub const PROOF_LEEWAY: TimeDelta = TimeDelta::seconds(60);
+
+// https://www.rfc-editor.org/rfc/rfc9449#section-4.2
+#[derive(Deserialize)]
+struct ProofClaims {
+    jti: String,
+    htm: String,
+    htu: String,
+    iat: i64,
+    ath: Option<String>,
+}
+
+// Checks a proof of possession was made for this request, returning the key the issued token should be bound to.
+// When presenting an access token the proof must also be made for that token.
+// https://www.rfc-editor.org/rfc/rfc9449#section-4.3
+pub fn verify_proof<P: ReplayCache>(replay_cache: &P, proof: &str, method: &str, uri: &str, access_token: Option<&str>) -> Result<Confirmation, String> {
+
+    let header = decode_header(proof).map_err(|_| "malformed proof")?;
+
+    if header.typ.as_deref() != Some(DPOP_PROOF_TYPE) {
+        Err("invalid proof type")?
+    }
+
+    if !SUPPORTED_ALGORITHMS.contains(&header.alg) {
+        Err("unsupported proof algorithm")?
+    }
+
+    let jwk = header.jwk.ok_or("missing proof key")?;
+
+    // Computed before trusting the key, as it also rejects any key that doesn't match the algorithm.
+    let jkt = thumbprint(header.alg, &jwk).ok_or("invalid proof key")?;
+    let key = DecodingKey::from_jwk(&jwk).map_err(|_| "invalid proof key")?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.required_spec_claims.clear();
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+
+    let claims = decode::<ProofClaims>(proof, &key, &validation)
+        .map_err(|_| "invalid proof")?
+        .claims;
+
+    if claims.htm != method {
+        Err("proof was not made for this method")?
+    }
+
+    // Any query or fragment is ignored when comparing the target URI.
+    if claims.htu.split(['?', '#']).next() != Some(uri) {
+        Err("proof was not made for this uri")?
+    }
+
+    if let Some(access_token) = access_token && claims.ath != Some(access_token_hash(access_token)) {
+        Err("proof was not made for this access token")?
+    }
+
+    let now = Utc::now();
+    let issued_at = DateTime::from_timestamp(claims.iat, 0).ok_or("invalid proof issued at")?;
+
+    if issued_at < now - PROOF_LEEWAY || issued_at > now + PROOF_LEEWAY {
+        Err("proof has expired or was issued in the future")?
+    }
+
+    // A proof can only ever be used once, it's remembered for as long as it would otherwise be accepted.
+    if !replay_cache.first_use(&format!("{jkt}:{}", claims.jti), issued_at + PROOF_LEEWAY) {
+        Err("proof has already been used")?
+    }
+
+    Ok(Confirmation { jkt })
+}
+
+// https://www.rfc-editor.org/rfc/rfc9449#section-4.2
+fn access_token_hash(access_token: &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(access_token))
+}
+
+// Computed here rather than by jsonwebtoken, which panics given a curve it wasn't expecting.
+// https://www.rfc-editor.org/rfc/rfc7638#section-3
+fn thumbprint(algorithm: Algorithm, jwk: &Jwk) -> Option<String> {
+    let members = match (algorithm, &jwk.algorithm) {
+        (Algorithm::RS256, AlgorithmParameters::RSA(rsa)) => {
+            format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, rsa.e, rsa.n)
+        },
+        (Algorithm::ES256, AlgorithmParameters::EllipticCurve(ec)) if ec.curve == EllipticCurve::P256 => {
+            format!(r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#, ec.x, ec.y)
+        },
+        (Algorithm::EdDSA, AlgorithmParameters::OctetKeyPair(okp)) if okp.curve == EllipticCurve::Ed25519 => {
+            format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, okp.x)
+        },
+        _ => return None,
+    };
+    Some(BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(members)))
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use assertables::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+    use crate::dpop::{access_token_hash, thumbprint, DPOP_PROOF_TYPE};
+    use crate::key::SigningKey;
+    use crate::key::test_support::TEST_EC_KEY;
+
+    // A client's own key, which just happens to be one of ours.
+    pub fn test_proof_key() -> SigningKey {
+        assert_ok!(SigningKey::from_pem("dpop", TEST_EC_KEY))
+    }
+
+    pub fn test_proof_thumbprint() -> String {
+        let key = test_proof_key();
+        assert_some!(thumbprint(key.algorithm, key.public_key()))
+    }
+
+    pub fn sign_proof(claims: Value) -> String {
+        let key = test_proof_key();
+        let header = Header {
+            typ: Some(DPOP_PROOF_TYPE.into()),
+            jwk: Some(key.public_key().clone()),
+            ..Header::new(key.algorithm)
+        };
+        let encoding_key = assert_ok!(EncodingKey::from_ec_pem(TEST_EC_KEY));
+        assert_ok!(encode(&header, &claims, &encoding_key))
+    }
+
+    pub fn new_proof(method: &str, uri: &str) -> String {
+        sign_proof(json!({
+            "jti": Uuid::new_v4().to_string(),
+            "htm": method,
+            "htu": uri,
+            "iat": chrono::Utc::now().timestamp(),
+        }))
+    }
+
+    pub fn new_access_token_proof(method: &str, uri: &str, access_token: &str) -> String {
+        sign_proof(json!({
+            "jti": Uuid::new_v4().to_string(),
+            "htm": method,
+            "htu": uri,
+            "iat": chrono::Utc::now().timestamp(),
+            "ath": access_token_hash(access_token),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+    use crate::dpop::test_support::*;
+    use crate::key::test_support::TEST_EC_KEY;
+    use crate::replay::InMemoryReplayCache;
+
+    const TEST_METHOD: &str = "POST";
+    const TEST_URI: &str = "http://127.0.0.1:8080/token";
+
+    fn verify(proof: &str) -> Result<Confirmation, String> {
+        verify_proof(&InMemoryReplayCache::new(), proof, TEST_METHOD, TEST_URI, None)
+    }
+
+    #[test]
+    fn should_bind_to_the_thumbprint_of_the_proof_key() {
+        let confirmation = assert_ok!(verify(&new_proof(TEST_METHOD, TEST_URI)));
+
+        assert_eq!(confirmation.jkt, test_proof_thumbprint());
+    }
+
+    #[test]
+    fn should_compute_the_rfc_7638_thumbprint() {
+        // https://www.rfc-editor.org/rfc/rfc7638#section-3.1
+        let jwk: Jwk = assert_ok!(serde_json::from_value(json!({
+            "kty": "RSA",
+            "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+            "e": "AQAB",
+        })));
+
+        assert_eq!(assert_some!(thumbprint(Algorithm::RS256, &jwk)), "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
+    }
+
+    #[test]
+    fn should_reject_a_key_that_does_not_match_the_algorithm() {
+        let key = test_proof_key();
+
+        assert_none!(thumbprint(Algorithm::RS256, key.public_key()));
+        assert_none!(thumbprint(Algorithm::EdDSA, key.public_key()));
+    }
+
+    #[test]
+    fn should_reject_a_replayed_proof() {
+        let replay_cache = InMemoryReplayCache::new();
+        let proof = new_proof(TEST_METHOD, TEST_URI);
+
+        assert_ok!(verify_proof(&replay_cache, &proof, TEST_METHOD, TEST_URI, None));
+        assert_eq!(assert_err!(verify_proof(&replay_cache, &proof, TEST_METHOD, TEST_URI, None)), "proof has already been used");
+    }
+
+    #[test]
+    fn should_accept_a_proof_for_the_access_token() {
+        let proof = new_access_token_proof(TEST_METHOD, TEST_URI, "aardvark");
+
+        assert_ok!(verify_proof(&InMemoryReplayCache::new(), &proof, TEST_METHOD, TEST_URI, Some("aardvark")));
+    }
+
+    #[test]
+    fn should_reject_a_proof_for_another_access_token() {
+        let proof = new_access_token_proof(TEST_METHOD, TEST_URI, "badger");
+
+        assert_eq!(assert_err!(verify_proof(&InMemoryReplayCache::new(), &proof, TEST_METHOD, TEST_URI, Some("aardvark"))), "proof was not made for this access token");
+    }
+
+    #[test]
+    fn should_reject_a_proof_without_an_access_token_hash() {
+        let proof = new_proof(TEST_METHOD, TEST_URI);
+
+        assert_eq!(assert_err!(verify_proof(&InMemoryReplayCache::new(), &proof, TEST_METHOD, TEST_URI, Some("aardvark"))), "proof was not made for this access token");
+    }
+
+    #[test]
+    fn should_reject_a_proof_for_another_method() {
+        assert_eq!(assert_err!(verify(&new_proof("GET", TEST_URI))), "proof was not made for this method");
+    }
+
+    #[test]
+    fn should_reject_a_proof_for_another_uri() {
+        assert_eq!(assert_err!(verify(&new_proof(TEST_METHOD, "http://127.0.0.1:8080/introspect"))), "proof was not made for this uri");
+    }
+
+    #[test]
+    fn should_ignore_the_query_and_fragment_of_the_uri() {
+        assert_ok!(verify(&new_proof(TEST_METHOD, &format!("{TEST_URI}?aardvark=badger#cicada"))));
+    }
+
+    #[test]
+    fn should_reject_a_stale_proof() {
+        let proof = sign_proof(json!({
+            "jti": "aardvark",
+            "htm": TEST_METHOD,
+            "htu": TEST_URI,
+            "iat": (Utc::now() - TimeDelta::minutes(5)).timestamp(),
+        }));
+
+        assert_eq!(assert_err!(verify(&proof)), "proof has expired or was issued in the future");
+    }
+
+    #[test]
+    fn should_reject_a_proof_from_the_future() {
+        let proof = sign_proof(json!({
+            "jti": "aardvark",
+            "htm": TEST_METHOD,
+            "htu": TEST_URI,
+            "iat": (Utc::now() + TimeDelta::minutes(5)).timestamp(),
+        }));
+
+        assert_eq!(assert_err!(verify(&proof)), "proof has expired or was issued in the future");
+    }
+
+    #[test]
+    fn should_reject_a_proof_missing_claims() {
+        let proof = sign_proof(json!({ "htm": TEST_METHOD, "htu": TEST_URI }));
+
+        assert_eq!(assert_err!(verify(&proof)), "invalid proof");
+    }
+
+    #[test]
+    fn should_reject_anything_but_a_proof() {
+        let key = test_proof_key();
+        let header = Header {
+            typ: Some("JWT".into()),
+            jwk: Some(key.public_key().clone()),
+            ..Header::new(key.algorithm)
+        };
+        let encoding_key = assert_ok!(EncodingKey::from_ec_pem(TEST_EC_KEY));
+        let proof = assert_ok!(encode(&header, &json!({}), &encoding_key));
+
+        assert_eq!(assert_err!(verify(&proof)), "invalid proof type");
+    }
+
+    #[test]
+    fn should_reject_a_symmetric_proof() {
+        let header = Header {
+            typ: Some(DPOP_PROOF_TYPE.into()),
+            ..Header::new(Algorithm::HS256)
+        };
+        let proof = assert_ok!(encode(&header, &json!({}), &EncodingKey::from_secret(b"aardvark")));
+
+        assert_eq!(assert_err!(verify(&proof)), "unsupported proof algorithm");
+    }
+
+    #[test]
+    fn should_reject_a_proof_without_a_key() {
+        let header = Header {
+            typ: Some(DPOP_PROOF_TYPE.into()),
+            ..Header::new(Algorithm::ES256)
+        };
+        let encoding_key = assert_ok!(EncodingKey::from_ec_pem(TEST_EC_KEY));
+        let proof = assert_ok!(encode(&header, &json!({}), &encoding_key));
+
+        assert_eq!(assert_err!(verify(&proof)), "missing proof key");
+    }
+
+    #[test]
+    fn should_reject_a_tampered_proof() {
+        let proof = new_proof(TEST_METHOD, TEST_URI);
+        let other = new_proof(TEST_METHOD, TEST_URI);
+
+        // The claims of one proof, with the signature of another.
+        let [header, _, signature] = assert_ok!(<[&str; 3]>::try_from(proof.split('.').collect::<Vec<_>>()));
+        let [_, claims, _] = assert_ok!(<[&str; 3]>::try_from(other.split('.').collect::<Vec<_>>()));
+
+        assert_eq!(assert_err!(verify(&format!("{header}.{claims}.{signature}"))), "invalid proof");
+    }
+
+    #[test]
+    fn should_reject_garbage() {
+        assert_eq!(assert_err!(verify("aardvark")), "malformed proof");
+    }
+
+}
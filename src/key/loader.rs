@@ -0,0 +1,55 @@
+use std::{fs, io};
+use std::ffi::OsStr;
+use std::path::Path;
+use crate::key::SigningKey;
+
+// Keys are PKCS#8 PEM files named after their kid, returned in file name order so dated kids rotate in date order.
+pub fn load_keys(directory: &Path) -> io::Result<Vec<SigningKey>> {
+    let mut paths = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    paths.retain(|path| path.extension() == Some(OsStr::new("pem")));
+    paths.sort();
+
+    paths.iter()
+        .map(|path| load_key(path))
+        .collect()
+}
+
+fn load_key(path: &Path) -> io::Result<SigningKey> {
+    let kid = path.file_stem()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid key file name: {}", path.display())))?;
+
+    SigningKey::from_pem(kid, &fs::read(path)?)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("invalid key file {}: {error}", path.display())))
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use jsonwebtoken::Algorithm;
+
+    #[test]
+    fn should_load_every_key_in_file_name_order() {
+        let keys = assert_ok!(load_keys(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/keys"))));
+
+        let loaded: Vec<(&str, Algorithm)> = keys.iter()
+            .map(|key| (key.kid.as_str(), key.algorithm))
+            .collect();
+
+        assert_eq!(loaded, vec![
+            ("2026-01-rs256", Algorithm::RS256),
+            ("2026-02-es256", Algorithm::ES256),
+            ("2026-03-ed25519", Algorithm::EdDSA),
+        ]);
+    }
+
+    #[test]
+    fn should_fail_on_a_missing_directory() {
+        assert_err!(load_keys(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/aardvark"))));
+    }
+}
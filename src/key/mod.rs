@@ -0,0 +1,454 @@
+mod loader;
+mod rotation;
+mod route;
+
+pub use loader::*;
+pub use rotation::*;
+pub use route::*;
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard};
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use chrono::{DateTime, TimeDelta, Utc};
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header};
+use jsonwebtoken::errors::{Error, ErrorKind};
+use jsonwebtoken::jwk::{AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse};
+use serde::Serialize;
+use crate::token::AccessToken;
+
+#[derive(Clone)]
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    public_key: Jwk,
+}
+
+// Never expose the key material, even in logs.
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+// One per family, which is all a PKCS#8 key can tell us.
+pub const SUPPORTED_ALGORITHMS: &[Algorithm] = &[Algorithm::RS256, Algorithm::ES256, Algorithm::EdDSA];
+
+impl SigningKey {
+
+    // The algorithm follows from the type of the PKCS#8 key: RS256, ES256 (P-256) or EdDSA (Ed25519).
+    pub fn from_pem(kid: &str, pem: &[u8]) -> Result<Self, Error> {
+        let (algorithm, encoding_key) = EncodingKey::from_rsa_pem(pem).map(|key| (Algorithm::RS256, key))
+            .or_else(|_| EncodingKey::from_ec_pem(pem).map(|key| (Algorithm::ES256, key)))
+            .or_else(|_| EncodingKey::from_ed_pem(pem).map(|key| (Algorithm::EdDSA, key)))?;
+
+        let public_key = public_key(kid, algorithm, &encoding_key)?;
+        let decoding_key = DecodingKey::from_jwk(&public_key)?;
+
+        Ok(Self {
+            kid: kid.into(),
+            algorithm,
+            encoding_key,
+            decoding_key,
+            public_key,
+        })
+    }
+
+    pub fn sign<T: Serialize>(&self, typ: &str, claims: &T) -> Result<String, Error> {
+        let header = Header {
+            typ: Some(typ.into()),
+            kid: Some(self.kid.clone()),
+            ..Header::new(self.algorithm)
+        };
+        jsonwebtoken::encode(&header, claims, &self.encoding_key)
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    pub fn public_key(&self) -> &Jwk {
+        &self.public_key
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc7517#section-4
+fn public_key(kid: &str, algorithm: Algorithm, encoding_key: &EncodingKey) -> Result<Jwk, Error> {
+    let mut jwk = match algorithm {
+        // jsonwebtoken cannot derive the public half of an Ed25519 key, so we do it ourselves.
+        // https://www.rfc-editor.org/rfc/rfc8037#section-2
+        Algorithm::EdDSA => {
+            let key = ed25519_dalek::SigningKey::from_pkcs8_der(encoding_key.inner())
+                .map_err(|_| Error::from(ErrorKind::InvalidEddsaKey))?;
+            Jwk {
+                common: CommonParameters {
+                    key_algorithm: Some(KeyAlgorithm::EdDSA),
+                    ..Default::default()
+                },
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: BASE64_URL_SAFE_NO_PAD.encode(key.verifying_key().as_bytes()),
+                }),
+            }
+        },
+        _ => Jwk::from_encoding_key(encoding_key, algorithm)?,
+    };
+    jwk.common.key_id = Some(kid.into());
+    jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+    Ok(jwk)
+}
+
+// TODO - Extract into configuration
+// How long relying parties may cache the key set, a next key only becomes active once they've all had time to fetch it.
+pub const JWKS_CACHE_INTERVAL: TimeDelta = TimeDelta::hours(1);
+
+// How long a retired key keeps verifying, long enough for the longest lived token it signed to have expired.
+// ID tokens expire with the access token they were issued alongside.
+pub const RETIRED_KEY_LIFETIME: TimeDelta = AccessToken::TIME_TO_LIVE;
+
+// Keys move from next, to active, to retired, and are then dropped.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KeyState {
+    // Published so relying parties can cache it ahead of use, but not yet signing.
+    Next,
+    // Signs everything new, there is only ever one.
+    Active,
+    // No longer signing, but still published and verifying until anything it signed has expired.
+    Retired,
+}
+
+pub trait KeyStore: Send + Sync + Clone {
+    // The key to sign anything new with.
+    fn signing_key(&self) -> Option<Arc<SigningKey>>;
+    // Any key that can still be used to verify a previous signature.
+    fn verification_key(&self, kid: &str) -> Option<Arc<SigningKey>>;
+    // The public half of every key a relying party may encounter.
+    fn public_keys(&self) -> JwkSet;
+    // Publishes a key to become active in a later rotation, ignoring any kid we already hold or have dropped.
+    fn add_key(&self, key: SigningKey, now: DateTime<Utc>) -> bool;
+    // Drops the keys retired for longer than RETIRED_KEY_LIFETIME, then retires the active key and activates the
+    // oldest next key published for at least JWKS_CACHE_INTERVAL, unless there isn't one.
+    fn rotate(&self, now: DateTime<Utc>) -> bool;
+}
+
+#[derive(Clone)]
+pub struct InMemoryKeyStore {
+    keys: Arc<Mutex<Keys>>,
+}
+
+struct Keys {
+    // Kept in the order they were added, which is the order they'll be activated in.
+    held: Vec<HeldKey>,
+    // Every kid we've ever held, so a dropped key is never brought back by reloading its file.
+    kids: HashSet<String>,
+}
+
+struct HeldKey {
+    state: KeyState,
+    // When the key entered its current state.
+    since: DateTime<Utc>,
+    key: Arc<SigningKey>,
+}
+
+impl InMemoryKeyStore {
+    pub fn new(signing_key: SigningKey) -> Self {
+        Self {
+            keys: Arc::new(Mutex::new(Keys {
+                kids: HashSet::from([signing_key.kid.clone()]),
+                held: vec![HeldKey { state: KeyState::Active, since: Utc::now(), key: Arc::new(signing_key) }],
+            })),
+        }
+    }
+    fn lock_keys(&self) -> MutexGuard<'_, Keys> {
+        self.keys.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    #[cfg(test)]
+    pub fn state(&self, kid: &str) -> Option<KeyState> {
+        self.lock_keys()
+            .held
+            .iter()
+            .find(|held| held.key.kid == kid)
+            .map(|held| held.state)
+    }
+}
+
+impl KeyStore for InMemoryKeyStore {
+    fn signing_key(&self) -> Option<Arc<SigningKey>> {
+        self.lock_keys()
+            .held
+            .iter()
+            .find(|held| held.state == KeyState::Active)
+            .map(|held| held.key.clone())
+    }
+
+    fn verification_key(&self, kid: &str) -> Option<Arc<SigningKey>> {
+        self.lock_keys()
+            .held
+            .iter()
+            .find(|held| held.state != KeyState::Next && held.key.kid == kid)
+            .map(|held| held.key.clone())
+    }
+
+    fn public_keys(&self) -> JwkSet {
+        JwkSet {
+            keys: self.lock_keys()
+                .held
+                .iter()
+                .map(|held| held.key.public_key().clone())
+                .collect(),
+        }
+    }
+
+    fn add_key(&self, key: SigningKey, now: DateTime<Utc>) -> bool {
+        let mut keys = self.lock_keys();
+        if !keys.kids.insert(key.kid.clone()) {
+            return false
+        }
+        keys.held.push(HeldKey { state: KeyState::Next, since: now, key: Arc::new(key) });
+        true
+    }
+
+    fn rotate(&self, now: DateTime<Utc>) -> bool {
+        let keys = &mut self.lock_keys().held;
+
+        // Anything these signed has since expired.
+        keys.retain(|held| held.state != KeyState::Retired || now < held.since + RETIRED_KEY_LIFETIME);
+
+        // A key published too recently could sign tokens that relying parties with a cached key set can't verify.
+        let Some(next) = keys.iter().position(|held| held.state == KeyState::Next && held.since + JWKS_CACHE_INTERVAL <= now) else {
+            return false
+        };
+
+        for held in keys.iter_mut().filter(|held| held.state == KeyState::Active) {
+            held.state = KeyState::Retired;
+            held.since = now;
+        }
+
+        if let Some(held) = keys.get_mut(next) {
+            held.state = KeyState::Active;
+            held.since = now;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use assertables::*;
+    use crate::key::{InMemoryKeyStore, SigningKey};
+
+    pub const TEST_RSA_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/2026-01-rs256.pem");
+    pub const TEST_EC_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/2026-02-es256.pem");
+    pub const TEST_ED_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/2026-03-ed25519.pem");
+
+    impl InMemoryKeyStore {
+        pub fn new_test_store() -> Self {
+            InMemoryKeyStore::new(assert_ok!(SigningKey::from_pem("2026-01-rs256", TEST_RSA_KEY)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use jsonwebtoken::{decode, Validation};
+    use serde::Deserialize;
+    use crate::key::test_support::{TEST_EC_KEY, TEST_ED_KEY, TEST_RSA_KEY};
+
+    #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+    struct Claims {
+        sub: String,
+    }
+
+    // The next keys have been published long enough to be activated.
+    fn new_key_store() -> InMemoryKeyStore {
+        let key_store = InMemoryKeyStore::new(assert_ok!(SigningKey::from_pem("rsa", TEST_RSA_KEY)));
+        let published_at = Utc::now() - JWKS_CACHE_INTERVAL;
+        assert!(key_store.add_key(assert_ok!(SigningKey::from_pem("ec", TEST_EC_KEY)), published_at));
+        assert!(key_store.add_key(assert_ok!(SigningKey::from_pem("ed", TEST_ED_KEY)), published_at));
+        key_store
+    }
+
+    mod signing_key {
+        use super::*;
+
+        fn should_verify_its_own_signature(pem: &[u8], algorithm: Algorithm) {
+            let key = assert_ok!(SigningKey::from_pem("aardvark", pem));
+            assert_eq!(key.algorithm, algorithm);
+
+            let claims = Claims { sub: "badger".into() };
+            let token = assert_ok!(key.sign("JWT", &claims));
+
+            let mut validation = Validation::new(algorithm);
+            validation.required_spec_claims.clear();
+
+            let decoded = assert_ok!(decode::<Claims>(&token, key.decoding_key(), &validation));
+            assert_eq!(decoded.claims, claims);
+            assert_eq!(decoded.header.kid.as_deref(), Some("aardvark"));
+        }
+
+        #[test]
+        fn should_support_rsa_keys() {
+            should_verify_its_own_signature(TEST_RSA_KEY, Algorithm::RS256);
+        }
+
+        #[test]
+        fn should_support_elliptic_curve_keys() {
+            should_verify_its_own_signature(TEST_EC_KEY, Algorithm::ES256);
+        }
+
+        #[test]
+        fn should_support_edwards_curve_keys() {
+            should_verify_its_own_signature(TEST_ED_KEY, Algorithm::EdDSA);
+        }
+
+        #[test]
+        fn should_reject_anything_else() {
+            assert_err!(SigningKey::from_pem("aardvark", b"badger"));
+        }
+
+        #[test]
+        fn should_describe_the_public_key() {
+            let key = assert_ok!(SigningKey::from_pem("aardvark", TEST_ED_KEY));
+            let public_key = key.public_key();
+
+            assert_eq!(public_key.common.key_id.as_deref(), Some("aardvark"));
+            assert_eq!(public_key.common.public_key_use, Some(PublicKeyUse::Signature));
+            assert_eq!(public_key.common.key_algorithm, Some(KeyAlgorithm::EdDSA));
+            assert_matches!(&public_key.algorithm, AlgorithmParameters::OctetKeyPair(parameters) if parameters.curve == EllipticCurve::Ed25519);
+        }
+    }
+
+    mod rotation {
+        use super::*;
+
+        #[test]
+        fn should_queue_added_keys_as_next() {
+            let key_store = new_key_store();
+
+            assert_some_eq_x!(key_store.state("rsa"), KeyState::Active);
+            assert_some_eq_x!(key_store.state("ec"), KeyState::Next);
+            assert_some_eq_x!(key_store.state("ed"), KeyState::Next);
+            assert_eq!(assert_some!(key_store.signing_key()).kid, "rsa");
+        }
+
+        #[test]
+        fn should_ignore_a_key_it_already_holds() {
+            let key_store = new_key_store();
+
+            assert!(!key_store.add_key(assert_ok!(SigningKey::from_pem("ec", TEST_EC_KEY)), Utc::now()));
+            assert_eq!(key_store.public_keys().keys.len(), 3);
+        }
+
+        #[test]
+        fn should_activate_the_oldest_next_key_and_retire_the_active_key() {
+            let key_store = new_key_store();
+
+            assert!(key_store.rotate(Utc::now()));
+
+            assert_some_eq_x!(key_store.state("rsa"), KeyState::Retired);
+            assert_some_eq_x!(key_store.state("ec"), KeyState::Active);
+            assert_some_eq_x!(key_store.state("ed"), KeyState::Next);
+            assert_eq!(assert_some!(key_store.signing_key()).kid, "ec");
+        }
+
+        #[test]
+        fn should_keep_retired_keys_until_anything_they_signed_has_expired() {
+            let key_store = new_key_store();
+            let retired_at = Utc::now();
+
+            assert!(key_store.rotate(retired_at));
+            assert!(key_store.rotate(retired_at + RETIRED_KEY_LIFETIME - TimeDelta::seconds(1)));
+
+            assert_some_eq_x!(key_store.state("rsa"), KeyState::Retired);
+            assert_some_eq_x!(key_store.state("ec"), KeyState::Retired);
+            assert_some_eq_x!(key_store.state("ed"), KeyState::Active);
+            assert_some!(key_store.verification_key("rsa"));
+        }
+
+        #[test]
+        fn should_drop_retired_keys_once_anything_they_signed_has_expired() {
+            let key_store = new_key_store();
+            let retired_at = Utc::now();
+
+            assert!(key_store.rotate(retired_at));
+            assert!(key_store.rotate(retired_at + RETIRED_KEY_LIFETIME));
+
+            assert_none!(key_store.state("rsa"));
+            assert_some_eq_x!(key_store.state("ec"), KeyState::Retired);
+            assert_some_eq_x!(key_store.state("ed"), KeyState::Active);
+        }
+
+        #[test]
+        fn should_not_rotate_without_a_next_key() {
+            let key_store = InMemoryKeyStore::new_test_store();
+
+            assert!(!key_store.rotate(Utc::now()));
+            assert_some_eq_x!(key_store.state("2026-01-rs256"), KeyState::Active);
+        }
+
+        #[test]
+        fn should_not_activate_a_key_before_relying_parties_could_have_fetched_it() {
+            let key_store = InMemoryKeyStore::new_test_store();
+            let published_at = Utc::now();
+            assert!(key_store.add_key(assert_ok!(SigningKey::from_pem("ec", TEST_EC_KEY)), published_at));
+
+            assert!(!key_store.rotate(published_at + JWKS_CACHE_INTERVAL - TimeDelta::seconds(1)));
+            assert_some_eq_x!(key_store.state("2026-01-rs256"), KeyState::Active);
+            assert_some_eq_x!(key_store.state("ec"), KeyState::Next);
+
+            assert!(key_store.rotate(published_at + JWKS_CACHE_INTERVAL));
+            assert_some_eq_x!(key_store.state("ec"), KeyState::Active);
+        }
+
+        #[test]
+        fn should_not_bring_back_a_dropped_key() {
+            let key_store = new_key_store();
+            let retired_at = Utc::now();
+
+            assert!(key_store.rotate(retired_at));
+            assert!(key_store.rotate(retired_at + RETIRED_KEY_LIFETIME));
+
+            assert!(!key_store.add_key(assert_ok!(SigningKey::from_pem("rsa", TEST_RSA_KEY)), Utc::now()));
+            assert_none!(key_store.state("rsa"));
+        }
+
+        #[test]
+        fn should_verify_with_active_and_retired_keys_only() {
+            let key_store = new_key_store();
+
+            assert!(key_store.rotate(Utc::now()));
+
+            assert_some!(key_store.verification_key("rsa"));
+            assert_some!(key_store.verification_key("ec"));
+            assert_none!(key_store.verification_key("ed"));
+            assert_none!(key_store.verification_key("aardvark"));
+        }
+
+        #[test]
+        fn should_publish_every_held_key() {
+            let key_store = new_key_store();
+            let retired_at = Utc::now();
+
+            assert!(key_store.rotate(retired_at));
+            assert!(key_store.rotate(retired_at + RETIRED_KEY_LIFETIME));
+
+            let kids: Vec<Option<String>> = key_store.public_keys().keys
+                .into_iter()
+                .map(|key| key.common.key_id)
+                .collect();
+
+            assert_eq!(kids, vec![Some("ec".into()), Some("ed".into())]);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use tokio::time::{interval_at, Instant};
+use crate::key::{load_keys, KeyStore, SigningKey, JWKS_CACHE_INTERVAL};
+
+pub async fn rotate_on_schedule<K: KeyStore>(key_store: K, period: Duration) {
+
+    let mut interval = interval_at(Instant::now() + period, period);
+
+    loop {
+        interval.tick().await;
+        rotate(&key_store, Utc::now());
+    }
+}
+
+// Stages or rotates on a SIGHUP, so a key dropped into the directory is published as next on one signal and only
+// activated on a later one, once relying parties have had time to fetch it before anything is signed with it.
+#[cfg(unix)]
+pub async fn rotate_on_signal<K: KeyStore>(key_store: K, directory: PathBuf) {
+
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+        println!("Unable to install a SIGHUP handler, keys will only rotate on schedule.");
+        return
+    };
+
+    println!("Waiting for a SIGHUP to trigger a key rotation.");
+
+    while hangup.recv().await.is_some() {
+        match load_keys(&directory) {
+            Ok(keys) => stage_or_rotate(&key_store, keys, Utc::now()),
+            Err(error) => println!("Unable to load keys from {}: {error}", directory.display()),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn rotate_on_signal<K: KeyStore>(_key_store: K, _directory: PathBuf) {}
+
+// Only rotates once there's nothing new to publish, a key activated in the same step as it's published could sign
+// tokens before anyone has been able to fetch it to verify them.
+fn stage_or_rotate<K: KeyStore>(key_store: &K, keys: Vec<SigningKey>, now: DateTime<Utc>) {
+    let staged: Vec<_> = keys.into_iter()
+        .filter_map(|key| {
+            let kid = key.kid.clone();
+            key_store.add_key(key, now).then_some(kid)
+        })
+        .collect();
+
+    match staged.is_empty() {
+        true => rotate(key_store, now),
+        false => println!("Published {staged:?} as next signing keys, send another SIGHUP in {} minutes to activate them.", JWKS_CACHE_INTERVAL.num_minutes()),
+    }
+}
+
+fn rotate<K: KeyStore>(key_store: &K, now: DateTime<Utc>) {
+    match key_store.rotate(now) {
+        true => println!("Rotated signing keys, now signing with {:?}.", key_store.signing_key().map(|key| key.kid.clone())),
+        false => println!("Skipped rotating signing keys, there is no next key published for at least {} minutes.", JWKS_CACHE_INTERVAL.num_minutes()),
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use chrono::TimeDelta;
+    use crate::key::{InMemoryKeyStore, KeyState};
+    use crate::key::test_support::{TEST_EC_KEY, TEST_RSA_KEY};
+
+    fn load_test_keys() -> Vec<SigningKey> {
+        vec![
+            assert_ok!(SigningKey::from_pem("rsa", TEST_RSA_KEY)),
+            assert_ok!(SigningKey::from_pem("ec", TEST_EC_KEY)),
+        ]
+    }
+
+    #[test]
+    fn should_only_publish_new_keys_as_next() {
+        let key_store = InMemoryKeyStore::new(assert_ok!(SigningKey::from_pem("rsa", TEST_RSA_KEY)));
+
+        stage_or_rotate(&key_store, load_test_keys(), Utc::now());
+
+        assert_some_eq_x!(key_store.state("rsa"), KeyState::Active);
+        assert_some_eq_x!(key_store.state("ec"), KeyState::Next);
+    }
+
+    #[test]
+    fn should_activate_published_keys_on_a_signal_once_they_could_have_been_fetched() {
+        let key_store = InMemoryKeyStore::new(assert_ok!(SigningKey::from_pem("rsa", TEST_RSA_KEY)));
+        let published_at = Utc::now();
+
+        stage_or_rotate(&key_store, load_test_keys(), published_at);
+        stage_or_rotate(&key_store, load_test_keys(), published_at + JWKS_CACHE_INTERVAL);
+
+        assert_some_eq_x!(key_store.state("rsa"), KeyState::Retired);
+        assert_some_eq_x!(key_store.state("ec"), KeyState::Active);
+    }
+
+    #[test]
+    fn should_not_activate_published_keys_on_a_signal_before_they_could_have_been_fetched() {
+        let key_store = InMemoryKeyStore::new(assert_ok!(SigningKey::from_pem("rsa", TEST_RSA_KEY)));
+        let published_at = Utc::now();
+
+        stage_or_rotate(&key_store, load_test_keys(), published_at);
+        stage_or_rotate(&key_store, load_test_keys(), published_at + TimeDelta::seconds(5));
+
+        assert_some_eq_x!(key_store.state("rsa"), KeyState::Active);
+        assert_some_eq_x!(key_store.state("ec"), KeyState::Next);
+    }
+}
@@ -0,0 +1,89 @@
+use axum::extract::State;
+use axum::http::header::CACHE_CONTROL;
+use axum::response::IntoResponse;
+use axum::{Json, Router};
+use axum::routing::get;
+use crate::key::{KeyStore, JWKS_CACHE_INTERVAL};
+
+pub const JWKS_ENDPOINT: &str = "/jwks.json";
+
+// https://www.rfc-editor.org/rfc/rfc7517#section-5
+pub fn route<S, K: KeyStore + 'static>(key_store: K) -> Router<S> {
+    Router::new()
+        .route(JWKS_ENDPOINT, get(jwks_handler::<K>))
+        .with_state(key_store)
+}
+
+// Relying parties are told how long they may cache the keys for, which is how long a next key is published before it's used.
+async fn jwks_handler<K: KeyStore>(State(key_store): State<K>) -> impl IntoResponse {
+    let cache_control = format!("max-age={}", JWKS_CACHE_INTERVAL.num_seconds());
+    ([(CACHE_CONTROL, cache_control)], Json(key_store.public_keys()))
+}
+
+#[cfg(test)]
+mod integration_tests {
+
+    use super::*;
+    use assertables::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request, StatusCode};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use crate::key::{InMemoryKeyStore, SigningKey};
+    use crate::key::test_support::TEST_EC_KEY;
+
+    async fn get_jwks(key_store: InMemoryKeyStore) -> Value {
+        let request = assert_ok!(Request::builder()
+            .method(Method::GET)
+            .uri(JWKS_ENDPOINT)
+            .body(Body::empty())
+        );
+
+        let response = assert_ok!(route::<(), _>(key_store).oneshot(request).await);
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = assert_ok!(response.into_body().collect().await).to_bytes();
+        assert_ok!(serde_json::from_slice(&body))
+    }
+
+    #[tokio::test]
+    async fn should_publish_the_public_keys() {
+        let key_store = InMemoryKeyStore::new_test_store();
+        assert!(key_store.add_key(assert_ok!(SigningKey::from_pem("2026-02-es256", TEST_EC_KEY)), chrono::Utc::now()));
+
+        let jwks = get_jwks(key_store).await;
+        let keys = assert_some!(jwks["keys"].as_array());
+
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0]["kid"], "2026-01-rs256");
+        assert_eq!(keys[0]["kty"], "RSA");
+        assert_eq!(keys[0]["alg"], "RS256");
+        assert_eq!(keys[0]["use"], "sig");
+        assert_eq!(keys[1]["kid"], "2026-02-es256");
+        assert_eq!(keys[1]["kty"], "EC");
+        assert_eq!(keys[1]["crv"], "P-256");
+    }
+
+    #[tokio::test]
+    async fn should_not_publish_private_key_material() {
+        let jwks = get_jwks(InMemoryKeyStore::new_test_store()).await;
+
+        for private_parameter in ["d", "p", "q", "dp", "dq", "qi"] {
+            assert_none!(jwks["keys"][0].get(private_parameter));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_let_relying_parties_cache_the_keys_for_as_long_as_a_next_key_is_published() {
+        let request = assert_ok!(Request::builder()
+            .method(Method::GET)
+            .uri(JWKS_ENDPOINT)
+            .body(Body::empty())
+        );
+
+        let response = assert_ok!(route::<(), _>(InMemoryKeyStore::new_test_store()).oneshot(request).await);
+
+        assert_eq!(assert_some!(response.headers().get(CACHE_CONTROL)), "max-age=3600");
+    }
+}
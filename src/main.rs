@@ -6,24 +6,59 @@
     clippy::panic,
 )]
 
+mod assertion;
+mod authorization;
+mod device_authorization;
+mod discovery;
+mod dpop;
+mod openid;
+mod pkce;
+mod replay;
+mod resource;
 mod scope;
 mod token;
 mod token_exchange;
 mod token_introspection;
+mod token_revocation;
+mod trusted_issuer;
 mod graceful_shutdown;
+mod key;
 mod client;
+mod client_registration;
+mod consent;
+mod user;
+mod userinfo;
 mod util;
 
 use axum::{serve, Router};
+use chrono::Utc;
 use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
 use tokio::net::TcpListener;
+use authorization::AuthorizationState;
+use client_registration::{ClientOperatorState, ClientRegistrationState};
+use device_authorization::DeviceAuthorizationState;
+use discovery::DiscoveryState;
 use client::authentication::ClientAuthenticationService;
 use client::configuration::InMemoryClientConfigurationRepository;
-use client::secret::InMemoryClientSecretRepository;
-use token::AccessToken;
+use client::registration::InMemoryRegistrationAccessTokenRepository;
+use client::ClientId;
+use client::secret::{ClientSecret, ClientSecretRepository, InMemoryClientSecretRepository};
+use consent::InMemoryConsentRepository;
+use key::{load_keys, rotate_on_schedule, rotate_on_signal, InMemoryKeyStore, KeyStore};
+use replay::InMemoryReplayCache;
+use resource::InMemoryProtectedResourceRepository;
+use scope::repository::InMemoryScopeRepository;
+use token::{AccessToken, AuthorizationCode, DeviceCode, PendingConsent, PushedAuthorizationRequest, RefreshToken};
 use token::repository::InMemoryTokenRepository;
 use token_exchange::TokenExchangeState;
 use token_introspection::TokenIntrospectionState;
+use token_revocation::TokenRevocationState;
+use trusted_issuer::{load_trusted_issuers, InMemoryTrustedIssuerRepository, TrustedIssuerRepository};
+use user::authentication::UserAuthenticationService;
+use user::repository::InMemoryUserRepository;
+use userinfo::UserInfoState;
 
 // TODO List:
 //  - Token endpoint
@@ -47,28 +82,140 @@ async fn main() -> io::Result<()> {
 
     // TODO - Do we bother with services, or just continue with passing the repositories directly?
     let access_token_repository = InMemoryTokenRepository::<AccessToken>::new();
+    let refresh_token_repository = InMemoryTokenRepository::<RefreshToken>::new();
+    let authorization_code_repository = InMemoryTokenRepository::<AuthorizationCode>::new();
+    let device_code_repository = InMemoryTokenRepository::<DeviceCode>::new();
+    let pushed_authorization_request_repository = InMemoryTokenRepository::<PushedAuthorizationRequest>::new();
+    let pending_consent_repository = InMemoryTokenRepository::<PendingConsent>::new();
     let client_secret_repository = InMemoryClientSecretRepository::new();
     let client_configuration_repository = InMemoryClientConfigurationRepository::new();
+    let registration_access_token_repository = InMemoryRegistrationAccessTokenRepository::new();
+    let user_repository = InMemoryUserRepository::new();
+    let trusted_issuer_repository = InMemoryTrustedIssuerRepository::new();
+    let protected_resource_repository = InMemoryProtectedResourceRepository::new();
+    let scope_repository = InMemoryScopeRepository::new();
+    let consent_repository = InMemoryConsentRepository::new();
+    let replay_cache = InMemoryReplayCache::new();
+
+    let user_authenticator = UserAuthenticationService::new(
+        user_repository.clone(),
+    );
+
+    // Signing keys are never shipped with the server, they have to be provisioned where it's deployed.
+    let key_directory = std::env::var_os("KEY_DIRECTORY").map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "KEY_DIRECTORY must name the directory holding the signing keys"))?;
+    let key_rotation_period = Duration::from_secs(24 * 60 * 60);
+
+    // The first key signs, the rest wait their turn to be rotated in.
+    let mut keys = load_keys(&key_directory)?.into_iter();
+    let signing_key = keys.next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no signing keys in {}", key_directory.display())))?;
+
+    let key_store = InMemoryKeyStore::new(signing_key);
+    keys.for_each(|key| { key_store.add_key(key, Utc::now()); });
+
+    // Without OPERATOR_CLIENT_SECRET no one can manage the configured clients' secrets.
+    if let Some(operator_secret) = std::env::var_os("OPERATOR_CLIENT_SECRET") {
+        let operator_secret = ClientSecret::new(ClientId::from(String::from("koala")), operator_secret.as_encoded_bytes(), None)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid OPERATOR_CLIENT_SECRET: {error}")))?;
+        client_secret_repository.save(&operator_secret);
+    }
+
+    // Without TRUSTED_ISSUERS no other issuer's assertions are accepted.
+    if let Some(trusted_issuers) = std::env::var_os("TRUSTED_ISSUERS").map(PathBuf::from) {
+        load_trusted_issuers(&trusted_issuers)?.iter()
+            .for_each(|trusted_issuer| trusted_issuer_repository.save(trusted_issuer));
+    }
+
+    tokio::spawn(rotate_on_schedule(key_store.clone(), key_rotation_period));
+    tokio::spawn(rotate_on_signal(key_store.clone(), key_directory));
+
+    // TODO - Extract into configuration
+    let tcp_listener = TcpListener::bind("127.0.0.1:8080") // Change :8080 to :0 for a random port number
+        .await?;
+
+    // TODO - Extract into configuration
+    let issuer = format!("http://{}", tcp_listener.local_addr()?);
 
     let client_authenticator = ClientAuthenticationService::new(
+        issuer.clone(),
         client_secret_repository.clone(),
         client_configuration_repository.clone(),
+        replay_cache.clone(),
     );
-    
+
     let application = Router::new()
+        .merge(discovery::route(DiscoveryState {
+            issuer: issuer.clone(),
+            scope_repository: scope_repository.clone(),
+        }))
+        .merge(key::route(key_store.clone()))
+        .merge(authorization::route(AuthorizationState {
+            authorization_code_repository: authorization_code_repository.clone(),
+            pushed_authorization_request_repository: pushed_authorization_request_repository.clone(),
+            client_configuration_repository: client_configuration_repository.clone(),
+            client_authenticator: client_authenticator.clone(),
+            user_authenticator: user_authenticator.clone(),
+            protected_resource_repository: protected_resource_repository.clone(),
+            scope_repository: scope_repository.clone(),
+            consent_repository: consent_repository.clone(),
+            pending_consent_repository: pending_consent_repository.clone(),
+        }))
+        .merge(device_authorization::route(DeviceAuthorizationState {
+            issuer: issuer.clone(),
+            device_code_repository: device_code_repository.clone(),
+            client_authenticator: client_authenticator.clone(),
+            user_authenticator: user_authenticator.clone(),
+            scope_repository: scope_repository.clone(),
+        }))
         .merge(token_exchange::route(TokenExchangeState {
+            issuer: issuer.clone(),
             access_token_repository: access_token_repository.clone(),
+            refresh_token_repository: refresh_token_repository.clone(),
+            authorization_code_repository: authorization_code_repository.clone(),
+            device_code_repository: device_code_repository.clone(),
             client_authenticator: client_authenticator.clone(),
+            user_authenticator: user_authenticator.clone(),
+            key_store: key_store.clone(),
+            replay_cache: replay_cache.clone(),
+            trusted_issuer_repository: trusted_issuer_repository.clone(),
+            protected_resource_repository: protected_resource_repository.clone(),
+            scope_repository: scope_repository.clone(),
         }))
         .merge(token_introspection::route(TokenIntrospectionState {
+            issuer: issuer.clone(),
+            access_token_repository: access_token_repository.clone(),
+            client_authenticator: client_authenticator.clone(),
+            key_store: key_store.clone(),
+            protected_resource_repository: protected_resource_repository.clone(),
+        }))
+        .merge(userinfo::route(UserInfoState {
+            issuer: issuer.clone(),
+            access_token_repository: access_token_repository.clone(),
+            user_repository: user_repository.clone(),
+            key_store: key_store.clone(),
+            replay_cache: replay_cache.clone(),
+        }))
+        .merge(token_revocation::route(TokenRevocationState {
+            issuer: issuer.clone(),
             access_token_repository: access_token_repository.clone(),
+            refresh_token_repository: refresh_token_repository.clone(),
+            client_authenticator: client_authenticator.clone(),
+            key_store: key_store.clone(),
+        }))
+        .merge(client_registration::route(ClientRegistrationState {
+            issuer: issuer.clone(),
+            client_configuration_repository: client_configuration_repository.clone(),
+            client_secret_repository: client_secret_repository.clone(),
+            registration_access_token_repository: registration_access_token_repository.clone(),
+            scope_repository: scope_repository.clone(),
+        }))
+        .merge(client_registration::operator_route(ClientOperatorState {
+            client_configuration_repository: client_configuration_repository.clone(),
+            client_secret_repository: client_secret_repository.clone(),
             client_authenticator: client_authenticator.clone(),
         }));
 
-    // TODO - Extract into configuration
-    let tcp_listener = TcpListener::bind("127.0.0.1:8080") // Change :8080 to :0 for a random port number
-        .await?;
-
     println!();
     println!("Listening on http://{}", tcp_listener.local_addr()?);
     println!();
@@ -0,0 +1,163 @@
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::errors::Error;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use crate::key::SigningKey;
+use crate::token::AccessToken;
+use crate::user::Username;
+use crate::util::value_struct::ValueStruct;
+
+// https://openid.net/specs/openid-connect-core-1_0.html#IDToken
+pub const ID_TOKEN_TYPE: &str = "JWT";
+
+// Resource owners only ever sign in with a password, a single factor, so level 1 of ISO/IEC 29115.
+pub const PASSWORD_ACR: &str = "1";
+
+// Who authenticated, when, and in response to which request, for any ID token issued off the back of it.
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct Authentication {
+    pub username: Username,
+    pub auth_time: DateTime<Utc>,
+    pub nonce: Option<String>,
+}
+
+// https://openid.net/specs/openid-connect-core-1_0.html#IDToken
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    pub auth_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub at_hash: String,
+    pub acr: String,
+}
+
+impl IdTokenClaims {
+    // Lives as long as the access token it was issued alongside.
+    pub fn new(issuer: &str, authentication: &Authentication, access_token: &AccessToken, at_hash: String) -> Self {
+        Self {
+            iss: issuer.into(),
+            sub: authentication.username.value().clone(),
+            aud: access_token.client_id.value().clone(),
+            exp: access_token.expires_at.timestamp(),
+            iat: access_token.issued_at.timestamp(),
+            auth_time: authentication.auth_time.timestamp(),
+            nonce: authentication.nonce.clone(),
+            at_hash,
+            acr: PASSWORD_ACR.into(),
+        }
+    }
+}
+
+pub fn encode_id_token(issuer: &str, authentication: &Authentication, access_token: &AccessToken, encoded_access_token: &str, signing_key: &SigningKey) -> Result<String, Error> {
+    let at_hash = access_token_hash(signing_key.algorithm, encoded_access_token);
+    signing_key.sign(ID_TOKEN_TYPE, &IdTokenClaims::new(issuer, authentication, access_token, at_hash))
+}
+
+// The left-most half of the hash of the access token, using the hash of the ID token's signing algorithm.
+// https://openid.net/specs/openid-connect-core-1_0.html#CodeIDToken
+pub fn access_token_hash(algorithm: Algorithm, encoded_access_token: &str) -> String {
+    let digest = match algorithm {
+        // Ed25519 signs with SHA-512.
+        Algorithm::EdDSA => Sha512::digest(encoded_access_token).to_vec(),
+        // Every other algorithm we sign with is SHA-256 based.
+        _ => Sha256::digest(encoded_access_token).to_vec(),
+    };
+    BASE64_URL_SAFE_NO_PAD.encode(&digest[..digest.len() / 2])
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use std::collections::HashSet;
+    use jsonwebtoken::{decode, decode_header, Validation};
+    use crate::client::ClientId;
+    use crate::key::{InMemoryKeyStore, KeyStore};
+    use crate::scope::{Scope, Scopes};
+
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
+
+    fn new_authentication(nonce: Option<&str>) -> Authentication {
+        Authentication {
+            username: Username::from(String::from("aardvark")),
+            auth_time: Utc::now(),
+            nonce: nonce.map(String::from),
+        }
+    }
+
+    fn new_access_token() -> AccessToken {
+        AccessToken::new(
+            ClientId::from(String::from("badger")),
+            Some(Username::from(String::from("aardvark"))),
+            Scopes(HashSet::from([Scope::OPENID])),
+            None,
+        )
+    }
+
+    #[test]
+    fn should_hash_the_access_token_as_per_the_specification_example() {
+        assert_eq!(access_token_hash(Algorithm::RS256, "jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y"), "77QmUPtjPfzWtF2AnpK9RQ");
+    }
+
+    #[test]
+    fn should_hash_the_access_token_with_sha_512_for_ed25519() {
+        assert_eq!(access_token_hash(Algorithm::EdDSA, "aardvark").len(), 43);
+        assert_ne!(access_token_hash(Algorithm::EdDSA, "aardvark"), access_token_hash(Algorithm::RS256, "aardvark"));
+    }
+
+    #[test]
+    fn should_sign_the_id_token_claims() {
+        let key_store = InMemoryKeyStore::new_test_store();
+        let signing_key = assert_some!(key_store.signing_key());
+        let authentication = new_authentication(Some("cicada"));
+        let access_token = new_access_token();
+
+        let id_token = assert_ok!(encode_id_token(TEST_ISSUER, &authentication, &access_token, "dingo", &signing_key));
+
+        let header = assert_ok!(decode_header(&id_token));
+        assert_eq!(header.typ.as_deref(), Some(ID_TOKEN_TYPE));
+        assert_eq!(header.kid.as_deref(), Some(signing_key.kid.as_str()));
+
+        let mut validation = Validation::new(signing_key.algorithm);
+        validation.set_audience(&["badger"]);
+        validation.set_issuer(&[TEST_ISSUER]);
+
+        let claims = assert_ok!(decode::<IdTokenClaims>(&id_token, signing_key.decoding_key(), &validation)).claims;
+        assert_eq!(claims, IdTokenClaims {
+            iss: TEST_ISSUER.into(),
+            sub: "aardvark".into(),
+            aud: "badger".into(),
+            exp: access_token.expires_at.timestamp(),
+            iat: access_token.issued_at.timestamp(),
+            auth_time: authentication.auth_time.timestamp(),
+            nonce: Some("cicada".into()),
+            at_hash: access_token_hash(signing_key.algorithm, "dingo"),
+            acr: PASSWORD_ACR.into(),
+        });
+    }
+
+    #[test]
+    fn should_omit_the_nonce_when_there_was_none() {
+        let key_store = InMemoryKeyStore::new_test_store();
+        let signing_key = assert_some!(key_store.signing_key());
+
+        let id_token = assert_ok!(encode_id_token(TEST_ISSUER, &new_authentication(None), &new_access_token(), "dingo", &signing_key));
+
+        let mut validation = Validation::new(signing_key.algorithm);
+        validation.set_audience(&["badger"]);
+
+        let claims = assert_ok!(decode::<serde_json::Value>(&id_token, signing_key.decoding_key(), &validation)).claims;
+        assert_none!(claims.get("nonce"));
+    }
+}
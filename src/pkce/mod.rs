@@ -0,0 +1,107 @@
+use base64::prelude::*;
+use sha2::{Digest, Sha256};
+use crate::enum_with_from_str;
+
+// https://www.rfc-editor.org/rfc/rfc7636#section-4.2
+enum_with_from_str! {
+    #[derive(Eq, PartialEq, Clone)]
+    #[cfg_attr(test, derive(Debug))]
+    pub enum CodeChallengeMethod {
+        Plain: "plain",
+        S256: "S256",
+    }
+}
+
+#[derive(Eq, PartialEq, Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct CodeChallenge {
+    pub challenge: String,
+    pub method: CodeChallengeMethod,
+}
+
+impl CodeChallenge {
+
+    // https://www.rfc-editor.org/rfc/rfc7636#section-4.6
+    pub fn verify(&self, code_verifier: &str) -> bool {
+        match self.method {
+            CodeChallengeMethod::Plain => constant_time_eq(code_verifier.as_bytes(), self.challenge.as_bytes()),
+            CodeChallengeMethod::S256 => {
+                let hashed = BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+                constant_time_eq(hashed.as_bytes(), self.challenge.as_bytes())
+            },
+        }
+    }
+}
+
+// Both the code_verifier and code_challenge share the same ABNF, 43 to 128 unreserved characters.
+// https://www.rfc-editor.org/rfc/rfc7636#section-4.1
+pub fn is_valid_code_verifier(value: &str) -> bool {
+    (43..=128).contains(&value.len()) && value.bytes().all(|byte| {
+        byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+    })
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+
+    // https://www.rfc-editor.org/rfc/rfc7636#appendix-B
+    const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    mod verify {
+        use super::*;
+
+        #[test]
+        fn should_verify_a_matching_s256_code_verifier() {
+            let challenge = CodeChallenge { challenge: CODE_CHALLENGE.into(), method: CodeChallengeMethod::S256 };
+            assert!(challenge.verify(CODE_VERIFIER));
+        }
+
+        #[test]
+        fn should_not_verify_a_mismatched_s256_code_verifier() {
+            let challenge = CodeChallenge { challenge: CODE_CHALLENGE.into(), method: CodeChallengeMethod::S256 };
+            assert!(!challenge.verify(CODE_CHALLENGE));
+        }
+
+        #[test]
+        fn should_verify_a_matching_plain_code_verifier() {
+            let challenge = CodeChallenge { challenge: CODE_VERIFIER.into(), method: CodeChallengeMethod::Plain };
+            assert!(challenge.verify(CODE_VERIFIER));
+        }
+
+        #[test]
+        fn should_not_verify_a_mismatched_plain_code_verifier() {
+            let challenge = CodeChallenge { challenge: CODE_CHALLENGE.into(), method: CodeChallengeMethod::Plain };
+            assert!(!challenge.verify(CODE_VERIFIER));
+        }
+    }
+
+    mod is_valid_code_verifier {
+        use super::*;
+
+        #[test]
+        fn should_accept_unreserved_characters_between_43_and_128_long() {
+            assert!(is_valid_code_verifier(CODE_VERIFIER));
+            assert!(is_valid_code_verifier(&"a-._~".repeat(9)[..43]));
+            assert!(is_valid_code_verifier(&"a".repeat(128)));
+        }
+
+        #[test]
+        fn should_reject_values_that_are_too_short_or_too_long() {
+            assert!(!is_valid_code_verifier(&"a".repeat(42)));
+            assert!(!is_valid_code_verifier(&"a".repeat(129)));
+        }
+
+        #[test]
+        fn should_reject_reserved_characters() {
+            assert!(!is_valid_code_verifier(&format!("{}+", "a".repeat(42))));
+            assert!(!is_valid_code_verifier(&format!("{}=", "a".repeat(42))));
+        }
+    }
+}
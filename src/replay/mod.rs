@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use chrono::{DateTime, Utc};
+
+// Remembers one time identifiers, such as the jti of a proof, for as long as whatever carried them could be accepted.
+pub trait ReplayCache: Send + Sync + Clone {
+    // False if the identifier has already been seen and has not yet expired, otherwise it's remembered until it does.
+    fn first_use(&self, id: &str, expires_at: DateTime<Utc>) -> bool;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryReplayCache {
+    store: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+}
+
+impl InMemoryReplayCache {
+    pub fn new() -> Self {
+        Self { store: Arc::new(Mutex::new(HashMap::new())) }
+    }
+    fn lock_store(&self) -> MutexGuard<'_, HashMap<String, DateTime<Utc>>> {
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ReplayCache for InMemoryReplayCache {
+    fn first_use(&self, id: &str, expires_at: DateTime<Utc>) -> bool {
+        let now = Utc::now();
+        let mut store = self.lock_store();
+
+        // Nothing expired can be replayed, so there's no point holding on to it.
+        store.retain(|_, expires_at| *expires_at > now);
+
+        match store.get(id) {
+            Some(_) => false,
+            None => {
+                store.insert(id.into(), expires_at);
+                true
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use chrono::TimeDelta;
+
+    #[test]
+    fn should_accept_the_first_use() {
+        let replay_cache = InMemoryReplayCache::new();
+
+        assert!(replay_cache.first_use("aardvark", Utc::now() + TimeDelta::minutes(1)));
+    }
+
+    #[test]
+    fn should_reject_a_replay_until_it_expires() {
+        let replay_cache = InMemoryReplayCache::new();
+
+        assert!(replay_cache.first_use("aardvark", Utc::now() + TimeDelta::minutes(1)));
+        assert!(!replay_cache.first_use("aardvark", Utc::now() + TimeDelta::minutes(1)));
+        assert!(replay_cache.first_use("badger", Utc::now() + TimeDelta::minutes(1)));
+    }
+
+    #[test]
+    fn should_forget_expired_identifiers() {
+        let replay_cache = InMemoryReplayCache::new();
+
+        assert!(replay_cache.first_use("aardvark", Utc::now() - TimeDelta::seconds(1)));
+        assert!(replay_cache.first_use("aardvark", Utc::now() + TimeDelta::minutes(1)));
+        assert_eq!(replay_cache.lock_store().len(), 1);
+    }
+}
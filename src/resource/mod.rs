@@ -0,0 +1,102 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::client::ClientId;
+use crate::scope::{Scope, Scopes};
+
+// An API that tokens can be restricted to, identified by its URI.
+// https://www.rfc-editor.org/rfc/rfc8707#section-2
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct ProtectedResource {
+    pub uri: String,
+    // The scopes the resource understands, any others are left out of the tokens issued for it.
+    pub allowed_scopes: HashSet<Scope>,
+    // The clients that may ask for tokens to use at the resource.
+    pub allowed_clients: HashSet<ClientId>,
+    // The clients the resource itself authenticates as, which may introspect the tokens issued for it.
+    pub resource_servers: HashSet<ClientId>,
+}
+
+impl ProtectedResource {
+    pub fn can_be_targeted_by(&self, client_id: &ClientId) -> bool {
+        self.allowed_clients.contains(client_id)
+    }
+
+    pub fn is_served_by(&self, client_id: &ClientId) -> bool {
+        self.resource_servers.contains(client_id)
+    }
+
+    pub fn restrict_scopes(&self, Scopes(scopes): Scopes) -> Scopes {
+        Scopes(scopes.into_iter()
+            .filter(|scope| self.allowed_scopes.iter().any(|allowed| scope.is_granted_by(allowed)))
+            .collect::<HashSet<_>>())
+    }
+}
+
+pub trait ProtectedResourceRepository: Send + Sync + Clone {
+    fn find_by_uri(&self, uri: &str) -> Option<ProtectedResource>;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryProtectedResourceRepository {
+    store: Arc<Mutex<HashMap<String, ProtectedResource>>>,
+}
+
+impl InMemoryProtectedResourceRepository {
+    pub fn new() -> Self {
+        Self {
+            store: Arc::new(Mutex::new(HashMap::from([
+                // TODO - Remove once we've got a means of registering protected resources
+                Self::create_entry(ProtectedResource {
+                    uri: String::from("https://api.baconi.co.uk"),
+                    allowed_scopes: HashSet::from([Scope::from("basic"), Scope::from("read"), Scope::from("write")]),
+                    allowed_clients: HashSet::from([ClientId::from(String::from("aardvark")), ClientId::from(String::from("badger"))]),
+                    resource_servers: HashSet::from([ClientId::from(String::from("dingo"))]),
+                }),
+            ])))
+        }
+    }
+    fn create_entry(protected_resource: ProtectedResource) -> (String, ProtectedResource) {
+        (protected_resource.uri.clone(), protected_resource)
+    }
+    fn lock_store(&self) -> MutexGuard<'_, HashMap<String, ProtectedResource>> {
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ProtectedResourceRepository for InMemoryProtectedResourceRepository {
+    fn find_by_uri(&self, uri: &str) -> Option<ProtectedResource> {
+        self.lock_store().get(uri).cloned()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+
+    #[test]
+    fn should_only_keep_the_scopes_the_resource_understands() {
+        let resource = assert_some!(InMemoryProtectedResourceRepository::new().find_by_uri("https://api.baconi.co.uk"));
+
+        let scopes = resource.restrict_scopes(Scopes(HashSet::from([Scope::from("basic"), Scope::OPENID, Scope::from("read")])));
+
+        assert_eq!(scopes, Scopes(HashSet::from([Scope::from("basic"), Scope::from("read")])));
+    }
+
+    #[test]
+    fn should_tell_the_clients_targeting_it_apart_from_those_serving_it() {
+        let resource = assert_some!(InMemoryProtectedResourceRepository::new().find_by_uri("https://api.baconi.co.uk"));
+
+        assert!(resource.can_be_targeted_by(&ClientId::from(String::from("aardvark"))));
+        assert!(!resource.can_be_targeted_by(&ClientId::from(String::from("dingo"))));
+        assert!(resource.is_served_by(&ClientId::from(String::from("dingo"))));
+        assert!(!resource.is_served_by(&ClientId::from(String::from("aardvark"))));
+    }
+
+    #[test]
+    fn should_not_find_an_unregistered_resource() {
+        assert_none!(InMemoryProtectedResourceRepository::new().find_by_uri("https://evil.example.com"));
+    }
+}
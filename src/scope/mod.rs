@@ -1,35 +1,99 @@
 pub mod parser;
+pub mod repository;
 
+use std::borrow::Cow;
 use std::collections::HashSet;
 use serde::{Serialize, Serializer};
 use crate::disable_deserialization;
-use crate::enum_with_from_str;
 
-enum_with_from_str! {
-    #[derive(Hash, Eq, PartialEq, Clone)]
-    #[cfg_attr(test, derive(Debug))]
-    pub enum Scope {
-        Basic: "basic",
-        Read: "read",
-        Write: "write",
+// Scopes are defined at runtime by the ScopeRepository, only those the server gives meaning to itself are known up front.
+#[derive(Hash, Eq, PartialEq, Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Scope(Cow<'static, str>);
+
+impl Scope {
+    // https://openid.net/specs/openid-connect-core-1_0.html#ScopeClaims
+    pub const OPENID: Scope = Scope(Cow::Borrowed("openid"));
+    pub const PROFILE: Scope = Scope(Cow::Borrowed("profile"));
+    pub const EMAIL: Scope = Scope(Cow::Borrowed("email"));
+
+    // Parameterised scopes separate their parts, e.g. accounts:read:1234
+    pub const SEPARATOR: char = ':';
+    // Stands in for any value of a part, only in what a client is allowed, e.g. accounts:read:*
+    pub const WILDCARD: &'static str = "*";
+
+    pub fn is_granted_by(&self, grant: &Scope) -> bool {
+        if self == grant {
+            return true;
+        }
+        let parts = self.0.split(Self::SEPARATOR).collect::<Vec<_>>();
+        let granted = grant.0.split(Self::SEPARATOR).collect::<Vec<_>>();
+        parts.len() == granted.len() && parts.iter().zip(granted).all(|(part, granted)| granted == Self::WILDCARD || *part == granted)
+    }
+}
+
+impl From<&'static str> for Scope {
+    fn from(name: &'static str) -> Self {
+        Scope(Cow::Borrowed(name))
     }
 }
 
-#[derive(Eq, PartialEq)]
+impl From<String> for Scope {
+    fn from(name: String) -> Self {
+        Scope(Cow::Owned(name))
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Default, Eq, PartialEq)]
 #[cfg_attr(test, derive(Debug))]
 pub struct Scopes(pub HashSet<Scope>);
 
+impl std::fmt::Display for Scopes {
+    // Display scopes as a space delimited list
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.iter()
+            .map(|scope| scope.to_string())
+            .collect::<Vec<String>>()
+            .join(" "))
+    }
+}
+
 impl Serialize for Scopes {
     // Serialize scopes as a space delimited list
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        self.0.iter()
-            .map(|scope| scope.to_string())
-            .collect::<Vec<String>>()
-            .join(" ")
-            .serialize(serializer)
+        self.to_string().serialize(serializer)
     }
 }
 
-// To enable us to trust Scope is valid, we don't allow direct deserialization of Scope.
+// To enable us to trust Scope is valid, we don't allow direct deserialization of Scope, only parsing against the ScopeRepository.
 disable_deserialization!(Scope);
 disable_deserialization!(Scopes);
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+
+    #[test]
+    fn should_be_granted_by_the_same_scope() {
+        assert!(Scope::from("basic").is_granted_by(&Scope::from("basic")));
+        assert!(Scope::from("accounts:read:1234").is_granted_by(&Scope::from("accounts:read:1234")));
+        assert!(!Scope::from("basic").is_granted_by(&Scope::from("read")));
+    }
+
+    #[test]
+    fn should_be_granted_by_a_wildcard_in_place_of_a_part() {
+        assert!(Scope::from("accounts:read:1234").is_granted_by(&Scope::from("accounts:read:*")));
+        assert!(Scope::from("accounts:read:1234").is_granted_by(&Scope::from("accounts:*:*")));
+        assert!(!Scope::from("accounts:write:1234").is_granted_by(&Scope::from("accounts:read:*")));
+        assert!(!Scope::from("accounts:read").is_granted_by(&Scope::from("accounts:read:*")));
+        assert!(!Scope::from("accounts:read:1234:5678").is_granted_by(&Scope::from("accounts:read:*")));
+        assert!(!Scope::from("basic").is_granted_by(&Scope::from("*:*")));
+    }
+}
@@ -1,8 +1,11 @@
+use std::borrow::Cow;
 use std::collections::HashSet;
+use crate::client::ClientId;
 use crate::scope::Scope;
 use crate::scope::Scopes;
+use crate::scope::repository::ScopeRepository;
 
-pub fn parse_scopes(maybe_space_delimited_scopes: Option<&String>) -> Result<Option<Scopes>, &str> {
+pub fn parse_scopes<R: ScopeRepository>(scope_repository: &R, client_id: Option<&ClientId>, maybe_space_delimited_scopes: Option<&String>) -> Result<Option<Scopes>, &'static str> {
     match maybe_space_delimited_scopes {
         Some(space_delimited_scopes) => {
 
@@ -23,9 +26,15 @@ pub fn parse_scopes(maybe_space_delimited_scopes: Option<&String>) -> Result<Opt
 
             let raw_scopes_count = raw_scopes.len();
 
+            // Unknown scopes, and those restricted to other clients, are both treated as invalid.
             let scopes = raw_scopes
                 .into_iter()
-                .flat_map(|scope| scope.parse::<Scope>())
+                .flat_map(|scope| scope_repository.find_by_name(&scope).map(|definition| (scope, definition)))
+                .filter(|(_, definition)| definition.can_be_requested_by(client_id))
+                .map(|(scope, definition)| match definition.is_template() {
+                    true => Scope(Cow::Owned(scope)),
+                    false => definition.scope,
+                })
                 .collect::<HashSet<Scope>>();
 
             if scopes.len() != raw_scopes_count {
@@ -37,3 +46,48 @@ pub fn parse_scopes(maybe_space_delimited_scopes: Option<&String>) -> Result<Opt
         None => Ok(None)
     }
 }
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use crate::scope::repository::InMemoryScopeRepository;
+
+    fn parse(scopes: Option<&str>) -> Result<Option<Scopes>, &'static str> {
+        parse_scopes(&InMemoryScopeRepository::with_restricted([("cicada", &["aardvark"][..])]), Some(&ClientId::from(String::from("badger"))), scopes.map(String::from).as_ref())
+    }
+
+    #[test]
+    fn should_parse_defined_scopes() {
+        assert_eq!(assert_ok!(parse(Some("basic openid"))), Some(Scopes(HashSet::from([Scope::from("basic"), Scope::OPENID]))));
+        assert_none!(assert_ok!(parse(None)));
+    }
+
+    #[test]
+    fn should_reject_empty_and_blank_scopes() {
+        assert_err_eq_x!(parse(Some("")), "defined but empty scopes");
+        assert_err_eq_x!(parse(Some("  ")), "defined but blank scopes");
+    }
+
+    #[test]
+    fn should_reject_undefined_repeated_and_restricted_scopes() {
+        assert_err_eq_x!(parse(Some("basic dingo")), "defined but invalid scope provided");
+        assert_err_eq_x!(parse(Some("basic basic")), "defined but invalid scope provided");
+        assert_err_eq_x!(parse(Some("basic cicada")), "defined but invalid scope provided");
+    }
+
+    #[test]
+    fn should_parse_parameterised_scopes() {
+        assert_eq!(assert_ok!(parse(Some("basic accounts:read:1234 accounts:read:5678"))), Some(Scopes(HashSet::from([Scope::from("basic"), Scope::from("accounts:read:1234"), Scope::from("accounts:read:5678")]))));
+        assert_eq!(assert_ok!(parse(Some("accounts:read:1234"))).map(|scopes| scopes.to_string()), Some(String::from("accounts:read:1234")));
+    }
+
+    #[test]
+    fn should_reject_templates_wildcards_and_mistyped_parameters() {
+        assert_err_eq_x!(parse(Some("accounts:read:{id}")), "defined but invalid scope provided");
+        assert_err_eq_x!(parse(Some("accounts:read:*")), "defined but invalid scope provided");
+        assert_err_eq_x!(parse(Some("accounts:read:abc")), "defined but invalid scope provided");
+        assert_err_eq_x!(parse(Some("accounts:read:1234 accounts:read:1234")), "defined but invalid scope provided");
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::client::ClientId;
+use crate::scope::Scope;
+
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct ScopeDefinition {
+    pub scope: Scope,
+    // Human-readable, for showing the resource owner what they're agreeing to.
+    pub description: String,
+    // Given to clients that register without asking for any scopes.
+    pub default: bool,
+    // When set only these clients may request the scope, otherwise any client allowed it in its configuration can.
+    pub allowed_clients: Option<HashSet<ClientId>>,
+    // The types of the {placeholders} in a parameterised scope's template, e.g. accounts:read:{id}
+    pub parameters: HashMap<String, ScopeParameter>,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub enum ScopeParameter {
+    Numeric,
+    // Letters, digits, hyphens and underscores.
+    Identifier,
+}
+
+impl ScopeParameter {
+    fn accepts(&self, value: &str) -> bool {
+        !value.is_empty() && match self {
+            ScopeParameter::Numeric => value.chars().all(|c| c.is_ascii_digit()),
+            ScopeParameter::Identifier => value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+        }
+    }
+}
+
+impl ScopeDefinition {
+    // Without a client, as when one is registering, only unrestricted scopes can be requested.
+    pub fn can_be_requested_by(&self, client_id: Option<&ClientId>) -> bool {
+        match (&self.allowed_clients, client_id) {
+            (None, _) => true,
+            (Some(allowed_clients), Some(client_id)) => allowed_clients.contains(client_id),
+            (Some(_), None) => false,
+        }
+    }
+
+    pub fn is_template(&self) -> bool {
+        !self.parameters.is_empty()
+    }
+
+    // Whether the concrete scope is this one, or for a template fills in every placeholder with a value of its type.
+    pub fn matches(&self, name: &str) -> bool {
+        if !self.is_template() {
+            return self.scope.to_string() == name;
+        }
+        let template = self.scope.to_string();
+        let parts = name.split(Scope::SEPARATOR).collect::<Vec<_>>();
+        let expected = template.split(Scope::SEPARATOR).collect::<Vec<_>>();
+        parts.len() == expected.len() && parts.iter().zip(expected).all(|(part, expected)| {
+            match expected.strip_prefix('{').and_then(|placeholder| placeholder.strip_suffix('}')) {
+                Some(placeholder) => self.parameters.get(placeholder).is_some_and(|parameter| parameter.accepts(part)),
+                None => *part == expected,
+            }
+        })
+    }
+}
+
+pub trait ScopeRepository: Send + Sync + Clone {
+    // For a parameterised scope this finds the template it was filled in from.
+    fn find_by_name(&self, name: &str) -> Option<ScopeDefinition>;
+    fn find_all(&self) -> Vec<ScopeDefinition>;
+}
+
+// Gives the request extractors, that validate before a handler is reached, the registry held in their route's state.
+pub trait ScopeRepositoryState {
+    type ScopeRepository: ScopeRepository;
+    fn scope_repository(&self) -> &Self::ScopeRepository;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryScopeRepository {
+    store: Arc<Mutex<HashMap<String, ScopeDefinition>>>,
+}
+
+impl InMemoryScopeRepository {
+    pub fn new() -> Self {
+        // TODO - Remove once we've got a means of defining new scopes
+        Self::from_definitions([
+            Self::create_entry(Scope::from("basic"), "Basic access to your account", true),
+            Self::create_entry(Scope::from("read"), "Read your data", false),
+            Self::create_entry(Scope::from("write"), "Change your data", false),
+            Self::create_entry(Scope::OPENID, "Sign you in", false),
+            Self::create_entry(Scope::PROFILE, "Your name and username", false),
+            Self::create_entry(Scope::EMAIL, "Your email address", false),
+            Self::create_template("accounts:read:{id}", "Read one of your accounts", [("id", ScopeParameter::Numeric)]),
+            Self::create_template("accounts:write:{id}", "Change one of your accounts", [("id", ScopeParameter::Numeric)]),
+            Self::create_template("payments:approve:{reference}", "Approve one of your payments", [("reference", ScopeParameter::Identifier)]),
+        ])
+    }
+    fn from_definitions(definitions: impl IntoIterator<Item = ScopeDefinition>) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(definitions.into_iter()
+                .map(|definition| (definition.scope.to_string(), definition))
+                .collect()))
+        }
+    }
+    fn create_entry(scope: Scope, description: &str, default: bool) -> ScopeDefinition {
+        ScopeDefinition { scope, description: description.into(), default, allowed_clients: None, parameters: HashMap::new() }
+    }
+    fn create_template<const N: usize>(template: &'static str, description: &str, parameters: [(&str, ScopeParameter); N]) -> ScopeDefinition {
+        ScopeDefinition {
+            parameters: parameters.into_iter().map(|(name, parameter)| (name.into(), parameter)).collect(),
+            ..Self::create_entry(Scope::from(template), description, false)
+        }
+    }
+    fn lock_store(&self) -> MutexGuard<'_, HashMap<String, ScopeDefinition>> {
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ScopeRepository for InMemoryScopeRepository {
+    fn find_by_name(&self, name: &str) -> Option<ScopeDefinition> {
+        let store = self.lock_store();
+        // A template itself can't be requested, only filled in.
+        store.get(name)
+            .filter(|definition| !definition.is_template())
+            .or_else(|| store.values().find(|definition| definition.is_template() && definition.matches(name)))
+            .cloned()
+    }
+    fn find_all(&self) -> Vec<ScopeDefinition> {
+        self.lock_store().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use super::*;
+
+    impl InMemoryScopeRepository {
+        // The usual scopes, plus ones only some clients may request.
+        pub fn with_restricted(restricted: impl IntoIterator<Item = (&'static str, &'static [&'static str])>) -> Self {
+            let repository = Self::new();
+            for (name, clients) in restricted {
+                repository.lock_store().insert(name.into(), ScopeDefinition {
+                    allowed_clients: Some(clients.iter().map(|client| ClientId::from(String::from(*client))).collect()),
+                    ..Self::create_entry(Scope::from(name), name, false)
+                });
+            }
+            repository
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+
+    #[test]
+    fn should_find_a_defined_scope() {
+        let definition = assert_some!(InMemoryScopeRepository::new().find_by_name("openid"));
+
+        assert_eq!(definition.scope, Scope::OPENID);
+        assert!(!definition.default);
+    }
+
+    #[test]
+    fn should_not_find_an_undefined_scope() {
+        assert_none!(InMemoryScopeRepository::new().find_by_name("cicada"));
+    }
+
+    #[test]
+    fn should_only_let_the_allowed_clients_request_a_restricted_scope() {
+        let repository = InMemoryScopeRepository::with_restricted([("cicada", &["aardvark"][..])]);
+        let definition = assert_some!(repository.find_by_name("cicada"));
+
+        assert!(definition.can_be_requested_by(Some(&ClientId::from(String::from("aardvark")))));
+        assert!(!definition.can_be_requested_by(Some(&ClientId::from(String::from("badger")))));
+        assert!(!definition.can_be_requested_by(None));
+        assert!(assert_some!(repository.find_by_name("basic")).can_be_requested_by(None));
+    }
+
+    #[test]
+    fn should_find_the_template_of_a_parameterised_scope() {
+        let definition = assert_some!(InMemoryScopeRepository::new().find_by_name("accounts:read:1234"));
+
+        assert_eq!(definition.scope, Scope::from("accounts:read:{id}"));
+        assert!(definition.is_template());
+    }
+
+    #[test]
+    fn should_not_find_a_parameterised_scope_with_a_mistyped_or_missing_parameter() {
+        let repository = InMemoryScopeRepository::new();
+
+        assert_none!(repository.find_by_name("accounts:read:{id}"));
+        assert_none!(repository.find_by_name("accounts:read:*"));
+        assert_none!(repository.find_by_name("accounts:read:abc"));
+        assert_none!(repository.find_by_name("accounts:read:"));
+        assert_none!(repository.find_by_name("accounts:read"));
+        assert_none!(repository.find_by_name("accounts:read:1234:5678"));
+    }
+
+    #[test]
+    fn should_accept_only_values_of_the_parameters_type() {
+        assert!(ScopeParameter::Numeric.accepts("1234"));
+        assert!(!ScopeParameter::Numeric.accepts("12a4"));
+        assert!(ScopeParameter::Identifier.accepts("current-account_1"));
+        assert!(!ScopeParameter::Identifier.accepts("current account"));
+        assert!(!ScopeParameter::Identifier.accepts(""));
+    }
+}
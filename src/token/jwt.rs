@@ -0,0 +1,224 @@
+use jsonwebtoken::{decode, decode_header, Validation};
+use jsonwebtoken::errors::Error;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::client::AccessTokenFormat;
+use crate::key::{KeyStore, SigningKey};
+use crate::token::{AccessToken, Actor, Confirmation};
+use crate::token::repository::TokenRepository;
+use crate::util::value_struct::ValueStruct;
+
+// https://www.rfc-editor.org/rfc/rfc9068#section-2.1
+pub const ACCESS_TOKEN_TYPE: &str = "at+jwt";
+
+// https://www.rfc-editor.org/rfc/rfc9068#section-2.2
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct AccessTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    pub jti: Uuid,
+    pub client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // https://www.rfc-editor.org/rfc/rfc8693#section-4.1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    // https://www.rfc-editor.org/rfc/rfc9449#section-6.1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<Confirmation>,
+}
+
+impl AccessTokenClaims {
+    pub fn new(issuer: &str, access_token: &AccessToken) -> Self {
+        Self {
+            iss: issuer.into(),
+            sub: access_token.subject().into(),
+            aud: access_token.audience().into(),
+            exp: access_token.expires_at.timestamp(),
+            iat: access_token.issued_at.timestamp(),
+            jti: access_token.id,
+            client_id: access_token.client_id.value().clone(),
+            scope: Some(access_token.scopes.to_string()).filter(|scope| !scope.is_empty()),
+            act: access_token.actor.clone(),
+            cnf: access_token.confirmation.clone(),
+        }
+    }
+}
+
+pub fn encode_access_token(issuer: &str, access_token: &AccessToken, signing_key: &SigningKey) -> Result<String, Error> {
+    signing_key.sign(ACCESS_TOKEN_TYPE, &AccessTokenClaims::new(issuer, access_token))
+}
+
+// Tokens are either opaque identifiers or JWTs we've signed, in which case the identifier is the jti.
+pub fn parse_token_id<K: KeyStore>(issuer: &str, key_store: &K, token: &str) -> Option<(Uuid, AccessTokenFormat)> {
+    match Uuid::parse_str(token) {
+        Ok(id) => Some((id, AccessTokenFormat::Opaque)),
+        Err(_) => decode_access_token(issuer, key_store, token).map(|claims| (claims.jti, AccessTokenFormat::Jwt)),
+    }
+}
+
+// Only found in the format it was issued in, otherwise the jti of a JWT, as seen in logs or introspection, would be a bearer credential.
+pub fn find_access_token<K: KeyStore, A: TokenRepository<AccessToken>>(issuer: &str, key_store: &K, access_token_repository: &A, token: &str) -> Option<AccessToken> {
+    let (id, format) = parse_token_id(issuer, key_store, token)?;
+    access_token_repository.get_token(id).filter(|access_token| access_token.format == format)
+}
+
+fn decode_access_token<K: KeyStore>(issuer: &str, key_store: &K, token: &str) -> Option<AccessTokenClaims> {
+
+    let header = decode_header(token).ok()?;
+
+    if header.typ.as_deref() != Some(ACCESS_TOKEN_TYPE) {
+        return None
+    }
+
+    let key = key_store.verification_key(header.kid.as_deref()?)?;
+
+    let mut validation = Validation::new(key.algorithm);
+    validation.set_issuer(&[issuer]);
+    validation.validate_aud = false;
+
+    decode::<AccessTokenClaims>(token, key.decoding_key(), &validation)
+        .ok()
+        .map(|data| data.claims)
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use std::collections::HashSet;
+    use crate::client::ClientId;
+    use crate::key::InMemoryKeyStore;
+    use crate::scope::{Scope, Scopes};
+    use crate::token::repository::InMemoryTokenRepository;
+    use crate::user::Username;
+
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
+
+    fn new_access_token(username: Option<&str>) -> AccessToken {
+        AccessToken::new(
+            ClientId::from(String::from("aardvark")),
+            username.map(|username| Username::from(String::from(username))),
+            Scopes(HashSet::from([Scope::from("basic")])),
+            None,
+        )
+    }
+
+    fn sign(key_store: &InMemoryKeyStore, access_token: &AccessToken) -> String {
+        assert_ok!(encode_access_token(TEST_ISSUER, access_token, assert_some!(key_store.signing_key()).as_ref()))
+    }
+
+    #[test]
+    fn should_sign_with_the_access_token_type_and_key_id() {
+        let key_store = InMemoryKeyStore::new_test_store();
+
+        let header = assert_ok!(decode_header(sign(&key_store, &new_access_token(Some("badger")))));
+
+        assert_eq!(header.typ.as_deref(), Some(ACCESS_TOKEN_TYPE));
+        assert_eq!(header.kid.as_deref(), Some("2026-01-rs256"));
+    }
+
+    #[test]
+    fn should_include_the_rfc_9068_claims() {
+        let key_store = InMemoryKeyStore::new_test_store();
+        let access_token = new_access_token(Some("badger"));
+
+        let claims = assert_some!(decode_access_token(TEST_ISSUER, &key_store, &sign(&key_store, &access_token)));
+
+        assert_eq!(claims, AccessTokenClaims {
+            iss: TEST_ISSUER.into(),
+            sub: "badger".into(),
+            aud: "aardvark".into(),
+            exp: access_token.expires_at.timestamp(),
+            iat: access_token.issued_at.timestamp(),
+            jti: access_token.id,
+            client_id: "aardvark".into(),
+            scope: Some("basic".into()),
+            act: None,
+            cnf: None,
+        });
+    }
+
+    #[test]
+    fn should_include_the_audience_and_actor_of_an_exchanged_token() {
+        let key_store = InMemoryKeyStore::new_test_store();
+        let actor = Actor { sub: "cicada".into(), act: Some(Box::new(Actor { sub: "dingo".into(), act: None })) };
+        let access_token = AccessToken {
+            audience: Some("https://api.baconi.co.uk".into()),
+            actor: Some(actor.clone()),
+            ..new_access_token(Some("badger"))
+        };
+
+        let claims = assert_some!(decode_access_token(TEST_ISSUER, &key_store, &sign(&key_store, &access_token)));
+
+        assert_eq!(claims.aud, "https://api.baconi.co.uk");
+        assert_some_eq_x!(&claims.act, &actor);
+    }
+
+    #[test]
+    fn should_use_the_client_as_the_subject_without_a_resource_owner() {
+        let key_store = InMemoryKeyStore::new_test_store();
+
+        let claims = assert_some!(decode_access_token(TEST_ISSUER, &key_store, &sign(&key_store, &new_access_token(None))));
+
+        assert_eq!(claims.sub, "aardvark");
+    }
+
+    #[test]
+    fn should_parse_the_id_of_an_opaque_token() {
+        let id = Uuid::new_v4();
+
+        assert_some_eq_x!(parse_token_id(TEST_ISSUER, &InMemoryKeyStore::new_test_store(), &id.to_string()), (id, AccessTokenFormat::Opaque));
+    }
+
+    #[test]
+    fn should_parse_the_id_of_a_signed_token() {
+        let key_store = InMemoryKeyStore::new_test_store();
+        let access_token = new_access_token(Some("badger"));
+
+        assert_some_eq_x!(parse_token_id(TEST_ISSUER, &key_store, &sign(&key_store, &access_token)), (access_token.id, AccessTokenFormat::Jwt));
+    }
+
+    #[test]
+    fn should_only_find_an_access_token_in_the_format_it_was_issued_in() {
+        let key_store = InMemoryKeyStore::new_test_store();
+        let access_token_repository = InMemoryTokenRepository::new();
+        let jwt_access_token = AccessToken { format: AccessTokenFormat::Jwt, ..new_access_token(Some("badger")) };
+        let opaque_access_token = new_access_token(Some("badger"));
+        access_token_repository.save_token(&jwt_access_token);
+        access_token_repository.save_token(&opaque_access_token);
+
+        assert_some_eq_x!(find_access_token(TEST_ISSUER, &key_store, &access_token_repository, &sign(&key_store, &jwt_access_token)), jwt_access_token.clone());
+        assert_none!(find_access_token(TEST_ISSUER, &key_store, &access_token_repository, &jwt_access_token.id.to_string()));
+
+        assert_some_eq_x!(find_access_token(TEST_ISSUER, &key_store, &access_token_repository, &opaque_access_token.id.to_string()), opaque_access_token.clone());
+        assert_none!(find_access_token(TEST_ISSUER, &key_store, &access_token_repository, &sign(&key_store, &opaque_access_token)));
+    }
+
+    #[test]
+    fn should_not_parse_a_token_from_another_issuer() {
+        let key_store = InMemoryKeyStore::new_test_store();
+        let token = assert_ok!(encode_access_token("https://evil.example.com", &new_access_token(Some("badger")), assert_some!(key_store.signing_key()).as_ref()));
+
+        assert_none!(parse_token_id(TEST_ISSUER, &key_store, &token));
+    }
+
+    #[test]
+    fn should_not_parse_a_tampered_token() {
+        let key_store = InMemoryKeyStore::new_test_store();
+        let token = sign(&key_store, &new_access_token(Some("badger")));
+        let tampered = format!("{}x", token);
+
+        assert_none!(parse_token_id(TEST_ISSUER, &key_store, &tampered));
+    }
+
+    #[test]
+    fn should_not_parse_garbage() {
+        assert_none!(parse_token_id(TEST_ISSUER, &InMemoryKeyStore::new_test_store(), "aardvark"));
+    }
+}
@@ -1,10 +1,67 @@
+pub mod jwt;
 pub mod repository;
+pub mod user_code;
 
-use serde::Serialize;
+use std::collections::HashMap;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use crate::authorization::AuthorizationRequest;
+use crate::client::{AccessTokenFormat, ClientId};
+use crate::disable_deserialization;
+use crate::enum_with_from_str;
+use crate::pkce::CodeChallenge;
+use crate::scope::Scopes;
+use crate::user::Username;
+use crate::util::value_struct::ValueStruct;
 
 pub trait Token {
     fn id(&self) -> Uuid;
+    fn expires_at(&self) -> DateTime<Utc>;
+
+    fn has_expired(&self) -> bool {
+        self.expires_at() <= Utc::now()
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc7009#section-2.1
+enum_with_from_str! {
+    #[derive(Eq, PartialEq, Clone)]
+    #[cfg_attr(test, derive(Debug))]
+    pub enum TokenTypeHint {
+        AccessToken: "access_token",
+        RefreshToken: "refresh_token",
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc8693#section-3
+enum_with_from_str! {
+    #[derive(Eq, PartialEq, Clone)]
+    #[cfg_attr(test, derive(Debug))]
+    pub enum TokenTypeIdentifier {
+        AccessToken: "urn:ietf:params:oauth:token-type:access_token",
+        Jwt: "urn:ietf:params:oauth:token-type:jwt",
+    }
+}
+
+disable_deserialization!(TokenTypeIdentifier);
+
+// Who is acting on behalf of the subject, with any prior actors nested within.
+// https://www.rfc-editor.org/rfc/rfc8693#section-4.1
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Actor {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<Box<Actor>>,
+}
+
+// The key a token is bound to, so only the holder of its private half can use it.
+// https://www.rfc-editor.org/rfc/rfc9449#section-6.1
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Confirmation {
+    pub jkt: String,
 }
 
 #[cfg_attr(test, derive(Debug))]
@@ -13,15 +70,376 @@ pub trait Token {
 pub enum TokenType {
     // https://www.rfc-editor.org/rfc/rfc6750
     Bearer,
+    // https://www.rfc-editor.org/rfc/rfc9449#section-5
+    #[serde(rename = "DPoP")]
+    DPoP,
 }
 
 #[derive(Serialize, Clone)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
 pub struct AccessToken {
-    pub id: Uuid
+    pub id: Uuid,
+    pub client_id: ClientId,
+    pub username: Option<Username>,
+    pub scopes: Scopes,
+    // The grant of the refresh token this was issued alongside, if any, so it can be revoked with it.
+    pub grant_id: Option<Uuid>,
+    // Who the token is intended for, when it was exchanged for a specific audience.
+    pub audience: Option<String>,
+    // Who is acting on behalf of the subject, when it was exchanged with an actor token.
+    pub actor: Option<Actor>,
+    // The key the token is bound to, when it was issued with a DPoP proof.
+    pub confirmation: Option<Confirmation>,
+    // How the token was handed to the client, and so the only way it can be presented.
+    #[serde(skip)]
+    pub format: AccessTokenFormat,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl AccessToken {
+
+    // TODO - Extract into configuration
+    pub const TIME_TO_LIVE: TimeDelta = TimeDelta::hours(2);
+
+    // The username is absent when the client is acting on its own behalf, such as the client_credentials grant.
+    pub fn new(client_id: ClientId, username: Option<Username>, scopes: Scopes, grant_id: Option<Uuid>) -> Self {
+        let issued_at = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            client_id,
+            username,
+            scopes,
+            grant_id,
+            audience: None,
+            actor: None,
+            confirmation: None,
+            format: AccessTokenFormat::Opaque,
+            issued_at,
+            expires_at: issued_at + Self::TIME_TO_LIVE,
+        }
+    }
+
+    // The lifetime in seconds of the access token, from the time it was issued.
+    pub fn expires_in(&self) -> i64 {
+        (self.expires_at - self.issued_at).num_seconds()
+    }
+
+    // Without a resource owner the client is the subject, as with the client_credentials grant.
+    pub fn subject(&self) -> &str {
+        match &self.username {
+            Some(username) => username.value(),
+            None => self.client_id.value(),
+        }
+    }
+
+    // Sender constrained tokens are presented as DPoP rather than Bearer tokens.
+    pub fn token_type(&self) -> TokenType {
+        match self.confirmation {
+            Some(_) => TokenType::DPoP,
+            None => TokenType::Bearer,
+        }
+    }
+
+    // Without an explicit audience the token is intended for the client it was issued to.
+    pub fn audience(&self) -> &str {
+        self.audience.as_deref().unwrap_or(self.client_id.value())
+    }
 }
 
 impl Token for AccessToken {
     fn id(&self) -> Uuid {
         self.id
     }
+    fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct RefreshToken {
+    pub id: Uuid,
+    // Shared by every rotation of this refresh token, and every access token issued from them.
+    pub grant_id: Uuid,
+    pub client_id: ClientId,
+    pub username: Username,
+    pub scopes: Scopes,
+    // The resource every access token issued from this is restricted to, if one was asked for.
+    pub resource: Option<String>,
+    // The key a public client must prove possession of to use it, when it was issued with a DPoP proof.
+    pub confirmation: Option<Confirmation>,
+    // When the resource owner originally authenticated, which every rotation keeps.
+    pub auth_time: DateTime<Utc>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl RefreshToken {
+
+    // TODO - Extract into configuration
+    pub const TIME_TO_LIVE: TimeDelta = TimeDelta::days(30);
+
+    pub fn new(client_id: ClientId, username: Username, scopes: Scopes, auth_time: DateTime<Utc>) -> Self {
+        let issued_at = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            grant_id: Uuid::new_v4(),
+            client_id,
+            username,
+            scopes,
+            resource: None,
+            confirmation: None,
+            auth_time,
+            issued_at,
+            expires_at: issued_at + Self::TIME_TO_LIVE,
+        }
+    }
+
+    // Rotation issues a replacement with the same scope and grant, which never outlives the original.
+    pub fn rotate(&self) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            issued_at: Utc::now(),
+            ..self.clone()
+        }
+    }
+}
+
+impl Token for RefreshToken {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct AuthorizationCode {
+    pub id: Uuid,
+    pub client_id: ClientId,
+    pub username: Username,
+    pub redirect_uri: String,
+    pub scopes: Scopes,
+    pub state: Option<String>,
+    #[serde(skip)]
+    pub code_challenge: Option<CodeChallenge>,
+    // Passed through to the ID token so the client can tie it to its authentication request.
+    pub nonce: Option<String>,
+    // The resource the resource owner authorized access to, see https://www.rfc-editor.org/rfc/rfc8707#section-2.1
+    pub resource: Option<String>,
+    // The resource owner authenticates as the code is issued.
+    pub auth_time: DateTime<Utc>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl AuthorizationCode {
+
+    // TODO - Extract into configuration
+    pub const TIME_TO_LIVE: TimeDelta = TimeDelta::minutes(1);
+
+    pub fn new(client_id: ClientId, username: Username, redirect_uri: String, scopes: Scopes, state: Option<String>, code_challenge: Option<CodeChallenge>, nonce: Option<String>) -> Self {
+        let issued_at = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            client_id,
+            username,
+            redirect_uri,
+            scopes,
+            state,
+            code_challenge,
+            nonce,
+            resource: None,
+            auth_time: issued_at,
+            issued_at,
+            expires_at: issued_at + Self::TIME_TO_LIVE,
+        }
+    }
+}
+
+impl Token for AuthorizationCode {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc8628#section-3.2
+#[derive(Serialize, Clone)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct DeviceCode {
+    pub id: Uuid,
+    // What the resource owner enters on another device to approve this one.
+    pub user_code: String,
+    pub client_id: ClientId,
+    pub scopes: Scopes,
+    pub status: DeviceCodeStatus,
+    // Seconds the client must wait between polls, which grows each time it polls too quickly.
+    pub interval: i64,
+    pub polled_at: Option<DateTime<Utc>>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Clone)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceCodeStatus {
+    Pending,
+    Approved {
+        username: Username,
+        auth_time: DateTime<Utc>,
+    },
+    Denied,
+}
+
+impl DeviceCode {
+
+    // TODO - Extract into configuration
+    pub const TIME_TO_LIVE: TimeDelta = TimeDelta::minutes(10);
+    pub const INTERVAL: i64 = 5;
+    // How long we hold on to an expired device code, to tell a polling client it expired rather than it's unknown.
+    pub const RETENTION: TimeDelta = TimeDelta::minutes(10);
+
+    pub fn new(client_id: ClientId, scopes: Scopes) -> Result<Self, getrandom::Error> {
+        let issued_at = Utc::now();
+        Ok(Self {
+            id: Uuid::new_v4(),
+            user_code: user_code::generate()?,
+            client_id,
+            scopes,
+            status: DeviceCodeStatus::Pending,
+            interval: Self::INTERVAL,
+            polled_at: None,
+            issued_at,
+            expires_at: issued_at + Self::TIME_TO_LIVE,
+        })
+    }
+
+    // The lifetime in seconds of the device code, from the time it was issued.
+    pub fn expires_in(&self) -> i64 {
+        (self.expires_at - self.issued_at).num_seconds()
+    }
+
+    // Unlike has_expired, which only becomes true once we no longer hold on to it.
+    pub fn is_usable(&self) -> bool {
+        self.expires_at > Utc::now()
+    }
+}
+
+impl Token for DeviceCode {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+    fn has_expired(&self) -> bool {
+        self.expires_at + Self::RETENTION <= Utc::now()
+    }
+}
+
+// The parameters of an authorization request, pushed by the client ahead of sending the user to us.
+// https://www.rfc-editor.org/rfc/rfc9126#section-2
+#[derive(Serialize, Clone)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct PushedAuthorizationRequest {
+    pub id: Uuid,
+    pub client_id: ClientId,
+    pub parameters: HashMap<String, String>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PushedAuthorizationRequest {
+
+    // TODO - Extract into configuration
+    // Long enough for the resource owner to sign in, as the request_uri is used until they have.
+    pub const TIME_TO_LIVE: TimeDelta = TimeDelta::minutes(5);
+
+    // https://www.rfc-editor.org/rfc/rfc9126#section-2.2
+    pub const REQUEST_URI_PREFIX: &'static str = "urn:ietf:params:oauth:request_uri:";
+
+    pub fn new(client_id: ClientId, parameters: HashMap<String, String>) -> Self {
+        let issued_at = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            client_id,
+            parameters,
+            issued_at,
+            expires_at: issued_at + Self::TIME_TO_LIVE,
+        }
+    }
+
+    // How the client refers to this request when sending the user to the authorization endpoint.
+    pub fn request_uri(&self) -> String {
+        format!("{}{}", Self::REQUEST_URI_PREFIX, self.id)
+    }
+
+    // The identifier of the request a request_uri refers to, if it's one of ours.
+    pub fn parse_request_uri(request_uri: &str) -> Option<Uuid> {
+        request_uri.strip_prefix(Self::REQUEST_URI_PREFIX).and_then(|id| Uuid::parse_str(id).ok())
+    }
+
+    // The lifetime in seconds of the request_uri, from the time it was issued.
+    pub fn expires_in(&self) -> i64 {
+        (self.expires_at - self.issued_at).num_seconds()
+    }
+}
+
+impl Token for PushedAuthorizationRequest {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+}
+
+// The resource owner has signed in, and is being asked to consent to the scopes they've not agreed to before.
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct PendingConsent {
+    pub id: Uuid,
+    // As it was validated before the resource owner signed in, so the consent form can't change where the code goes or how it's redeemed.
+    pub request: AuthorizationRequest,
+    pub username: Username,
+    // Only these can be agreed to, whatever else the consent form is sent back with.
+    pub scopes: Scopes,
+    // The resource owner authenticates just before being asked.
+    pub auth_time: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PendingConsent {
+
+    // TODO - Extract into configuration
+    // Long enough for the resource owner to read the consent screen.
+    pub const TIME_TO_LIVE: TimeDelta = TimeDelta::minutes(5);
+
+    pub fn new(request: AuthorizationRequest, username: Username, scopes: Scopes) -> Self {
+        let auth_time = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            request,
+            username,
+            scopes,
+            auth_time,
+            expires_at: auth_time + Self::TIME_TO_LIVE,
+        }
+    }
+}
+
+impl Token for PendingConsent {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+    fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
 }
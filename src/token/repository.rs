@@ -4,8 +4,17 @@ use std::sync::{Arc, Mutex, MutexGuard};
 use uuid::Uuid;
 
 pub trait TokenRepository<T: Token + Clone + Send>: Send + Sync + Clone {
+    // Expired tokens are treated as if they don't exist.
     fn get_token(&self, id: Uuid) -> Option<T>;
     fn save_token(&self, token: &T);
+    // Removes the token, returning it only to the first caller so it can be used exactly once.
+    fn remove_token(&self, id: Uuid) -> Option<T>;
+    // Removes every token matching the predicate, such as all those issued from a revoked grant.
+    fn remove_tokens_where(&self, predicate: impl Fn(&T) -> bool);
+    // Finds a token by something other than its identifier, such as the user code of a device code.
+    fn find_token_where(&self, predicate: impl Fn(&T) -> bool) -> Option<T>;
+    // Updates the token in place, atomically, so concurrent updates to different fields can't overwrite each other.
+    fn update_token<R>(&self, id: Uuid, update: impl FnOnce(&mut T) -> R) -> Option<R>;
 }
 
 #[derive(Clone, Default)]
@@ -25,10 +34,193 @@ impl<T: Token> InMemoryTokenRepository<T> {
 impl<T: Token + Clone + Send> TokenRepository<T> for InMemoryTokenRepository<T>
 {
     fn get_token(&self, id: Uuid) -> Option<T> {
-        self.lock_store().get(&id).cloned()
+        let mut store = self.lock_store();
+        match store.get(&id) {
+            Some(token) if token.has_expired() => {
+                store.remove(&id);
+                None
+            },
+            maybe_token => maybe_token.cloned(),
+        }
     }
 
     fn save_token(&self, token: &T) {
         self.lock_store().insert(token.id(), token.clone());
     }
+
+    fn remove_token(&self, id: Uuid) -> Option<T> {
+        self.lock_store()
+            .remove(&id)
+            .filter(|token| !token.has_expired())
+    }
+
+    fn remove_tokens_where(&self, predicate: impl Fn(&T) -> bool) {
+        self.lock_store().retain(|_, token| !predicate(token))
+    }
+
+    fn find_token_where(&self, predicate: impl Fn(&T) -> bool) -> Option<T> {
+        self.lock_store()
+            .values()
+            .find(|token| !token.has_expired() && predicate(token))
+            .cloned()
+    }
+
+    fn update_token<R>(&self, id: Uuid, update: impl FnOnce(&mut T) -> R) -> Option<R> {
+        self.lock_store()
+            .get_mut(&id)
+            .filter(|token| !token.has_expired())
+            .map(update)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use chrono::{TimeDelta, Utc};
+    use crate::client::ClientId;
+    use crate::scope::Scopes;
+    use crate::token::AccessToken;
+    use crate::user::Username;
+
+    fn new_access_token() -> AccessToken {
+        AccessToken::new(
+            ClientId::from(String::from("aardvark")),
+            Some(Username::from(String::from("aardvark"))),
+            Scopes::default(),
+            None,
+        )
+    }
+
+    #[test]
+    fn should_return_a_saved_token() {
+        let repository = InMemoryTokenRepository::new();
+        let access_token = new_access_token();
+
+        repository.save_token(&access_token);
+
+        assert_some_eq_x!(repository.get_token(access_token.id), access_token);
+    }
+
+    #[test]
+    fn should_return_none_for_an_unknown_token() {
+        let repository = InMemoryTokenRepository::<AccessToken>::new();
+
+        assert_none!(repository.get_token(uuid::Uuid::new_v4()));
+    }
+
+    #[test]
+    fn should_return_none_for_an_expired_token() {
+        let repository = InMemoryTokenRepository::new();
+        let access_token = AccessToken {
+            issued_at: Utc::now() - TimeDelta::hours(3),
+            expires_at: Utc::now() - TimeDelta::hours(1),
+            ..new_access_token()
+        };
+
+        repository.save_token(&access_token);
+
+        assert_none!(repository.get_token(access_token.id));
+    }
+
+    #[test]
+    fn should_only_return_a_removed_token_once() {
+        let repository = InMemoryTokenRepository::new();
+        let access_token = new_access_token();
+
+        repository.save_token(&access_token);
+
+        assert_some_eq_x!(repository.remove_token(access_token.id), access_token);
+        assert_none!(repository.remove_token(access_token.id));
+        assert_none!(repository.get_token(access_token.id));
+    }
+
+    #[test]
+    fn should_not_return_a_removed_expired_token() {
+        let repository = InMemoryTokenRepository::new();
+        let access_token = AccessToken {
+            issued_at: Utc::now() - TimeDelta::hours(3),
+            expires_at: Utc::now() - TimeDelta::hours(1),
+            ..new_access_token()
+        };
+
+        repository.save_token(&access_token);
+
+        assert_none!(repository.remove_token(access_token.id));
+    }
+
+    #[test]
+    fn should_only_remove_tokens_matching_the_predicate() {
+        let repository = InMemoryTokenRepository::new();
+        let grant_id = uuid::Uuid::new_v4();
+        let first = AccessToken { grant_id: Some(grant_id), ..new_access_token() };
+        let second = AccessToken { grant_id: Some(grant_id), ..new_access_token() };
+        let unrelated = new_access_token();
+
+        repository.save_token(&first);
+        repository.save_token(&second);
+        repository.save_token(&unrelated);
+
+        repository.remove_tokens_where(|token| token.grant_id == Some(grant_id));
+
+        assert_none!(repository.get_token(first.id));
+        assert_none!(repository.get_token(second.id));
+        assert_some_eq_x!(repository.get_token(unrelated.id), unrelated);
+    }
+
+    #[test]
+    fn should_find_a_token_matching_the_predicate() {
+        let repository = InMemoryTokenRepository::new();
+        let access_token = new_access_token();
+        let grant_id = uuid::Uuid::new_v4();
+        let matching = AccessToken { grant_id: Some(grant_id), ..new_access_token() };
+
+        repository.save_token(&access_token);
+        repository.save_token(&matching);
+
+        assert_some_eq_x!(repository.find_token_where(|token| token.grant_id == Some(grant_id)), matching);
+        assert_none!(repository.find_token_where(|token| token.grant_id == Some(uuid::Uuid::new_v4())));
+    }
+
+    #[test]
+    fn should_not_find_an_expired_token() {
+        let repository = InMemoryTokenRepository::new();
+        let access_token = AccessToken {
+            issued_at: Utc::now() - TimeDelta::hours(3),
+            expires_at: Utc::now() - TimeDelta::hours(1),
+            ..new_access_token()
+        };
+
+        repository.save_token(&access_token);
+
+        assert_none!(repository.find_token_where(|token| token.id == access_token.id));
+    }
+
+    #[test]
+    fn should_update_a_saved_token_in_place() {
+        let repository = InMemoryTokenRepository::new();
+        let access_token = new_access_token();
+        let grant_id = uuid::Uuid::new_v4();
+
+        repository.save_token(&access_token);
+
+        assert_some_eq_x!(repository.update_token(access_token.id, |token| token.grant_id = Some(grant_id)), ());
+        assert_eq!(assert_some!(repository.get_token(access_token.id)).grant_id, Some(grant_id));
+    }
+
+    #[test]
+    fn should_not_update_an_expired_token() {
+        let repository = InMemoryTokenRepository::new();
+        let access_token = AccessToken {
+            issued_at: Utc::now() - TimeDelta::hours(3),
+            expires_at: Utc::now() - TimeDelta::hours(1),
+            ..new_access_token()
+        };
+
+        repository.save_token(&access_token);
+
+        assert_none!(repository.update_token(access_token.id, |token| token.grant_id = Some(uuid::Uuid::new_v4())));
+        assert_none!(repository.update_token(uuid::Uuid::new_v4(), |_| ()));
+    }
 }
@@ -0,0 +1,69 @@
+// Consonants only, so codes can't spell words and are hard to mistype, as suggested by the specification.
+// https://www.rfc-editor.org/rfc/rfc8628#section-6.1
+const ALPHABET: &[u8; 20] = b"BCDFGHJKLMNPQRSTVWXZ";
+const LENGTH: usize = 8;
+
+// Eight characters from twenty gives over 34 bits of entropy, displayed as two groups such as BDWP-HQPK.
+pub fn generate() -> Result<String, getrandom::Error> {
+    // 64 bits from the operating system's CSPRNG, far more than needed for the digits to be evenly spread.
+    let mut bytes = [0u8; 8];
+    getrandom::fill(&mut bytes)?;
+    let mut random = u64::from_le_bytes(bytes);
+    let mut code = String::with_capacity(LENGTH + 1);
+    for index in 0..LENGTH {
+        if index == LENGTH / 2 {
+            code.push('-');
+        }
+        code.push(ALPHABET[(random % ALPHABET.len() as u64) as usize] as char);
+        random /= ALPHABET.len() as u64;
+    }
+    Ok(code)
+}
+
+// Users may type in lowercase, and with or without the dash or spaces, so match however it was entered.
+pub fn normalise(input: &str) -> String {
+    let characters: Vec<char> = input.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|character| character.to_ascii_uppercase())
+        .collect();
+
+    if characters.len() != LENGTH {
+        return characters.into_iter().collect()
+    }
+
+    let (first, second) = characters.split_at(LENGTH / 2);
+    format!("{}-{}", first.iter().collect::<String>(), second.iter().collect::<String>())
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+
+    #[test]
+    fn should_generate_two_groups_of_consonants() {
+        let code = assert_ok!(generate());
+
+        assert_eq!(code.len(), 9);
+        assert_eq!(&code[4..5], "-");
+        assert!(code.chars().filter(|character| *character != '-').all(|character| ALPHABET.contains(&(character as u8))));
+    }
+
+    #[test]
+    fn should_generate_different_codes() {
+        assert_ne!(assert_ok!(generate()), assert_ok!(generate()));
+    }
+
+    #[test]
+    fn should_normalise_however_the_code_was_entered() {
+        assert_eq!(normalise("BDWP-HQPK"), "BDWP-HQPK");
+        assert_eq!(normalise("bdwphqpk"), "BDWP-HQPK");
+        assert_eq!(normalise(" bdwp hqpk "), "BDWP-HQPK");
+    }
+
+    #[test]
+    fn should_not_invent_a_dash_for_the_wrong_length() {
+        assert_eq!(normalise("bdw-phq"), "BDWPHQ");
+    }
+}
@@ -1,9 +1,291 @@
+use std::collections::HashMap;
 use serde::Deserialize;
+use uuid::Uuid;
+use crate::client::authentication::ClientAuthenticator;
+use crate::key::KeyStore;
+use crate::replay::ReplayCache;
+use crate::openid::Authentication;
+use crate::client::{ClientAction, ClientPrincipal, GrantType};
+use crate::pkce::is_valid_code_verifier;
+use crate::token::{AccessToken, AuthorizationCode, Confirmation, DeviceCode, RefreshToken};
+use crate::token::repository::TokenRepository;
+use crate::token_exchange::response::{ErrorType, TokenExchangeResponse};
+use crate::token_exchange::route::{refresh_token_confirmation, restrict_scopes, TokenExchangeState};
+use crate::scope::repository::ScopeRepository;
+use crate::resource::ProtectedResourceRepository;
+use crate::trusted_issuer::TrustedIssuerRepository;
+use crate::user::authentication::UserAuthenticator;
 
 #[derive(Deserialize, Eq, PartialEq)]
 #[cfg_attr(test, derive(Debug))]
 pub struct AuthorizationCodeGrantRequest {
+    pub principal: ClientPrincipal,
     pub code: String,
     pub redirect_uri: String,
     pub code_verifier: Option<String>,
+    pub resource: Option<String>,
+}
+
+pub async fn handle_authorization_code_grant<A, R, Z, D, C, U, K, J, I, P, Q>(
+    state: TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>,
+    request: AuthorizationCodeGrantRequest,
+    confirmation: Option<Confirmation>,
+) -> TokenExchangeResponse
+where
+    A: TokenRepository<AccessToken>,
+    R: TokenRepository<RefreshToken>,
+    Z: TokenRepository<AuthorizationCode>,
+    D: TokenRepository<DeviceCode>,
+    C: ClientAuthenticator,
+    U: UserAuthenticator,
+    K: KeyStore,
+    J: ReplayCache,
+    I: TrustedIssuerRepository,
+    P: ProtectedResourceRepository,
+    Q: ScopeRepository,
+{
+
+    let invalid_grant = || TokenExchangeResponse::Failure {
+        error: ErrorType::InvalidGrant,
+        error_description: Some("invalid authorization code".into()),
+    };
+
+    let authorization_code = match Uuid::parse_str(&request.code).ok().and_then(|id| state.authorization_code_repository.get_token(id)) {
+        Some(code) if &code.client_id == request.principal.id() && code.redirect_uri == request.redirect_uri => code,
+        _ => return invalid_grant(),
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc7636#section-4.6
+    let verified = match (&authorization_code.code_challenge, &request.code_verifier) {
+        (Some(code_challenge), Some(code_verifier)) => code_challenge.verify(code_verifier),
+        (None, None) => !request.principal.can_perform_action(&ClientAction::ProofKeyForCodeExchange),
+        _ => false,
+    };
+
+    if !verified {
+        return TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidGrant,
+            error_description: Some("invalid code_verifier".into()),
+        }
+    }
+
+    // When the resource owner authorized a resource, that's the only one the client can have a token for.
+    // https://www.rfc-editor.org/rfc/rfc8707#section-2.2
+    let resource = match (request.resource, &authorization_code.resource) {
+        (Some(requested), Some(authorized)) if &requested != authorized => return TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidTarget,
+            error_description: Some("invalid parameter: resource".into()),
+        },
+        (requested, authorized) => requested.or(authorized.clone()),
+    };
+
+    let resource = match state.find_resource(request.principal.id(), resource.as_deref()) {
+        Err(failure) => return failure,
+        Ok(resource) => resource,
+    };
+
+    let access_token_scopes = match restrict_scopes(resource.as_ref(), authorization_code.scopes.clone()) {
+        Err(failure) => return failure,
+        Ok(scopes) => scopes,
+    };
+
+    // Authorization codes are single use, only the first caller gets to remove it.
+    let authorization_code = match state.authorization_code_repository.remove_token(authorization_code.id) {
+        None => return invalid_grant(),
+        Some(authorization_code) => authorization_code,
+    };
+
+    let refresh_token = if request.principal.can_perform_grant_type(&GrantType::RefreshToken) {
+        let refresh_token = RefreshToken {
+            resource: resource.as_ref().map(|resource| resource.uri.clone()),
+            confirmation: refresh_token_confirmation(&request.principal, confirmation.as_ref()),
+            ..RefreshToken::new(
+                authorization_code.client_id.clone(),
+                authorization_code.username.clone(),
+                authorization_code.scopes.clone(),
+                authorization_code.auth_time,
+            )
+        };
+        state.refresh_token_repository.save_token(&refresh_token);
+        Some(refresh_token)
+    } else {
+        None
+    };
+
+    let authentication = Authentication {
+        username: authorization_code.username.clone(),
+        auth_time: authorization_code.auth_time,
+        nonce: authorization_code.nonce,
+    };
+
+    let access_token = AccessToken {
+        audience: resource.map(|resource| resource.uri),
+        confirmation,
+        ..AccessToken::new(
+            authorization_code.client_id,
+            Some(authorization_code.username),
+            access_token_scopes,
+            refresh_token.as_ref().map(|refresh_token| refresh_token.grant_id),
+        )
+    };
+
+    state.issue_tokens(request.principal.access_token_format(), access_token, refresh_token, Some(authentication), authorization_code.state)
+}
+
+pub fn validate_authorization_code_grant(principal: ClientPrincipal, request: HashMap<String, String>) -> Result<AuthorizationCodeGrantRequest, TokenExchangeResponse> {
+
+    if !principal.can_perform_grant_type(&GrantType::AuthorizationCode) {
+        Err(TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some(format!("not authorized to: {:?}", GrantType::AuthorizationCode)),
+        })?
+    }
+
+    let code = match request.get("code") {
+        None => Err(TokenExchangeResponse::missing_parameter("code"))?,
+        Some(code) if code.trim().is_empty() => Err(TokenExchangeResponse::invalid_parameter("code"))?,
+        Some(code) => code,
+    };
+
+    let redirect_uri = match request.get("redirect_uri") {
+        None => Err(TokenExchangeResponse::missing_parameter("redirect_uri"))?,
+        Some(redirect_uri) if redirect_uri.trim().is_empty() => Err(TokenExchangeResponse::invalid_parameter("redirect_uri"))?,
+        Some(redirect_uri) => redirect_uri,
+    };
+
+    let code_verifier = match request.get("code_verifier") {
+        Some(code_verifier) if !is_valid_code_verifier(code_verifier) => Err(TokenExchangeResponse::invalid_parameter("code_verifier"))?,
+        maybe_code_verifier => maybe_code_verifier.cloned(),
+    };
+
+    Ok(AuthorizationCodeGrantRequest {
+        principal,
+        code: code.into(),
+        redirect_uri: redirect_uri.into(),
+        code_verifier,
+        resource: request.get("resource").cloned(),
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    // See: https://github.com/beercanx/oauth-api/blob/main/api/token/src/test/kotlin/uk/co/baconi/oauth/api/token/AuthorisationCodeValidationTest.kt
+
+    use super::*;
+    use assertables::*;
+    use crate::client::ClientType;
+    use crate::client::configuration::ClientConfiguration;
+    use crate::map_of;
+
+    #[test]
+    fn should_return_unauthorized_client_for_an_unauthorised_client() {
+        let result = validate_authorization_code_grant(
+            ClientPrincipal::new_principal(ClientConfiguration {
+                client_id: String::from("unauthorised").into(),
+                client_type: ClientType::Confidential,
+                redirect_uris: Default::default(),
+                allowed_scopes: Default::default(),
+                default_scopes: Default::default(),
+                allowed_actions: Default::default(),
+                allowed_grant_types: Default::default(),
+                allowed_audiences: Default::default(),
+                jwks: Default::default(),
+                access_token_format: Default::default(),
+            }),
+            map_of! {
+                "code" => "aardvark",
+                "redirect_uri" => "https://redirect.baconi.co.uk",
+            },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some("not authorized to: AuthorizationCode".into())
+        });
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_missing_code() {
+        let result = validate_authorization_code_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            map_of! {
+                "redirect_uri" => "https://redirect.baconi.co.uk",
+            },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::missing_parameter("code"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_blank_code() {
+        let result = validate_authorization_code_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            map_of! {
+                "code" => " ",
+                "redirect_uri" => "https://redirect.baconi.co.uk",
+            },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::invalid_parameter("code"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_missing_redirect_uri() {
+        let result = validate_authorization_code_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            map_of! {
+                "code" => "aardvark",
+            },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::missing_parameter("redirect_uri"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_malformed_code_verifier() {
+        let result = validate_authorization_code_grant(
+            ClientPrincipal::new_public_principal("badger"),
+            map_of! {
+                "code" => "aardvark",
+                "redirect_uri" => "https://redirect.baconi.co.uk",
+                "code_verifier" => "too-short",
+            },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::invalid_parameter("code_verifier"));
+    }
+
+    #[test]
+    fn should_return_valid_request_with_code_verifier() {
+        let result = validate_authorization_code_grant(
+            ClientPrincipal::new_public_principal("badger"),
+            map_of! {
+                "code" => "aardvark",
+                "redirect_uri" => "https://redirect.baconi.co.uk",
+                "code_verifier" => "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk",
+            },
+        );
+
+        assert_eq!(assert_ok!(result).code_verifier, Some("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".into()));
+    }
+
+    #[test]
+    fn should_return_valid_request() {
+        let result = validate_authorization_code_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            map_of! {
+                "code" => "aardvark",
+                "redirect_uri" => "https://redirect.baconi.co.uk",
+            },
+        );
+
+        assert_eq!(assert_ok!(result), AuthorizationCodeGrantRequest {
+            principal: ClientPrincipal::new_confidential_principal("aardvark"),
+            code: "aardvark".into(),
+            redirect_uri: "https://redirect.baconi.co.uk".into(),
+            code_verifier: None,
+            resource: None,
+        });
+    }
 }
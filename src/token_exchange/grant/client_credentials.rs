@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+use ClientPrincipal::Confidential;
+use GrantType::ClientCredentials;
+use crate::client::authentication::ClientAuthenticator;
+use crate::key::KeyStore;
+use crate::replay::ReplayCache;
+use crate::client::{ClientPrincipal, ConfidentialClient, GrantType};
+use crate::token::{AccessToken, AuthorizationCode, Confirmation, DeviceCode, RefreshToken};
+use crate::token::repository::TokenRepository;
+use crate::token_exchange::response::{ErrorType, TokenExchangeResponse};
+use crate::token_exchange::route::{restrict_scopes, TokenExchangeState};
+use crate::resource::ProtectedResourceRepository;
+use crate::trusted_issuer::TrustedIssuerRepository;
+use crate::scope::Scopes;
+use crate::scope::parser::parse_scopes;
+use crate::scope::repository::ScopeRepository;
+use crate::user::authentication::UserAuthenticator;
+
+#[derive(Deserialize, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct ClientCredentialsGrantRequest {
+    pub principal: ConfidentialClient,
+    pub scopes: Scopes,
+    pub resource: Option<String>,
+}
+
+pub async fn handle_client_credentials_grant<A, R, Z, D, C, U, K, J, I, P, Q>(
+    state: TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>,
+    request: ClientCredentialsGrantRequest,
+    confirmation: Option<Confirmation>,
+) -> TokenExchangeResponse
+where
+    A: TokenRepository<AccessToken>,
+    R: TokenRepository<RefreshToken>,
+    Z: TokenRepository<AuthorizationCode>,
+    D: TokenRepository<DeviceCode>,
+    C: ClientAuthenticator,
+    U: UserAuthenticator,
+    K: KeyStore,
+    J: ReplayCache,
+    I: TrustedIssuerRepository,
+    P: ProtectedResourceRepository,
+    Q: ScopeRepository,
+{
+    let resource = match state.find_resource(request.principal.id(), request.resource.as_deref()) {
+        Err(failure) => return failure,
+        Ok(resource) => resource,
+    };
+
+    let scopes = match restrict_scopes(resource.as_ref(), request.scopes) {
+        Err(failure) => return failure,
+        Ok(scopes) => scopes,
+    };
+
+    // There is no resource owner, so the client is acting on its own behalf.
+    let access_token = AccessToken {
+        audience: resource.map(|resource| resource.uri),
+        confirmation,
+        ..AccessToken::new(
+            request.principal.id().clone(),
+            None,
+            scopes,
+            None,
+        )
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc6749#section-4.4.3 - A refresh token SHOULD NOT be included.
+    state.issue_tokens(request.principal.access_token_format(), access_token, None, None, None)
+}
+
+pub fn validate_client_credentials_grant<Q: ScopeRepository>(principal: ClientPrincipal, scope_repository: &Q, request: HashMap<String, String>) -> Result<ClientCredentialsGrantRequest, TokenExchangeResponse> {
+    let client = match principal {
+        Confidential(client) if client.can_perform_grant_type(&ClientCredentials) => client,
+        _ => Err(TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some(format!("not authorized to: {:?}", ClientCredentials)),
+        })?,
+    };
+
+    // Only issue the requested scopes the client is allowed, rather than rejecting the request outright.
+    let scopes = match parse_scopes(scope_repository, Some(client.id()), request.get("scope")) {
+        Err(_) => Err(TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        })?,
+        Ok(None) => client.default_scopes(),
+        Ok(Some(Scopes(scopes))) => {
+            let allowed = scopes.into_iter()
+                .filter(|scope| client.can_be_issued(scope))
+                .collect::<std::collections::HashSet<_>>();
+
+            if allowed.is_empty() {
+                Err(TokenExchangeResponse::Failure {
+                    error: ErrorType::InvalidScope,
+                    error_description: Some("invalid parameter: scope".into()),
+                })?
+            }
+
+            Scopes(allowed)
+        },
+    };
+
+    Ok(ClientCredentialsGrantRequest {
+        principal: client,
+        scopes,
+        resource: request.get("resource").cloned(),
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use crate::scope::repository::InMemoryScopeRepository;
+    use assertables::*;
+    use std::collections::HashSet;
+    use crate::client::ClientType;
+    use crate::client::configuration::ClientConfiguration;
+    use crate::scope::Scope;
+    use crate::map_of;
+
+    mod client {
+        use super::*;
+
+        #[test]
+        fn should_return_unauthorized_client_for_a_public_client() {
+            let result = validate_client_credentials_grant(
+                ClientPrincipal::new_public_principal("badger"),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "scope" => "basic",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+                error: ErrorType::UnauthorizedClient,
+                error_description: Some("not authorized to: ClientCredentials".into())
+            });
+        }
+
+        #[test]
+        fn should_return_unauthorized_client_for_an_unauthorised_client() {
+            let result = validate_client_credentials_grant(
+                ClientPrincipal::new_principal(ClientConfiguration {
+                    client_id: String::from("unauthorised").into(),
+                    client_type: ClientType::Confidential,
+                    redirect_uris: Default::default(),
+                    allowed_scopes: Default::default(),
+                    default_scopes: Default::default(),
+                    allowed_actions: Default::default(),
+                    allowed_grant_types: Default::default(),
+                    allowed_audiences: Default::default(),
+                    jwks: Default::default(),
+                    access_token_format: Default::default(),
+                }),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "scope" => "basic",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+                error: ErrorType::UnauthorizedClient,
+                error_description: Some("not authorized to: ClientCredentials".into())
+            });
+        }
+    }
+
+    mod scope {
+        use super::*;
+
+        #[test]
+        fn should_return_invalid_scope_on_blank_scope() {
+            let result = validate_client_credentials_grant(
+                ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "scope" => " ",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+                error: ErrorType::InvalidScope,
+                error_description: Some("invalid parameter: scope".into()),
+            });
+        }
+
+        #[test]
+        fn should_return_invalid_scope_with_an_invalid_scope() {
+            let result = validate_client_credentials_grant(
+                ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "scope" => "basic cicada",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+                error: ErrorType::InvalidScope,
+                error_description: Some("invalid parameter: scope".into()),
+            });
+        }
+
+        #[test]
+        fn should_return_invalid_scope_when_none_of_the_requested_scopes_are_allowed() {
+            let result = validate_client_credentials_grant(
+                ClientPrincipal::new_principal(ClientConfiguration {
+                    client_id: String::from("aardvark").into(),
+                    client_type: ClientType::Confidential,
+                    redirect_uris: Default::default(),
+                    allowed_scopes: HashSet::from([Scope::from("read")]),
+                    default_scopes: Default::default(),
+                    allowed_actions: Default::default(),
+                    allowed_grant_types: HashSet::from([ClientCredentials]),
+                    allowed_audiences: Default::default(),
+                    jwks: Default::default(),
+                    access_token_format: Default::default(),
+                }),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "scope" => "write",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+                error: ErrorType::InvalidScope,
+                error_description: Some("invalid parameter: scope".into()),
+            });
+        }
+    }
+
+    mod valid {
+        use super::*;
+
+        #[test]
+        fn should_return_valid_request_with_the_default_scopes_if_scope_is_not_provided() {
+            let result = validate_client_credentials_grant(
+                ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
+                map_of! {},
+            );
+
+            assert_eq!(assert_ok!(result), ClientCredentialsGrantRequest {
+                principal: ClientPrincipal::new_confidential_client("aardvark"),
+                scopes: Scopes(HashSet::from([Scope::from("basic")])),
+                resource: None,
+            });
+        }
+
+        #[test]
+        fn should_return_valid_request_limited_to_the_allowed_scopes() {
+            let result = validate_client_credentials_grant(
+                ClientPrincipal::new_principal(ClientConfiguration {
+                    client_id: String::from("aardvark").into(),
+                    client_type: ClientType::Confidential,
+                    redirect_uris: Default::default(),
+                    allowed_scopes: HashSet::from([Scope::from("basic"), Scope::from("read")]),
+                    default_scopes: Default::default(),
+                    allowed_actions: Default::default(),
+                    allowed_grant_types: HashSet::from([ClientCredentials]),
+                    allowed_audiences: Default::default(),
+                    jwks: Default::default(),
+                    access_token_format: Default::default(),
+                }),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "scope" => "basic write",
+                },
+            );
+
+            assert_eq!(assert_ok!(result).scopes, Scopes(HashSet::from([Scope::from("basic")])));
+        }
+    }
+}
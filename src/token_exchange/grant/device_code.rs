@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use chrono::{TimeDelta, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+use crate::client::authentication::ClientAuthenticator;
+use crate::key::KeyStore;
+use crate::replay::ReplayCache;
+use crate::openid::Authentication;
+use crate::client::{ClientPrincipal, GrantType};
+use crate::token::{AccessToken, AuthorizationCode, Confirmation, DeviceCode, DeviceCodeStatus, RefreshToken};
+use crate::token::repository::TokenRepository;
+use crate::token_exchange::response::{ErrorType, TokenExchangeResponse};
+use crate::token_exchange::route::{refresh_token_confirmation, restrict_scopes, TokenExchangeState};
+use crate::scope::repository::ScopeRepository;
+use crate::resource::ProtectedResourceRepository;
+use crate::trusted_issuer::TrustedIssuerRepository;
+use crate::user::authentication::UserAuthenticator;
+
+// https://www.rfc-editor.org/rfc/rfc8628#section-3.5
+const SLOW_DOWN_INCREMENT: i64 = 5;
+
+#[derive(Deserialize, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct DeviceCodeGrantRequest {
+    pub principal: ClientPrincipal,
+    pub device_code: String,
+    pub resource: Option<String>,
+}
+
+// https://www.rfc-editor.org/rfc/rfc8628#section-3.4
+pub async fn handle_device_code_grant<A, R, Z, D, C, U, K, J, I, P, Q>(
+    state: TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>,
+    request: DeviceCodeGrantRequest,
+    confirmation: Option<Confirmation>,
+) -> TokenExchangeResponse
+where
+    A: TokenRepository<AccessToken>,
+    R: TokenRepository<RefreshToken>,
+    Z: TokenRepository<AuthorizationCode>,
+    D: TokenRepository<DeviceCode>,
+    C: ClientAuthenticator,
+    U: UserAuthenticator,
+    K: KeyStore,
+    J: ReplayCache,
+    I: TrustedIssuerRepository,
+    P: ProtectedResourceRepository,
+    Q: ScopeRepository,
+{
+
+    let failure = |error: ErrorType, description: &str| TokenExchangeResponse::Failure {
+        error,
+        error_description: Some(description.into()),
+    };
+
+    let device_code = match Uuid::parse_str(&request.device_code).ok().and_then(|id| state.device_code_repository.get_token(id)) {
+        Some(device_code) if &device_code.client_id == request.principal.id() => device_code,
+        _ => return failure(ErrorType::InvalidGrant, "invalid device code"),
+    };
+
+    let resource = match state.find_resource(request.principal.id(), request.resource.as_deref()) {
+        Err(failure) => return failure,
+        Ok(resource) => resource,
+    };
+
+    let access_token_scopes = match restrict_scopes(resource.as_ref(), device_code.scopes.clone()) {
+        Err(failure) => return failure,
+        Ok(scopes) => scopes,
+    };
+
+    if !device_code.is_usable() {
+        state.device_code_repository.remove_token(device_code.id);
+        return failure(ErrorType::ExpiredToken, "device code has expired");
+    }
+
+    let (username, auth_time) = match device_code.status.clone() {
+
+        DeviceCodeStatus::Pending => {
+            // Only the polling fields are touched, the user may be approving the code at the same moment.
+            let too_soon = state.device_code_repository.update_token(device_code.id, |device_code| {
+                let now = Utc::now();
+                let too_soon = device_code.polled_at
+                    .is_some_and(|polled_at| now < polled_at + TimeDelta::seconds(device_code.interval));
+
+                if too_soon {
+                    device_code.interval += SLOW_DOWN_INCREMENT;
+                }
+                device_code.polled_at = Some(now);
+                too_soon
+            });
+
+            return if too_soon == Some(true) {
+                failure(ErrorType::SlowDown, "polling too frequently")
+            } else {
+                failure(ErrorType::AuthorizationPending, "authorization pending")
+            }
+        },
+
+        DeviceCodeStatus::Denied => {
+            state.device_code_repository.remove_token(device_code.id);
+            return failure(ErrorType::AccessDenied, "authorization denied");
+        },
+
+        DeviceCodeStatus::Approved { username, auth_time } => (username, auth_time),
+    };
+
+    // Device codes are single use, only the first caller gets to remove it.
+    let device_code = match state.device_code_repository.remove_token(device_code.id) {
+        None => return failure(ErrorType::InvalidGrant, "invalid device code"),
+        Some(device_code) => device_code,
+    };
+
+    let refresh_token = if request.principal.can_perform_grant_type(&GrantType::RefreshToken) {
+        let refresh_token = RefreshToken {
+            resource: resource.as_ref().map(|resource| resource.uri.clone()),
+            confirmation: refresh_token_confirmation(&request.principal, confirmation.as_ref()),
+            ..RefreshToken::new(
+                device_code.client_id.clone(),
+                username.clone(),
+                device_code.scopes.clone(),
+                auth_time,
+            )
+        };
+        state.refresh_token_repository.save_token(&refresh_token);
+        Some(refresh_token)
+    } else {
+        None
+    };
+
+    let access_token = AccessToken {
+        audience: resource.map(|resource| resource.uri),
+        confirmation,
+        ..AccessToken::new(
+            device_code.client_id,
+            Some(username.clone()),
+            access_token_scopes,
+            refresh_token.as_ref().map(|refresh_token| refresh_token.grant_id),
+        )
+    };
+
+    let authentication = Authentication {
+        username,
+        auth_time,
+        nonce: None,
+    };
+
+    state.issue_tokens(request.principal.access_token_format(), access_token, refresh_token, Some(authentication), None)
+}
+
+pub fn validate_device_code_grant(principal: ClientPrincipal, request: HashMap<String, String>) -> Result<DeviceCodeGrantRequest, TokenExchangeResponse> {
+
+    if !principal.can_perform_grant_type(&GrantType::DeviceCode) {
+        Err(TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some(format!("not authorized to: {:?}", GrantType::DeviceCode)),
+        })?
+    }
+
+    let device_code = match request.get("device_code") {
+        None => Err(TokenExchangeResponse::missing_parameter("device_code"))?,
+        Some(device_code) if device_code.trim().is_empty() => Err(TokenExchangeResponse::invalid_parameter("device_code"))?,
+        Some(device_code) => device_code,
+    };
+
+    Ok(DeviceCodeGrantRequest {
+        principal,
+        device_code: device_code.into(),
+        resource: request.get("resource").cloned(),
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use crate::client::ClientType;
+    use crate::client::configuration::ClientConfiguration;
+    use crate::map_of;
+
+    #[test]
+    fn should_return_unauthorized_client_for_an_unauthorised_client() {
+        let result = validate_device_code_grant(
+            ClientPrincipal::new_principal(ClientConfiguration {
+                client_id: String::from("unauthorised").into(),
+                client_type: ClientType::Confidential,
+                redirect_uris: Default::default(),
+                allowed_scopes: Default::default(),
+                default_scopes: Default::default(),
+                allowed_actions: Default::default(),
+                allowed_grant_types: Default::default(),
+                allowed_audiences: Default::default(),
+                jwks: Default::default(),
+                access_token_format: Default::default(),
+            }),
+            map_of!("device_code" => "aardvark"),
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some("not authorized to: DeviceCode".into())
+        });
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_missing_device_code() {
+        let result = validate_device_code_grant(ClientPrincipal::new_confidential_principal("aardvark"), map_of!());
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::missing_parameter("device_code"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_blank_device_code() {
+        let result = validate_device_code_grant(ClientPrincipal::new_confidential_principal("aardvark"), map_of!("device_code" => " "));
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::invalid_parameter("device_code"));
+    }
+
+    #[test]
+    fn should_return_valid_request_with_the_device_code() {
+        let result = validate_device_code_grant(ClientPrincipal::new_confidential_principal("aardvark"), map_of!("device_code" => "aardvark"));
+
+        assert_eq!(assert_ok!(result), DeviceCodeGrantRequest {
+            principal: ClientPrincipal::new_confidential_principal("aardvark"),
+            device_code: "aardvark".into(),
+            resource: None,
+        });
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use chrono::Utc;
+use serde::Deserialize;
+use crate::assertion::{unverified_claims, verify_assertion};
+use crate::client::authentication::ClientAuthenticator;
+use crate::client::{ClientPrincipal, GrantType};
+use crate::key::KeyStore;
+use crate::openid::Authentication;
+use crate::replay::ReplayCache;
+use crate::scope::Scopes;
+use crate::scope::parser::parse_scopes;
+use crate::scope::repository::ScopeRepository;
+use crate::token::{AccessToken, AuthorizationCode, Confirmation, DeviceCode, RefreshToken};
+use crate::token::repository::TokenRepository;
+use crate::token_exchange::response::{ErrorType, TokenExchangeResponse};
+use crate::token_exchange::route::{refresh_token_confirmation, restrict_scopes, TokenExchangeState, TOKEN_ENDPOINT};
+use crate::resource::ProtectedResourceRepository;
+use crate::trusted_issuer::TrustedIssuerRepository;
+use crate::user::authentication::UserAuthenticator;
+
+#[derive(Deserialize, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct JwtBearerGrantRequest {
+    pub principal: ClientPrincipal,
+    pub assertion: String,
+    pub scopes: Option<Scopes>,
+    pub resource: Option<String>,
+}
+
+// https://www.rfc-editor.org/rfc/rfc7523#section-2.1
+pub async fn handle_jwt_bearer_grant<A, R, Z, D, C, U, K, J, I, P, Q>(
+    state: TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>,
+    request: JwtBearerGrantRequest,
+    confirmation: Option<Confirmation>,
+) -> TokenExchangeResponse
+where
+    A: TokenRepository<AccessToken>,
+    R: TokenRepository<RefreshToken>,
+    Z: TokenRepository<AuthorizationCode>,
+    D: TokenRepository<DeviceCode>,
+    C: ClientAuthenticator,
+    U: UserAuthenticator,
+    K: KeyStore,
+    J: ReplayCache,
+    I: TrustedIssuerRepository,
+    P: ProtectedResourceRepository,
+    Q: ScopeRepository,
+{
+
+    let failure = |error: ErrorType, description: &str| TokenExchangeResponse::Failure {
+        error,
+        error_description: Some(description.into()),
+    };
+
+    let resource = match state.find_resource(request.principal.id(), request.resource.as_deref()) {
+        Err(failure) => return failure,
+        Ok(resource) => resource,
+    };
+
+    let trusted_issuer = match unverified_claims(&request.assertion).and_then(|claims| state.trusted_issuer_repository.find_by_issuer(&claims.iss)) {
+        None => return failure(ErrorType::InvalidGrant, "untrusted issuer"),
+        Some(trusted_issuer) => trusted_issuer,
+    };
+
+    let jwks = match trusted_issuer.jwks.load() {
+        None => return failure(ErrorType::ServerError, "unable to load issuer keys"),
+        Some(jwks) => jwks,
+    };
+
+    let audiences = [state.issuer.clone(), format!("{}{TOKEN_ENDPOINT}", state.issuer)];
+
+    let claims = match verify_assertion(&jwks, &request.assertion, &trusted_issuer.issuer, None, &audiences) {
+        None => return failure(ErrorType::InvalidGrant, "invalid assertion"),
+        Some(claims) => claims,
+    };
+
+    let username = match trusted_issuer.map_subject(&claims.sub) {
+        None => return failure(ErrorType::InvalidGrant, "subject not allowed"),
+        Some(username) => username.clone(),
+    };
+
+    // An assertion can only ever be used once, it's remembered until it would have expired anyway.
+    let first_use = claims.expires_at()
+        .is_some_and(|expires_at| state.replay_cache.first_use(&format!("{}:{}", claims.iss, claims.jti), expires_at));
+
+    if !first_use {
+        return failure(ErrorType::InvalidGrant, "assertion has already been used");
+    }
+
+    // Only what the issuer granted that the client is allowed, narrowed to what was requested if anything was.
+    let Scopes(granted) = trusted_issuer.map_scopes(claims.scope.as_deref());
+    let granted = granted.into_iter()
+        .filter(|scope| request.principal.can_be_issued(scope))
+        .collect();
+
+    let scopes = match request.scopes {
+        None => Scopes(granted),
+        Some(Scopes(requested)) if requested.is_subset(&granted) => Scopes(requested),
+        Some(_) => return failure(ErrorType::InvalidScope, "invalid parameter: scope"),
+    };
+
+    let access_token_scopes = match restrict_scopes(resource.as_ref(), scopes.clone()) {
+        Err(failure) => return failure,
+        Ok(scopes) => scopes,
+    };
+
+    let authentication = Authentication {
+        username: username.clone(),
+        auth_time: Utc::now(),
+        nonce: None,
+    };
+
+    let refresh_token = if request.principal.can_perform_grant_type(&GrantType::RefreshToken) {
+        let refresh_token = RefreshToken {
+            resource: resource.as_ref().map(|resource| resource.uri.clone()),
+            confirmation: refresh_token_confirmation(&request.principal, confirmation.as_ref()),
+            ..RefreshToken::new(request.principal.id().clone(), username.clone(), scopes, authentication.auth_time)
+        };
+        state.refresh_token_repository.save_token(&refresh_token);
+        Some(refresh_token)
+    } else {
+        None
+    };
+
+    let access_token = AccessToken {
+        audience: resource.map(|resource| resource.uri),
+        confirmation,
+        ..AccessToken::new(
+            request.principal.id().clone(),
+            Some(username),
+            access_token_scopes,
+            refresh_token.as_ref().map(|refresh_token| refresh_token.grant_id),
+        )
+    };
+
+    state.issue_tokens(request.principal.access_token_format(), access_token, refresh_token, Some(authentication), None)
+}
+
+pub fn validate_jwt_bearer_grant<Q: ScopeRepository>(principal: ClientPrincipal, scope_repository: &Q, request: HashMap<String, String>) -> Result<JwtBearerGrantRequest, TokenExchangeResponse> {
+
+    if !principal.can_perform_grant_type(&GrantType::JwtBearer) {
+        Err(TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some(format!("not authorized to: {:?}", GrantType::JwtBearer)),
+        })?
+    }
+
+    let assertion = match request.get("assertion") {
+        None => Err(TokenExchangeResponse::missing_parameter("assertion"))?,
+        Some(assertion) if assertion.trim().is_empty() => Err(TokenExchangeResponse::invalid_parameter("assertion"))?,
+        Some(assertion) => assertion,
+    };
+
+    let scopes = match parse_scopes(scope_repository, Some(principal.id()), request.get("scope")) {
+        Err(_) => Err(TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        })?,
+        Ok(scopes) => scopes,
+    };
+
+    Ok(JwtBearerGrantRequest {
+        principal,
+        assertion: assertion.into(),
+        scopes,
+        resource: request.get("resource").cloned(),
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use crate::scope::repository::InMemoryScopeRepository;
+    use assertables::*;
+    use std::collections::HashSet;
+    use crate::client::ClientType;
+    use crate::client::configuration::ClientConfiguration;
+    use crate::map_of;
+    use crate::scope::Scope;
+
+    #[test]
+    fn should_return_unauthorized_client_for_an_unauthorised_client() {
+        let result = validate_jwt_bearer_grant(
+            ClientPrincipal::new_principal(ClientConfiguration {
+                client_id: String::from("unauthorised").into(),
+                client_type: ClientType::Confidential,
+                redirect_uris: Default::default(),
+                allowed_scopes: Default::default(),
+                default_scopes: Default::default(),
+                allowed_actions: Default::default(),
+                allowed_grant_types: Default::default(),
+                allowed_audiences: Default::default(),
+                jwks: Default::default(),
+                access_token_format: Default::default(),
+            }),
+            &InMemoryScopeRepository::new(),
+            map_of!("assertion" => "aardvark"),
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some("not authorized to: JwtBearer".into())
+        });
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_missing_assertion() {
+        let result = validate_jwt_bearer_grant(ClientPrincipal::new_confidential_principal("aardvark"), &InMemoryScopeRepository::new(), map_of!());
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::missing_parameter("assertion"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_blank_assertion() {
+        let result = validate_jwt_bearer_grant(ClientPrincipal::new_confidential_principal("aardvark"), &InMemoryScopeRepository::new(), map_of!("assertion" => " "));
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::invalid_parameter("assertion"));
+    }
+
+    #[test]
+    fn should_return_invalid_scope_on_an_unknown_scope() {
+        let result = validate_jwt_bearer_grant(ClientPrincipal::new_confidential_principal("aardvark"), &InMemoryScopeRepository::new(), map_of!("assertion" => "aardvark", "scope" => "cicada"));
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        });
+    }
+
+    #[test]
+    fn should_return_valid_request_with_the_assertion_and_scopes() {
+        let result = validate_jwt_bearer_grant(ClientPrincipal::new_confidential_principal("aardvark"), &InMemoryScopeRepository::new(), map_of!("assertion" => "aardvark", "scope" => "basic"));
+
+        assert_eq!(assert_ok!(result), JwtBearerGrantRequest {
+            principal: ClientPrincipal::new_confidential_principal("aardvark"),
+            assertion: "aardvark".into(),
+            scopes: Some(Scopes(HashSet::from([Scope::from("basic")]))),
+            resource: None,
+        });
+    }
+}
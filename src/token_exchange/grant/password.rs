@@ -2,14 +2,23 @@ use std::collections::HashMap;
 use serde::Deserialize;
 use ClientPrincipal::Confidential;
 use GrantType::Password;
+use GrantType::RefreshToken as RefreshTokenGrant;
 use crate::client::authentication::ClientAuthenticator;
+use chrono::Utc;
+use crate::key::KeyStore;
+use crate::replay::ReplayCache;
+use crate::openid::Authentication;
 use crate::client::{ClientPrincipal, ConfidentialClient, GrantType};
-use crate::token::{AccessToken, TokenType};
+use crate::token::{AccessToken, AuthorizationCode, Confirmation, DeviceCode, RefreshToken};
 use crate::token::repository::TokenRepository;
 use crate::token_exchange::response::{ErrorType, TokenExchangeResponse};
-use crate::token_exchange::route::TokenExchangeState;
+use crate::token_exchange::route::{restrict_scopes, TokenExchangeState};
+use crate::resource::ProtectedResourceRepository;
+use crate::trusted_issuer::TrustedIssuerRepository;
 use crate::scope::Scopes;
 use crate::scope::parser::parse_scopes;
+use crate::scope::repository::ScopeRepository;
+use crate::user::authentication::UserAuthenticator;
 
 #[derive(Deserialize, Eq, PartialEq)]
 #[cfg_attr(test, derive(Debug))]
@@ -17,37 +26,81 @@ pub struct PasswordGrantRequest {
     pub principal: ConfidentialClient,
     pub username: String,
     pub password: String,
-    pub scopes: Option<Scopes>,
+    pub scopes: Scopes,
+    pub resource: Option<String>,
 }
 
-pub async fn handle_password_grant<A, C>(
-    state: TokenExchangeState<A, C>,
-    request: PasswordGrantRequest
+pub async fn handle_password_grant<A, R, Z, D, C, U, K, J, I, P, Q>(
+    state: TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>,
+    request: PasswordGrantRequest,
+    confirmation: Option<Confirmation>,
 ) -> TokenExchangeResponse
 where
     A: TokenRepository<AccessToken>,
+    R: TokenRepository<RefreshToken>,
+    Z: TokenRepository<AuthorizationCode>,
+    D: TokenRepository<DeviceCode>,
     C: ClientAuthenticator,
+    U: UserAuthenticator,
+    K: KeyStore,
+    J: ReplayCache,
+    I: TrustedIssuerRepository,
+    P: ProtectedResourceRepository,
+    Q: ScopeRepository,
 {
 
-    // TODO - Implement it...
+    let user = match state.user_authenticator.authenticate(&request.username, request.password.as_bytes()) {
+        None => return TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidGrant,
+            error_description: Some("invalid resource owner credentials".into()),
+        },
+        Some(user) => user,
+    };
 
-    let access_token = AccessToken {
-        id: uuid::Uuid::new_v4(),
+    let resource = match state.find_resource(request.principal.id(), request.resource.as_deref()) {
+        Err(failure) => return failure,
+        Ok(resource) => resource,
     };
 
-    state.access_token_repository.save_token(&access_token);
+    let scopes = request.scopes;
 
-    TokenExchangeResponse::Success {
-        access_token: access_token.id,
-        token_type: TokenType::Bearer,
-        expires_in: 7200,
-        refresh_token: Some(uuid::Uuid::new_v4()),
-        scope: request.scopes,
-        state: None,
-    }
+    let access_token_scopes = match restrict_scopes(resource.as_ref(), scopes.clone()) {
+        Err(failure) => return failure,
+        Ok(scopes) => scopes,
+    };
+
+    let authentication = Authentication {
+        username: user.username.clone(),
+        auth_time: Utc::now(),
+        nonce: None,
+    };
+
+    let refresh_token = if request.principal.can_perform_grant_type(&RefreshTokenGrant) {
+        let refresh_token = RefreshToken {
+            resource: resource.as_ref().map(|resource| resource.uri.clone()),
+            ..RefreshToken::new(request.principal.id().clone(), user.username.clone(), scopes, authentication.auth_time)
+        };
+        state.refresh_token_repository.save_token(&refresh_token);
+        Some(refresh_token)
+    } else {
+        None
+    };
+
+    let access_token = AccessToken {
+        audience: resource.map(|resource| resource.uri),
+        confirmation,
+        ..AccessToken::new(
+            request.principal.id().clone(),
+            Some(user.username),
+            access_token_scopes,
+            refresh_token.as_ref().map(|refresh_token| refresh_token.grant_id),
+        )
+    };
+
+    state.issue_tokens(request.principal.access_token_format(), access_token, refresh_token, Some(authentication), None)
 }
 
-pub fn validate_password_grant(principal: ClientPrincipal, request: HashMap<String, String>) -> Result<PasswordGrantRequest, TokenExchangeResponse> {
+pub fn validate_password_grant<Q: ScopeRepository>(principal: ClientPrincipal, scope_repository: &Q, request: HashMap<String, String>) -> Result<PasswordGrantRequest, TokenExchangeResponse> {
     let client = match principal {
         Confidential(client) if client.can_perform_grant_type(&Password) => client,
         _ => Err(TokenExchangeResponse::Failure {
@@ -67,7 +120,7 @@ pub fn validate_password_grant(principal: ClientPrincipal, request: HashMap<Stri
         Some(password) => password,
     };
 
-    let maybe_scopes = match parse_scopes(request.get("scope")) {
+    let scopes = match parse_scopes(scope_repository, Some(client.id()), request.get("scope")) {
         Err(_) => Err(TokenExchangeResponse::Failure {
             error: ErrorType::InvalidScope,
             error_description: Some("invalid parameter: scope".into()),
@@ -78,14 +131,16 @@ pub fn validate_password_grant(principal: ClientPrincipal, request: HashMap<Stri
                 error_description: Some("invalid parameter: scope".into()),
             })?
         }
-        Ok(maybe_scopes) => maybe_scopes
+        Ok(None) => client.default_scopes(),
+        Ok(Some(scopes)) => scopes,
     };
 
     Ok(PasswordGrantRequest {
         principal: client,
         username: username.into(),
         password: password.into(),
-        scopes: maybe_scopes,
+        scopes,
+        resource: request.get("resource").cloned(),
     })
 }
 
@@ -95,6 +150,7 @@ mod unit_tests {
     // See: https://github.com/beercanx/oauth-api/blob/main/api/token/src/test/kotlin/uk/co/baconi/oauth/api/token/PasswordValidationTest.kt
 
     use super::*;
+    use crate::scope::repository::InMemoryScopeRepository;
     use assertables::*;
     use std::collections::HashSet;
     use crate::client::ClientType;
@@ -110,6 +166,7 @@ mod unit_tests {
         fn should_return_invalid_request_for_a_public_client() {
             let result = validate_password_grant(
                 ClientPrincipal::new_public_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "password" => "<REDACTED>",
@@ -133,9 +190,14 @@ mod unit_tests {
                     client_type: ClientType::Confidential,
                     redirect_uris: Default::default(),
                     allowed_scopes: Default::default(),
+                    default_scopes: Default::default(),
                     allowed_actions: Default::default(),
                     allowed_grant_types: Default::default(),
+                    allowed_audiences: Default::default(),
+                    jwks: Default::default(),
+                    access_token_format: Default::default(),
                 }),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "password" => "<REDACTED>",
@@ -155,6 +217,7 @@ mod unit_tests {
         fn should_return_invalid_request_on_missing_username() {
             let result = validate_password_grant(
                 ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "password" => "<REDACTED>",
                     "scope" => "read write",
@@ -177,6 +240,7 @@ mod unit_tests {
         fn should_return_invalid_request_on_blank_username() {
             let result = validate_password_grant(
                 ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => " ",
                     "password" => "<REDACTED>",
@@ -196,6 +260,7 @@ mod unit_tests {
         fn should_return_invalid_request_on_missing_password() {
             let result = validate_password_grant(
                 ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "scope" => "read write",
@@ -218,6 +283,7 @@ mod unit_tests {
         fn should_return_invalid_request_on_blank_scope() {
             let result = validate_password_grant(
                 ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "password" => "<REDACTED>",
@@ -241,9 +307,14 @@ mod unit_tests {
                     client_type: ClientType::Confidential,
                     redirect_uris: Default::default(),
                     allowed_scopes: Default::default(),
+                    default_scopes: Default::default(),
                     allowed_actions: Default::default(),
                     allowed_grant_types: HashSet::from([Password]),
+                    allowed_audiences: Default::default(),
+                    jwks: Default::default(),
+                    access_token_format: Default::default(),
                 }),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "password" => "<REDACTED>",
@@ -263,6 +334,7 @@ mod unit_tests {
         fn should_return_invalid_request_with_an_invalid_scope_and_a_valid_scope() {
             let result = validate_password_grant(
                 ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! { "username" => "aardvark",
                     "password" => "<REDACTED>",
                     "scope" => "basic cicada",
@@ -281,6 +353,7 @@ mod unit_tests {
         fn should_return_invalid_request_with_an_duplicated_valid_scopes() {
             let result = validate_password_grant(
                 ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "password" => "<REDACTED>",
@@ -303,10 +376,15 @@ mod unit_tests {
                     client_id: String::from("aardvark").into(),
                     client_type: ClientType::Confidential,
                     redirect_uris: Default::default(),
-                    allowed_scopes: HashSet::from([Scope::Read]),
+                    allowed_scopes: HashSet::from([Scope::from("read")]),
+                    default_scopes: Default::default(),
                     allowed_actions: Default::default(),
                     allowed_grant_types: HashSet::from([Password]),
+                    allowed_audiences: Default::default(),
+                    jwks: Default::default(),
+                    access_token_format: Default::default(),
                 }),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "password" => "<REDACTED>",
@@ -327,9 +405,10 @@ mod unit_tests {
         use super::*;
 
         #[test]
-        fn should_return_valid_request_if_only_scope_is_not_provided() {
+        fn should_return_valid_request_with_the_default_scopes_if_scope_is_not_provided() {
             let result = validate_password_grant(
                 ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "password" => "<REDACTED>",
@@ -342,7 +421,9 @@ mod unit_tests {
                 principal: ClientPrincipal::new_confidential_client("aardvark"),
                 username: "aardvark".into(),
                 password: "<REDACTED>".into(),
-                scopes: None,
+                // The client's default scopes, as it didn't ask for any.
+                scopes: Scopes(HashSet::from([Scope::from("basic")])),
+                resource: None,
             });
         }
 
@@ -350,6 +431,7 @@ mod unit_tests {
         fn should_return_valid_request_if_only_one_scope_is_provided() {
             let result = validate_password_grant(
                 ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "password" => "<REDACTED>",
@@ -363,7 +445,8 @@ mod unit_tests {
                 principal: ClientPrincipal::new_confidential_client("aardvark"),
                 username: "aardvark".into(),
                 password: "<REDACTED>".into(),
-                scopes: Some(Scopes(HashSet::from([Scope::Basic]))),
+                scopes: Scopes(HashSet::from([Scope::from("basic")])),
+                resource: None,
             });
         }
 
@@ -371,6 +454,7 @@ mod unit_tests {
         fn should_return_valid_request_if_multiple_scopes_are_provided() {
             let result = validate_password_grant(
                 ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
                 map_of! {
                     "username" => "aardvark",
                     "password" => "<REDACTED>",
@@ -384,7 +468,8 @@ mod unit_tests {
                 principal: ClientPrincipal::new_confidential_client("aardvark"),
                 username: "aardvark".into(),
                 password: "<REDACTED>".into(),
-                scopes: Some(Scopes(HashSet::from([Scope::Basic, Scope::Read, Scope::Write]))),
+                scopes: Scopes(HashSet::from([Scope::from("basic"), Scope::from("read"), Scope::from("write")])),
+                resource: None,
             });
         }
     }
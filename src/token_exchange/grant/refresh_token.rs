@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+use uuid::Uuid;
+use crate::client::authentication::ClientAuthenticator;
+use crate::key::KeyStore;
+use crate::replay::ReplayCache;
+use crate::openid::Authentication;
+use crate::client::{ClientPrincipal, GrantType};
+use crate::scope::Scopes;
+use crate::scope::parser::parse_scopes;
+use crate::scope::repository::ScopeRepository;
+use crate::token::{AccessToken, AuthorizationCode, Confirmation, DeviceCode, RefreshToken};
+use crate::token::repository::TokenRepository;
+use crate::token_exchange::response::{ErrorType, TokenExchangeResponse};
+use crate::token_exchange::route::{restrict_scopes, TokenExchangeState};
+use crate::resource::ProtectedResourceRepository;
+use crate::trusted_issuer::TrustedIssuerRepository;
+use crate::user::authentication::UserAuthenticator;
+
+#[derive(Deserialize, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct RefreshTokenGrantRequest {
+    pub principal: ClientPrincipal,
+    pub refresh_token: String,
+    pub scopes: Option<Scopes>,
+    pub resource: Option<String>,
+}
+
+pub async fn handle_refresh_token_grant<A, R, Z, D, C, U, K, J, I, P, Q>(
+    state: TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>,
+    request: RefreshTokenGrantRequest,
+    confirmation: Option<Confirmation>,
+) -> TokenExchangeResponse
+where
+    A: TokenRepository<AccessToken>,
+    R: TokenRepository<RefreshToken>,
+    Z: TokenRepository<AuthorizationCode>,
+    D: TokenRepository<DeviceCode>,
+    C: ClientAuthenticator,
+    U: UserAuthenticator,
+    K: KeyStore,
+    J: ReplayCache,
+    I: TrustedIssuerRepository,
+    P: ProtectedResourceRepository,
+    Q: ScopeRepository,
+{
+
+    let invalid_grant = || TokenExchangeResponse::Failure {
+        error: ErrorType::InvalidGrant,
+        error_description: Some("invalid refresh token".into()),
+    };
+
+    let refresh_token = match Uuid::parse_str(&request.refresh_token).ok().and_then(|id| state.refresh_token_repository.get_token(id)) {
+        Some(refresh_token) if &refresh_token.client_id == request.principal.id() => refresh_token,
+        _ => return invalid_grant(),
+    };
+
+    // A bound refresh token can only be used with a proof from the same key.
+    // https://www.rfc-editor.org/rfc/rfc9449#section-5
+    if refresh_token.confirmation.is_some() && refresh_token.confirmation != confirmation {
+        return invalid_grant()
+    }
+
+    // The requested scope MUST NOT include any scope not originally granted by the resource owner.
+    let scopes = match request.scopes {
+        None => refresh_token.scopes.clone(),
+        Some(Scopes(scopes)) if scopes.is_subset(&refresh_token.scopes.0) => Scopes(scopes),
+        Some(_) => return TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        },
+    };
+
+    // A refresh token for a resource can't be used to get tokens for any other.
+    let resource = match (request.resource, &refresh_token.resource) {
+        (Some(requested), Some(granted)) if &requested != granted => return TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidTarget,
+            error_description: Some("invalid parameter: resource".into()),
+        },
+        (requested, granted) => requested.or(granted.clone()),
+    };
+
+    let resource = match state.find_resource(request.principal.id(), resource.as_deref()) {
+        Err(failure) => return failure,
+        Ok(resource) => resource,
+    };
+
+    let scopes = match restrict_scopes(resource.as_ref(), scopes) {
+        Err(failure) => return failure,
+        Ok(scopes) => scopes,
+    };
+
+    // Rotate the refresh token, only the first caller gets to remove it.
+    let refresh_token = match state.refresh_token_repository.remove_token(refresh_token.id) {
+        None => return invalid_grant(),
+        Some(refresh_token) => refresh_token.rotate(),
+    };
+
+    state.refresh_token_repository.save_token(&refresh_token);
+
+    let access_token = AccessToken {
+        audience: resource.map(|resource| resource.uri),
+        confirmation,
+        ..AccessToken::new(
+            refresh_token.client_id.clone(),
+            Some(refresh_token.username.clone()),
+            scopes,
+            Some(refresh_token.grant_id),
+        )
+    };
+
+    // A refreshed ID token keeps the original auth_time, but has no nonce as there was no authentication request.
+    // https://openid.net/specs/openid-connect-core-1_0.html#RefreshTokenResponse
+    let authentication = Authentication {
+        username: refresh_token.username.clone(),
+        auth_time: refresh_token.auth_time,
+        nonce: None,
+    };
+
+    state.issue_tokens(request.principal.access_token_format(), access_token, Some(refresh_token), Some(authentication), None)
+}
+
+pub fn validate_refresh_token_grant<Q: ScopeRepository>(principal: ClientPrincipal, scope_repository: &Q, request: HashMap<String, String>) -> Result<RefreshTokenGrantRequest, TokenExchangeResponse> {
+
+    if !principal.can_perform_grant_type(&GrantType::RefreshToken) {
+        Err(TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some(format!("not authorized to: {:?}", GrantType::RefreshToken)),
+        })?
+    }
+
+    let refresh_token = match request.get("refresh_token") {
+        None => Err(TokenExchangeResponse::missing_parameter("refresh_token"))?,
+        Some(refresh_token) if refresh_token.trim().is_empty() => Err(TokenExchangeResponse::invalid_parameter("refresh_token"))?,
+        Some(refresh_token) => refresh_token,
+    };
+
+    let maybe_scopes = match parse_scopes(scope_repository, Some(principal.id()), request.get("scope")) {
+        Err(_) => Err(TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        })?,
+        Ok(Some(Scopes(scopes))) if !scopes.iter().all(|scope| principal.can_be_issued(scope)) => {
+            Err(TokenExchangeResponse::Failure {
+                error: ErrorType::InvalidScope,
+                error_description: Some("invalid parameter: scope".into()),
+            })?
+        }
+        Ok(maybe_scopes) => maybe_scopes
+    };
+
+    Ok(RefreshTokenGrantRequest {
+        principal,
+        refresh_token: refresh_token.into(),
+        scopes: maybe_scopes,
+        resource: request.get("resource").cloned(),
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    // See: https://github.com/beercanx/oauth-api/blob/main/api/token/src/test/kotlin/uk/co/baconi/oauth/api/token/RefreshTokenValidationTest.kt
+
+    use super::*;
+    use crate::scope::repository::InMemoryScopeRepository;
+    use assertables::*;
+    use std::collections::HashSet;
+    use crate::client::ClientType;
+    use crate::client::configuration::ClientConfiguration;
+    use crate::scope::Scope;
+    use crate::map_of;
+
+    mod client {
+        use super::*;
+
+        #[test]
+        fn should_return_unauthorized_client_for_an_unauthorised_client() {
+            let result = validate_refresh_token_grant(
+                ClientPrincipal::new_principal(ClientConfiguration {
+                    client_id: String::from("unauthorised").into(),
+                    client_type: ClientType::Confidential,
+                    redirect_uris: Default::default(),
+                    allowed_scopes: Default::default(),
+                    default_scopes: Default::default(),
+                    allowed_actions: Default::default(),
+                    allowed_grant_types: Default::default(),
+                    allowed_audiences: Default::default(),
+                    jwks: Default::default(),
+                    access_token_format: Default::default(),
+                }),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "refresh_token" => "aardvark",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+                error: ErrorType::UnauthorizedClient,
+                error_description: Some("not authorized to: RefreshToken".into())
+            });
+        }
+    }
+
+    mod refresh_token {
+        use super::*;
+
+        #[test]
+        fn should_return_invalid_request_on_missing_refresh_token() {
+            let result = validate_refresh_token_grant(
+                ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "scope" => "basic",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::missing_parameter("refresh_token"));
+        }
+
+        #[test]
+        fn should_return_invalid_request_on_blank_refresh_token() {
+            let result = validate_refresh_token_grant(
+                ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "refresh_token" => " ",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::invalid_parameter("refresh_token"));
+        }
+    }
+
+    mod scope {
+        use super::*;
+
+        #[test]
+        fn should_return_invalid_scope_on_an_invalid_scope() {
+            let result = validate_refresh_token_grant(
+                ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "refresh_token" => "aardvark",
+                    "scope" => "basic cicada",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+                error: ErrorType::InvalidScope,
+                error_description: Some("invalid parameter: scope".into()),
+            });
+        }
+
+        #[test]
+        fn should_return_invalid_scope_on_an_unauthorised_scope() {
+            let result = validate_refresh_token_grant(
+                ClientPrincipal::new_principal(ClientConfiguration {
+                    client_id: String::from("aardvark").into(),
+                    client_type: ClientType::Public,
+                    redirect_uris: Default::default(),
+                    allowed_scopes: HashSet::from([Scope::from("read")]),
+                    default_scopes: Default::default(),
+                    allowed_actions: Default::default(),
+                    allowed_grant_types: HashSet::from([GrantType::RefreshToken]),
+                    allowed_audiences: Default::default(),
+                    jwks: Default::default(),
+                    access_token_format: Default::default(),
+                }),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "refresh_token" => "aardvark",
+                    "scope" => "write",
+                },
+            );
+
+            assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+                error: ErrorType::InvalidScope,
+                error_description: Some("invalid parameter: scope".into()),
+            });
+        }
+    }
+
+    mod valid {
+        use super::*;
+
+        #[test]
+        fn should_return_valid_request_for_a_public_client() {
+            let result = validate_refresh_token_grant(
+                ClientPrincipal::new_public_principal("badger"),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "refresh_token" => "aardvark",
+                },
+            );
+
+            assert_eq!(assert_ok!(result), RefreshTokenGrantRequest {
+                principal: ClientPrincipal::new_public_principal("badger"),
+                refresh_token: "aardvark".into(),
+                scopes: None,
+                resource: None,
+            });
+        }
+
+        #[test]
+        fn should_return_valid_request_with_a_narrowed_scope() {
+            let result = validate_refresh_token_grant(
+                ClientPrincipal::new_confidential_principal("aardvark"),
+                &InMemoryScopeRepository::new(),
+                map_of! {
+                    "refresh_token" => "aardvark",
+                    "scope" => "read",
+                },
+            );
+
+            assert_eq!(assert_ok!(result), RefreshTokenGrantRequest {
+                principal: ClientPrincipal::new_confidential_principal("aardvark"),
+                refresh_token: "aardvark".into(),
+                scopes: Some(Scopes(HashSet::from([Scope::from("read")]))),
+                resource: None,
+            });
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::{HashMap, HashSet};
+use serde::Deserialize;
+use crate::client::authentication::ClientAuthenticator;
+use crate::client::{AccessTokenFormat, ClientPrincipal, GrantType};
+use crate::key::KeyStore;
+use crate::replay::ReplayCache;
+use crate::scope::Scopes;
+use crate::scope::parser::parse_scopes;
+use crate::scope::repository::ScopeRepository;
+use crate::token::{AccessToken, Actor, AuthorizationCode, Confirmation, DeviceCode, RefreshToken, TokenTypeIdentifier};
+use crate::token::jwt::find_access_token;
+use crate::token::repository::TokenRepository;
+use crate::token_exchange::response::{ErrorType, TokenExchangeResponse};
+use crate::token_exchange::route::{restrict_scopes, TokenExchangeState};
+use crate::resource::ProtectedResourceRepository;
+use crate::trusted_issuer::TrustedIssuerRepository;
+use crate::user::authentication::UserAuthenticator;
+use crate::util::value_struct::ValueStruct;
+
+#[derive(Deserialize, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct TokenExchangeGrantRequest {
+    pub principal: ClientPrincipal,
+    pub subject_token: String,
+    pub actor_token: Option<String>,
+    pub requested_token_type: Option<TokenTypeIdentifier>,
+    // Either the audience or the resource, as we only issue tokens for a single target.
+    pub audience: Option<String>,
+    pub scopes: Option<Scopes>,
+}
+
+// https://www.rfc-editor.org/rfc/rfc8693#section-2.1
+pub async fn handle_token_exchange_grant<A, R, Z, D, C, U, K, J, I, P, Q>(
+    state: TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>,
+    request: TokenExchangeGrantRequest,
+    confirmation: Option<Confirmation>,
+) -> TokenExchangeResponse
+where
+    A: TokenRepository<AccessToken>,
+    R: TokenRepository<RefreshToken>,
+    Z: TokenRepository<AuthorizationCode>,
+    D: TokenRepository<DeviceCode>,
+    C: ClientAuthenticator,
+    U: UserAuthenticator,
+    K: KeyStore,
+    J: ReplayCache,
+    I: TrustedIssuerRepository,
+    P: ProtectedResourceRepository,
+    Q: ScopeRepository,
+{
+
+    let invalid_grant = |description: &str| TokenExchangeResponse::Failure {
+        error: ErrorType::InvalidGrant,
+        error_description: Some(description.into()),
+    };
+
+    // Either an opaque token or a JWT we've signed, presented just as it was issued.
+    let find_access_token = |token: &str| find_access_token(&state.issuer, &state.key_store, &state.access_token_repository, token);
+
+    // The caller must be the client the token was issued to, or its audience, either by name or as a server of the resource.
+    // Otherwise any client that came across a token could exchange it for one of its own.
+    let is_presented_by_caller = |access_token: &AccessToken| {
+        let client_id = request.principal.id();
+        access_token.client_id == *client_id || match &access_token.audience {
+            None => false,
+            Some(audience) if audience == client_id.value() => true,
+            Some(audience) => state.protected_resource_repository.find_by_uri(audience)
+                .is_some_and(|resource| resource.is_served_by(client_id)),
+        }
+    };
+
+    // Only a resource owner's token can be exchanged, otherwise the subject would become the calling client.
+    let subject_token = match find_access_token(&request.subject_token) {
+        Some(subject_token) if subject_token.username.is_some() && is_presented_by_caller(&subject_token) => subject_token,
+        _ => return invalid_grant("invalid subject token"),
+    };
+
+    // A bound subject token can only be exchanged with a proof from the same key, or a stolen one could be exchanged
+    // for a bearer token.
+    // https://www.rfc-editor.org/rfc/rfc9449#section-5
+    if subject_token.confirmation.is_some() && subject_token.confirmation != confirmation {
+        return invalid_grant("invalid subject token")
+    }
+
+    // Delegation nests any prior actors within the new one, while impersonation carries them across as is.
+    // https://www.rfc-editor.org/rfc/rfc8693#section-4.1
+    let actor = match request.actor_token.as_deref().map(find_access_token) {
+        None => subject_token.actor.clone(),
+        Some(None) => return invalid_grant("invalid actor token"),
+        Some(Some(actor_token)) => Some(Actor {
+            sub: actor_token.subject().into(),
+            act: subject_token.actor.clone().map(Box::new),
+        }),
+    };
+
+    // The exchanged token can never carry more than the subject token, nor more than the client is allowed.
+    let scopes = match request.scopes {
+        None => Scopes(subject_token.scopes.0.iter()
+            .filter(|scope| request.principal.can_be_issued(scope))
+            .cloned()
+            .collect::<HashSet<_>>()),
+        Some(Scopes(scopes)) if scopes.is_subset(&subject_token.scopes.0) => Scopes(scopes),
+        Some(_) => return TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        },
+    };
+
+    // The audience is a protected resource like any other, so the token only carries the scopes it understands.
+    let resource = match state.find_resource(request.principal.id(), request.audience.as_deref()) {
+        Err(failure) => return failure,
+        Ok(resource) => resource,
+    };
+
+    let scopes = match restrict_scopes(resource.as_ref(), scopes) {
+        Err(failure) => return failure,
+        Ok(scopes) => scopes,
+    };
+
+    let (format, issued_token_type) = match request.requested_token_type {
+        Some(TokenTypeIdentifier::Jwt) => (&AccessTokenFormat::Jwt, TokenTypeIdentifier::Jwt),
+        _ => (request.principal.access_token_format(), TokenTypeIdentifier::AccessToken),
+    };
+
+    let access_token = AccessToken {
+        audience: resource.map(|resource| resource.uri),
+        actor,
+        confirmation,
+        ..AccessToken::new(
+            request.principal.id().clone(),
+            subject_token.username,
+            scopes,
+            None,
+        )
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc8693#section-2.2.1 - A refresh token is not typically issued.
+    state.issue_tokens(format, access_token, None, None, None).with_issued_token_type(issued_token_type)
+}
+
+pub fn validate_token_exchange_grant<Q: ScopeRepository>(principal: ClientPrincipal, scope_repository: &Q, request: HashMap<String, String>) -> Result<TokenExchangeGrantRequest, TokenExchangeResponse> {
+
+    if !principal.can_perform_grant_type(&GrantType::TokenExchange) {
+        Err(TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some(format!("not authorized to: {:?}", GrantType::TokenExchange)),
+        })?
+    }
+
+    let subject_token = match request.get("subject_token") {
+        None => Err(TokenExchangeResponse::missing_parameter("subject_token"))?,
+        Some(subject_token) if subject_token.trim().is_empty() => Err(TokenExchangeResponse::invalid_parameter("subject_token"))?,
+        Some(subject_token) => subject_token,
+    };
+
+    // Both token types we support are resolved the same way, so it only needs to be one of them.
+    match request.get("subject_token_type").map(|token_type| token_type.parse::<TokenTypeIdentifier>()) {
+        None => Err(TokenExchangeResponse::missing_parameter("subject_token_type"))?,
+        Some(Err(_)) => Err(TokenExchangeResponse::invalid_parameter("subject_token_type"))?,
+        Some(Ok(_)) => (),
+    };
+
+    // The actor_token_type is REQUIRED when the actor_token is present, and MUST NOT be included otherwise.
+    let actor_token = match (request.get("actor_token"), request.get("actor_token_type")) {
+        (None, None) => None,
+        (None, Some(_)) => Err(TokenExchangeResponse::missing_parameter("actor_token"))?,
+        (Some(_), None) => Err(TokenExchangeResponse::missing_parameter("actor_token_type"))?,
+        (Some(actor_token), Some(_)) if actor_token.trim().is_empty() => Err(TokenExchangeResponse::invalid_parameter("actor_token"))?,
+        (Some(_), Some(actor_token_type)) if actor_token_type.parse::<TokenTypeIdentifier>().is_err() => Err(TokenExchangeResponse::invalid_parameter("actor_token_type"))?,
+        (Some(actor_token), Some(_)) => Some(actor_token.clone()),
+    };
+
+    let requested_token_type = match request.get("requested_token_type").map(|token_type| token_type.parse::<TokenTypeIdentifier>()) {
+        None => None,
+        Some(Err(_)) => Err(TokenExchangeResponse::invalid_parameter("requested_token_type"))?,
+        Some(Ok(requested_token_type)) => Some(requested_token_type),
+    };
+
+    let invalid_target = |parameter: &str| TokenExchangeResponse::Failure {
+        error: ErrorType::InvalidTarget,
+        error_description: Some(format!("invalid parameter: {parameter}")),
+    };
+
+    // Which audiences a client may exchange for is its token exchange policy.
+    let audience = match (request.get("audience"), request.get("resource")) {
+        (Some(audience), Some(resource)) if audience != resource => Err(invalid_target("resource"))?,
+        (Some(audience), _) if !principal.can_exchange_for(audience) => Err(invalid_target("audience"))?,
+        (None, Some(resource)) if !principal.can_exchange_for(resource) => Err(invalid_target("resource"))?,
+        (maybe_audience, maybe_resource) => maybe_audience.or(maybe_resource).cloned(),
+    };
+
+    let scopes = match parse_scopes(scope_repository, Some(principal.id()), request.get("scope")) {
+        Err(_) => Err(TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        })?,
+        Ok(Some(Scopes(scopes))) if !scopes.iter().all(|scope| principal.can_be_issued(scope)) => {
+            Err(TokenExchangeResponse::Failure {
+                error: ErrorType::InvalidScope,
+                error_description: Some("invalid parameter: scope".into()),
+            })?
+        }
+        Ok(maybe_scopes) => maybe_scopes,
+    };
+
+    Ok(TokenExchangeGrantRequest {
+        principal,
+        subject_token: subject_token.into(),
+        actor_token,
+        requested_token_type,
+        audience,
+        scopes,
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use crate::scope::repository::InMemoryScopeRepository;
+    use assertables::*;
+    use crate::client::ClientType;
+    use crate::client::configuration::ClientConfiguration;
+    use crate::scope::Scope;
+    use crate::map_of;
+
+    const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+    const AUDIENCE: &str = "https://api.baconi.co.uk";
+
+    #[test]
+    fn should_return_unauthorized_client_for_an_unauthorised_client() {
+        let result = validate_token_exchange_grant(
+            ClientPrincipal::new_principal(ClientConfiguration {
+                client_id: String::from("unauthorised").into(),
+                client_type: ClientType::Confidential,
+                redirect_uris: Default::default(),
+                allowed_scopes: Default::default(),
+                default_scopes: Default::default(),
+                allowed_actions: Default::default(),
+                allowed_grant_types: Default::default(),
+                allowed_audiences: Default::default(),
+                jwks: Default::default(),
+                access_token_format: Default::default(),
+            }),
+            &InMemoryScopeRepository::new(),
+            map_of! { "subject_token" => "aardvark", "subject_token_type" => ACCESS_TOKEN_TYPE },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+            error: ErrorType::UnauthorizedClient,
+            error_description: Some("not authorized to: TokenExchange".into())
+        });
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_missing_subject_token() {
+        let result = validate_token_exchange_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            &InMemoryScopeRepository::new(),
+            map_of! { "subject_token_type" => ACCESS_TOKEN_TYPE },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::missing_parameter("subject_token"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_missing_subject_token_type() {
+        let result = validate_token_exchange_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            &InMemoryScopeRepository::new(),
+            map_of! { "subject_token" => "aardvark" },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::missing_parameter("subject_token_type"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_unsupported_subject_token_type() {
+        let result = validate_token_exchange_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            &InMemoryScopeRepository::new(),
+            map_of! { "subject_token" => "aardvark", "subject_token_type" => "urn:ietf:params:oauth:token-type:saml2" },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::invalid_parameter("subject_token_type"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_an_actor_token_without_its_type() {
+        let result = validate_token_exchange_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            &InMemoryScopeRepository::new(),
+            map_of! { "subject_token" => "aardvark", "subject_token_type" => ACCESS_TOKEN_TYPE, "actor_token" => "badger" },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::missing_parameter("actor_token_type"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_an_actor_token_type_without_the_token() {
+        let result = validate_token_exchange_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            &InMemoryScopeRepository::new(),
+            map_of! { "subject_token" => "aardvark", "subject_token_type" => ACCESS_TOKEN_TYPE, "actor_token_type" => ACCESS_TOKEN_TYPE },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::missing_parameter("actor_token"));
+    }
+
+    #[test]
+    fn should_return_invalid_target_for_an_audience_outside_of_the_clients_policy() {
+        let result = validate_token_exchange_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            &InMemoryScopeRepository::new(),
+            map_of! { "subject_token" => "aardvark", "subject_token_type" => ACCESS_TOKEN_TYPE, "audience" => "https://cicada.example.com" },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidTarget,
+            error_description: Some("invalid parameter: audience".into()),
+        });
+    }
+
+    #[test]
+    fn should_return_invalid_target_for_a_resource_that_differs_from_the_audience() {
+        let result = validate_token_exchange_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            &InMemoryScopeRepository::new(),
+            map_of! { "subject_token" => "aardvark", "subject_token_type" => ACCESS_TOKEN_TYPE, "audience" => AUDIENCE, "resource" => "https://cicada.example.com" },
+        );
+
+        assert_eq!(assert_err!(result), TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidTarget,
+            error_description: Some("invalid parameter: resource".into()),
+        });
+    }
+
+    #[test]
+    fn should_return_valid_request_with_every_parameter() {
+        let result = validate_token_exchange_grant(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            &InMemoryScopeRepository::new(),
+            map_of! {
+                "subject_token" => "aardvark",
+                "subject_token_type" => ACCESS_TOKEN_TYPE,
+                "actor_token" => "badger",
+                "actor_token_type" => "urn:ietf:params:oauth:token-type:jwt",
+                "requested_token_type" => "urn:ietf:params:oauth:token-type:jwt",
+                "resource" => AUDIENCE,
+                "scope" => "basic",
+            },
+        );
+
+        assert_eq!(assert_ok!(result), TokenExchangeGrantRequest {
+            principal: ClientPrincipal::new_confidential_principal("aardvark"),
+            subject_token: "aardvark".into(),
+            actor_token: Some("badger".into()),
+            requested_token_type: Some(TokenTypeIdentifier::Jwt),
+            audience: Some(AUDIENCE.into()),
+            scopes: Some(Scopes(HashSet::from([Scope::from("basic")]))),
+        });
+    }
+}
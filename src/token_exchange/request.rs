@@ -6,15 +6,31 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use serde::Deserialize;
 use crate::client::{ClientPrincipal, GrantType};
+use crate::scope::repository::{ScopeRepository, ScopeRepositoryState};
+use crate::token_exchange::grant::authorization_code::{validate_authorization_code_grant, AuthorizationCodeGrantRequest};
+use crate::token_exchange::grant::client_credentials::{validate_client_credentials_grant, ClientCredentialsGrantRequest};
+use crate::token_exchange::grant::device_code::{validate_device_code_grant, DeviceCodeGrantRequest};
+use crate::token_exchange::grant::jwt_bearer::{validate_jwt_bearer_grant, JwtBearerGrantRequest};
 use crate::token_exchange::grant::password::{validate_password_grant, PasswordGrantRequest};
-use crate::token_exchange::request::TokenExchangeRequest::Password;
+use crate::token_exchange::grant::refresh_token::{validate_refresh_token_grant, RefreshTokenGrantRequest};
+use crate::token_exchange::grant::token_exchange::{validate_token_exchange_grant, TokenExchangeGrantRequest};
+use crate::token_exchange::request::TokenExchangeRequest::{AuthorizationCode, ClientCredentials, DeviceCode, JwtBearer, Password, RefreshToken, TokenExchange};
 use crate::token_exchange::response::{ErrorType, TokenExchangeResponse};
 
 #[derive(Deserialize, Eq, PartialEq)]
 #[cfg_attr(test, derive(Debug))]
 #[serde(tag = "grant_type", rename_all = "snake_case")]
 pub enum TokenExchangeRequest {
+    AuthorizationCode(AuthorizationCodeGrantRequest),
+    ClientCredentials(ClientCredentialsGrantRequest),
+    #[serde(rename = "urn:ietf:params:oauth:grant-type:device_code")]
+    DeviceCode(DeviceCodeGrantRequest),
+    #[serde(rename = "urn:ietf:params:oauth:grant-type:jwt-bearer")]
+    JwtBearer(JwtBearerGrantRequest),
     Password(PasswordGrantRequest),
+    RefreshToken(RefreshTokenGrantRequest),
+    #[serde(rename = "urn:ietf:params:oauth:grant-type:token-exchange")]
+    TokenExchange(TokenExchangeGrantRequest),
 }
 
 #[derive(Eq, PartialEq)]
@@ -24,7 +40,7 @@ pub struct TokenExchangeForm(pub TokenExchangeRequest);
 // The request is a URL encoded form, but the responses are JSON.
 impl<S> FromRequest<S> for TokenExchangeForm
 where
-    S: Send + Sync,
+    S: ScopeRepositoryState + Send + Sync,
     Form<TokenExchangeRequest>: FromRequest<S, Rejection = FormRejection>,
 {
     type Rejection = Response;
@@ -41,7 +57,7 @@ where
 
         match Form::<HashMap<String, String>>::from_request(req, state).await {
             Err(rejection) => Err(handle_form_rejection(rejection)),
-            Ok(Form(request)) => match validate_grant_type(principal, request) {
+            Ok(Form(request)) => match validate_grant_type(principal, state.scope_repository(), request) {
                 Err(failure) => Err(handle_validation_failure(failure)),
                 Ok(valid) => Ok(valid),
             }
@@ -49,7 +65,7 @@ where
     }
 }
 
-pub fn validate_grant_type(principal: ClientPrincipal, request: HashMap<String, String>) -> Result<TokenExchangeForm, TokenExchangeResponse> {
+pub fn validate_grant_type<Q: ScopeRepository>(principal: ClientPrincipal, scope_repository: &Q, request: HashMap<String, String>) -> Result<TokenExchangeForm, TokenExchangeResponse> {
     match request.get("grant_type").map(|s| s.parse::<GrantType>()) {
 
         None => Err(TokenExchangeResponse::missing_parameter("grant_type")),
@@ -68,8 +84,32 @@ pub fn validate_grant_type(principal: ClientPrincipal, request: HashMap<String,
             }
         ),
 
+        Some(Ok(GrantType::AuthorizationCode)) => Ok(TokenExchangeForm(
+            AuthorizationCode(validate_authorization_code_grant(principal, request)?)
+        )),
+
+        Some(Ok(GrantType::ClientCredentials)) => Ok(TokenExchangeForm(
+            ClientCredentials(validate_client_credentials_grant(principal, scope_repository, request)?)
+        )),
+
+        Some(Ok(GrantType::DeviceCode)) => Ok(TokenExchangeForm(
+            DeviceCode(validate_device_code_grant(principal, request)?)
+        )),
+
+        Some(Ok(GrantType::JwtBearer)) => Ok(TokenExchangeForm(
+            JwtBearer(validate_jwt_bearer_grant(principal, scope_repository, request)?)
+        )),
+
         Some(Ok(GrantType::Password)) => Ok(TokenExchangeForm(
-            Password(validate_password_grant(principal, request)?)
+            Password(validate_password_grant(principal, scope_repository, request)?)
+        )),
+
+        Some(Ok(GrantType::RefreshToken)) => Ok(TokenExchangeForm(
+            RefreshToken(validate_refresh_token_grant(principal, scope_repository, request)?)
+        )),
+
+        Some(Ok(GrantType::TokenExchange)) => Ok(TokenExchangeForm(
+            TokenExchange(validate_token_exchange_grant(principal, scope_repository, request)?)
         )),
     }
 }
@@ -95,6 +135,7 @@ mod unit_tests {
     use crate::client::ClientType;
     use crate::client::configuration::ClientConfiguration;
     use crate::token_exchange::request::validate_grant_type;
+    use crate::scope::repository::InMemoryScopeRepository;
 
     macro_rules! input_parameters {
         ($($k:expr => $v:expr),* $(,)?) => {{
@@ -106,7 +147,7 @@ mod unit_tests {
         ($name:ident, $principal:expr, $request:expr, $expected:expr) => {
             #[test]
             fn $name() {
-                assert_eq!(assert_err!(validate_grant_type($principal, $request)), $expected);
+                assert_eq!(assert_err!(validate_grant_type($principal, &InMemoryScopeRepository::new(), $request)), $expected);
             }
         }
     }
@@ -115,7 +156,7 @@ mod unit_tests {
         ($name:ident, $principal:expr, $request:expr, $expected:expr) => {
             #[test]
             fn $name() {
-                assert_eq!(assert_ok!(validate_grant_type($principal, $request)), $expected);
+                assert_eq!(assert_ok!(validate_grant_type($principal, &InMemoryScopeRepository::new(), $request)), $expected);
             }
         }
     }
@@ -154,8 +195,12 @@ mod unit_tests {
             client_type: ClientType::Confidential,
             redirect_uris: Default::default(),
             allowed_scopes: Default::default(),
+            default_scopes: Default::default(),
             allowed_actions: Default::default(),
             allowed_grant_types: Default::default(),
+            allowed_audiences: Default::default(),
+            jwks: Default::default(),
+            access_token_format: Default::default(),
         }),
         input_parameters! { "grant_type" => "password" },
         TokenExchangeResponse::Failure {
@@ -172,22 +217,59 @@ mod unit_tests {
             principal: ClientPrincipal::new_confidential_client("aardvark"),
             username: "aardvark".into(),
             password: "".into(),
+            scopes: crate::scope::Scopes(std::collections::HashSet::from([crate::scope::Scope::from("basic")])),
+            resource: None,
+        }))
+    }
+
+    validate_ok! {
+        should_return_valid_request_for_client_credentials_grant_type,
+        ClientPrincipal::new_confidential_principal("aardvark"),
+        input_parameters! { "grant_type" => "client_credentials", "scope" => "basic" },
+        TokenExchangeForm(ClientCredentials(ClientCredentialsGrantRequest {
+            principal: ClientPrincipal::new_confidential_client("aardvark"),
+            scopes: crate::scope::Scopes(std::collections::HashSet::from([crate::scope::Scope::from("basic")])),
+            resource: None,
+        }))
+    }
+
+    validate_ok! {
+        should_return_valid_request_for_refresh_token_grant_type,
+        ClientPrincipal::new_confidential_principal("aardvark"),
+        input_parameters! { "grant_type" => "refresh_token", "refresh_token" => "aardvark" },
+        TokenExchangeForm(RefreshToken(RefreshTokenGrantRequest {
+            principal: ClientPrincipal::new_confidential_principal("aardvark"),
+            refresh_token: "aardvark".into(),
             scopes: None,
+            resource: None,
+        }))
+    }
+
+    validate_ok! {
+        should_return_valid_request_for_device_code_grant_type,
+        ClientPrincipal::new_confidential_principal("aardvark"),
+        input_parameters! { "grant_type" => "urn:ietf:params:oauth:grant-type:device_code", "device_code" => "aardvark" },
+        TokenExchangeForm(DeviceCode(DeviceCodeGrantRequest {
+            principal: ClientPrincipal::new_confidential_principal("aardvark"),
+            device_code: "aardvark".into(),
+            resource: None,
         }))
     }
 
-    // TODO - Re-enable once authorization code grant type is implemented
-    // validate_ok! {
-    //     should_return_valid_request_for_authorization_code_grant_type,
-    //     input_parameters! {
-    //         "grant_type" => "authorization_code",
-    //         "code" => "1234567890",
-    //         "redirect_uri" => "https://example.com/callback"
-    //     },
-    //     TokenExchangeForm(AuthorizationCode(AuthorizationCodeGrantRequest {
-    //         code: "1234567890".into(),
-    //         redirect_uri: "https://example.com/callback".into(),
-    //         code_verifier: None,
-    //     }))
-    // }
+    validate_ok! {
+        should_return_valid_request_for_authorization_code_grant_type,
+        ClientPrincipal::new_confidential_principal("aardvark"),
+        input_parameters! {
+            "grant_type" => "authorization_code",
+            "code" => "1234567890",
+            "redirect_uri" => "https://example.com/callback"
+        },
+        TokenExchangeForm(AuthorizationCode(AuthorizationCodeGrantRequest {
+            principal: ClientPrincipal::new_confidential_principal("aardvark"),
+            code: "1234567890".into(),
+            redirect_uri: "https://example.com/callback".into(),
+            code_verifier: None,
+            resource: None,
+        }))
+    }
 }
\ No newline at end of file
@@ -1,44 +1,13 @@
 use serde::Serialize;
 use crate::scope::Scopes;
-use crate::token::TokenType;
+use crate::token::{AccessToken, RefreshToken, TokenType, TokenTypeIdentifier};
 
 #[cfg_attr(test, derive(Debug))]
 #[derive(Serialize, Eq, PartialEq)]
 #[serde(untagged)]
 pub enum TokenExchangeResponse {
 
-    Success {
-
-        // The access token issued by the authorization server.
-        access_token: uuid::Uuid,
-
-        // The type of the token issued as described in
-        // https://www.rfc-editor.org/rfc/rfc6749#section-7.1
-        token_type: TokenType,
-
-        // The lifetime in seconds of the access token. For example, the value
-        // "3600" denotes that the access token will expire in one hour from the time the
-        // response was generated. If omitted, the authorization server SHOULD provide
-        // the expiration time via other means or document the default value.
-        expires_in: i64,
-
-        // OPTIONAL. The refresh token, which can be used to obtain new
-        // access tokens using the same authorization grant as described in
-        // https://www.rfc-editor.org/rfc/rfc6749#section-6
-        #[serde(skip_serializing_if = "Option::is_none")]
-        refresh_token: Option<uuid::Uuid>,
-
-        // OPTIONAL if identical to the scope requested by the client; otherwise,
-        // REQUIRED. The scope of the access token as described by
-        // https://www.rfc-editor.org/rfc/rfc6749#section-3.3
-        #[serde(skip_serializing_if = "Option::is_none")]
-        scope: Option<Scopes>,
-
-        // State REQUIRED if the "state" parameter was present in the client
-        // authorization request. The exact value received from the client.
-        #[serde(skip_serializing_if = "Option::is_none")]
-        state: Option<String>,
-    },
+    Success(Box<IssuedTokens>),
     Failure {
 
         // A single ASCII error code from the defined list.
@@ -51,8 +20,73 @@ pub enum TokenExchangeResponse {
     }
 }
 
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+pub struct IssuedTokens {
+
+    // The access token issued by the authorization server.
+    access_token: String,
+
+    // The type of the token issued as described in
+    // https://www.rfc-editor.org/rfc/rfc6749#section-7.1
+    token_type: TokenType,
+
+    // REQUIRED for a token exchange, an identifier for the representation of the issued security token.
+    // https://www.rfc-editor.org/rfc/rfc8693#section-2.2.1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issued_token_type: Option<String>,
+
+    // The lifetime in seconds of the access token. For example, the value
+    // "3600" denotes that the access token will expire in one hour from the time the
+    // response was generated. If omitted, the authorization server SHOULD provide
+    // the expiration time via other means or document the default value.
+    expires_in: i64,
+
+    // OPTIONAL. The refresh token, which can be used to obtain new
+    // access tokens using the same authorization grant as described in
+    // https://www.rfc-editor.org/rfc/rfc6749#section-6
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<uuid::Uuid>,
+
+    // OPTIONAL if identical to the scope requested by the client; otherwise,
+    // REQUIRED. The scope of the access token as described by
+    // https://www.rfc-editor.org/rfc/rfc6749#section-3.3
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<Scopes>,
+
+    // ID Token value associated with the authenticated session, when the openid scope was granted.
+    // https://openid.net/specs/openid-connect-core-1_0.html#TokenResponse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token: Option<String>,
+
+    // State REQUIRED if the "state" parameter was present in the client
+    // authorization request. The exact value received from the client.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+}
+
 impl TokenExchangeResponse {
 
+    pub fn success(encoded_access_token: String, access_token: AccessToken, refresh_token: Option<RefreshToken>, id_token: Option<String>, state: Option<String>) -> Self {
+        TokenExchangeResponse::Success(Box::new(IssuedTokens {
+            access_token: encoded_access_token,
+            token_type: access_token.token_type(),
+            issued_token_type: None,
+            expires_in: access_token.expires_in(),
+            refresh_token: refresh_token.map(|refresh_token| refresh_token.id),
+            scope: Some(access_token.scopes).filter(|Scopes(scopes)| !scopes.is_empty()),
+            id_token,
+            state,
+        }))
+    }
+
+    pub fn with_issued_token_type(mut self, issued_token_type: TokenTypeIdentifier) -> Self {
+        if let TokenExchangeResponse::Success(issued_tokens) = &mut self {
+            issued_tokens.issued_token_type = Some(issued_token_type.to_string());
+        }
+        self
+    }
+
     pub fn missing_parameter(parameter: &str) -> Self {
         TokenExchangeResponse::Failure {
             error: ErrorType::InvalidRequest,
@@ -87,6 +121,7 @@ pub enum ErrorType {
     // authorization server MUST respond with an HTTP 401 (Unauthorized) status code
     // and include the "WWW-Authenticate" response header field matching the
     // authentication scheme used by the client.
+    #[allow(dead_code)] // TODO - Remove once client authentication failures are reported in the body
     InvalidClient,
 
     // The provided authorization grant (e.g., authorization code,
@@ -106,4 +141,31 @@ pub enum ErrorType {
     // The authorization grant type is not supported by the
     // authorization server.
     UnsupportedGrantType,
+
+    // The authorization request is still pending as the end user hasn't yet completed
+    // the user-interaction steps, see https://www.rfc-editor.org/rfc/rfc8628#section-3.5
+    AuthorizationPending,
+
+    // A variant of "authorization_pending", the authorization request is still pending
+    // and polling should continue, but the interval MUST be increased by 5 seconds.
+    SlowDown,
+
+    // The authorization request was denied.
+    AccessDenied,
+
+    // The "device_code" has expired, and the device authorization session has concluded.
+    ExpiredToken,
+
+    // The authorization server is unwilling or unable to issue a token for any target
+    // service indicated by the "resource" or "audience" parameters.
+    // https://www.rfc-editor.org/rfc/rfc8693#section-2.2.2
+    InvalidTarget,
+
+    // The DPoP proof in the request was missing something, malformed, replayed or otherwise invalid.
+    // https://www.rfc-editor.org/rfc/rfc9449#section-5
+    InvalidDpopProof,
+
+    // The authorization server encountered an unexpected condition that prevented it
+    // from fulfilling the request, borrowed from https://www.rfc-editor.org/rfc/rfc6749#section-4.1.2.1
+    ServerError,
 }
@@ -1,54 +1,250 @@
 use axum::extract::State;
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, Method, StatusCode};
 use axum::{middleware, Router};
 use axum::routing::post;
 use axum::response::Json;
 use middleware::from_fn_with_state;
+use crate::client::{AccessTokenFormat, ClientId, ClientPrincipal};
 use crate::client::authentication::ClientAuthenticator;
 use crate::client::middleware::require_client_authentication;
-use crate::token::AccessToken;
+use crate::dpop::{verify_proof, DPOP_HEADER};
+use crate::key::KeyStore;
+use crate::openid::{encode_id_token, Authentication};
+use crate::replay::ReplayCache;
+use crate::scope::repository::{ScopeRepository, ScopeRepositoryState};
+use crate::resource::ProtectedResource;
+use crate::scope::{Scope, Scopes};
+use crate::token::{AccessToken, AuthorizationCode, Confirmation, DeviceCode, RefreshToken};
+use crate::token::jwt::encode_access_token;
 use crate::token::repository::TokenRepository;
+use crate::token_exchange::grant::authorization_code::handle_authorization_code_grant;
+use crate::token_exchange::grant::client_credentials::handle_client_credentials_grant;
+use crate::token_exchange::grant::device_code::handle_device_code_grant;
+use crate::token_exchange::grant::jwt_bearer::handle_jwt_bearer_grant;
 use crate::token_exchange::grant::password::handle_password_grant;
-use crate::token_exchange::response::TokenExchangeResponse;
+use crate::token_exchange::grant::refresh_token::handle_refresh_token_grant;
+use crate::token_exchange::grant::token_exchange::handle_token_exchange_grant;
+use crate::token_exchange::response::{ErrorType, TokenExchangeResponse};
 use crate::token_exchange::request::{TokenExchangeForm, TokenExchangeRequest};
+use crate::resource::ProtectedResourceRepository;
+use crate::trusted_issuer::TrustedIssuerRepository;
+use crate::user::authentication::UserAuthenticator;
+
+pub const TOKEN_ENDPOINT: &str = "/token";
 
 // https://www.rfc-editor.org/rfc/rfc6749#section-3.2
-pub fn route<A, C>(state: TokenExchangeState<A, C>) -> Router<()>
+pub fn route<A, R, Z, D, C, U, K, J, I, P, Q>(state: TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>) -> Router<()>
 where
     A: TokenRepository<AccessToken> + 'static,
+    R: TokenRepository<RefreshToken> + 'static,
+    Z: TokenRepository<AuthorizationCode> + 'static,
+    D: TokenRepository<DeviceCode> + 'static,
     C: ClientAuthenticator + 'static,
+    U: UserAuthenticator + 'static,
+    K: KeyStore + 'static,
+    J: ReplayCache + 'static,
+    I: TrustedIssuerRepository + 'static,
+    P: ProtectedResourceRepository + 'static,
+    Q: ScopeRepository + 'static,
 {
     Router::new()
-        .route("/token", post(token_exchange_handler))
+        .route(TOKEN_ENDPOINT, post(token_exchange_handler))
         .route_layer(from_fn_with_state(state.client_authenticator.clone(), require_client_authentication::<C>))
         .with_state(state)
 }
 
 #[derive(Clone)]
-pub struct TokenExchangeState<A: TokenRepository<AccessToken>, C: ClientAuthenticator> {
+pub struct TokenExchangeState<A: TokenRepository<AccessToken>, R: TokenRepository<RefreshToken>, Z: TokenRepository<AuthorizationCode>, D: TokenRepository<DeviceCode>, C: ClientAuthenticator, U: UserAuthenticator, K: KeyStore, J: ReplayCache, I: TrustedIssuerRepository, P: ProtectedResourceRepository, Q: ScopeRepository> {
+    pub issuer: String,
     pub access_token_repository: A,
-    pub client_authenticator: C
+    pub refresh_token_repository: R,
+    pub authorization_code_repository: Z,
+    pub device_code_repository: D,
+    pub client_authenticator: C,
+    pub user_authenticator: U,
+    pub key_store: K,
+    pub replay_cache: J,
+    pub trusted_issuer_repository: I,
+    pub protected_resource_repository: P,
+    pub scope_repository: Q,
+}
+
+impl<A, R, Z, D, C, U, K, J, I, P, Q> ScopeRepositoryState for TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>
+where
+    A: TokenRepository<AccessToken>,
+    R: TokenRepository<RefreshToken>,
+    Z: TokenRepository<AuthorizationCode>,
+    D: TokenRepository<DeviceCode>,
+    C: ClientAuthenticator,
+    U: UserAuthenticator,
+    K: KeyStore,
+    J: ReplayCache,
+    I: TrustedIssuerRepository,
+    P: ProtectedResourceRepository,
+    Q: ScopeRepository,
+{
+    type ScopeRepository = Q;
+    fn scope_repository(&self) -> &Q {
+        &self.scope_repository
+    }
+}
+
+impl<A, R, Z, D, C, U, K, J, I, P, Q> TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>
+where
+    A: TokenRepository<AccessToken>,
+    R: TokenRepository<RefreshToken>,
+    Z: TokenRepository<AuthorizationCode>,
+    D: TokenRepository<DeviceCode>,
+    C: ClientAuthenticator,
+    U: UserAuthenticator,
+    K: KeyStore,
+    J: ReplayCache,
+    I: TrustedIssuerRepository,
+    P: ProtectedResourceRepository,
+    Q: ScopeRepository,
+{
+    // Encodes the access token in the format the client is configured for, by default just its identifier.
+    // When a resource owner authenticated and granted the openid scope, an ID token is issued alongside it.
+    // The access token is only saved once it's been encoded, remembering its format so it can only be presented as issued.
+    pub fn issue_tokens(&self, format: &AccessTokenFormat, access_token: AccessToken, refresh_token: Option<RefreshToken>, authentication: Option<Authentication>, state: Option<String>) -> TokenExchangeResponse {
+
+        let access_token = AccessToken { format: format.clone(), ..access_token };
+
+        let encoded_access_token = match format {
+            AccessTokenFormat::Opaque => access_token.id.to_string(),
+            AccessTokenFormat::Jwt => match self.key_store.signing_key().map(|key| encode_access_token(&self.issuer, &access_token, &key)) {
+                Some(Ok(jwt)) => jwt,
+                _ => return TokenExchangeResponse::Failure {
+                    error: ErrorType::ServerError,
+                    error_description: Some("unable to sign access token".into()),
+                },
+            },
+        };
+
+        self.access_token_repository.save_token(&access_token);
+
+        let id_token = match authentication.filter(|_| access_token.scopes.0.contains(&Scope::OPENID)) {
+            None => None,
+            Some(authentication) => match self.key_store.signing_key().map(|key| encode_id_token(&self.issuer, &authentication, &access_token, &encoded_access_token, &key)) {
+                Some(Ok(jwt)) => Some(jwt),
+                _ => return TokenExchangeResponse::Failure {
+                    error: ErrorType::ServerError,
+                    error_description: Some("unable to sign id token".into()),
+                },
+            },
+        };
+
+        TokenExchangeResponse::success(encoded_access_token, access_token, refresh_token, id_token, state)
+    }
+
+    // The resource the client asked for a token to use at, which it must be allowed to target.
+    // https://www.rfc-editor.org/rfc/rfc8707#section-2.2
+    pub fn find_resource(&self, client_id: &ClientId, resource: Option<&str>) -> Result<Option<ProtectedResource>, TokenExchangeResponse> {
+        match resource.map(|resource| self.protected_resource_repository.find_by_uri(resource)) {
+            None => Ok(None),
+            Some(Some(protected_resource)) if protected_resource.can_be_targeted_by(client_id) => Ok(Some(protected_resource)),
+            Some(_) => Err(TokenExchangeResponse::Failure {
+                error: ErrorType::InvalidTarget,
+                error_description: Some("invalid parameter: resource".into()),
+            }),
+        }
+    }
+}
+
+// Confidential clients authenticate to use a refresh token, but a public client's could be used by anyone holding it,
+// so instead it's bound to the key the client proved possession of when it was issued.
+// https://www.rfc-editor.org/rfc/rfc9449#section-5
+pub fn refresh_token_confirmation(principal: &ClientPrincipal, confirmation: Option<&Confirmation>) -> Option<Confirmation> {
+    match principal {
+        ClientPrincipal::Public(_) => confirmation.cloned(),
+        ClientPrincipal::Confidential(_) => None,
+    }
+}
+
+// A token for a resource only carries the scopes it understands, which must leave at least one of those asked for.
+pub fn restrict_scopes(resource: Option<&ProtectedResource>, scopes: Scopes) -> Result<Scopes, TokenExchangeResponse> {
+    let resource = match resource {
+        None => return Ok(scopes),
+        Some(resource) => resource,
+    };
+    let requested = !scopes.0.is_empty();
+    match resource.restrict_scopes(scopes) {
+        Scopes(scopes) if requested && scopes.is_empty() => Err(TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidScope,
+            error_description: Some("invalid parameter: scope".into()),
+        }),
+        scopes => Ok(scopes),
+    }
 }
 
-async fn token_exchange_handler<A: TokenRepository<AccessToken>, C: ClientAuthenticator>(
-    State(state): State<TokenExchangeState<A, C>>,
+// Allowed because the state is generic over every repository and service a grant could need
+#[allow(clippy::type_complexity)]
+async fn token_exchange_handler<A: TokenRepository<AccessToken>, R: TokenRepository<RefreshToken>, Z: TokenRepository<AuthorizationCode>, D: TokenRepository<DeviceCode>, C: ClientAuthenticator, U: UserAuthenticator, K: KeyStore, J: ReplayCache, I: TrustedIssuerRepository, P: ProtectedResourceRepository, Q: ScopeRepository>(
+    State(state): State<TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>>,
+    headers: HeaderMap,
     TokenExchangeForm(request): TokenExchangeForm,
 ) -> (StatusCode, Json<TokenExchangeResponse>) {
 
+    // https://www.rfc-editor.org/rfc/rfc9449#section-5
+    let confirmation = match verify_dpop_proof(&state, &headers) {
+        Ok(confirmation) => confirmation,
+        Err(description) => return (StatusCode::BAD_REQUEST, Json(TokenExchangeResponse::Failure {
+            error: ErrorType::InvalidDpopProof,
+            error_description: Some(description),
+        })),
+    };
+
     let result = match request {
+        TokenExchangeRequest::AuthorizationCode(authorization_code_grant_request) => {
+            handle_authorization_code_grant(state, authorization_code_grant_request, confirmation).await
+        },
+        TokenExchangeRequest::ClientCredentials(client_credentials_grant_request) => {
+            handle_client_credentials_grant(state, client_credentials_grant_request, confirmation).await
+        },
+        TokenExchangeRequest::DeviceCode(device_code_grant_request) => {
+            handle_device_code_grant(state, device_code_grant_request, confirmation).await
+        },
+        TokenExchangeRequest::JwtBearer(jwt_bearer_grant_request) => {
+            handle_jwt_bearer_grant(state, jwt_bearer_grant_request, confirmation).await
+        },
         TokenExchangeRequest::Password(password_grant_request) => {
-            handle_password_grant(state, password_grant_request).await
+            handle_password_grant(state, password_grant_request, confirmation).await
+        },
+        TokenExchangeRequest::RefreshToken(refresh_token_grant_request) => {
+            handle_refresh_token_grant(state, refresh_token_grant_request, confirmation).await
+        },
+        TokenExchangeRequest::TokenExchange(token_exchange_grant_request) => {
+            handle_token_exchange_grant(state, token_exchange_grant_request, confirmation).await
         },
     };
 
     let status = match result {
+        TokenExchangeResponse::Failure { error: ErrorType::ServerError, .. } => StatusCode::INTERNAL_SERVER_ERROR,
         TokenExchangeResponse::Failure { .. } => StatusCode::BAD_REQUEST,
-        TokenExchangeResponse::Success { .. } => StatusCode::OK,
+        TokenExchangeResponse::Success(_) => StatusCode::OK,
     };
 
     (status, Json(result))
 }
 
+// Without a proof the tokens are issued as bearer tokens, otherwise they're bound to the key that signed it.
+fn verify_dpop_proof<A: TokenRepository<AccessToken>, R: TokenRepository<RefreshToken>, Z: TokenRepository<AuthorizationCode>, D: TokenRepository<DeviceCode>, C: ClientAuthenticator, U: UserAuthenticator, K: KeyStore, J: ReplayCache, I: TrustedIssuerRepository, P: ProtectedResourceRepository, Q: ScopeRepository>(
+    state: &TokenExchangeState<A, R, Z, D, C, U, K, J, I, P, Q>,
+    headers: &HeaderMap,
+) -> Result<Option<Confirmation>, String> {
+
+    let mut proofs = headers.get_all(DPOP_HEADER).iter();
+
+    let proof = match (proofs.next(), proofs.next()) {
+        (None, _) => return Ok(None),
+        (Some(_), Some(_)) => Err("multiple proofs")?,
+        (Some(proof), None) => proof.to_str().map_err(|_| "malformed proof")?,
+    };
+
+    verify_proof(&state.replay_cache, proof, Method::POST.as_str(), &format!("{}{TOKEN_ENDPOINT}", state.issuer), None)
+        .map(Some)
+}
+
 #[cfg(test)]
 mod integration_tests {
 
@@ -63,22 +259,51 @@ mod integration_tests {
     use base64::prelude::*;
     use serde_json::Value;
     use tower::ServiceExt;
+    use crate::token::repository::InMemoryTokenRepository;
 
     // See: https://github.com/beercanx/oauth-api/blob/main/api/token/src/test/kotlin/uk/co/baconi/oauth/api/token/TokenRouteIntegrationTests.kt
 
-    const TOKEN_ENDPOINT: &str = "/token";
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
     const APPLICATION_WWW_FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
     const TEST_CLIENT_USERNAME: &str = "aardvark";
     const TEST_CLIENT_PASSWORD: &str = "badger";
+    const TEST_USER_USERNAME: &str = "aardvark";
+    const TEST_USER_PASSWORD: &str = "P%4055w0rd";
 
     macro_rules! under_test {
         () => {
+            under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new())
+        };
+        ($access_token_repository:expr, $refresh_token_repository:expr) => {
+            under_test!($access_token_repository, $refresh_token_repository, InMemoryTokenRepository::new())
+        };
+        ($access_token_repository:expr, $refresh_token_repository:expr, $authorization_code_repository:expr) => {
+            under_test!($access_token_repository, $refresh_token_repository, $authorization_code_repository, InMemoryTokenRepository::new())
+        };
+        ($access_token_repository:expr, $refresh_token_repository:expr, $authorization_code_repository:expr, $device_code_repository:expr) => {
+            under_test!($access_token_repository, $refresh_token_repository, $authorization_code_repository, $device_code_repository, crate::resource::InMemoryProtectedResourceRepository::new())
+        };
+        ($access_token_repository:expr, $refresh_token_repository:expr, $authorization_code_repository:expr, $device_code_repository:expr, $protected_resource_repository:expr) => {
             route(TokenExchangeState {
-                access_token_repository: crate::token::repository::InMemoryTokenRepository::new(),
+                issuer: TEST_ISSUER.into(),
+                access_token_repository: $access_token_repository,
+                refresh_token_repository: $refresh_token_repository,
+                authorization_code_repository: $authorization_code_repository,
+                device_code_repository: $device_code_repository,
                 client_authenticator: crate::client::authentication::ClientAuthenticationService::new(
+                    TEST_ISSUER.into(),
                     crate::client::secret::InMemoryClientSecretRepository::new(),
                     crate::client::configuration::InMemoryClientConfigurationRepository::new(),
+                    crate::replay::InMemoryReplayCache::new(),
+                ),
+                user_authenticator: crate::user::authentication::UserAuthenticationService::new(
+                    crate::user::repository::InMemoryUserRepository::new(),
                 ),
+                key_store: crate::key::InMemoryKeyStore::new_test_store(),
+                replay_cache: crate::replay::InMemoryReplayCache::new(),
+                trusted_issuer_repository: crate::trusted_issuer::InMemoryTrustedIssuerRepository::new_test_repository(),
+                protected_resource_repository: $protected_resource_repository,
+                scope_repository: crate::scope::repository::InMemoryScopeRepository::new(),
             })
         };
     }
@@ -255,106 +480,1695 @@ mod integration_tests {
             assert_eq!(body["error_description"], "unsupported: aardvark");
         }
 
-    }
-
-    mod success_token_request {
-        use super::*;
-
         #[tokio::test]
-        async fn should_return_ok_for_valid_password_grants() {
+        async fn should_return_bad_request_for_invalid_resource_owner_credentials() {
+
             let router = under_test!();
 
-            let request = assert_ok!(Request::builder()
+            let request = assert_ok!(
+                Request::builder()
                 .method(Method::POST)
                 .uri(TOKEN_ENDPOINT)
                 .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
                 .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
-                .body(Body::from("grant_type=password&username=aardvark&password=badger&scope=basic"))
+                .body(Body::from(format!("grant_type=password&username={TEST_USER_USERNAME}&password=badger&scope=basic")))
             );
 
             let response = assert_ok!(router.oneshot(request).await);
-            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
             let body = extract_json_body(response).await;
-            assert_some!(body.get("access_token"));
-            assert_some!(body.get("refresh_token"));
-            assert_some_eq_x!(body.get("token_type"), "bearer");
-            assert_some_eq_x!(body.get("expires_in"), 7200);
-            assert_some_eq_x!(body.get("scope"), "basic");
-            assert_none!(body.get("state"));
+            assert_eq!(body["error"], "invalid_grant");
+            assert_eq!(body["error_description"], "invalid resource owner credentials");
         }
 
         #[tokio::test]
-        #[ignore = "authorization code not yet implemented"] // TODO - Re-enable once implemented
-        async fn should_return_ok_for_valid_authorization_code_grants() {
+        async fn should_return_bad_request_for_an_unknown_resource_owner() {
+
             let router = under_test!();
 
-            let request = assert_ok!(Request::builder()
+            let request = assert_ok!(
+                Request::builder()
                 .method(Method::POST)
                 .uri(TOKEN_ENDPOINT)
                 .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
                 .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
-                .body(Body::from(format!("grant_type=authorization_code&code={}&scope=basic&redirect_uri=https%3A%2F%2Fredirect.baconi.co.uk", uuid::Uuid::new_v4())))
+                .body(Body::from(format!("grant_type=password&username=badger&password={TEST_USER_PASSWORD}&scope=basic")))
             );
 
             let response = assert_ok!(router.oneshot(request).await);
-            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
             let body = extract_json_body(response).await;
-            assert_some!(body.get("access_token"));
-            assert_some!(body.get("refresh_token"));
-            assert_some_eq_x!(body.get("token_type"), "bearer");
-            assert_some_eq_x!(body.get("expires_in"), 7200);
-            assert_some_eq_x!(body.get("scope"), "basic");
+            assert_eq!(body["error"], "invalid_grant");
         }
 
-        #[tokio::test]
-        #[ignore = "refresh grant not yet implemented"] // TODO - Re-enable once implemented
-        async fn should_return_ok_for_valid_refresh_token_grant() {
-            let router = under_test!();
+    }
+
+    fn new_refresh_token(client_id: &str) -> RefreshToken {
+        RefreshToken::new(
+            crate::client::ClientId::from(String::from(client_id)),
+            crate::user::Username::from(String::from(TEST_USER_USERNAME)),
+            crate::scope::Scopes(std::collections::HashSet::from([crate::scope::Scope::from("basic"), crate::scope::Scope::from("read")])),
+            chrono::Utc::now(),
+        )
+    }
 
+    async fn exchange_token(router: Router, body: String) -> Response<Body> {
+        let request = assert_ok!(Request::builder()
+            .method(Method::POST)
+            .uri(TOKEN_ENDPOINT)
+            .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+            .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+            .body(Body::from(body))
+        );
+        assert_ok!(router.oneshot(request).await)
+    }
+
+    fn new_authorization_code(client_id: &str) -> AuthorizationCode {
+        AuthorizationCode::new(
+            crate::client::ClientId::from(String::from(client_id)),
+            crate::user::Username::from(String::from(TEST_USER_USERNAME)),
+            String::from("https://redirect.baconi.co.uk"),
+            crate::scope::Scopes(std::collections::HashSet::from([crate::scope::Scope::from("basic")])),
+            Some(String::from("aardvark")),
+            None,
+            None,
+        )
+    }
+
+    async fn exchange_token_as_public_client(router: Router, body: String) -> Response<Body> {
+        let request = assert_ok!(Request::builder()
+            .method(Method::POST)
+            .uri(TOKEN_ENDPOINT)
+            .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+            .body(Body::from(body))
+        );
+        assert_ok!(router.oneshot(request).await)
+    }
+
+    mod client_assertion {
+        use super::*;
+        use crate::assertion::CLIENT_ASSERTION_TYPE;
+        use crate::assertion::test_support::{new_claims, sign_client_secret_jwt, sign_private_key_jwt};
+
+        const TEST_TOKEN_URI: &str = "http://127.0.0.1:8080/token";
+
+        fn assertion_body(client_assertion_type: &str, client_assertion: &str) -> String {
+            form_urlencoded::Serializer::new(String::new())
+                .append_pair("grant_type", "client_credentials")
+                .append_pair("scope", "basic")
+                .append_pair("client_assertion_type", client_assertion_type)
+                .append_pair("client_assertion", client_assertion)
+                .finish()
+        }
+
+        async fn exchange_token_with_assertion(router: Router, body: String) -> Response<Body> {
             let request = assert_ok!(Request::builder()
                 .method(Method::POST)
                 .uri(TOKEN_ENDPOINT)
-                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
                 .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
-                .body(Body::from(format!("grant_type=refresh_token&refresh_token={}&scope=basic", uuid::Uuid::new_v4())))
+                .body(Body::from(body))
             );
+            assert_ok!(router.oneshot(request).await)
+        }
 
-            let response = assert_ok!(router.oneshot(request).await);
+        #[tokio::test]
+        async fn should_authenticate_with_a_private_key_jwt() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let router = under_test!(access_token_repository.clone(), InMemoryTokenRepository::new());
+
+            let assertion = sign_private_key_jwt(&new_claims("ferret", TEST_TOKEN_URI));
+            let response = exchange_token_with_assertion(router, assertion_body(CLIENT_ASSERTION_TYPE, &assertion)).await;
             assert_eq!(response.status(), StatusCode::OK);
 
             let body = extract_json_body(response).await;
-            assert_some!(body.get("access_token"));
-            assert_some!(body.get("refresh_token"));
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+            let access_token: AccessToken = assert_some!(access_token_repository.get_token(access_token_id));
+            assert_eq!(access_token.client_id, crate::client::ClientId::from(String::from("ferret")));
+        }
+
+        #[tokio::test]
+        async fn should_authenticate_with_a_client_secret_jwt() {
+            let assertion = sign_client_secret_jwt(&new_claims("gecko", TEST_TOKEN_URI));
+            let response = exchange_token_with_assertion(under_test!(), assertion_body(CLIENT_ASSERTION_TYPE, &assertion)).await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn should_accept_the_issuer_as_the_audience() {
+            let assertion = sign_private_key_jwt(&new_claims("ferret", TEST_ISSUER));
+            let response = exchange_token_with_assertion(under_test!(), assertion_body(CLIENT_ASSERTION_TYPE, &assertion)).await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_replayed_assertion() {
+            let router = under_test!();
+            let assertion = sign_private_key_jwt(&new_claims("ferret", TEST_TOKEN_URI));
+
+            let response = exchange_token_with_assertion(router.clone(), assertion_body(CLIENT_ASSERTION_TYPE, &assertion)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = exchange_token_with_assertion(router, assertion_body(CLIENT_ASSERTION_TYPE, &assertion)).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_assertion_for_another_endpoint() {
+            let assertion = sign_private_key_jwt(&new_claims("ferret", "http://127.0.0.1:8080/introspect"));
+            let response = exchange_token_with_assertion(under_test!(), assertion_body(CLIENT_ASSERTION_TYPE, &assertion)).await;
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_client_id_that_does_not_match_the_assertion() {
+            let assertion = sign_private_key_jwt(&new_claims("ferret", TEST_TOKEN_URI));
+            let body = format!("{}&client_id=gecko", assertion_body(CLIENT_ASSERTION_TYPE, &assertion));
+
+            let response = exchange_token_with_assertion(under_test!(), body).await;
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unsupported_assertion_type() {
+            let assertion = sign_private_key_jwt(&new_claims("ferret", TEST_TOKEN_URI));
+            let response = exchange_token_with_assertion(under_test!(), assertion_body("urn:ietf:params:oauth:client-assertion-type:saml2-bearer", &assertion)).await;
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_assertion_alongside_basic_authentication() {
+            let assertion = sign_private_key_jwt(&new_claims("ferret", TEST_TOKEN_URI));
+            let response = exchange_token(under_test!(), assertion_body(CLIENT_ASSERTION_TYPE, &assertion)).await;
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    mod authorization_code_grant {
+        use super::*;
+        use crate::pkce::{CodeChallenge, CodeChallengeMethod};
+
+        const REDIRECT_URI: &str = "https%3A%2F%2Fredirect.baconi.co.uk";
+
+        #[tokio::test]
+        async fn should_only_allow_the_authorization_code_to_be_used_once() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let authorization_code = new_authorization_code(TEST_CLIENT_USERNAME);
+            authorization_code_repository.save_token(&authorization_code);
+
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository.clone());
+
+            let response = exchange_token(router.clone(), format!("grant_type=authorization_code&code={}&redirect_uri={REDIRECT_URI}", authorization_code.id)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_none!(authorization_code_repository.get_token(authorization_code.id));
+
+            let replayed = exchange_token(router, format!("grant_type=authorization_code&code={}&redirect_uri={REDIRECT_URI}", authorization_code.id)).await;
+            assert_eq!(replayed.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(replayed).await;
+            assert_eq!(body["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_mismatched_redirect_uri() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let authorization_code = new_authorization_code(TEST_CLIENT_USERNAME);
+            authorization_code_repository.save_token(&authorization_code);
+
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository.clone());
+
+            let response = exchange_token(router, format!("grant_type=authorization_code&code={}&redirect_uri=https%3A%2F%2Fexample.com", authorization_code.id)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_grant");
+            assert_some!(authorization_code_repository.get_token(authorization_code.id));
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_authorization_code_issued_to_another_client() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let authorization_code = new_authorization_code("badger");
+            authorization_code_repository.save_token(&authorization_code);
+
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository.clone());
+
+            let response = exchange_token(router, format!("grant_type=authorization_code&code={}&redirect_uri={REDIRECT_URI}", authorization_code.id)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_grant");
+            assert_some!(authorization_code_repository.get_token(authorization_code.id));
+        }
+
+        mod proof_key_for_code_exchange {
+            use super::*;
+
+            // https://www.rfc-editor.org/rfc/rfc7636#appendix-B
+            const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+            const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+            fn new_challenged_authorization_code(method: CodeChallengeMethod, challenge: &str) -> AuthorizationCode {
+                AuthorizationCode {
+                    code_challenge: Some(CodeChallenge { challenge: challenge.into(), method }),
+                    ..new_authorization_code("badger")
+                }
+            }
+
+            #[tokio::test]
+            async fn should_return_ok_for_a_matching_s256_code_verifier() {
+                let authorization_code_repository = InMemoryTokenRepository::new();
+                let authorization_code = new_challenged_authorization_code(CodeChallengeMethod::S256, CODE_CHALLENGE);
+                authorization_code_repository.save_token(&authorization_code);
+
+                let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository.clone());
+
+                let response = exchange_token_as_public_client(router, format!("grant_type=authorization_code&client_id=badger&code={}&redirect_uri={REDIRECT_URI}&code_verifier={CODE_VERIFIER}", authorization_code.id)).await;
+                assert_eq!(response.status(), StatusCode::OK);
+
+                let body = extract_json_body(response).await;
+                assert_some!(body.get("access_token"));
+                assert_none!(body.get("refresh_token"));
+                assert_none!(authorization_code_repository.get_token(authorization_code.id));
+            }
+
+            #[tokio::test]
+            async fn should_return_ok_for_a_matching_plain_code_verifier() {
+                let authorization_code_repository = InMemoryTokenRepository::new();
+                let authorization_code = new_challenged_authorization_code(CodeChallengeMethod::Plain, CODE_VERIFIER);
+                authorization_code_repository.save_token(&authorization_code);
+
+                let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository);
+
+                let response = exchange_token_as_public_client(router, format!("grant_type=authorization_code&client_id=badger&code={}&redirect_uri={REDIRECT_URI}&code_verifier={CODE_VERIFIER}", authorization_code.id)).await;
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+
+            #[tokio::test]
+            async fn should_reject_a_mismatched_code_verifier() {
+                let authorization_code_repository = InMemoryTokenRepository::new();
+                let authorization_code = new_challenged_authorization_code(CodeChallengeMethod::S256, CODE_CHALLENGE);
+                authorization_code_repository.save_token(&authorization_code);
+
+                let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository);
+
+                let response = exchange_token_as_public_client(router, format!("grant_type=authorization_code&client_id=badger&code={}&redirect_uri={REDIRECT_URI}&code_verifier={CODE_CHALLENGE}", authorization_code.id)).await;
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+                let body = extract_json_body(response).await;
+                assert_eq!(body["error"], "invalid_grant");
+                assert_eq!(body["error_description"], "invalid code_verifier");
+            }
+
+            #[tokio::test]
+            async fn should_reject_a_missing_code_verifier() {
+                let authorization_code_repository = InMemoryTokenRepository::new();
+                let authorization_code = new_challenged_authorization_code(CodeChallengeMethod::S256, CODE_CHALLENGE);
+                authorization_code_repository.save_token(&authorization_code);
+
+                let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository);
+
+                let response = exchange_token_as_public_client(router, format!("grant_type=authorization_code&client_id=badger&code={}&redirect_uri={REDIRECT_URI}", authorization_code.id)).await;
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+                let body = extract_json_body(response).await;
+                assert_eq!(body["error"], "invalid_grant");
+            }
+
+            #[tokio::test]
+            async fn should_reject_a_code_verifier_for_an_unchallenged_authorization_code() {
+                let authorization_code_repository = InMemoryTokenRepository::new();
+                let authorization_code = new_authorization_code(TEST_CLIENT_USERNAME);
+                authorization_code_repository.save_token(&authorization_code);
+
+                let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository);
+
+                let response = exchange_token(router, format!("grant_type=authorization_code&code={}&redirect_uri={REDIRECT_URI}&code_verifier={CODE_VERIFIER}", authorization_code.id)).await;
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+                let body = extract_json_body(response).await;
+                assert_eq!(body["error"], "invalid_grant");
+            }
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unknown_authorization_code() {
+            let router = under_test!();
+
+            let response = exchange_token(router, format!("grant_type=authorization_code&code={}&redirect_uri={REDIRECT_URI}", uuid::Uuid::new_v4())).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_grant");
+        }
+    }
+
+    mod client_credentials_grant {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_issue_a_subject_less_access_token_without_a_refresh_token() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let refresh_token_repository = InMemoryTokenRepository::<RefreshToken>::new();
+            let router = under_test!(access_token_repository.clone(), refresh_token_repository.clone());
+
+            let response = exchange_token(router, String::from("grant_type=client_credentials&scope=basic")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_none!(body.get("refresh_token"));
             assert_some_eq_x!(body.get("token_type"), "bearer");
             assert_some_eq_x!(body.get("expires_in"), 7200);
             assert_some_eq_x!(body.get("scope"), "basic");
+
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+            let access_token: AccessToken = assert_some!(access_token_repository.get_token(access_token_id));
+            assert_eq!(access_token.client_id, crate::client::ClientId::from(String::from(TEST_CLIENT_USERNAME)));
+            assert_none!(access_token.username);
         }
 
         #[tokio::test]
-        #[ignore = "assertion grant not yet implemented"] // TODO - Re-enable once implemented
-        async fn should_return_ok_for_valid_assertion_grant() {
+        async fn should_only_issue_the_allowed_scopes_that_were_requested() {
             let router = under_test!();
 
-            let request = assert_ok!(
-                Request::builder()
-                .method(Method::POST)
-                .uri(TOKEN_ENDPOINT)
-                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
-                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
-                .body(Body::from(format!("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer&assertion={}", uuid::Uuid::new_v4())))
-            );
+            let response = exchange_token(router, String::from("grant_type=client_credentials&scope=basic%20write")).await;
+            assert_eq!(response.status(), StatusCode::OK);
 
-            let response = assert_ok!(router.oneshot(request).await);
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("scope"), "basic");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_public_client() {
+            let router = under_test!();
+
+            let response = exchange_token_as_public_client(router, String::from("grant_type=client_credentials&client_id=badger")).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "unauthorized_client");
+        }
+    }
+
+    mod default_scopes {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_issue_the_clients_default_scopes_for_a_client_credentials_grant_without_scope() {
+            let response = exchange_token(under_test!(), String::from("grant_type=client_credentials")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("scope"), "basic");
+        }
+
+        #[tokio::test]
+        async fn should_issue_the_clients_default_scopes_for_a_password_grant_without_scope() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let router = under_test!(access_token_repository.clone(), InMemoryTokenRepository::new());
+
+            let response = exchange_token(router, format!("grant_type=password&username={TEST_USER_USERNAME}&password={TEST_USER_PASSWORD}")).await;
             assert_eq!(response.status(), StatusCode::OK);
 
             let body = extract_json_body(response).await;
-            assert_some!(body.get("access_token"));
-            assert_some!(body.get("refresh_token"));
-            assert_some_eq_x!(body.get("token_type"), "bearer");
-            assert_some_eq_x!(body.get("expires_in"), 7200);
             assert_some_eq_x!(body.get("scope"), "basic");
+
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+            let access_token: AccessToken = assert_some!(access_token_repository.get_token(access_token_id));
+            assert_eq!(access_token.scopes, crate::scope::Scopes(std::collections::HashSet::from([crate::scope::Scope::from("basic")])));
         }
     }
-}
\ No newline at end of file
+
+    mod parameterised_scopes {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_issue_a_parameterised_scope_allowed_by_a_wildcard() {
+            let response = exchange_token(under_test!(), format!("grant_type=password&scope=basic%20accounts:read:1234&username={TEST_USER_USERNAME}&password={TEST_USER_PASSWORD}")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            let scope = assert_some!(body["scope"].as_str());
+            assert_eq!(scope.split(' ').collect::<std::collections::HashSet<_>>(), std::collections::HashSet::from(["basic", "accounts:read:1234"]));
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_parameterised_scope_the_client_is_not_allowed() {
+            let response = exchange_token(under_test!(), format!("grant_type=password&scope=accounts:write:1234&username={TEST_USER_USERNAME}&password={TEST_USER_PASSWORD}")).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_scope");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_wildcard_or_mistyped_parameter_being_requested() {
+            for scope in ["accounts:read:*", "accounts:read:abc", "accounts:read:%7Bid%7D"] {
+                let response = exchange_token(under_test!(), format!("grant_type=password&scope={scope}&username={TEST_USER_USERNAME}&password={TEST_USER_PASSWORD}")).await;
+                assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+                let body = extract_json_body(response).await;
+                assert_eq!(body["error"], "invalid_scope");
+            }
+        }
+    }
+
+    mod device_code_grant {
+        use super::*;
+        use chrono::{TimeDelta, Utc};
+        use crate::token::DeviceCodeStatus;
+
+        const DEVICE_CODE_GRANT_TYPE: &str = "urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code";
+
+        fn new_device_code(status: DeviceCodeStatus) -> DeviceCode {
+            DeviceCode {
+                status,
+                ..assert_ok!(DeviceCode::new(
+                    crate::client::ClientId::from(String::from(TEST_CLIENT_USERNAME)),
+                    crate::scope::Scopes(std::collections::HashSet::from([crate::scope::Scope::from("basic")])),
+                ))
+            }
+        }
+
+        fn approved() -> DeviceCodeStatus {
+            DeviceCodeStatus::Approved {
+                username: crate::user::Username::from(String::from(TEST_USER_USERNAME)),
+                auth_time: Utc::now(),
+            }
+        }
+
+        async fn poll(device_code_repository: InMemoryTokenRepository<DeviceCode>, device_code: &DeviceCode) -> Response<Body> {
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), device_code_repository);
+            exchange_token(router, format!("grant_type={DEVICE_CODE_GRANT_TYPE}&device_code={}", device_code.id)).await
+        }
+
+        #[tokio::test]
+        async fn should_return_authorization_pending_until_the_user_has_approved() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = new_device_code(DeviceCodeStatus::Pending);
+            device_code_repository.save_token(&device_code);
+
+            let response = poll(device_code_repository.clone(), &device_code).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "authorization_pending");
+            assert_some!(assert_some!(device_code_repository.get_token(device_code.id)).polled_at);
+        }
+
+        #[tokio::test]
+        async fn should_return_slow_down_and_increase_the_interval_when_polling_too_quickly() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = DeviceCode { polled_at: Some(Utc::now()), ..new_device_code(DeviceCodeStatus::Pending) };
+            device_code_repository.save_token(&device_code);
+
+            let response = poll(device_code_repository.clone(), &device_code).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "slow_down");
+            assert_eq!(assert_some!(device_code_repository.get_token(device_code.id)).interval, DeviceCode::INTERVAL + 5);
+        }
+
+        // Approves the device code as soon as the poll has read it, as if the user had submitted the verification form in between.
+        #[derive(Clone)]
+        struct ApprovedMidPoll(InMemoryTokenRepository<DeviceCode>);
+
+        impl TokenRepository<DeviceCode> for ApprovedMidPoll {
+            fn get_token(&self, id: uuid::Uuid) -> Option<DeviceCode> {
+                let device_code = self.0.get_token(id);
+                self.0.update_token(id, |device_code| device_code.status = approved());
+                device_code
+            }
+            fn save_token(&self, token: &DeviceCode) {
+                self.0.save_token(token)
+            }
+            fn remove_token(&self, id: uuid::Uuid) -> Option<DeviceCode> {
+                self.0.remove_token(id)
+            }
+            fn remove_tokens_where(&self, predicate: impl Fn(&DeviceCode) -> bool) {
+                self.0.remove_tokens_where(predicate)
+            }
+            fn find_token_where(&self, predicate: impl Fn(&DeviceCode) -> bool) -> Option<DeviceCode> {
+                self.0.find_token_where(predicate)
+            }
+            fn update_token<T>(&self, id: uuid::Uuid, update: impl FnOnce(&mut DeviceCode) -> T) -> Option<T> {
+                self.0.update_token(id, update)
+            }
+        }
+
+        #[tokio::test]
+        async fn should_keep_an_approval_made_while_polling() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = new_device_code(DeviceCodeStatus::Pending);
+            device_code_repository.save_token(&device_code);
+
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), ApprovedMidPoll(device_code_repository.clone()));
+            let response = exchange_token(router, format!("grant_type={DEVICE_CODE_GRANT_TYPE}&device_code={}", device_code.id)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "authorization_pending");
+
+            let saved = assert_some!(device_code_repository.get_token(device_code.id));
+            assert_matches!(saved.status, DeviceCodeStatus::Approved { .. });
+            assert_some!(saved.polled_at);
+
+            let response = poll(device_code_repository.clone(), &device_code).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn should_return_access_denied_when_the_user_denied_the_device() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = new_device_code(DeviceCodeStatus::Denied);
+            device_code_repository.save_token(&device_code);
+
+            let response = poll(device_code_repository.clone(), &device_code).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "access_denied");
+            assert_none!(device_code_repository.get_token(device_code.id));
+        }
+
+        #[tokio::test]
+        async fn should_return_expired_token_once_the_device_code_has_expired() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = DeviceCode {
+                issued_at: Utc::now() - TimeDelta::minutes(15),
+                expires_at: Utc::now() - TimeDelta::minutes(5),
+                ..new_device_code(approved())
+            };
+            device_code_repository.save_token(&device_code);
+
+            let response = poll(device_code_repository.clone(), &device_code).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "expired_token");
+            assert_none!(device_code_repository.get_token(device_code.id));
+        }
+
+        #[tokio::test]
+        async fn should_return_invalid_grant_for_another_clients_device_code() {
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = DeviceCode { client_id: crate::client::ClientId::from(String::from("badger")), ..new_device_code(approved()) };
+            device_code_repository.save_token(&device_code);
+
+            let response = poll(device_code_repository.clone(), &device_code).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_grant");
+            assert_some!(device_code_repository.get_token(device_code.id));
+        }
+
+        #[tokio::test]
+        async fn should_issue_tokens_once_approved_and_only_the_once() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let device_code_repository = InMemoryTokenRepository::new();
+            let device_code = new_device_code(approved());
+            device_code_repository.save_token(&device_code);
+
+            let router = under_test!(access_token_repository.clone(), InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), device_code_repository.clone());
+
+            let response = exchange_token(router.clone(), format!("grant_type={DEVICE_CODE_GRANT_TYPE}&device_code={}", device_code.id)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some!(body.get("refresh_token"));
+            assert_some_eq_x!(body.get("scope"), "basic");
+
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+            let access_token: AccessToken = assert_some!(access_token_repository.get_token(access_token_id));
+            assert_some_eq_x!(&access_token.username, &crate::user::Username::from(String::from(TEST_USER_USERNAME)));
+
+            let replayed = exchange_token(router, format!("grant_type={DEVICE_CODE_GRANT_TYPE}&device_code={}", device_code.id)).await;
+            assert_eq!(replayed.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(replayed).await;
+            assert_eq!(body["error"], "invalid_grant");
+        }
+    }
+
+    mod token_exchange_grant {
+        use super::*;
+        use crate::client::ClientId;
+        use crate::scope::{Scope, Scopes};
+        use crate::dpop::test_support::{new_proof, test_proof_thumbprint};
+        use crate::token::Actor;
+        use crate::user::Username;
+
+        const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Atoken-exchange";
+        const ACCESS_TOKEN_TYPE: &str = "urn%3Aietf%3Aparams%3Aoauth%3Atoken-type%3Aaccess_token";
+        const AUDIENCE: &str = "https%3A%2F%2Fapi.baconi.co.uk";
+
+        fn new_access_token(client_id: &str, username: Option<&str>) -> AccessToken {
+            AccessToken::new(
+                ClientId::from(String::from(client_id)),
+                username.map(|username| Username::from(String::from(username))),
+                Scopes(std::collections::HashSet::from([Scope::from("basic"), Scope::OPENID])),
+                None,
+            )
+        }
+
+        // A token the caller was sent, by a user of the badger client, to use at its API.
+        fn new_subject_token() -> AccessToken {
+            AccessToken {
+                audience: Some(String::from(TEST_CLIENT_USERNAME)),
+                ..new_access_token("badger", Some(TEST_USER_USERNAME))
+            }
+        }
+
+        async fn exchange(access_token_repository: InMemoryTokenRepository<AccessToken>, body: String) -> (StatusCode, HashMap<String, Value>) {
+            let response = exchange_token(under_test!(access_token_repository, InMemoryTokenRepository::new()), body).await;
+            (response.status(), extract_json_body(response).await)
+        }
+
+        fn issued_access_token(access_token_repository: &InMemoryTokenRepository<AccessToken>, body: &HashMap<String, Value>) -> AccessToken {
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+            assert_some!(access_token_repository.get_token(access_token_id))
+        }
+
+        #[tokio::test]
+        async fn should_impersonate_the_subject_for_the_requested_audience() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_subject_token();
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange(access_token_repository.clone(), format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}&audience={AUDIENCE}", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["issued_token_type"], "urn:ietf:params:oauth:token-type:access_token");
+            assert_none!(body.get("refresh_token"));
+
+            let access_token = issued_access_token(&access_token_repository, &body);
+            assert_eq!(access_token.client_id, ClientId::from(String::from(TEST_CLIENT_USERNAME)));
+            assert_eq!(access_token.username, subject_token.username);
+            assert_eq!(access_token.scopes, Scopes(std::collections::HashSet::from([Scope::from("basic")])));
+            assert_some_eq_x!(access_token.audience.as_deref(), "https://api.baconi.co.uk");
+            assert_none!(access_token.actor);
+        }
+
+        #[tokio::test]
+        async fn should_delegate_to_the_actor_and_keep_the_chain_of_prior_actors() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = AccessToken {
+                actor: Some(Actor { sub: "cicada".into(), act: None }),
+                ..new_subject_token()
+            };
+            let actor_token = new_access_token("dingo", None);
+            access_token_repository.save_token(&subject_token);
+            access_token_repository.save_token(&actor_token);
+
+            let (status, body) = exchange(access_token_repository.clone(), format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}&actor_token={}&actor_token_type={ACCESS_TOKEN_TYPE}&resource={AUDIENCE}&scope=basic",
+                subject_token.id, actor_token.id,
+            )).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["scope"], "basic");
+
+            let access_token = issued_access_token(&access_token_repository, &body);
+            assert_eq!(access_token.actor, Some(Actor {
+                sub: "dingo".into(),
+                act: Some(Box::new(Actor { sub: "cicada".into(), act: None })),
+            }));
+        }
+
+        #[tokio::test]
+        async fn should_issue_a_jwt_when_requested() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_subject_token();
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}&requested_token_type=urn%3Aietf%3Aparams%3Aoauth%3Atoken-type%3Ajwt", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["issued_token_type"], "urn:ietf:params:oauth:token-type:jwt");
+
+            let header = assert_ok!(jsonwebtoken::decode_header(assert_some!(body["access_token"].as_str())));
+            assert_eq!(header.typ.as_deref(), Some(crate::token::jwt::ACCESS_TOKEN_TYPE));
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unknown_subject_token() {
+            let (status, body) = exchange(InMemoryTokenRepository::new(), format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}", uuid::Uuid::new_v4()
+            )).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_subject_token_without_a_resource_owner() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_access_token(TEST_CLIENT_USERNAME, None);
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_exchange_a_subject_token_issued_to_the_caller() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_access_token(TEST_CLIENT_USERNAME, Some(TEST_USER_USERNAME));
+            access_token_repository.save_token(&subject_token);
+
+            let (status, _) = exchange(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        async fn exchange_with_proof(access_token_repository: InMemoryTokenRepository<AccessToken>, body: String, maybe_proof: Option<String>) -> (StatusCode, HashMap<String, Value>) {
+            let mut request = Request::builder()
+                .method(Method::POST)
+                .uri(TOKEN_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED);
+            if let Some(proof) = maybe_proof {
+                request = request.header(DPOP_HEADER, proof);
+            }
+            let request = assert_ok!(request.body(Body::from(body)));
+            let response = assert_ok!(under_test!(access_token_repository, InMemoryTokenRepository::new()).oneshot(request).await);
+            (response.status(), extract_json_body(response).await)
+        }
+
+        fn new_bound_subject_token() -> AccessToken {
+            AccessToken {
+                confirmation: Some(Confirmation { jkt: test_proof_thumbprint() }),
+                ..new_subject_token()
+            }
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_bound_subject_token_without_a_proof() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_bound_subject_token();
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange_with_proof(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}", subject_token.id
+            ), None).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_keep_a_bound_subject_token_bound_to_the_same_key() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_bound_subject_token();
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange_with_proof(access_token_repository.clone(), format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}", subject_token.id
+            ), Some(new_proof("POST", "http://127.0.0.1:8080/token"))).await;
+            assert_eq!(status, StatusCode::OK);
+
+            let access_token = issued_access_token(&access_token_repository, &body);
+            assert_eq!(access_token.confirmation, subject_token.confirmation);
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_subject_token_issued_to_another_client() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_access_token("badger", Some(TEST_USER_USERNAME));
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_subject_token_for_another_audience() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = AccessToken {
+                audience: Some(String::from("dingo")),
+                ..new_access_token("badger", Some(TEST_USER_USERNAME))
+            };
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_subject_token_for_a_resource_the_caller_does_not_serve() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = AccessToken {
+                audience: Some(String::from("https://api.baconi.co.uk")),
+                ..new_access_token("badger", Some(TEST_USER_USERNAME))
+            };
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unregistered_audience() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_subject_token();
+            access_token_repository.save_token(&subject_token);
+
+            let router = under_test!(access_token_repository, InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), crate::resource::InMemoryProtectedResourceRepository::default());
+            let response = exchange_token(router, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}&audience={AUDIENCE}", subject_token.id
+            )).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_target");
+        }
+
+        #[tokio::test]
+        async fn should_reject_scopes_the_audience_does_not_understand() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_subject_token();
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}&audience={AUDIENCE}&scope=openid", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_scope");
+        }
+
+        #[tokio::test]
+        async fn should_reject_more_scope_than_the_subject_token_carries() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = AccessToken {
+                scopes: Scopes(std::collections::HashSet::from([Scope::OPENID])),
+                ..new_subject_token()
+            };
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}&scope=basic", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_scope");
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_audience_outside_of_the_clients_policy() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let subject_token = new_subject_token();
+            access_token_repository.save_token(&subject_token);
+
+            let (status, body) = exchange(access_token_repository, format!(
+                "grant_type={TOKEN_EXCHANGE_GRANT_TYPE}&subject_token={}&subject_token_type={ACCESS_TOKEN_TYPE}&audience=https%3A%2F%2Fcicada.example.com", subject_token.id
+            )).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error"], "invalid_target");
+        }
+    }
+
+    mod jwt_bearer_grant {
+        use super::*;
+        use crate::scope::{Scope, Scopes};
+        use crate::trusted_issuer::test_support::{new_claims, sign_assertion, TEST_TRUSTED_ISSUER};
+        use crate::user::Username;
+
+        const TEST_TOKEN_URI: &str = "http://127.0.0.1:8080/token";
+
+        fn assertion_body(assertion: &str, scope: Option<&str>) -> String {
+            let mut body = form_urlencoded::Serializer::new(String::new());
+            body.append_pair("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer");
+            body.append_pair("assertion", assertion);
+            if let Some(scope) = scope {
+                body.append_pair("scope", scope);
+            }
+            body.finish()
+        }
+
+        #[tokio::test]
+        async fn should_issue_an_access_token_for_the_mapped_subject_and_scopes() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let router = under_test!(access_token_repository.clone(), InMemoryTokenRepository::new());
+
+            let assertion = sign_assertion(&new_claims("aardvark@iguana", TEST_TOKEN_URI, "iguana:basic iguana:profile cicada"));
+            let response = exchange_token(router, assertion_body(&assertion, None)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some!(body.get("refresh_token"));
+
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+            let access_token: AccessToken = assert_some!(access_token_repository.get_token(access_token_id));
+            assert_eq!(access_token.username, Some(Username::from(String::from(TEST_USER_USERNAME))));
+            assert_eq!(access_token.scopes, Scopes(std::collections::HashSet::from([Scope::from("basic"), Scope::PROFILE])));
+        }
+
+        #[tokio::test]
+        async fn should_accept_the_issuer_as_the_audience() {
+            let assertion = sign_assertion(&new_claims("aardvark@iguana", TEST_ISSUER, "iguana:basic"));
+            let response = exchange_token(under_test!(), assertion_body(&assertion, Some("basic"))).await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_replayed_assertion() {
+            let router = under_test!();
+            let assertion = sign_assertion(&new_claims("aardvark@iguana", TEST_TOKEN_URI, "iguana:basic"));
+
+            let response = exchange_token(router.clone(), assertion_body(&assertion, None)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = exchange_token(router, assertion_body(&assertion, None)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(extract_json_body(response).await["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_subject_that_is_not_allowed() {
+            let assertion = sign_assertion(&new_claims("cicada@iguana", TEST_TOKEN_URI, "iguana:basic"));
+            let response = exchange_token(under_test!(), assertion_body(&assertion, None)).await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(extract_json_body(response).await["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_untrusted_issuer() {
+            let mut claims = new_claims("aardvark@iguana", TEST_TOKEN_URI, "iguana:basic");
+            claims["iss"] = "https://cicada.baconi.co.uk".into();
+            let response = exchange_token(under_test!(), assertion_body(&sign_assertion(&claims), None)).await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(extract_json_body(response).await["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_assertion_for_another_audience() {
+            let assertion = sign_assertion(&new_claims("aardvark@iguana", "http://127.0.0.1:8080/introspect", "iguana:basic"));
+            let response = exchange_token(under_test!(), assertion_body(&assertion, None)).await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(extract_json_body(response).await["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_expired_assertion() {
+            let mut claims = new_claims("aardvark@iguana", TEST_TOKEN_URI, "iguana:basic");
+            claims["exp"] = (chrono::Utc::now() - chrono::TimeDelta::minutes(5)).timestamp().into();
+            let response = exchange_token(under_test!(), assertion_body(&sign_assertion(&claims), None)).await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(extract_json_body(response).await["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_reject_more_scope_than_the_issuer_granted() {
+            let assertion = sign_assertion(&new_claims("aardvark@iguana", TEST_TOKEN_URI, "iguana:basic"));
+            let response = exchange_token(under_test!(), assertion_body(&assertion, Some("basic profile"))).await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(extract_json_body(response).await["error"], "invalid_scope");
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_assertion_not_signed_by_the_issuer() {
+            let assertion = crate::assertion::test_support::sign_private_key_jwt(&serde_json::json!({
+                "iss": TEST_TRUSTED_ISSUER,
+                "sub": "aardvark@iguana",
+                "aud": TEST_TOKEN_URI,
+                "jti": uuid::Uuid::new_v4().to_string(),
+                "exp": (chrono::Utc::now() + chrono::TimeDelta::minutes(1)).timestamp(),
+            }));
+            let response = exchange_token(under_test!(), assertion_body(&assertion, None)).await;
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(extract_json_body(response).await["error"], "invalid_grant");
+        }
+    }
+
+    mod dpop_bound_token {
+        use super::*;
+        use crate::dpop::test_support::{new_proof, test_proof_thumbprint};
+        use crate::token::jwt::AccessTokenClaims;
+
+        const TEST_TOKEN_URI: &str = "http://127.0.0.1:8080/token";
+
+        async fn exchange_token_with_proofs(router: Router, client: (&str, &str), proofs: &[String]) -> Response<Body> {
+            exchange_token_body_with_proofs(router, client, String::from("grant_type=client_credentials&scope=basic"), proofs).await
+        }
+
+        async fn exchange_token_body_with_proofs(router: Router, client: (&str, &str), body: String, proofs: &[String]) -> Response<Body> {
+            let mut request = Request::builder()
+                .method(Method::POST)
+                .uri(TOKEN_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth(client.0, client.1))
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED);
+            for proof in proofs {
+                request = request.header(DPOP_HEADER, proof);
+            }
+            let request = assert_ok!(request.body(Body::from(body)));
+            assert_ok!(router.oneshot(request).await)
+        }
+
+        #[tokio::test]
+        async fn should_bind_the_access_token_to_the_proof_key() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let router = under_test!(access_token_repository.clone(), InMemoryTokenRepository::new());
+
+            let response = exchange_token_with_proofs(router, (TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD), &[new_proof("POST", TEST_TOKEN_URI)]).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("token_type"), "DPoP");
+
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+            let access_token: AccessToken = assert_some!(access_token_repository.get_token(access_token_id));
+            assert_eq!(access_token.confirmation, Some(Confirmation { jkt: test_proof_thumbprint() }));
+        }
+
+        #[tokio::test]
+        async fn should_include_the_confirmation_in_a_jwt_access_token() {
+            let router = under_test!();
+
+            let response = exchange_token_with_proofs(router, ("dingo", "echidna"), &[new_proof("POST", TEST_TOKEN_URI)]).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            let claims: AccessTokenClaims = assert_ok!(jsonwebtoken::dangerous::insecure_decode(assert_some!(body["access_token"].as_str()))).claims;
+            assert_eq!(claims.cnf, Some(Confirmation { jkt: test_proof_thumbprint() }));
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_replayed_proof() {
+            let router = under_test!();
+            let proof = new_proof("POST", TEST_TOKEN_URI);
+
+            let response = exchange_token_with_proofs(router.clone(), (TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD), std::slice::from_ref(&proof)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = exchange_token_with_proofs(router, (TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD), &[proof]).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("error"), "invalid_dpop_proof");
+            assert_some_eq_x!(body.get("error_description"), "proof has already been used");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_proof_for_another_endpoint() {
+            let router = under_test!();
+
+            let response = exchange_token_with_proofs(router, (TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD), &[new_proof("POST", "http://127.0.0.1:8080/introspect")]).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("error"), "invalid_dpop_proof");
+        }
+
+        #[tokio::test]
+        async fn should_reject_multiple_proofs() {
+            let router = under_test!();
+
+            let proofs = [new_proof("POST", TEST_TOKEN_URI), new_proof("POST", TEST_TOKEN_URI)];
+            let response = exchange_token_with_proofs(router, (TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD), &proofs).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("error"), "invalid_dpop_proof");
+            assert_some_eq_x!(body.get("error_description"), "multiple proofs");
+        }
+
+        fn new_bound_refresh_token() -> RefreshToken {
+            RefreshToken {
+                confirmation: Some(Confirmation { jkt: test_proof_thumbprint() }),
+                ..new_refresh_token(TEST_CLIENT_USERNAME)
+            }
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_bound_refresh_token_without_a_proof() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_bound_refresh_token();
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository);
+
+            let response = exchange_token(router, format!("grant_type=refresh_token&refresh_token={}", refresh_token.id)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("error"), "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_accept_a_bound_refresh_token_with_a_proof_from_the_same_key() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_bound_refresh_token();
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository);
+
+            let body = format!("grant_type=refresh_token&refresh_token={}", refresh_token.id);
+            let response = exchange_token_body_with_proofs(router, (TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD), body, &[new_proof("POST", TEST_TOKEN_URI)]).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("token_type"), "DPoP");
+        }
+    }
+
+    mod jwt_access_token {
+        use super::*;
+        use crate::key::InMemoryKeyStore;
+        use crate::token::jwt::{find_access_token, ACCESS_TOKEN_TYPE};
+
+        #[tokio::test]
+        async fn should_issue_a_signed_access_token_for_a_client_configured_for_jwts() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let router = under_test!(access_token_repository.clone(), InMemoryTokenRepository::new());
+
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(TOKEN_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth("dingo", "echidna"))
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from("grant_type=client_credentials&scope=basic"))
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            let jwt = assert_some!(body["access_token"].as_str());
+
+            let header = assert_ok!(jsonwebtoken::decode_header(jwt));
+            assert_eq!(header.typ.as_deref(), Some(ACCESS_TOKEN_TYPE));
+
+            let access_token = assert_some!(find_access_token(TEST_ISSUER, &InMemoryKeyStore::new_test_store(), &access_token_repository, jwt));
+            assert_eq!(access_token.client_id, crate::client::ClientId::from(String::from("dingo")));
+        }
+
+        #[tokio::test]
+        async fn should_issue_an_opaque_access_token_by_default() {
+            let response = exchange_token(under_test!(), String::from("grant_type=client_credentials&scope=basic")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+        }
+    }
+
+    mod openid_connect {
+        use super::*;
+        use chrono::{TimeDelta, Utc};
+        use jsonwebtoken::{decode, decode_header, Validation};
+        use crate::key::KeyStore;
+        use crate::openid::{access_token_hash, IdTokenClaims, ID_TOKEN_TYPE, PASSWORD_ACR};
+        use crate::scope::{Scope, Scopes};
+
+        const REDIRECT_URI: &str = "https%3A%2F%2Fredirect.baconi.co.uk";
+
+        fn decode_id_token(body: &HashMap<String, Value>) -> IdTokenClaims {
+            let id_token = assert_some!(body["id_token"].as_str());
+            assert_eq!(assert_ok!(decode_header(id_token)).typ.as_deref(), Some(ID_TOKEN_TYPE));
+
+            let signing_key = assert_some!(crate::key::InMemoryKeyStore::new_test_store().signing_key());
+            let mut validation = Validation::new(signing_key.algorithm);
+            validation.set_audience(&[TEST_CLIENT_USERNAME]);
+            validation.set_issuer(&[TEST_ISSUER]);
+
+            assert_ok!(decode::<IdTokenClaims>(id_token, signing_key.decoding_key(), &validation)).claims
+        }
+
+        #[tokio::test]
+        async fn should_issue_an_id_token_for_an_authorization_code_with_the_openid_scope() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let authorization_code = AuthorizationCode::new(
+                crate::client::ClientId::from(String::from(TEST_CLIENT_USERNAME)),
+                crate::user::Username::from(String::from(TEST_USER_USERNAME)),
+                String::from("https://redirect.baconi.co.uk"),
+                Scopes(std::collections::HashSet::from([Scope::OPENID])),
+                None,
+                None,
+                Some(String::from("cicada")),
+            );
+            authorization_code_repository.save_token(&authorization_code);
+
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository);
+
+            let response = exchange_token(router, format!("grant_type=authorization_code&code={}&redirect_uri={REDIRECT_URI}", authorization_code.id)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            let claims = decode_id_token(&body);
+
+            assert_eq!(claims.sub, TEST_USER_USERNAME);
+            assert_eq!(claims.aud, TEST_CLIENT_USERNAME);
+            assert_eq!(claims.nonce.as_deref(), Some("cicada"));
+            assert_eq!(claims.auth_time, authorization_code.auth_time.timestamp());
+            assert_eq!(claims.acr, PASSWORD_ACR);
+            assert_eq!(claims.at_hash, access_token_hash(jsonwebtoken::Algorithm::RS256, assert_some!(body["access_token"].as_str())));
+        }
+
+        #[tokio::test]
+        async fn should_issue_an_id_token_for_a_password_grant_with_the_openid_scope() {
+            let response = exchange_token(under_test!(), format!("grant_type=password&scope=openid&username={TEST_USER_USERNAME}&password={TEST_USER_PASSWORD}")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let claims = decode_id_token(&extract_json_body(response).await);
+            assert_eq!(claims.sub, TEST_USER_USERNAME);
+            assert_none!(claims.nonce);
+        }
+
+        #[tokio::test]
+        async fn should_not_issue_an_id_token_without_the_openid_scope() {
+            let response = exchange_token(under_test!(), format!("grant_type=password&scope=basic&username={TEST_USER_USERNAME}&password={TEST_USER_PASSWORD}")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_none!(body.get("id_token"));
+        }
+
+        #[tokio::test]
+        async fn should_not_issue_an_id_token_without_a_resource_owner() {
+            let response = exchange_token(under_test!(), String::from("grant_type=client_credentials&scope=openid")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_none!(body.get("id_token"));
+        }
+
+        #[tokio::test]
+        async fn should_keep_the_original_auth_time_when_refreshed() {
+            let auth_time = Utc::now() - TimeDelta::days(1);
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = RefreshToken::new(
+                crate::client::ClientId::from(String::from(TEST_CLIENT_USERNAME)),
+                crate::user::Username::from(String::from(TEST_USER_USERNAME)),
+                Scopes(std::collections::HashSet::from([Scope::OPENID])),
+                auth_time,
+            );
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository);
+
+            let response = exchange_token(router, format!("grant_type=refresh_token&refresh_token={}", refresh_token.id)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let claims = decode_id_token(&extract_json_body(response).await);
+            assert_eq!(claims.auth_time, auth_time.timestamp());
+            assert_none!(claims.nonce);
+        }
+    }
+
+    mod refresh_token_grant {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_rotate_the_refresh_token() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token(TEST_CLIENT_USERNAME);
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository.clone());
+
+            let response = exchange_token(router.clone(), format!("grant_type=refresh_token&refresh_token={}", refresh_token.id)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            let rotated_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["refresh_token"].as_str())));
+            assert_ne!(rotated_id, refresh_token.id);
+            assert_none!(refresh_token_repository.get_token(refresh_token.id));
+
+            let rotated = assert_some!(refresh_token_repository.get_token(rotated_id));
+            assert_eq!(rotated.scopes, refresh_token.scopes);
+            assert_eq!(rotated.expires_at, refresh_token.expires_at);
+
+            let replayed = exchange_token(router, format!("grant_type=refresh_token&refresh_token={}", refresh_token.id)).await;
+            assert_eq!(replayed.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(replayed).await;
+            assert_eq!(body["error"], "invalid_grant");
+        }
+
+        #[tokio::test]
+        async fn should_keep_the_original_scope_when_not_requested() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token(TEST_CLIENT_USERNAME);
+            refresh_token_repository.save_token(&refresh_token);
+
+            let access_token_repository = InMemoryTokenRepository::new();
+            let router = under_test!(access_token_repository.clone(), refresh_token_repository);
+
+            let response = exchange_token(router, format!("grant_type=refresh_token&refresh_token={}", refresh_token.id)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+            let access_token: AccessToken = assert_some!(access_token_repository.get_token(access_token_id));
+            assert_eq!(access_token.scopes, refresh_token.scopes);
+            assert_eq!(access_token.username, Some(refresh_token.username));
+        }
+
+        #[tokio::test]
+        async fn should_reject_widening_the_scope() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token(TEST_CLIENT_USERNAME);
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository.clone());
+
+            let response = exchange_token(router, format!("grant_type=refresh_token&refresh_token={}&scope=basic%20write", refresh_token.id)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_scope");
+            assert_some!(refresh_token_repository.get_token(refresh_token.id));
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_refresh_token_issued_to_another_client() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token("badger");
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository.clone());
+
+            let response = exchange_token(router, format!("grant_type=refresh_token&refresh_token={}", refresh_token.id)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_grant");
+            assert_some!(refresh_token_repository.get_token(refresh_token.id));
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unknown_refresh_token() {
+            let router = under_test!();
+
+            let response = exchange_token(router, format!("grant_type=refresh_token&refresh_token={}", uuid::Uuid::new_v4())).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_grant");
+        }
+    }
+
+    mod success_token_request {
+        use super::*;
+        use crate::trusted_issuer::test_support::{new_claims, sign_assertion};
+
+        #[tokio::test]
+        async fn should_return_ok_for_valid_password_grants() {
+            let router = under_test!();
+
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(TOKEN_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from(format!("grant_type=password&username={TEST_USER_USERNAME}&password={TEST_USER_PASSWORD}&scope=basic")))
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some!(body.get("access_token"));
+            assert_some!(body.get("refresh_token"));
+            assert_some_eq_x!(body.get("token_type"), "bearer");
+            assert_some_eq_x!(body.get("expires_in"), 7200);
+            assert_some_eq_x!(body.get("scope"), "basic");
+            assert_none!(body.get("state"));
+        }
+
+        #[tokio::test]
+        async fn should_store_the_issued_access_token_for_valid_password_grants() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let router = under_test!(access_token_repository.clone(), refresh_token_repository.clone());
+
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(TOKEN_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from(format!("grant_type=password&username={TEST_USER_USERNAME}&password={TEST_USER_PASSWORD}&scope=basic")))
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+
+            let access_token = assert_some!(access_token_repository.get_token(access_token_id));
+            assert_eq!(access_token.client_id, crate::client::ClientId::from(String::from(TEST_CLIENT_USERNAME)));
+            assert_eq!(access_token.username, Some(crate::user::Username::from(String::from(TEST_USER_USERNAME))));
+            assert_eq!(access_token.scopes, crate::scope::Scopes(std::collections::HashSet::from([crate::scope::Scope::from("basic")])));
+            assert_eq!(access_token.expires_at - access_token.issued_at, AccessToken::TIME_TO_LIVE);
+
+            let refresh_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["refresh_token"].as_str())));
+
+            let refresh_token = assert_some!(refresh_token_repository.get_token(refresh_token_id));
+            assert_eq!(refresh_token.client_id, access_token.client_id);
+            assert_eq!(Some(refresh_token.username), access_token.username);
+            assert_eq!(refresh_token.scopes, access_token.scopes);
+        }
+
+        #[tokio::test]
+        async fn should_return_ok_for_valid_authorization_code_grants() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let authorization_code = new_authorization_code(TEST_CLIENT_USERNAME);
+            authorization_code_repository.save_token(&authorization_code);
+
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository);
+
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(TOKEN_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from(format!("grant_type=authorization_code&code={}&redirect_uri=https%3A%2F%2Fredirect.baconi.co.uk", authorization_code.id)))
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some!(body.get("access_token"));
+            assert_some!(body.get("refresh_token"));
+            assert_some_eq_x!(body.get("token_type"), "bearer");
+            assert_some_eq_x!(body.get("expires_in"), 7200);
+            assert_some_eq_x!(body.get("scope"), "basic");
+            assert_some_eq_x!(body.get("state"), "aardvark");
+        }
+
+        #[tokio::test]
+        async fn should_return_ok_for_valid_refresh_token_grant() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token(TEST_CLIENT_USERNAME);
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository);
+
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(TOKEN_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from(format!("grant_type=refresh_token&refresh_token={}&scope=basic", refresh_token.id)))
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some!(body.get("access_token"));
+            assert_some!(body.get("refresh_token"));
+            assert_some_eq_x!(body.get("token_type"), "bearer");
+            assert_some_eq_x!(body.get("expires_in"), 7200);
+            assert_some_eq_x!(body.get("scope"), "basic");
+        }
+
+        #[tokio::test]
+        async fn should_return_ok_for_valid_assertion_grant() {
+            let router = under_test!();
+            let assertion = sign_assertion(&new_claims("aardvark@iguana", TEST_ISSUER, "iguana:basic"));
+
+            let request = assert_ok!(
+                Request::builder()
+                .method(Method::POST)
+                .uri(TOKEN_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from(format!("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer&assertion={assertion}")))
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some!(body.get("access_token"));
+            assert_some!(body.get("refresh_token"));
+            assert_some_eq_x!(body.get("token_type"), "bearer");
+            assert_some_eq_x!(body.get("expires_in"), 7200);
+            assert_some_eq_x!(body.get("scope"), "basic");
+        }
+    }
+
+    mod resource_indicator {
+        use super::*;
+
+        const TEST_RESOURCE: &str = "https%3A%2F%2Fapi.baconi.co.uk";
+        const REDIRECT_URI: &str = "https%3A%2F%2Fredirect.baconi.co.uk";
+
+        fn issued_access_token(access_token_repository: &InMemoryTokenRepository<AccessToken>, body: &HashMap<String, Value>) -> AccessToken {
+            let access_token_id = assert_ok!(uuid::Uuid::parse_str(assert_some!(body["access_token"].as_str())));
+            assert_some!(access_token_repository.get_token(access_token_id))
+        }
+
+        #[tokio::test]
+        async fn should_restrict_the_access_token_to_the_resource_and_the_scopes_it_understands() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let router = under_test!(access_token_repository.clone(), InMemoryTokenRepository::new());
+
+            let response = exchange_token(router, format!("grant_type=client_credentials&scope=basic%20email&resource={TEST_RESOURCE}")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("scope"), "basic");
+            assert_eq!(issued_access_token(&access_token_repository, &body).audience, Some(String::from("https://api.baconi.co.uk")));
+        }
+
+        #[tokio::test]
+        async fn should_reject_scopes_the_resource_does_not_understand() {
+            let response = exchange_token(under_test!(), format!("grant_type=client_credentials&scope=email&resource={TEST_RESOURCE}")).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_scope");
+        }
+
+        #[tokio::test]
+        async fn should_reject_an_unregistered_resource() {
+            let response = exchange_token(under_test!(), String::from("grant_type=client_credentials&scope=basic&resource=https%3A%2F%2Fexample.com")).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_target");
+            assert_eq!(body["error_description"], "invalid parameter: resource");
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_resource_the_client_may_not_target() {
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(TOKEN_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth("dingo", "echidna"))
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from(format!("grant_type=client_credentials&scope=basic&resource={TEST_RESOURCE}")))
+            );
+            let response = assert_ok!(under_test!().oneshot(request).await);
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_target");
+        }
+
+        #[tokio::test]
+        async fn should_keep_the_resource_the_authorization_code_was_issued_for() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let authorization_code = AuthorizationCode {
+                resource: Some(String::from("https://api.baconi.co.uk")),
+                ..new_authorization_code(TEST_CLIENT_USERNAME)
+            };
+            authorization_code_repository.save_token(&authorization_code);
+
+            let router = under_test!(access_token_repository.clone(), InMemoryTokenRepository::new(), authorization_code_repository);
+
+            let response = exchange_token(router, format!("grant_type=authorization_code&code={}&redirect_uri={REDIRECT_URI}", authorization_code.id)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(issued_access_token(&access_token_repository, &body).audience, Some(String::from("https://api.baconi.co.uk")));
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_different_resource_to_the_one_the_authorization_code_was_issued_for() {
+            let authorization_code_repository = InMemoryTokenRepository::new();
+            let authorization_code = AuthorizationCode {
+                resource: Some(String::from("https://api.baconi.co.uk")),
+                ..new_authorization_code(TEST_CLIENT_USERNAME)
+            };
+            authorization_code_repository.save_token(&authorization_code);
+
+            let router = under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new(), authorization_code_repository);
+
+            let response = exchange_token(router, format!("grant_type=authorization_code&code={}&redirect_uri={REDIRECT_URI}&resource=https%3A%2F%2Fexample.com", authorization_code.id)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_target");
+        }
+
+        #[tokio::test]
+        async fn should_keep_the_resource_through_a_refresh() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = RefreshToken {
+                resource: Some(String::from("https://api.baconi.co.uk")),
+                ..new_refresh_token(TEST_CLIENT_USERNAME)
+            };
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(access_token_repository.clone(), refresh_token_repository);
+
+            let response = exchange_token(router, format!("grant_type=refresh_token&refresh_token={}", refresh_token.id)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(issued_access_token(&access_token_repository, &body).audience, Some(String::from("https://api.baconi.co.uk")));
+        }
+
+        #[tokio::test]
+        async fn should_reject_a_different_resource_to_the_one_the_refresh_token_was_issued_for() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = RefreshToken {
+                resource: Some(String::from("https://api.baconi.co.uk")),
+                ..new_refresh_token(TEST_CLIENT_USERNAME)
+            };
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository);
+
+            let response = exchange_token(router, format!("grant_type=refresh_token&refresh_token={}&resource=https%3A%2F%2Fexample.com", refresh_token.id)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_target");
+        }
+    }
+}
@@ -1,4 +1,5 @@
 mod route;
-mod middleware;
+mod request;
+mod response;
 
 pub use route::*;
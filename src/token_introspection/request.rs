@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use axum::extract::{FromRequest, Request};
+use axum::extract::rejection::FormRejection;
+use axum::{Form, Json};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use crate::client::ConfidentialClient;
+use crate::token::TokenTypeHint;
+use crate::token_introspection::response::{ErrorType, TokenIntrospectionResponse};
+
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct TokenIntrospectionRequest {
+    pub principal: ConfidentialClient,
+    pub token: String,
+    pub token_type_hint: Option<TokenTypeHint>,
+}
+
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct TokenIntrospectionForm(pub TokenIntrospectionRequest);
+
+// The request is a URL encoded form, but the responses are JSON.
+impl<S> FromRequest<S> for TokenIntrospectionForm
+where
+    S: Send + Sync,
+    Form<HashMap<String, String>>: FromRequest<S, Rejection = FormRejection>,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+
+        let principal = req.extensions()
+            .get::<ConfidentialClient>()
+            .cloned()
+            .ok_or_else(|| handle_validation_failure(TokenIntrospectionResponse::Failure {
+                error: ErrorType::InvalidRequest,
+                error_description: Some("missing client authentication".into()),
+            }))?;
+
+        match Form::<HashMap<String, String>>::from_request(req, state).await {
+            Err(rejection) => Err(handle_form_rejection(rejection)),
+            Ok(Form(request)) => match validate_introspection_request(principal, request) {
+                Err(failure) => Err(handle_validation_failure(failure)),
+                Ok(valid) => Ok(TokenIntrospectionForm(valid)),
+            }
+        }
+    }
+}
+
+pub fn validate_introspection_request(principal: ConfidentialClient, request: HashMap<String, String>) -> Result<TokenIntrospectionRequest, TokenIntrospectionResponse> {
+
+    let token = match request.get("token") {
+        None => Err(TokenIntrospectionResponse::missing_parameter("token"))?,
+        Some(token) if token.trim().is_empty() => Err(TokenIntrospectionResponse::invalid_parameter("token"))?,
+        Some(token) => token,
+    };
+
+    let token_type_hint = match request.get("token_type_hint").map(|hint| hint.parse::<TokenTypeHint>()) {
+        None => None,
+        Some(Err(_)) => Err(TokenIntrospectionResponse::invalid_parameter("token_type_hint"))?,
+        Some(Ok(hint)) => Some(hint),
+    };
+
+    Ok(TokenIntrospectionRequest {
+        principal,
+        token: token.into(),
+        token_type_hint,
+    })
+}
+
+fn handle_validation_failure(failure: TokenIntrospectionResponse) -> Response {
+    (StatusCode::BAD_REQUEST, Json(failure)).into_response()
+}
+
+fn handle_form_rejection(rejection: FormRejection) -> Response {
+    (rejection.status(), Json(TokenIntrospectionResponse::Failure {
+        error: ErrorType::InvalidRequest,
+        error_description: Some(rejection.body_text()),
+    })).into_response()
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use crate::client::ClientPrincipal;
+    use crate::map_of;
+
+    #[test]
+    fn should_return_invalid_request_on_missing_token() {
+        let result = validate_introspection_request(
+            ClientPrincipal::new_confidential_client("aardvark"),
+            map_of! {},
+        );
+
+        assert_eq!(assert_err!(result), TokenIntrospectionResponse::Failure {
+            error: ErrorType::InvalidRequest,
+            error_description: Some("missing parameter: token".into()),
+        });
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_blank_token() {
+        let result = validate_introspection_request(
+            ClientPrincipal::new_confidential_client("aardvark"),
+            map_of! { "token" => " " },
+        );
+
+        assert_eq!(assert_err!(result), TokenIntrospectionResponse::Failure {
+            error: ErrorType::InvalidRequest,
+            error_description: Some("invalid parameter: token".into()),
+        });
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_unsupported_token_type_hint() {
+        let result = validate_introspection_request(
+            ClientPrincipal::new_confidential_client("aardvark"),
+            map_of! { "token" => "aardvark", "token_type_hint" => "badger" },
+        );
+
+        assert_eq!(assert_err!(result), TokenIntrospectionResponse::Failure {
+            error: ErrorType::InvalidRequest,
+            error_description: Some("invalid parameter: token_type_hint".into()),
+        });
+    }
+
+    #[test]
+    fn should_return_valid_request_without_a_token_type_hint() {
+        let result = validate_introspection_request(
+            ClientPrincipal::new_confidential_client("aardvark"),
+            map_of! { "token" => "aardvark" },
+        );
+
+        assert_eq!(assert_ok!(result), TokenIntrospectionRequest {
+            principal: ClientPrincipal::new_confidential_client("aardvark"),
+            token: "aardvark".into(),
+            token_type_hint: None,
+        });
+    }
+
+    #[test]
+    fn should_return_valid_request_with_a_token_type_hint() {
+        let result = validate_introspection_request(
+            ClientPrincipal::new_confidential_client("aardvark"),
+            map_of! { "token" => "aardvark", "token_type_hint" => "access_token" },
+        );
+
+        assert_eq!(assert_ok!(result), TokenIntrospectionRequest {
+            principal: ClientPrincipal::new_confidential_client("aardvark"),
+            token: "aardvark".into(),
+            token_type_hint: Some(TokenTypeHint::AccessToken),
+        });
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+use uuid::Uuid;
+use crate::client::ClientId;
+use crate::scope::Scopes;
+use crate::token::{AccessToken, Actor, Confirmation, TokenType};
+use crate::user::Username;
+
+// https://www.rfc-editor.org/rfc/rfc7662#section-2.2
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum TokenIntrospectionResponse {
+
+    Active(Box<ActiveToken>),
+    Inactive {
+
+        // If the introspection call is properly authorized but the token is not active, does
+        // not exist on this server, or the protected resource is not allowed to introspect
+        // this particular token, then the authorization server MUST return an introspection
+        // response with the "active" field set to "false".
+        active: bool,
+    },
+    Failure {
+
+        // A single ASCII error code from the defined list.
+        error: ErrorType,
+
+        // Description Human-readable ASCII text providing additional information, used
+        // to assist the client developer in understanding the error that occurred.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_description: Option<String>,
+    }
+}
+
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+pub struct ActiveToken {
+
+    // Boolean indicator of whether the presented token is currently active.
+    active: bool,
+
+    // A space-separated list of scopes associated with this token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<Scopes>,
+
+    // Client identifier for the OAuth 2.0 client that requested this token.
+    client_id: ClientId,
+
+    // Human-readable identifier for the resource owner who authorized this token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<Username>,
+
+    // Type of the token as defined in https://www.rfc-editor.org/rfc/rfc6749#section-5.1
+    token_type: TokenType,
+
+    // Integer timestamp, measured in the number of seconds since January 1 1970 UTC,
+    // indicating when this token will expire.
+    exp: i64,
+
+    // Integer timestamp, measured in the number of seconds since January 1 1970 UTC,
+    // indicating when this token was originally issued.
+    iat: i64,
+
+    // Integer timestamp, measured in the number of seconds since January 1 1970 UTC,
+    // indicating when this token is not to be used before.
+    nbf: i64,
+
+    // Subject of the token, usually a machine-readable identifier of the resource owner
+    // who authorized this token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<Username>,
+
+    // Service-specific string identifier or list of string identifiers representing the
+    // intended audience for this token.
+    aud: String,
+
+    // String representing the issuer of this token.
+    iss: String,
+
+    // String identifier for the token.
+    jti: Uuid,
+
+    // The party acting on behalf of the subject, for a token issued by a delegation token exchange.
+    // https://www.rfc-editor.org/rfc/rfc8693#section-4.1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    act: Option<Actor>,
+
+    // The thumbprint of the key the token is bound to, for a token issued with a DPoP proof.
+    // https://www.rfc-editor.org/rfc/rfc9449#section-6.2
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cnf: Option<Confirmation>,
+}
+
+impl TokenIntrospectionResponse {
+
+    pub fn active(issuer: &str, access_token: AccessToken) -> Self {
+        let aud = access_token.audience().into();
+        let token_type = access_token.token_type();
+        TokenIntrospectionResponse::Active(Box::new(ActiveToken {
+            active: true,
+            scope: Some(access_token.scopes).filter(|Scopes(scopes)| !scopes.is_empty()),
+            client_id: access_token.client_id.clone(),
+            username: access_token.username.clone(),
+            token_type,
+            exp: access_token.expires_at.timestamp(),
+            iat: access_token.issued_at.timestamp(),
+            nbf: access_token.issued_at.timestamp(),
+            sub: access_token.username,
+            aud,
+            iss: issuer.into(),
+            jti: access_token.id,
+            act: access_token.actor,
+            cnf: access_token.confirmation,
+        }))
+    }
+
+    pub fn inactive() -> Self {
+        TokenIntrospectionResponse::Inactive {
+            active: false,
+        }
+    }
+
+    pub fn missing_parameter(parameter: &str) -> Self {
+        TokenIntrospectionResponse::Failure {
+            error: ErrorType::InvalidRequest,
+            error_description: Some(format!("missing parameter: {parameter}")),
+        }
+    }
+
+    pub fn invalid_parameter(parameter: &str) -> Self {
+        TokenIntrospectionResponse::Failure {
+            error: ErrorType::InvalidRequest,
+            error_description: Some(format!("invalid parameter: {parameter}")),
+        }
+    }
+}
+
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorType {
+
+    // The request is missing a required parameter, includes an
+    // unsupported parameter value, repeats a parameter, or is otherwise malformed.
+    InvalidRequest,
+}
@@ -1,24 +1,33 @@
 use axum::http::StatusCode;
-use axum::{middleware, Extension, Json, Router};
+use axum::{middleware, Json, Router};
 use axum::extract::State;
 use axum::routing::post;
 use middleware::from_fn_with_state;
-use serde::Serialize;
 use tower::ServiceBuilder;
 use crate::client::authentication::ClientAuthenticator;
-use crate::client::{ClientAction, ConfidentialClient};
-use crate::client::middleware::require_confidential_client_authentication;
+use crate::client::{ClientAction, ClientId};
+use crate::client::middleware::{require_confidential_client_action, require_confidential_client_authentication};
+use crate::key::KeyStore;
+use crate::resource::ProtectedResourceRepository;
 use crate::token::AccessToken;
+use crate::token::jwt::find_access_token;
 use crate::token::repository::TokenRepository;
-use crate::token_introspection::middleware::require_confidential_client_action;
+use crate::token_introspection::request::TokenIntrospectionForm;
+use crate::token_introspection::response::TokenIntrospectionResponse;
+use crate::util::value_struct::ValueStruct;
 
-pub fn route<S, A, C>(state: TokenIntrospectionState<A, C>) -> Router<S>
+pub const INTROSPECTION_ENDPOINT: &str = "/introspect";
+
+// https://www.rfc-editor.org/rfc/rfc7662#section-2
+pub fn route<S, A, C, K, P>(state: TokenIntrospectionState<A, C, K, P>) -> Router<S>
 where
     A: TokenRepository<AccessToken> + 'static,
-    C: ClientAuthenticator + 'static
+    C: ClientAuthenticator + 'static,
+    K: KeyStore + 'static,
+    P: ProtectedResourceRepository + 'static,
 {
     Router::new()
-        .route("/introspect", post(token_introspection_handler))
+        .route(INTROSPECTION_ENDPOINT, post(token_introspection_handler))
         .route_layer(
             ServiceBuilder::new()
                 .layer(from_fn_with_state(state.client_authenticator.clone(), require_confidential_client_authentication::<C>))
@@ -28,26 +37,414 @@ where
 }
 
 #[derive(Clone)]
-pub struct TokenIntrospectionState<A: TokenRepository<AccessToken>, C: ClientAuthenticator> {
+pub struct TokenIntrospectionState<A: TokenRepository<AccessToken>, C: ClientAuthenticator, K: KeyStore, P: ProtectedResourceRepository> {
+    pub issuer: String,
     pub access_token_repository: A,
     pub client_authenticator: C,
+    pub key_store: K,
+    pub protected_resource_repository: P,
+}
+
+impl<A: TokenRepository<AccessToken>, C: ClientAuthenticator, K: KeyStore, P: ProtectedResourceRepository> TokenIntrospectionState<A, C, K, P> {
+
+    // Tokens without an audience can be introspected by any client, otherwise only by the audience or the servers of that resource.
+    // https://www.rfc-editor.org/rfc/rfc8707#section-2
+    fn is_audience(&self, access_token: &AccessToken, client_id: &ClientId) -> bool {
+        match &access_token.audience {
+            None => true,
+            Some(audience) if audience == client_id.value() => true,
+            Some(audience) => self.protected_resource_repository.find_by_uri(audience)
+                .is_some_and(|resource| resource.is_served_by(client_id)),
+        }
+    }
 }
 
-async fn token_introspection_handler<A : TokenRepository<AccessToken>, C: ClientAuthenticator>(
-    State(state): State<TokenIntrospectionState<A, C>>,
-    Extension(client) : Extension<ConfidentialClient>,
+async fn token_introspection_handler<A : TokenRepository<AccessToken>, C: ClientAuthenticator, K: KeyStore, P: ProtectedResourceRepository>(
+    State(state): State<TokenIntrospectionState<A, C, K, P>>,
+    TokenIntrospectionForm(request): TokenIntrospectionForm,
 ) -> (StatusCode, Json<TokenIntrospectionResponse>) {
 
-    // TODO - Validate request
-    // TODO - Actually implement
+    let maybe_access_token = find_access_token(&state.issuer, &state.key_store, &state.access_token_repository, &request.token)
+        .filter(|access_token| state.is_audience(access_token, request.principal.id()));
 
-    match state.access_token_repository.get_token(uuid::Uuid::new_v4()) {
-        Some(_) => (StatusCode::OK, Json(TokenIntrospectionResponse { active: true })),
-        None => (StatusCode::OK, Json(TokenIntrospectionResponse { active: false })),
+    match maybe_access_token {
+        Some(access_token) => (StatusCode::OK, Json(TokenIntrospectionResponse::active(&state.issuer, access_token))),
+        None => (StatusCode::OK, Json(TokenIntrospectionResponse::inactive())),
     }
 }
 
-#[derive(Serialize)]
-struct TokenIntrospectionResponse {
-    active: bool,
+#[cfg(test)]
+mod integration_tests {
+
+    use super::*;
+
+    use assertables::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request, Response};
+    use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+    use chrono::{TimeDelta, Utc};
+    use http_body_util::BodyExt;
+    use std::collections::{HashMap, HashSet};
+    use base64::prelude::*;
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+    use crate::client::ClientId;
+    use crate::scope::{Scope, Scopes};
+    use crate::token::repository::InMemoryTokenRepository;
+    use crate::user::Username;
+
+    const APPLICATION_WWW_FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
+    const TEST_CLIENT_USERNAME: &str = "aardvark";
+    const TEST_CLIENT_PASSWORD: &str = "badger";
+
+    macro_rules! under_test {
+        () => {
+            under_test!(InMemoryTokenRepository::new())
+        };
+        ($access_token_repository:expr) => {
+            route::<(), _, _, _, _>(TokenIntrospectionState {
+                issuer: TEST_ISSUER.into(),
+                access_token_repository: $access_token_repository,
+                client_authenticator: crate::client::authentication::ClientAuthenticationService::new(
+                    TEST_ISSUER.into(),
+                    crate::client::secret::InMemoryClientSecretRepository::new(),
+                    crate::client::configuration::InMemoryClientConfigurationRepository::new(),
+                    crate::replay::InMemoryReplayCache::new(),
+                ),
+                key_store: crate::key::InMemoryKeyStore::new_test_store(),
+                protected_resource_repository: crate::resource::InMemoryProtectedResourceRepository::new(),
+            })
+        };
+    }
+
+    async fn extract_json_body(response: Response<Body>) -> HashMap<String, Value> {
+        let body_bytes = assert_ok!(response.into_body().collect().await);
+        assert_ok!(serde_json::from_slice(body_bytes.to_bytes().as_ref()))
+    }
+
+    fn basic_auth(username: &str, password: &str) -> String {
+        format!("Basic {}", BASE64_STANDARD.encode(format!("{}:{}", username, password)))
+    }
+
+    fn introspection_request(body: String) -> Request<Body> {
+        assert_ok!(Request::builder()
+            .method(Method::POST)
+            .uri(INTROSPECTION_ENDPOINT)
+            .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+            .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+            .body(Body::from(body))
+        )
+    }
+
+    // Dingo serves https://api.baconi.co.uk, so can introspect the tokens issued for it.
+    fn resource_server_introspection_request(body: String) -> Request<Body> {
+        assert_ok!(Request::builder()
+            .method(Method::POST)
+            .uri(INTROSPECTION_ENDPOINT)
+            .header(AUTHORIZATION, basic_auth("dingo", "echidna"))
+            .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+            .body(Body::from(body))
+        )
+    }
+
+    fn new_access_token() -> AccessToken {
+        AccessToken::new(
+            ClientId::from(String::from("aardvark")),
+            Some(Username::from(String::from("badger"))),
+            Scopes(HashSet::from([Scope::from("basic")])),
+            None,
+        )
+    }
+
+    mod invalid_http_request {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_not_support_http_method_get() {
+            let router = under_test!();
+
+            let request = assert_ok!(Request::builder()
+                .method(Method::GET)
+                .uri(INTROSPECTION_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+                .body(Body::empty())
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+
+            assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        }
+
+        #[tokio::test]
+        async fn should_require_client_authentication() {
+            let router = under_test!();
+
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(INTROSPECTION_ENDPOINT)
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from("token=aardvark"))
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        #[tokio::test]
+        async fn should_accept_a_client_assertion() {
+            use crate::assertion::CLIENT_ASSERTION_TYPE;
+            use crate::assertion::test_support::{new_claims, sign_private_key_jwt};
+
+            let router = under_test!();
+
+            let assertion = sign_private_key_jwt(&new_claims("ferret", "http://127.0.0.1:8080/introspect"));
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(INTROSPECTION_ENDPOINT)
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from(format!("token=aardvark&client_assertion_type={CLIENT_ASSERTION_TYPE}&client_assertion={assertion}")))
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+
+    mod invalid_introspection_request {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_return_bad_request_on_missing_token() {
+            let router = under_test!();
+
+            let response = assert_ok!(router.oneshot(introspection_request("token_type_hint=access_token".into())).await);
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_request");
+            assert_eq!(body["error_description"], "missing parameter: token");
+        }
+
+        #[tokio::test]
+        async fn should_return_bad_request_on_unsupported_token_type_hint() {
+            let router = under_test!();
+
+            let response = assert_ok!(router.oneshot(introspection_request("token=aardvark&token_type_hint=badger".into())).await);
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body["error"], "invalid_request");
+            assert_eq!(body["error_description"], "invalid parameter: token_type_hint");
+        }
+    }
+
+    mod inactive_token {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_return_inactive_for_a_malformed_token() {
+            let router = under_test!();
+
+            let response = assert_ok!(router.oneshot(introspection_request("token=aardvark".into())).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body, HashMap::from([(String::from("active"), Value::Bool(false))]));
+        }
+
+        #[tokio::test]
+        async fn should_return_inactive_for_an_unknown_token() {
+            let router = under_test!();
+
+            let response = assert_ok!(router.oneshot(introspection_request(format!("token={}", Uuid::new_v4()))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body, HashMap::from([(String::from("active"), Value::Bool(false))]));
+        }
+
+        #[tokio::test]
+        async fn should_return_inactive_for_an_expired_token() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let access_token = AccessToken {
+                issued_at: Utc::now() - TimeDelta::hours(3),
+                expires_at: Utc::now() - TimeDelta::hours(1),
+                ..new_access_token()
+            };
+            access_token_repository.save_token(&access_token);
+
+            let router = under_test!(access_token_repository);
+
+            let response = assert_ok!(router.oneshot(introspection_request(format!("token={}", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_eq!(body, HashMap::from([(String::from("active"), Value::Bool(false))]));
+        }
+    }
+
+    mod active_token {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_return_active_with_the_token_details() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let access_token = new_access_token();
+            access_token_repository.save_token(&access_token);
+
+            let router = under_test!(access_token_repository);
+
+            let response = assert_ok!(router.oneshot(introspection_request(format!("token={}&token_type_hint=access_token", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("active"), true);
+            assert_some_eq_x!(body.get("scope"), "basic");
+            assert_some_eq_x!(body.get("client_id"), "aardvark");
+            assert_some_eq_x!(body.get("username"), "badger");
+            assert_some_eq_x!(body.get("token_type"), "bearer");
+            assert_some_eq_x!(body.get("exp"), access_token.expires_at.timestamp());
+            assert_some_eq_x!(body.get("iat"), access_token.issued_at.timestamp());
+            assert_some_eq_x!(body.get("nbf"), access_token.issued_at.timestamp());
+            assert_some_eq_x!(body.get("sub"), "badger");
+            assert_some_eq_x!(body.get("aud"), "aardvark");
+            assert_some_eq_x!(body.get("iss"), TEST_ISSUER);
+            assert_some_eq_x!(body.get("jti"), &Value::String(access_token.id.to_string()));
+            assert_none!(body.get("act"));
+            assert_none!(body.get("cnf"));
+        }
+
+        #[tokio::test]
+        async fn should_return_the_audience_and_actor_of_an_exchanged_token() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let access_token = AccessToken {
+                audience: Some("https://api.baconi.co.uk".into()),
+                actor: Some(crate::token::Actor { sub: "dingo".into(), act: None }),
+                ..new_access_token()
+            };
+            access_token_repository.save_token(&access_token);
+
+            let router = under_test!(access_token_repository);
+
+            let response = assert_ok!(router.oneshot(resource_server_introspection_request(format!("token={}", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("aud"), "https://api.baconi.co.uk");
+            assert_some_eq_x!(body.get("act"), &serde_json::json!({ "sub": "dingo" }));
+        }
+
+        #[tokio::test]
+        async fn should_return_inactive_to_a_client_outside_the_audience() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let access_token = AccessToken {
+                audience: Some("https://api.baconi.co.uk".into()),
+                ..new_access_token()
+            };
+            access_token_repository.save_token(&access_token);
+
+            let router = under_test!(access_token_repository);
+
+            let response = assert_ok!(router.oneshot(introspection_request(format!("token={}", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("active"), false);
+            assert_none!(body.get("aud"));
+        }
+
+        #[tokio::test]
+        async fn should_return_active_to_a_client_that_is_the_audience() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let access_token = AccessToken {
+                audience: Some("aardvark".into()),
+                ..new_access_token()
+            };
+            access_token_repository.save_token(&access_token);
+
+            let router = under_test!(access_token_repository);
+
+            let response = assert_ok!(router.oneshot(introspection_request(format!("token={}", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("active"), true);
+        }
+
+        #[tokio::test]
+        async fn should_return_the_confirmation_of_a_dpop_bound_token() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let access_token = AccessToken {
+                confirmation: Some(crate::token::Confirmation { jkt: "aardvark".into() }),
+                ..new_access_token()
+            };
+            access_token_repository.save_token(&access_token);
+
+            let router = under_test!(access_token_repository);
+
+            let response = assert_ok!(router.oneshot(introspection_request(format!("token={}", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("token_type"), "DPoP");
+            assert_some_eq_x!(body.get("cnf"), &serde_json::json!({ "jkt": "aardvark" }));
+        }
+
+        #[tokio::test]
+        async fn should_return_active_for_a_signed_access_token() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let access_token = AccessToken { format: crate::client::AccessTokenFormat::Jwt, ..new_access_token() };
+            access_token_repository.save_token(&access_token);
+
+            let key_store = crate::key::InMemoryKeyStore::new_test_store();
+            let signing_key = assert_some!(crate::key::KeyStore::signing_key(&key_store));
+            let jwt = assert_ok!(crate::token::jwt::encode_access_token(TEST_ISSUER, &access_token, &signing_key));
+
+            let router = under_test!(access_token_repository);
+
+            let response = assert_ok!(router.oneshot(introspection_request(format!("token={jwt}"))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("active"), true);
+            assert_some_eq_x!(body.get("jti"), &Value::String(access_token.id.to_string()));
+        }
+
+        #[tokio::test]
+        async fn should_return_inactive_for_the_bare_id_of_a_signed_access_token() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let access_token = AccessToken { format: crate::client::AccessTokenFormat::Jwt, ..new_access_token() };
+            access_token_repository.save_token(&access_token);
+
+            let router = under_test!(access_token_repository);
+
+            let response = assert_ok!(router.oneshot(introspection_request(format!("token={}", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("active"), false);
+        }
+
+        #[tokio::test]
+        async fn should_omit_the_subject_for_a_client_credentials_token() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let access_token = AccessToken { username: None, ..new_access_token() };
+            access_token_repository.save_token(&access_token);
+
+            let router = under_test!(access_token_repository);
+
+            let response = assert_ok!(router.oneshot(introspection_request(format!("token={}", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("active"), true);
+            assert_some_eq_x!(body.get("client_id"), "aardvark");
+            assert_none!(body.get("username"));
+            assert_none!(body.get("sub"));
+        }
+    }
 }
@@ -0,0 +1,5 @@
+mod route;
+mod request;
+mod response;
+
+pub use route::*;
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use axum::extract::{FromRequest, Request};
+use axum::extract::rejection::FormRejection;
+use axum::{Form, Json};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use crate::client::ClientPrincipal;
+use crate::token::TokenTypeHint;
+use crate::token_revocation::response::{ErrorType, TokenRevocationResponse};
+
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct TokenRevocationRequest {
+    pub principal: ClientPrincipal,
+    pub token: String,
+    pub token_type_hint: Option<TokenTypeHint>,
+}
+
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct TokenRevocationForm(pub TokenRevocationRequest);
+
+// The request is a URL encoded form, but the responses are JSON.
+impl<S> FromRequest<S> for TokenRevocationForm
+where
+    S: Send + Sync,
+    Form<HashMap<String, String>>: FromRequest<S, Rejection = FormRejection>,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+
+        let principal = req.extensions()
+            .get::<ClientPrincipal>()
+            .cloned()
+            .ok_or_else(|| handle_validation_failure(TokenRevocationResponse::Failure {
+                error: ErrorType::InvalidRequest,
+                error_description: Some("missing client authentication".into()),
+            }))?;
+
+        match Form::<HashMap<String, String>>::from_request(req, state).await {
+            Err(rejection) => Err(handle_form_rejection(rejection)),
+            Ok(Form(request)) => match validate_revocation_request(principal, request) {
+                Err(failure) => Err(handle_validation_failure(failure)),
+                Ok(valid) => Ok(TokenRevocationForm(valid)),
+            }
+        }
+    }
+}
+
+pub fn validate_revocation_request(principal: ClientPrincipal, request: HashMap<String, String>) -> Result<TokenRevocationRequest, TokenRevocationResponse> {
+
+    let token = match request.get("token") {
+        None => Err(TokenRevocationResponse::missing_parameter("token"))?,
+        Some(token) if token.trim().is_empty() => Err(TokenRevocationResponse::invalid_parameter("token"))?,
+        Some(token) => token,
+    };
+
+    // https://www.rfc-editor.org/rfc/rfc7009#section-2.2.1
+    let token_type_hint = match request.get("token_type_hint").map(|hint| hint.parse::<TokenTypeHint>()) {
+        None => None,
+        Some(Err(error_message)) => Err(TokenRevocationResponse::Failure {
+            error: ErrorType::UnsupportedTokenType,
+            error_description: Some(error_message),
+        })?,
+        Some(Ok(hint)) => Some(hint),
+    };
+
+    Ok(TokenRevocationRequest {
+        principal,
+        token: token.into(),
+        token_type_hint,
+    })
+}
+
+fn handle_validation_failure(failure: TokenRevocationResponse) -> Response {
+    (StatusCode::BAD_REQUEST, Json(failure)).into_response()
+}
+
+fn handle_form_rejection(rejection: FormRejection) -> Response {
+    (rejection.status(), Json(TokenRevocationResponse::Failure {
+        error: ErrorType::InvalidRequest,
+        error_description: Some(rejection.body_text()),
+    })).into_response()
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use crate::map_of;
+
+    #[test]
+    fn should_return_invalid_request_on_missing_token() {
+        let result = validate_revocation_request(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            map_of! {},
+        );
+
+        assert_eq!(assert_err!(result), TokenRevocationResponse::missing_parameter("token"));
+    }
+
+    #[test]
+    fn should_return_invalid_request_on_blank_token() {
+        let result = validate_revocation_request(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            map_of! { "token" => " " },
+        );
+
+        assert_eq!(assert_err!(result), TokenRevocationResponse::invalid_parameter("token"));
+    }
+
+    #[test]
+    fn should_return_unsupported_token_type_on_unsupported_token_type_hint() {
+        let result = validate_revocation_request(
+            ClientPrincipal::new_confidential_principal("aardvark"),
+            map_of! { "token" => "aardvark", "token_type_hint" => "badger" },
+        );
+
+        assert_eq!(assert_err!(result), TokenRevocationResponse::Failure {
+            error: ErrorType::UnsupportedTokenType,
+            error_description: Some("unsupported: badger".into()),
+        });
+    }
+
+    #[test]
+    fn should_return_valid_request_for_a_public_client() {
+        let result = validate_revocation_request(
+            ClientPrincipal::new_public_principal("badger"),
+            map_of! { "token" => "aardvark", "token_type_hint" => "refresh_token" },
+        );
+
+        assert_eq!(assert_ok!(result), TokenRevocationRequest {
+            principal: ClientPrincipal::new_public_principal("badger"),
+            token: "aardvark".into(),
+            token_type_hint: Some(TokenTypeHint::RefreshToken),
+        });
+    }
+}
@@ -0,0 +1,48 @@
+use serde::Serialize;
+
+// https://www.rfc-editor.org/rfc/rfc7009#section-2.2
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum TokenRevocationResponse {
+    Failure {
+
+        // A single ASCII error code from the defined list.
+        error: ErrorType,
+
+        // Description Human-readable ASCII text providing additional information, used
+        // to assist the client developer in understanding the error that occurred.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_description: Option<String>,
+    }
+}
+
+impl TokenRevocationResponse {
+
+    pub fn missing_parameter(parameter: &str) -> Self {
+        TokenRevocationResponse::Failure {
+            error: ErrorType::InvalidRequest,
+            error_description: Some(format!("missing parameter: {parameter}")),
+        }
+    }
+
+    pub fn invalid_parameter(parameter: &str) -> Self {
+        TokenRevocationResponse::Failure {
+            error: ErrorType::InvalidRequest,
+            error_description: Some(format!("invalid parameter: {parameter}")),
+        }
+    }
+}
+
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorType {
+
+    // The request is missing a required parameter, includes an
+    // unsupported parameter value, repeats a parameter, or is otherwise malformed.
+    InvalidRequest,
+
+    // The authorization server does not support the revocation of the presented token type.
+    UnsupportedTokenType,
+}
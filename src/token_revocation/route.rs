@@ -0,0 +1,348 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{middleware, Router};
+use axum::routing::post;
+use middleware::from_fn_with_state;
+use uuid::Uuid;
+use crate::client::authentication::ClientAuthenticator;
+use crate::client::{AccessTokenFormat, ClientPrincipal};
+use crate::client::middleware::require_client_authentication;
+use crate::key::KeyStore;
+use crate::token::{AccessToken, RefreshToken, TokenTypeHint};
+use crate::token::jwt::parse_token_id;
+use crate::token::repository::TokenRepository;
+use crate::token_revocation::request::TokenRevocationForm;
+
+pub const REVOCATION_ENDPOINT: &str = "/revoke";
+
+// https://www.rfc-editor.org/rfc/rfc7009#section-2
+pub fn route<S, A, R, C, K>(state: TokenRevocationState<A, R, C, K>) -> Router<S>
+where
+    A: TokenRepository<AccessToken> + 'static,
+    R: TokenRepository<RefreshToken> + 'static,
+    C: ClientAuthenticator + 'static,
+    K: KeyStore + 'static,
+{
+    Router::new()
+        .route(REVOCATION_ENDPOINT, post(token_revocation_handler))
+        .route_layer(from_fn_with_state(state.client_authenticator.clone(), require_client_authentication::<C>))
+        .with_state(state)
+}
+
+#[derive(Clone)]
+pub struct TokenRevocationState<A: TokenRepository<AccessToken>, R: TokenRepository<RefreshToken>, C: ClientAuthenticator, K: KeyStore> {
+    pub issuer: String,
+    pub access_token_repository: A,
+    pub refresh_token_repository: R,
+    pub client_authenticator: C,
+    pub key_store: K,
+}
+
+// Invalid tokens do not cause an error response, because the client cannot handle it in a reasonable way.
+// https://www.rfc-editor.org/rfc/rfc7009#section-2.2
+async fn token_revocation_handler<A: TokenRepository<AccessToken>, R: TokenRepository<RefreshToken>, C: ClientAuthenticator, K: KeyStore>(
+    State(state): State<TokenRevocationState<A, R, C, K>>,
+    TokenRevocationForm(request): TokenRevocationForm,
+) -> StatusCode {
+
+    let (id, format) = match parse_token_id(&state.issuer, &state.key_store, &request.token) {
+        None => return StatusCode::OK,
+        Some(parsed) => parsed,
+    };
+
+    // The hint only decides where to look first, the other token type is still searched.
+    match request.token_type_hint {
+        Some(TokenTypeHint::RefreshToken) => if !revoke_refresh_token(&state, &request.principal, id, &format) {
+            revoke_access_token(&state, &request.principal, id, &format);
+        },
+        _ => if !revoke_access_token(&state, &request.principal, id, &format) {
+            revoke_refresh_token(&state, &request.principal, id, &format);
+        },
+    }
+
+    StatusCode::OK
+}
+
+fn revoke_access_token<A: TokenRepository<AccessToken>, R: TokenRepository<RefreshToken>, C: ClientAuthenticator, K: KeyStore>(
+    state: &TokenRevocationState<A, R, C, K>,
+    principal: &ClientPrincipal,
+    id: Uuid,
+    format: &AccessTokenFormat,
+) -> bool {
+    match state.access_token_repository.get_token(id) {
+        Some(access_token) if &access_token.client_id == principal.id() && &access_token.format == format => {
+            state.access_token_repository.remove_token(id);
+            true
+        },
+        _ => false,
+    }
+}
+
+// Revoking a refresh token also revokes every access token that was issued from the same grant.
+fn revoke_refresh_token<A: TokenRepository<AccessToken>, R: TokenRepository<RefreshToken>, C: ClientAuthenticator, K: KeyStore>(
+    state: &TokenRevocationState<A, R, C, K>,
+    principal: &ClientPrincipal,
+    id: Uuid,
+    format: &AccessTokenFormat,
+) -> bool {
+    // Refresh tokens are only ever opaque.
+    match state.refresh_token_repository.get_token(id) {
+        Some(refresh_token) if &refresh_token.client_id == principal.id() && format == &AccessTokenFormat::Opaque => {
+            state.refresh_token_repository.remove_token(id);
+            state.access_token_repository.remove_tokens_where(|access_token| access_token.grant_id == Some(refresh_token.grant_id));
+            true
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+
+    use super::*;
+
+    use assertables::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request, Response};
+    use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+    use http_body_util::BodyExt;
+    use std::collections::{HashMap, HashSet};
+    use base64::prelude::*;
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use crate::client::ClientId;
+    use crate::scope::{Scope, Scopes};
+    use crate::token::repository::InMemoryTokenRepository;
+    use crate::user::Username;
+
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
+    const APPLICATION_WWW_FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
+    const TEST_CLIENT_USERNAME: &str = "aardvark";
+    const TEST_CLIENT_PASSWORD: &str = "badger";
+
+    macro_rules! under_test {
+        () => {
+            under_test!(InMemoryTokenRepository::new(), InMemoryTokenRepository::new())
+        };
+        ($access_token_repository:expr, $refresh_token_repository:expr) => {
+            route::<(), _, _, _, _>(TokenRevocationState {
+                issuer: TEST_ISSUER.into(),
+                access_token_repository: $access_token_repository,
+                refresh_token_repository: $refresh_token_repository,
+                client_authenticator: crate::client::authentication::ClientAuthenticationService::new(
+                    TEST_ISSUER.into(),
+                    crate::client::secret::InMemoryClientSecretRepository::new(),
+                    crate::client::configuration::InMemoryClientConfigurationRepository::new(),
+                    crate::replay::InMemoryReplayCache::new(),
+                ),
+                key_store: crate::key::InMemoryKeyStore::new_test_store(),
+            })
+        };
+    }
+
+    async fn extract_json_body(response: Response<Body>) -> HashMap<String, Value> {
+        let body_bytes = assert_ok!(response.into_body().collect().await);
+        assert_ok!(serde_json::from_slice(body_bytes.to_bytes().as_ref()))
+    }
+
+    fn basic_auth(username: &str, password: &str) -> String {
+        format!("Basic {}", BASE64_STANDARD.encode(format!("{}:{}", username, password)))
+    }
+
+    fn revocation_request(body: String) -> Request<Body> {
+        assert_ok!(Request::builder()
+            .method(Method::POST)
+            .uri(REVOCATION_ENDPOINT)
+            .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+            .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+            .body(Body::from(body))
+        )
+    }
+
+    fn new_refresh_token(client_id: &str) -> RefreshToken {
+        RefreshToken::new(
+            ClientId::from(String::from(client_id)),
+            Username::from(String::from("aardvark")),
+            Scopes(HashSet::from([Scope::from("basic")])),
+            chrono::Utc::now(),
+        )
+    }
+
+    fn new_access_token(refresh_token: &RefreshToken) -> AccessToken {
+        AccessToken::new(
+            refresh_token.client_id.clone(),
+            Some(refresh_token.username.clone()),
+            refresh_token.scopes.clone(),
+            Some(refresh_token.grant_id),
+        )
+    }
+
+    mod invalid_http_request {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_not_support_http_method_get() {
+            let request = assert_ok!(Request::builder()
+                .method(Method::GET)
+                .uri(REVOCATION_ENDPOINT)
+                .header(AUTHORIZATION, basic_auth(TEST_CLIENT_USERNAME, TEST_CLIENT_PASSWORD))
+                .body(Body::empty())
+            );
+
+            let response = assert_ok!(under_test!().oneshot(request).await);
+
+            assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        }
+
+        #[tokio::test]
+        async fn should_require_client_authentication() {
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(REVOCATION_ENDPOINT)
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from(format!("token={}", Uuid::new_v4())))
+            );
+
+            let response = assert_ok!(under_test!().oneshot(request).await);
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    mod invalid_revocation_request {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_return_bad_request_on_missing_token() {
+            let response = assert_ok!(under_test!().oneshot(revocation_request(String::new())).await);
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("error"), "invalid_request");
+            assert_some_eq_x!(body.get("error_description"), "missing parameter: token");
+        }
+
+        #[tokio::test]
+        async fn should_return_bad_request_on_unsupported_token_type_hint() {
+            let response = assert_ok!(under_test!().oneshot(revocation_request(format!("token={}&token_type_hint=badger", Uuid::new_v4()))).await);
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body = extract_json_body(response).await;
+            assert_some_eq_x!(body.get("error"), "unsupported_token_type");
+        }
+    }
+
+    mod revocation {
+        use super::*;
+
+        #[tokio::test]
+        async fn should_return_ok_for_an_unknown_token() {
+            let response = assert_ok!(under_test!().oneshot(revocation_request(format!("token={}", Uuid::new_v4()))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn should_return_ok_for_a_malformed_token() {
+            let response = assert_ok!(under_test!().oneshot(revocation_request(String::from("token=aardvark"))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn should_revoke_an_access_token() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token(TEST_CLIENT_USERNAME);
+            let access_token = new_access_token(&refresh_token);
+            access_token_repository.save_token(&access_token);
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(access_token_repository.clone(), refresh_token_repository.clone());
+
+            let response = assert_ok!(router.oneshot(revocation_request(format!("token={}&token_type_hint=access_token", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            assert_none!(access_token_repository.get_token(access_token.id));
+            assert_some!(refresh_token_repository.get_token(refresh_token.id));
+        }
+
+        #[tokio::test]
+        async fn should_revoke_a_refresh_token_and_the_access_tokens_issued_from_it() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token(TEST_CLIENT_USERNAME);
+            let first_access_token = new_access_token(&refresh_token);
+            let second_access_token = new_access_token(&refresh_token);
+            let unrelated_access_token = new_access_token(&new_refresh_token(TEST_CLIENT_USERNAME));
+            access_token_repository.save_token(&first_access_token);
+            access_token_repository.save_token(&second_access_token);
+            access_token_repository.save_token(&unrelated_access_token);
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(access_token_repository.clone(), refresh_token_repository.clone());
+
+            let response = assert_ok!(router.oneshot(revocation_request(format!("token={}&token_type_hint=refresh_token", refresh_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            assert_none!(refresh_token_repository.get_token(refresh_token.id));
+            assert_none!(access_token_repository.get_token(first_access_token.id));
+            assert_none!(access_token_repository.get_token(second_access_token.id));
+            assert_some!(access_token_repository.get_token(unrelated_access_token.id));
+        }
+
+        #[tokio::test]
+        async fn should_revoke_a_refresh_token_with_the_wrong_token_type_hint() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token(TEST_CLIENT_USERNAME);
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository.clone());
+
+            let response = assert_ok!(router.oneshot(revocation_request(format!("token={}&token_type_hint=access_token", refresh_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            assert_none!(refresh_token_repository.get_token(refresh_token.id));
+        }
+
+        #[tokio::test]
+        async fn should_not_revoke_a_token_issued_to_another_client() {
+            let access_token_repository = InMemoryTokenRepository::new();
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token("badger");
+            let access_token = new_access_token(&refresh_token);
+            access_token_repository.save_token(&access_token);
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(access_token_repository.clone(), refresh_token_repository.clone());
+
+            let response = assert_ok!(router.clone().oneshot(revocation_request(format!("token={}", access_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = assert_ok!(router.oneshot(revocation_request(format!("token={}", refresh_token.id))).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            assert_some!(access_token_repository.get_token(access_token.id));
+            assert_some!(refresh_token_repository.get_token(refresh_token.id));
+        }
+
+        #[tokio::test]
+        async fn should_allow_a_public_client_to_revoke_its_own_token() {
+            let refresh_token_repository = InMemoryTokenRepository::new();
+            let refresh_token = new_refresh_token("badger");
+            refresh_token_repository.save_token(&refresh_token);
+
+            let router = under_test!(InMemoryTokenRepository::new(), refresh_token_repository.clone());
+
+            let request = assert_ok!(Request::builder()
+                .method(Method::POST)
+                .uri(REVOCATION_ENDPOINT)
+                .header(CONTENT_TYPE, APPLICATION_WWW_FORM_URLENCODED)
+                .body(Body::from(format!("client_id=badger&token={}", refresh_token.id)))
+            );
+
+            let response = assert_ok!(router.oneshot(request).await);
+            assert_eq!(response.status(), StatusCode::OK);
+
+            assert_none!(refresh_token_repository.get_token(refresh_token.id));
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::assertion::JwksSource;
+use crate::scope::Scope;
+use crate::trusted_issuer::TrustedIssuer;
+use crate::user::Username;
+
+#[derive(Deserialize)]
+struct TrustedIssuerJson {
+    issuer: String,
+    // Relative to the file it's configured in.
+    jwks_file: PathBuf,
+    #[serde(default)]
+    allowed_subjects: HashMap<String, String>,
+    #[serde(default)]
+    scope_mapping: HashMap<String, String>,
+}
+
+// A JSON array of the issuers to trust, so none are trusted unless the deployment says so.
+pub fn load_trusted_issuers(path: &Path) -> io::Result<Vec<TrustedIssuer>> {
+    let issuers: Vec<TrustedIssuerJson> = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("invalid trusted issuers file {}: {error}", path.display())))?;
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    Ok(issuers.into_iter()
+        .map(|issuer| TrustedIssuer {
+            issuer: issuer.issuer,
+            jwks: JwksSource::File(directory.join(issuer.jwks_file)),
+            allowed_subjects: issuer.allowed_subjects.into_iter()
+                .map(|(subject, username)| (subject, Username::from(username)))
+                .collect(),
+            scope_mapping: issuer.scope_mapping.into_iter()
+                .map(|(scope, mapped)| (scope, Scope::from(mapped)))
+                .collect(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+
+    #[test]
+    fn should_load_every_trusted_issuer() {
+        let issuers = assert_ok!(load_trusted_issuers(Path::new("tests/fixtures/issuers/trusted_issuers.json")));
+        assert_eq!(issuers.len(), 1);
+
+        let iguana = &issuers[0];
+        assert_eq!(iguana.issuer, "https://iguana.baconi.co.uk");
+        assert_eq!(iguana.jwks, JwksSource::File(PathBuf::from("tests/fixtures/issuers/iguana.jwks.json")));
+        assert_some!(iguana.jwks.load());
+        assert_eq!(iguana.map_subject("aardvark@iguana"), Some(&Username::from(String::from("aardvark"))));
+        assert_eq!(iguana.scope_mapping.get("iguana:basic"), Some(&Scope::from("basic")));
+    }
+
+    #[test]
+    fn should_reject_an_invalid_trusted_issuers_file() {
+        assert_err!(load_trusted_issuers(Path::new("tests/fixtures/issuers/iguana.pem")));
+        assert_err!(load_trusted_issuers(Path::new("tests/fixtures/issuers/missing.json")));
+    }
+}
@@ -0,0 +1,158 @@
+mod loader;
+
+pub use loader::*;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::assertion::JwksSource;
+use crate::scope::{Scope, Scopes};
+use crate::user::Username;
+
+// Another authorization server whose assertions we'll trade for our own tokens.
+// https://www.rfc-editor.org/rfc/rfc7523#section-3
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct TrustedIssuer {
+    pub issuer: String,
+    pub jwks: JwksSource,
+    // The subjects we'll accept assertions about, and who each of them is here.
+    pub allowed_subjects: HashMap<String, Username>,
+    // What each of the issuer's scopes is worth here, anything not mapped is ignored.
+    pub scope_mapping: HashMap<String, Scope>,
+}
+
+impl TrustedIssuer {
+    pub fn map_subject(&self, subject: &str) -> Option<&Username> {
+        self.allowed_subjects.get(subject)
+    }
+
+    // The scope claim is space delimited, just like the scope parameter.
+    pub fn map_scopes(&self, scope: Option<&str>) -> Scopes {
+        Scopes(scope.unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|scope| self.scope_mapping.get(scope))
+            .cloned()
+            .collect::<HashSet<_>>())
+    }
+}
+
+pub trait TrustedIssuerRepository: Send + Sync + Clone {
+    fn find_by_issuer(&self, issuer: &str) -> Option<TrustedIssuer>;
+    fn save(&self, trusted_issuer: &TrustedIssuer);
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryTrustedIssuerRepository {
+    store: Arc<Mutex<HashMap<String, TrustedIssuer>>>,
+}
+
+impl InMemoryTrustedIssuerRepository {
+    // Trusts no one, issuers have to be configured where the server is deployed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn lock_store(&self) -> MutexGuard<'_, HashMap<String, TrustedIssuer>> {
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl TrustedIssuerRepository for InMemoryTrustedIssuerRepository {
+    fn find_by_issuer(&self, issuer: &str) -> Option<TrustedIssuer> {
+        self.lock_store().get(issuer).cloned()
+    }
+    fn save(&self, trusted_issuer: &TrustedIssuer) {
+        self.lock_store().insert(trusted_issuer.issuer.clone(), trusted_issuer.clone());
+    }
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use std::collections::HashMap;
+    use assertables::*;
+    use chrono::{TimeDelta, Utc};
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+    use crate::assertion::JwksSource;
+    use crate::scope::Scope;
+    use crate::trusted_issuer::{InMemoryTrustedIssuerRepository, TrustedIssuer, TrustedIssuerRepository};
+    use crate::user::Username;
+
+    pub const TEST_TRUSTED_ISSUER: &str = "https://iguana.baconi.co.uk";
+    pub const TEST_IGUANA_KEY: &[u8] = include_bytes!("../../tests/fixtures/issuers/iguana.pem");
+    pub const TEST_IGUANA_JWKS: &[u8] = include_bytes!("../../tests/fixtures/issuers/iguana.jwks.json");
+
+    impl InMemoryTrustedIssuerRepository {
+        // Trusts iguana, which vouches for aardvark.
+        pub fn new_test_repository() -> Self {
+            let repository = InMemoryTrustedIssuerRepository::new();
+            repository.save(&TrustedIssuer {
+                issuer: String::from(TEST_TRUSTED_ISSUER),
+                jwks: JwksSource::Inline(assert_ok!(serde_json::from_slice(TEST_IGUANA_JWKS))),
+                allowed_subjects: HashMap::from([
+                    (String::from("aardvark@iguana"), Username::from(String::from("aardvark"))),
+                ]),
+                scope_mapping: HashMap::from([
+                    (String::from("iguana:basic"), Scope::from("basic")),
+                    (String::from("iguana:profile"), Scope::PROFILE),
+                ]),
+            });
+            repository
+        }
+    }
+
+    pub fn new_claims(subject: &str, audience: &str, scope: &str) -> Value {
+        json!({
+            "iss": TEST_TRUSTED_ISSUER,
+            "sub": subject,
+            "aud": audience,
+            "jti": Uuid::new_v4().to_string(),
+            "exp": (Utc::now() + TimeDelta::minutes(1)).timestamp(),
+            "scope": scope,
+        })
+    }
+
+    // As iguana would, with its private key.
+    pub fn sign_assertion(claims: &Value) -> String {
+        let header = Header { kid: Some("iguana-2026-01".into()), ..Header::new(Algorithm::ES256) };
+        assert_ok!(encode(&header, claims, &assert_ok!(EncodingKey::from_ec_pem(TEST_IGUANA_KEY))))
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+
+    fn iguana() -> TrustedIssuer {
+        assert_some!(InMemoryTrustedIssuerRepository::new_test_repository().find_by_issuer("https://iguana.baconi.co.uk"))
+    }
+
+    #[test]
+    fn should_only_find_trusted_issuers() {
+        assert_none!(InMemoryTrustedIssuerRepository::new_test_repository().find_by_issuer("https://cicada.baconi.co.uk"));
+    }
+
+    #[test]
+    fn should_trust_no_issuers_by_default() {
+        assert_none!(InMemoryTrustedIssuerRepository::new().find_by_issuer("https://iguana.baconi.co.uk"));
+    }
+
+    #[test]
+    fn should_load_the_issuers_keys() {
+        assert_some!(iguana().jwks.load());
+    }
+
+    #[test]
+    fn should_map_allowed_subjects_to_local_usernames() {
+        assert_eq!(iguana().map_subject("aardvark@iguana"), Some(&Username::from(String::from("aardvark"))));
+        assert_none!(iguana().map_subject("cicada@iguana"));
+    }
+
+    #[test]
+    fn should_map_only_known_scopes() {
+        assert_eq!(iguana().map_scopes(Some("iguana:basic iguana:profile cicada")), Scopes(HashSet::from([Scope::from("basic"), Scope::PROFILE])));
+        assert_eq!(iguana().map_scopes(None), Scopes::default());
+    }
+}
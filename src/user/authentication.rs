@@ -0,0 +1,91 @@
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use crate::user::Username;
+use crate::user::repository::UserRepository;
+
+// Verified against when there is no such user, so an unknown username takes as long to reject as a wrong password.
+const UNKNOWN_USER_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$dGltaW5nLWVxdWFsaXNlcg$K6DbLXOuEWl9Fed54ItpBvCmDUUOIQDG7p+yvtDoZf0";
+
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct AuthenticatedUser {
+    pub username: Username,
+}
+
+pub trait UserAuthenticator: Send + Sync + Clone {
+    fn authenticate(&self, username: &str, password: &[u8]) -> Option<AuthenticatedUser>;
+}
+
+#[derive(Clone)]
+pub struct UserAuthenticationService<U: UserRepository> {
+    user_repository: U,
+}
+
+impl<U: UserRepository> UserAuthenticationService<U> {
+    pub fn new(user_repository: U) -> Self {
+        Self {
+            user_repository,
+        }
+    }
+}
+
+impl<U: UserRepository> UserAuthenticator for UserAuthenticationService<U> {
+    fn authenticate(&self, username: &str, password: &[u8]) -> Option<AuthenticatedUser> {
+
+        let user = self.user_repository.find_by_username(username);
+
+        let hashed_password = user.as_ref().map_or(UNKNOWN_USER_HASH, |user| user.hashed_password.as_str());
+        let hash = PasswordHash::new(hashed_password).ok()?;
+        let verified = Argon2::default().verify_password(password, &hash).is_ok();
+
+        match user {
+            Some(user) if verified => Some(AuthenticatedUser { username: user.username }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use super::*;
+    use assertables::*;
+    use crate::user::repository::InMemoryUserRepository;
+
+    fn under_test() -> UserAuthenticationService<InMemoryUserRepository> {
+        UserAuthenticationService::new(InMemoryUserRepository::new())
+    }
+
+    #[test]
+    fn should_authenticate_a_user_with_valid_credentials() {
+        let user = assert_some!(under_test().authenticate("aardvark", b"P@55w0rd"));
+
+        assert_eq!(user, AuthenticatedUser { username: Username(String::from("aardvark")) });
+    }
+
+    #[test]
+    fn should_not_authenticate_a_user_with_an_invalid_password() {
+        assert_none!(under_test().authenticate("aardvark", b"badger"));
+    }
+
+    #[test]
+    fn should_not_authenticate_an_unknown_user() {
+        assert_none!(under_test().authenticate("badger", b"P@55w0rd"));
+    }
+
+    #[test]
+    fn should_hash_as_costly_for_an_unknown_user_as_for_a_known_one() {
+        let hashed_password = assert_some!(InMemoryUserRepository::new().find_by_username("aardvark").map(|user| user.hashed_password));
+
+        let known = assert_ok!(PasswordHash::new(&hashed_password));
+        let unknown = assert_ok!(PasswordHash::new(UNKNOWN_USER_HASH));
+
+        assert_eq!(unknown.algorithm, known.algorithm);
+        assert_eq!(unknown.version, known.version);
+        assert_eq!(unknown.params, known.params);
+    }
+
+    #[test]
+    fn should_not_authenticate_a_user_with_a_differently_cased_username() {
+        assert_none!(under_test().authenticate("Aardvark", b"P@55w0rd"));
+    }
+}
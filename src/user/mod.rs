@@ -0,0 +1,11 @@
+pub mod authentication;
+pub mod repository;
+
+use crate::value_struct;
+use crate::disable_deserialization;
+
+value_struct! {
+    pub struct Username(String);
+}
+
+disable_deserialization!(Username);
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::user::Username;
+
+#[derive(Clone)]
+pub struct User {
+    pub username: Username,
+    pub hashed_password: String,
+    // https://openid.net/specs/openid-connect-core-1_0.html#StandardClaims
+    pub name: String,
+    pub email: String,
+    pub email_verified: bool,
+}
+
+pub trait UserRepository: Send + Sync + Clone {
+    fn find_by_username(&self, username: &str) -> Option<User>;
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryUserRepository {
+    store: Arc<Mutex<HashMap<Username, User>>>,
+}
+
+impl InMemoryUserRepository {
+    pub fn new() -> Self {
+        Self {
+            store: Arc::new(Mutex::new(HashMap::from([
+                Self::create_hashed_entry("aardvark", b"P@55w0rd", "Aardvark", "aardvark@baconi.co.uk"),
+            ])))
+        }
+    }
+
+    // TODO - Remove once we've got a means of creating new users
+    fn create_hashed_entry(username: &str, password: &[u8], name: &str, email: &str) -> (Username, User) {
+
+        // Allowed because this isn't intended to be production used code
+        #![allow(clippy::unwrap_used)]
+
+        use argon2::Argon2;
+        use argon2::password_hash::Salt;
+        use argon2::password_hash::SaltString;
+        use argon2::password_hash::PasswordHasher;
+
+        let argon2 = Argon2::default();
+        let salt = vec![0u8; Salt::RECOMMENDED_LENGTH];
+        let salt_string = SaltString::encode_b64(&salt).unwrap();
+        let hashed = argon2.hash_password(password, &salt_string).unwrap().to_string();
+
+        let username = Username(String::from(username));
+
+        (username.clone(), User {
+            username,
+            hashed_password: hashed,
+            name: name.into(),
+            email: email.into(),
+            email_verified: true,
+        })
+    }
+
+    fn lock_store(&self) -> MutexGuard<'_, HashMap<Username, User>> {
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl UserRepository for InMemoryUserRepository {
+    fn find_by_username(&self, username: &str) -> Option<User> {
+        self.lock_store().get(&Username(String::from(username))).cloned()
+    }
+}
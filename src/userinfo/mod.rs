@@ -0,0 +1,4 @@
+mod route;
+mod response;
+
+pub use route::*;
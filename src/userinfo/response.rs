@@ -0,0 +1,79 @@
+use axum::http::StatusCode;
+use axum::http::header::WWW_AUTHENTICATE;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use crate::scope::{Scope, Scopes};
+use crate::user::repository::User;
+use crate::util::value_struct::ValueStruct;
+
+// https://openid.net/specs/openid-connect-core-1_0.html#UserInfoResponse
+#[derive(Serialize, Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct UserInfoResponse {
+
+    // Subject - Identifier for the End-User at the Issuer.
+    pub sub: String,
+
+    // End-User's full name in displayable form, released with the profile scope.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    // Shorthand name by which the End-User wishes to be referred to, released with the profile scope.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+
+    // End-User's preferred e-mail address, released with the email scope.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+
+    // True if the End-User's e-mail address has been verified, released with the email scope.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+impl UserInfoResponse {
+    // Only the claims the access token's scopes allow are released.
+    // https://openid.net/specs/openid-connect-core-1_0.html#ScopeClaims
+    pub fn new(user: &User, Scopes(scopes): &Scopes) -> Self {
+        let profile = scopes.contains(&Scope::PROFILE);
+        let email = scopes.contains(&Scope::EMAIL);
+        Self {
+            sub: user.username.value().clone(),
+            name: Some(user.name.clone()).filter(|_| profile),
+            preferred_username: Some(user.username.value().clone()).filter(|_| profile),
+            email: Some(user.email.clone()).filter(|_| email),
+            email_verified: Some(user.email_verified).filter(|_| email),
+        }
+    }
+}
+
+// https://www.rfc-editor.org/rfc/rfc6750#section-3.1
+#[derive(Eq, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub enum UserInfoFailure {
+
+    // The request lacks any authentication information.
+    MissingToken,
+
+    // The access token provided is expired, revoked, malformed, or invalid for other reasons.
+    InvalidToken,
+
+    // The request requires higher privileges than provided by the access token.
+    InsufficientScope,
+
+    // The proof of possession for a bound access token is missing or invalid.
+    // https://www.rfc-editor.org/rfc/rfc9449#section-7.1
+    InvalidDpopProof,
+}
+
+impl IntoResponse for UserInfoFailure {
+    fn into_response(self) -> Response {
+        let (status, challenge) = match self {
+            UserInfoFailure::MissingToken => (StatusCode::UNAUTHORIZED, String::from("Bearer")),
+            UserInfoFailure::InvalidToken => (StatusCode::UNAUTHORIZED, String::from(r#"Bearer error="invalid_token""#)),
+            UserInfoFailure::InsufficientScope => (StatusCode::FORBIDDEN, format!(r#"Bearer error="insufficient_scope", scope="{}""#, Scope::OPENID)),
+            UserInfoFailure::InvalidDpopProof => (StatusCode::UNAUTHORIZED, String::from(r#"DPoP error="invalid_dpop_proof""#)),
+        };
+        (status, [(WWW_AUTHENTICATE, challenge)]).into_response()
+    }
+}
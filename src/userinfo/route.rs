@@ -0,0 +1,351 @@
+use axum::extract::State;
+use axum::http::{HeaderMap, Method};
+use axum::http::header::AUTHORIZATION;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use crate::dpop::{verify_proof, DPOP_HEADER};
+use crate::key::KeyStore;
+use crate::replay::ReplayCache;
+use crate::scope::Scope;
+use crate::token::{AccessToken, Confirmation, TokenType};
+use crate::token::jwt::find_access_token;
+use crate::token::repository::TokenRepository;
+use crate::user::repository::UserRepository;
+use crate::userinfo::response::{UserInfoFailure, UserInfoResponse};
+use crate::util::value_struct::ValueStruct;
+
+pub const USERINFO_ENDPOINT: &str = "/userinfo";
+
+// https://openid.net/specs/openid-connect-core-1_0.html#UserInfo
+pub fn route<S, A, U, K, J>(state: UserInfoState<A, U, K, J>) -> Router<S>
+where
+    A: TokenRepository<AccessToken> + 'static,
+    U: UserRepository + 'static,
+    K: KeyStore + 'static,
+    J: ReplayCache + 'static,
+{
+    Router::new()
+        .route(USERINFO_ENDPOINT, get(userinfo_handler).post(userinfo_handler))
+        .with_state(state)
+}
+
+#[derive(Clone)]
+pub struct UserInfoState<A: TokenRepository<AccessToken>, U: UserRepository, K: KeyStore, J: ReplayCache> {
+    pub issuer: String,
+    pub access_token_repository: A,
+    pub user_repository: U,
+    pub key_store: K,
+    pub replay_cache: J,
+}
+
+// The access token is only accepted in the header, as a bearer token or, when bound to a key, alongside a proof of possession.
+// https://www.rfc-editor.org/rfc/rfc6750#section-2.1
+// https://www.rfc-editor.org/rfc/rfc9449#section-7.1
+async fn userinfo_handler<A: TokenRepository<AccessToken>, U: UserRepository, K: KeyStore, J: ReplayCache>(
+    State(state): State<UserInfoState<A, U, K, J>>,
+    method: Method,
+    headers: HeaderMap,
+) -> Response {
+
+    let Some((scheme, token)) = authorization(&headers) else {
+        return UserInfoFailure::MissingToken.into_response()
+    };
+
+    let access_token = match find_access_token(&state.issuer, &state.key_store, &state.access_token_repository, token) {
+        None => return UserInfoFailure::InvalidToken.into_response(),
+        Some(access_token) => access_token,
+    };
+
+    // A bound token presented as a bearer token, or the other way around, is as good as stolen.
+    if access_token.token_type() != scheme {
+        return UserInfoFailure::InvalidToken.into_response()
+    }
+
+    if let Some(confirmation) = &access_token.confirmation {
+        match verify_dpop_proof(&state, &method, &headers, token) {
+            Err(_) => return UserInfoFailure::InvalidDpopProof.into_response(),
+            Ok(proven) if proven != *confirmation => return UserInfoFailure::InvalidToken.into_response(),
+            Ok(_) => {},
+        }
+    }
+
+    if !access_token.scopes.0.contains(&Scope::OPENID) {
+        return UserInfoFailure::InsufficientScope.into_response()
+    }
+
+    // Tokens issued without a resource owner, such as by the client_credentials grant, have nobody to describe.
+    let user = match access_token.username.as_ref().and_then(|username| state.user_repository.find_by_username(username.value())) {
+        None => return UserInfoFailure::InvalidToken.into_response(),
+        Some(user) => user,
+    };
+
+    Json(UserInfoResponse::new(&user, &access_token.scopes)).into_response()
+}
+
+// The authentication scheme is case-insensitive, the token is whatever follows it.
+// https://www.rfc-editor.org/rfc/rfc9110#section-11.1
+fn authorization(headers: &HeaderMap) -> Option<(TokenType, &str)> {
+    let (scheme, token) = headers.get(AUTHORIZATION)?.to_str().ok()?.split_once(' ')?;
+    let scheme = match scheme {
+        scheme if scheme.eq_ignore_ascii_case("Bearer") => TokenType::Bearer,
+        scheme if scheme.eq_ignore_ascii_case("DPoP") => TokenType::DPoP,
+        _ => return None,
+    };
+    Some((scheme, token.trim()))
+}
+
+// The proof must be made for this request and for the access token it's presented with.
+// https://www.rfc-editor.org/rfc/rfc9449#section-7.1
+fn verify_dpop_proof<A: TokenRepository<AccessToken>, U: UserRepository, K: KeyStore, J: ReplayCache>(
+    state: &UserInfoState<A, U, K, J>,
+    method: &Method,
+    headers: &HeaderMap,
+    access_token: &str,
+) -> Result<Confirmation, String> {
+
+    let mut proofs = headers.get_all(DPOP_HEADER).iter();
+
+    let proof = match (proofs.next(), proofs.next()) {
+        (None, _) => Err("missing proof")?,
+        (Some(_), Some(_)) => Err("multiple proofs")?,
+        (Some(proof), None) => proof.to_str().map_err(|_| "malformed proof")?,
+    };
+
+    verify_proof(&state.replay_cache, proof, method.as_str(), &format!("{}{USERINFO_ENDPOINT}", state.issuer), Some(access_token))
+}
+
+#[cfg(test)]
+mod integration_tests {
+
+    use super::*;
+    use assertables::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request, Response, StatusCode};
+    use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use std::collections::HashSet;
+    use tower::ServiceExt;
+    use crate::client::ClientId;
+    use crate::dpop::test_support::{new_access_token_proof, new_proof, test_proof_thumbprint};
+    use crate::key::InMemoryKeyStore;
+    use crate::replay::InMemoryReplayCache;
+    use crate::scope::Scopes;
+    use crate::token::jwt::encode_access_token;
+    use crate::token::repository::InMemoryTokenRepository;
+    use crate::user::Username;
+    use crate::user::repository::InMemoryUserRepository;
+
+    const TEST_ISSUER: &str = "http://127.0.0.1:8080";
+    const TEST_USERINFO_URI: &str = "http://127.0.0.1:8080/userinfo";
+
+    macro_rules! under_test {
+        ($access_token_repository:expr) => {
+            route::<(), _, _, _, _>(UserInfoState {
+                issuer: TEST_ISSUER.into(),
+                access_token_repository: $access_token_repository,
+                user_repository: InMemoryUserRepository::new(),
+                key_store: InMemoryKeyStore::new_test_store(),
+                replay_cache: InMemoryReplayCache::new(),
+            })
+        };
+    }
+
+    fn new_access_token(username: Option<&str>, scopes: &[Scope]) -> AccessToken {
+        AccessToken::new(
+            ClientId::from(String::from("aardvark")),
+            username.map(|username| Username::from(String::from(username))),
+            Scopes(HashSet::from_iter(scopes.iter().cloned())),
+            None,
+        )
+    }
+
+    async fn userinfo(method: Method, access_token_repository: InMemoryTokenRepository<AccessToken>, maybe_token: Option<String>) -> Response<Body> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(USERINFO_ENDPOINT);
+
+        if let Some(token) = maybe_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+
+        assert_ok!(under_test!(access_token_repository).oneshot(assert_ok!(request.body(Body::empty()))).await)
+    }
+
+    async fn userinfo_with(access_token: &AccessToken) -> Response<Body> {
+        let access_token_repository = InMemoryTokenRepository::new();
+        access_token_repository.save_token(access_token);
+        userinfo(Method::GET, access_token_repository, Some(access_token.id.to_string())).await
+    }
+
+    fn new_bound_access_token() -> AccessToken {
+        AccessToken {
+            confirmation: Some(Confirmation { jkt: test_proof_thumbprint() }),
+            ..new_access_token(Some("aardvark"), &[Scope::OPENID])
+        }
+    }
+
+    async fn userinfo_with_proof(access_token: &AccessToken, scheme: &str, maybe_proof: Option<String>) -> Response<Body> {
+        let access_token_repository = InMemoryTokenRepository::new();
+        access_token_repository.save_token(access_token);
+
+        let mut request = Request::builder()
+            .method(Method::GET)
+            .uri(USERINFO_ENDPOINT)
+            .header(AUTHORIZATION, format!("{scheme} {}", access_token.id));
+
+        if let Some(proof) = maybe_proof {
+            request = request.header(DPOP_HEADER, proof);
+        }
+
+        assert_ok!(under_test!(access_token_repository).oneshot(assert_ok!(request.body(Body::empty()))).await)
+    }
+
+    async fn extract_json_body(response: Response<Body>) -> Value {
+        let body = assert_ok!(response.into_body().collect().await).to_bytes();
+        assert_ok!(serde_json::from_slice(&body))
+    }
+
+    fn www_authenticate(response: &Response<Body>) -> &str {
+        assert_ok!(assert_some!(response.headers().get(WWW_AUTHENTICATE)).to_str())
+    }
+
+    #[tokio::test]
+    async fn should_return_only_the_subject_with_just_the_openid_scope() {
+        let response = userinfo_with(&new_access_token(Some("aardvark"), &[Scope::OPENID])).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(extract_json_body(response).await, json!({ "sub": "aardvark" }));
+    }
+
+    #[tokio::test]
+    async fn should_return_the_claims_allowed_by_the_profile_and_email_scopes() {
+        let response = userinfo_with(&new_access_token(Some("aardvark"), &[Scope::OPENID, Scope::PROFILE, Scope::EMAIL])).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(extract_json_body(response).await, json!({
+            "sub": "aardvark",
+            "name": "Aardvark",
+            "preferred_username": "aardvark",
+            "email": "aardvark@baconi.co.uk",
+            "email_verified": true,
+        }));
+    }
+
+    #[tokio::test]
+    async fn should_accept_a_post_request() {
+        let access_token = new_access_token(Some("aardvark"), &[Scope::OPENID]);
+        let access_token_repository = InMemoryTokenRepository::new();
+        access_token_repository.save_token(&access_token);
+
+        let response = userinfo(Method::POST, access_token_repository, Some(access_token.id.to_string())).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_accept_a_signed_access_token() {
+        let access_token = AccessToken { format: crate::client::AccessTokenFormat::Jwt, ..new_access_token(Some("aardvark"), &[Scope::OPENID]) };
+        let access_token_repository = InMemoryTokenRepository::new();
+        access_token_repository.save_token(&access_token);
+
+        let signing_key = assert_some!(InMemoryKeyStore::new_test_store().signing_key());
+        let jwt = assert_ok!(encode_access_token(TEST_ISSUER, &access_token, &signing_key));
+
+        let response = userinfo(Method::GET, access_token_repository, Some(jwt)).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_challenge_a_request_without_a_token() {
+        let response = userinfo(Method::GET, InMemoryTokenRepository::new(), None).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate(&response), "Bearer");
+    }
+
+    #[tokio::test]
+    async fn should_reject_an_unknown_token() {
+        let response = userinfo(Method::GET, InMemoryTokenRepository::new(), Some(String::from("aardvark"))).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate(&response), r#"Bearer error="invalid_token""#);
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_token_without_the_openid_scope() {
+        let response = userinfo_with(&new_access_token(Some("aardvark"), &[Scope::PROFILE])).await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(www_authenticate(&response), r#"Bearer error="insufficient_scope", scope="openid""#);
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_token_without_a_resource_owner() {
+        let response = userinfo_with(&new_access_token(None, &[Scope::OPENID])).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate(&response), r#"Bearer error="invalid_token""#);
+    }
+
+    #[tokio::test]
+    async fn should_accept_a_bound_token_with_a_proof_for_it() {
+        let access_token = new_bound_access_token();
+        let proof = new_access_token_proof("GET", TEST_USERINFO_URI, &access_token.id.to_string());
+
+        let response = userinfo_with_proof(&access_token, "DPoP", Some(proof)).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_bound_token_presented_as_a_bearer_token() {
+        let response = userinfo_with(&new_bound_access_token()).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate(&response), r#"Bearer error="invalid_token""#);
+    }
+
+    #[tokio::test]
+    async fn should_reject_an_unbound_token_presented_as_a_dpop_token() {
+        let access_token = new_access_token(Some("aardvark"), &[Scope::OPENID]);
+        let proof = new_access_token_proof("GET", TEST_USERINFO_URI, &access_token.id.to_string());
+
+        let response = userinfo_with_proof(&access_token, "DPoP", Some(proof)).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate(&response), r#"Bearer error="invalid_token""#);
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_bound_token_without_a_proof() {
+        let response = userinfo_with_proof(&new_bound_access_token(), "DPoP", None).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate(&response), r#"DPoP error="invalid_dpop_proof""#);
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_proof_not_made_for_the_access_token() {
+        let response = userinfo_with_proof(&new_bound_access_token(), "DPoP", Some(new_proof("GET", TEST_USERINFO_URI))).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate(&response), r#"DPoP error="invalid_dpop_proof""#);
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_proof_from_another_key() {
+        let access_token = AccessToken {
+            confirmation: Some(Confirmation { jkt: String::from("aardvark") }),
+            ..new_bound_access_token()
+        };
+        let proof = new_access_token_proof("GET", TEST_USERINFO_URI, &access_token.id.to_string());
+
+        let response = userinfo_with_proof(&access_token, "DPoP", Some(proof)).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate(&response), r#"Bearer error="invalid_token""#);
+    }
+}
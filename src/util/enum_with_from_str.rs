@@ -11,6 +11,11 @@ macro_rules! enum_with_from_str {
         $vis enum $enum_name {
             $($enum_value),+
         }
+        impl $enum_name {
+            // Every variant in declaration order, so lists of what we support can't drift from the enum.
+            #[allow(dead_code)]
+            $vis const VALUES: &[Self] = &[$(Self::$enum_value),+];
+        }
         impl std::str::FromStr for $enum_name {
             type Err = String;
             fn from_str(value: &str) -> Result<Self, Self::Err> {
@@ -61,6 +66,15 @@ mod test {
         }
     }
 
+    mod values {
+        use super::*;
+
+        #[test]
+        fn should_return_every_variant_in_declaration_order() {
+            assert_eq!(TestEnum::VALUES, &[TestEnum::TestValue1, TestEnum::TestValue2]);
+        }
+    }
+
     mod to_string {
         use super::*;
 
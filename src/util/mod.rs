@@ -1,5 +1,6 @@
 pub mod disable_deserialization;
 pub mod disable_serialization;
 pub mod enum_with_from_str;
+pub mod html;
 pub mod map_of;
 pub mod value_struct;
@@ -1,6 +1,7 @@
 pub trait ValueStruct {
     type ValueType;
     fn value(&self) -> &Self::ValueType;
+    #[allow(dead_code)]
     fn into_value(self) -> Self::ValueType;
 }
 
@@ -16,7 +17,7 @@ macro_rules! value_struct {
         #[cfg_attr(test, derive(Debug))]
         $vis struct $struct_name($field_type);
 
-        impl crate::util::value_struct::ValueStruct for $struct_name {
+        impl $crate::util::value_struct::ValueStruct for $struct_name {
             type ValueType = $field_type;
 
             #[inline]